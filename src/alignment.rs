@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+
+use itertools::Itertools;
+use rayon::prelude::*;
+use rustyms::{
+    align::*,
+    imgt::{self, GeneType},
+    peptidoform::Region,
+    AtMax, Peptidoform, SimpleLinear,
+};
+
+use crate::cli::AlignmentKind;
+use crate::stats::weighted_score;
+
+/// The settings for aligning two sequences
+#[derive(Clone, Copy)]
+pub struct AlignSettings<'a> {
+    pub scoring: AlignScoring<'a>,
+    pub ty: AlignType,
+    /// Determines the maximal isobaric step
+    pub kind: AlignmentKind,
+}
+
+/// The germline selection and settings for a domain alignment, see [`consecutive_align`]
+#[derive(Clone)]
+pub struct DomainSettings<'a> {
+    /// The gene types that are aligned in order, with the alignment type for every segment
+    pub segments: Vec<(GeneType, AlignType)>,
+    /// The minimal normalised score for a segment to be seen as present
+    pub threshold: f64,
+    /// The weights per region to rank the hits on, see `--region-weights`
+    pub weights: Vec<(Region, f64)>,
+    pub species: Option<HashSet<imgt::Species>>,
+    pub chains: Option<HashSet<imgt::ChainType>>,
+    pub allele: imgt::AlleleSelection,
+    pub scoring: AlignScoring<'a>,
+    /// The number of hits that are kept for every segment
+    pub return_number: usize,
+    /// Determines the maximal isobaric step
+    pub kind: AlignmentKind,
+}
+
+/// Run the given expression with `$steps` bound as a constant to the runtime maximal isobaric
+/// step, this dispatches to the compiled versions for all
+/// [`crate::SUPPORTED_ISOBARIC_LENGTHS`], any other step is handled as unbounded.
+macro_rules! with_max_step {
+    ($max_step:expr, $steps:ident => $body:expr) => {
+        with_max_step!($max_step, $steps => $body; 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 20, 24, 32, 48, 64)
+    };
+    ($max_step:expr, $steps:ident => $body:expr; $($n:literal),*) => {
+        match $max_step {
+            $($n => {
+                const $steps: u16 = $n;
+                $body
+            })*
+            _ => {
+                const $steps: u16 = u16::MAX;
+                $body
+            }
+        }
+    };
+}
+
+/// Align two sequences, the alignment kind determines the maximal isobaric step
+pub fn align<'a, A: AtMax<SimpleLinear>, B: AtMax<SimpleLinear>>(
+    seq_a: &'a Peptidoform<A>,
+    seq_b: &'a Peptidoform<B>,
+    scoring: AlignScoring<'a>,
+    ty: AlignType,
+    kind: AlignmentKind,
+) -> Alignment<'a, A, B> {
+    let kind = kind.resolve(seq_a.len(), seq_b.len(), scoring.tolerance);
+    with_max_step!(kind.max_step(), STEPS => rustyms::align::align::<STEPS, A, B>(seq_a, seq_b, scoring, ty))
+}
+
+/// Align A against all rotations of B (treating B as a circular sequence) and return the best
+/// alignment with the number of residues B was rotated to the left. On equal scores the smallest
+/// rotation is returned. Terminal modifications on B stay on the termini of the rotated sequence.
+pub fn best_rotation(
+    a: &Peptidoform<SimpleLinear>,
+    b: &Peptidoform<SimpleLinear>,
+    settings: AlignSettings,
+) -> (usize, Alignment<'static, SimpleLinear, SimpleLinear>) {
+    (0..b.len().max(1))
+        .into_par_iter()
+        .map(|rotation| {
+            let mut rotated = b.clone();
+            rotated.sequence_mut().rotate_left(rotation);
+            let alignment =
+                align(a, &rotated, settings.scoring, settings.ty, settings.kind).to_owned();
+            (rotation, alignment)
+        })
+        .max_by(|x, y| x.1.cmp(&y.1).then(y.0.cmp(&x.0)))
+        .unwrap()
+}
+
+/// Find all non overlapping hits of B in A. The best alignment is found, the aligned region of A is
+/// masked, and this is repeated on the remaining unmasked regions until no alignment with at least
+/// the given normalised score is left. The hits are returned in the order they were found (best
+/// first) together with the offset of the region of A they were aligned to.
+pub fn all_hits(
+    a: &Peptidoform<SimpleLinear>,
+    b: &Peptidoform<SimpleLinear>,
+    threshold: f64,
+    settings: AlignSettings,
+) -> Vec<(usize, Alignment<'static, SimpleLinear, SimpleLinear>)> {
+    let mut regions = std::iter::once(0..a.len()).collect_vec();
+    let mut hits = Vec::new();
+    loop {
+        let best = regions
+            .par_iter()
+            .enumerate()
+            .filter(|(_, region)| !region.is_empty())
+            .map(|(index, region)| {
+                let alignment = align(
+                    &a.sub_peptide(region.clone()),
+                    b,
+                    settings.scoring,
+                    settings.ty,
+                    settings.kind,
+                )
+                .to_owned();
+                (index, alignment)
+            })
+            .filter(|(_, alignment)| alignment.normalised_score() >= threshold)
+            .max_by(|x, y| x.1.cmp(&y.1).then(y.0.cmp(&x.0)));
+        let Some((index, alignment)) = best else {
+            break;
+        };
+        if alignment.len_a() == 0 {
+            break;
+        }
+        let region = regions.remove(index);
+        let start = region.start + alignment.start_a();
+        let end = start + alignment.len_a();
+        regions.insert(index, end..region.end);
+        regions.insert(index, region.start..start);
+        hits.push((region.start, alignment));
+    }
+    hits
+}
+
+/// Align a sequence consecutively against the given segments (gene types with their alignment type,
+/// see `--segments`) of the selected IMGT germlines. After the first segment only germlines of the
+/// same species and chain as the previous segment are used. If the best alignment for a segment has
+/// a normalised score below the threshold the segment is reported as absent, and the next segment is
+/// aligned from the same location in the sequence. If region weights are given the hits for every
+/// segment are ranked on the weighted score (see `--region-weights`). The absent segments are
+/// returned next to the alignment.
+pub fn consecutive_align(
+    seq: &Peptidoform<SimpleLinear>,
+    settings: &DomainSettings,
+) -> (ConsecutiveAlignment<'static, SimpleLinear>, Vec<GeneType>) {
+    // The germlines are about as long as the domain they align to, so the query length is used for both
+    let kind = settings
+        .kind
+        .resolve(seq.len(), seq.len(), settings.scoring.tolerance);
+    with_max_step!(kind.max_step(), STEPS => consecutive_segments::<STEPS>(seq, settings))
+}
+
+fn consecutive_segments<const STEPS: u16>(
+    seq: &Peptidoform<SimpleLinear>,
+    settings: &DomainSettings,
+) -> (ConsecutiveAlignment<'static, SimpleLinear>, Vec<GeneType>) {
+    let DomainSettings {
+        segments: genes,
+        threshold,
+        weights,
+        species,
+        chains,
+        allele,
+        scoring,
+        return_number,
+        ..
+    } = settings;
+    let (threshold, allele, scoring, return_number) =
+        (*threshold, *allele, *scoring, (*return_number).max(1));
+    let mut output: Vec<Vec<(imgt::Allele<'static>, Alignment<'static, _, SimpleLinear>)>> =
+        Vec::with_capacity(genes.len());
+    let mut absent = Vec::new();
+    let mut offset = 0;
+    for (gene, ty) in genes {
+        let (use_species, use_chains) = output.last().and_then(|v| v.first()).map_or_else(
+            || (species.clone(), chains.clone()),
+            |last| {
+                (
+                    Some(HashSet::from([last.0.species])),
+                    Some(HashSet::from([last.0.gene.chain])),
+                )
+            },
+        );
+        let left = seq.sub_peptide(offset..);
+        if left.is_empty() {
+            absent.push(*gene);
+            continue;
+        }
+        let mut hits: Vec<_> = imgt::Selection {
+            species: use_species,
+            chains: use_chains,
+            allele,
+            genes: Some(HashSet::from([*gene])),
+        }
+        .par_germlines()
+        .map(|germline| {
+            let alignment = rustyms::align::align::<STEPS, _, SimpleLinear>(
+                germline.sequence,
+                &left,
+                scoring,
+                *ty,
+            )
+            .to_owned();
+            (germline, alignment)
+        })
+        .collect();
+        if weights.is_empty() {
+            hits.sort_by(|a, b| b.1.cmp(&a.1));
+        } else {
+            hits.sort_by(|a, b| {
+                weighted_score(&b.1, b.0.regions, weights).total_cmp(&weighted_score(
+                    &a.1,
+                    a.0.regions,
+                    weights,
+                ))
+            });
+        }
+        hits.truncate(return_number);
+        if hits
+            .first()
+            .is_none_or(|(_, a)| a.normalised_score().is_nan() || a.normalised_score() < threshold)
+        {
+            absent.push(*gene);
+            continue;
+        }
+        offset += hits[0].1.start_b() + hits[0].1.len_b();
+        output.push(hits);
+    }
+    (ConsecutiveAlignment { alignments: output }, absent)
+}
+
+/// Get the number of residues covered on A and B by a path in the short notation (see
+/// [`Alignment::short`]), returns None if the path is not valid.
+pub fn path_length(path: &str) -> Option<(usize, usize)> {
+    let mut length = (0, 0);
+    let mut number = String::new();
+    let mut first = None;
+    for c in path.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let n: usize = number.parse().ok()?;
+        number.clear();
+        match (c, first.take()) {
+            (':', None) => first = Some(n),
+            ('i', Some(a)) => length = (length.0 + a, length.1 + n),
+            ('I', None) => length.1 += n,
+            ('D', None) => length.0 += n,
+            ('X' | 'm' | '=' | 'r' | 'i', None) => length = (length.0 + n, length.1 + n),
+            _ => return None,
+        }
+    }
+    (number.is_empty() && first.is_none()).then_some(length)
+}
+
+#[test]
+fn supported_isobaric_lengths() {
+    use crate::cli::SUPPORTED_ISOBARIC_LENGTHS;
+    for length in SUPPORTED_ISOBARIC_LENGTHS {
+        assert_eq!(with_max_step!(length, STEPS => STEPS), length);
+    }
+    assert_eq!(with_max_step!(17, STEPS => STEPS), u16::MAX);
+}
+
+#[test]
+fn path_lengths() {
+    assert_eq!(path_length("4=1I6="), Some((10, 11)));
+    assert_eq!(path_length("2=2:3i1D"), Some((5, 5)));
+    assert_eq!(path_length("4=1"), None);
+    assert_eq!(path_length("4=1Q"), None);
+}
@@ -0,0 +1,257 @@
+use colored::Colorize;
+use itertools::Itertools;
+use rustyms::{
+    align::{align, matrix::BLOSUM90, AlignScoring, AlignType, Alignment, MatchType},
+    imgt::Allele,
+    peptidoform::UnAmbiguous,
+    Peptidoform,
+};
+
+/// Stack multiple sequences on the first sequence. Every other sequence is globally aligned to the
+/// first and the alignments are merged, so that every row has the same number of columns. Gaps are
+/// shown as `-`, residues inserted with respect to the first sequence get their own columns.
+pub fn stack(sequences: &[&Peptidoform<UnAmbiguous>]) -> Vec<Vec<char>> {
+    let Some(reference) = sequences.first() else {
+        return Vec::new();
+    };
+    let scoring = AlignScoring::<'static> {
+        matrix: BLOSUM90,
+        ..Default::default()
+    };
+    let residues = |sequence: &Peptidoform<UnAmbiguous>| {
+        sequence
+            .sequence()
+            .iter()
+            .map(|s| s.aminoacid.char())
+            .collect_vec()
+    };
+    // For every sequence the residue on every reference position, and the insertions before every
+    // reference position (and at the end)
+    let mut placed = vec![(
+        residues(reference).into_iter().map(Some).collect_vec(),
+        vec![Vec::new(); reference.len() + 1],
+    )];
+    for sequence in &sequences[1..] {
+        let alignment =
+            align::<1, UnAmbiguous, UnAmbiguous>(reference, sequence, scoring, AlignType::GLOBAL);
+        let other = residues(sequence);
+        let mut on_reference = vec![None; reference.len()];
+        let mut insertions = vec![Vec::new(); reference.len() + 1];
+        let (mut a, mut b) = alignment.start();
+        for piece in alignment.path() {
+            match (piece.step_a, piece.step_b) {
+                (0, _) => insertions[a].push(other[b]),
+                (_, 0) => (),
+                _ => on_reference[a] = Some(other[b]),
+            }
+            a += piece.step_a as usize;
+            b += piece.step_b as usize;
+        }
+        placed.push((on_reference, insertions));
+    }
+    let mut output = vec![Vec::new(); sequences.len()];
+    for position in 0..=reference.len() {
+        let inserted = placed
+            .iter()
+            .map(|(_, i)| i[position].len())
+            .max()
+            .unwrap_or(0);
+        for (row, (on_reference, insertions)) in output.iter_mut().zip(&placed) {
+            row.extend(insertions[position].iter().copied());
+            row.extend(std::iter::repeat_n(
+                '-',
+                inserted - insertions[position].len(),
+            ));
+            if position < reference.len() {
+                row.push(on_reference[position].unwrap_or('-'));
+            }
+        }
+    }
+    output
+}
+
+/// Show all given alleles stacked, with all positions that differ from the first allele
+/// highlighted and the number of differences for every allele.
+pub fn show_alleles(alleles: &[Allele], line_width: usize) {
+    let rows = stack(&alleles.iter().map(|a| a.sequence).collect_vec());
+    let Some(reference) = rows.first() else {
+        return;
+    };
+    let names = alleles.iter().map(Allele::name).collect_vec();
+    let differences = rows
+        .iter()
+        .map(|row| row.iter().zip(reference).filter(|(a, b)| a != b).count())
+        .collect_vec();
+    println!(
+        "{} {} {}",
+        alleles[0].species.scientific_name().to_string().purple(),
+        alleles[0].species.common_name(),
+        format!("{} ({} alleles)", alleles[0].gene, alleles.len()).purple(),
+    );
+    println!(
+        "{} {}",
+        "Differences".underline().italic(),
+        names
+            .iter()
+            .zip(&differences)
+            .skip(1)
+            .map(|(name, d)| format!("{name}: {d}"))
+            .join(", ")
+            .dimmed()
+    );
+    let line_width = line_width.max(1);
+    // The position on the first allele of every column, for the ruler
+    let mut position = 0;
+    let positions = reference
+        .iter()
+        .map(|c| {
+            if *c == '-' {
+                None
+            } else {
+                position += 1;
+                Some(position)
+            }
+        })
+        .collect_vec();
+    for start in (0..reference.len()).step_by(line_width) {
+        let end = (start + line_width).min(reference.len());
+        let mut ruler = String::new();
+        for (column, position) in positions.iter().enumerate().take(end).skip(start) {
+            if ruler.chars().count() > column - start {
+                continue;
+            }
+            match position {
+                Some(p) if p % 10 == 0 && column + p.to_string().len() <= end => {
+                    ruler += &p.to_string();
+                }
+                _ => ruler.push(' '),
+            }
+        }
+        println!("{}", ruler.dimmed());
+        for (index, row) in rows.iter().enumerate() {
+            let line = row[start..end]
+                .iter()
+                .zip(&reference[start..end])
+                .map(|(c, r)| {
+                    if index == 0 || c == r {
+                        c.to_string().normal()
+                    } else {
+                        c.to_string().red().bold()
+                    }
+                })
+                .join("");
+            println!("{line} {}", names[index].dimmed());
+        }
+        println!();
+    }
+}
+
+/// The positions (0 based) of an allele that distinguish it from at least one of the other alleles
+/// of the same gene, with the residue of this allele and the differing residues of the other
+/// alleles (`-` for a deletion). Insertions in the other alleles have no position on this allele
+/// and are not included.
+pub fn distinguishing_positions(
+    allele: &Allele,
+    others: &[Allele],
+) -> Vec<(usize, char, Vec<char>)> {
+    let rows = stack(
+        &std::iter::once(allele.sequence)
+            .chain(others.iter().map(|a| a.sequence))
+            .collect_vec(),
+    );
+    let mut output = Vec::new();
+    let mut position = 0;
+    for (column, residue) in rows[0].iter().enumerate() {
+        if *residue == '-' {
+            continue;
+        }
+        let differing = rows[1..]
+            .iter()
+            .map(|row| row[column])
+            .filter(|c| c != residue)
+            .unique()
+            .collect_vec();
+        if !differing.is_empty() {
+            output.push((position, *residue, differing));
+        }
+        position += 1;
+    }
+    output
+}
+
+/// Check the distinguishing positions of an allele (see [`distinguishing_positions`]) in an
+/// alignment of the allele (as A) against a query. For every distinguishing position covered by the
+/// alignment this gives the index of the step and if the query has the residue of this allele.
+pub fn allele_support<A, B>(
+    alignment: &Alignment<'_, A, B>,
+    positions: &[(usize, char, Vec<char>)],
+) -> Vec<(usize, usize, bool)> {
+    let mut output = Vec::new();
+    let mut a = alignment.start_a();
+    for (index, piece) in alignment.path().iter().enumerate() {
+        let identical = matches!(
+            piece.match_type,
+            MatchType::FullIdentity | MatchType::IdentityMassMismatch
+        );
+        for (position, _, _) in positions
+            .iter()
+            .filter(|(p, _, _)| (a..a + piece.step_a as usize).contains(p))
+        {
+            output.push((index, *position, identical));
+        }
+        a += piece.step_a as usize;
+    }
+    output
+}
+
+#[test]
+fn stacked() {
+    let parse = |s: &str| {
+        Peptidoform::pro_forma(s, None)
+            .unwrap()
+            .into_unambiguous()
+            .unwrap()
+    };
+    let sequences = [
+        parse("EVQLVESGGG"),
+        parse("EVQLVQSGGG"),
+        parse("EVQLVESGG"),
+        parse("EVQLVEWSGGG"),
+    ];
+    let rows = stack(&sequences.iter().collect_vec());
+    let rows = rows
+        .iter()
+        .map(|r| r.iter().collect::<String>())
+        .collect_vec();
+    assert_eq!(
+        rows,
+        ["EVQLVE-SGGG", "EVQLVQ-SGGG", "EVQLVE-SGG-", "EVQLVEWSGGG"]
+    );
+}
+
+#[test]
+fn distinguishing() {
+    let selection = rustyms::imgt::Selection::<std::hash::RandomState, std::hash::RandomState> {
+        allele: rustyms::imgt::AlleleSelection::All,
+        ..Default::default()
+    };
+    let alleles = selection
+        .germlines()
+        .filter(|a| {
+            a.species == rustyms::imgt::Species::HomoSapiens && a.gene.to_string() == "IGHV3-30"
+        })
+        .collect_vec();
+    assert!(alleles.len() > 1);
+    let positions = distinguishing_positions(&alleles[0], &alleles[1..]);
+    assert!(!positions.is_empty());
+    let residues = alleles[0]
+        .sequence
+        .sequence()
+        .iter()
+        .map(|s| s.aminoacid.char())
+        .collect_vec();
+    for (position, residue, others) in &positions {
+        assert_eq!(residues[*position], *residue);
+        assert!(!others.contains(residue));
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashSet;
+
+use rustyms::{
+    align::{AlignScoring, Alignment, MatchType},
+    AtMax, SimpleLinear,
+};
+
+/// The maximal number of co-optimal alignments that are generated
+const MAX_VARIANTS: usize = 50;
+
+/// A single step in an alignment path, in the granularity needed to shift gaps around
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) enum Step {
+    /// A one to one step (identity or mismatch), these are rescored when the path is recreated
+    Pair,
+    Deletion,
+    Insertion,
+    /// An isobaric or rotated step, saved in the short path notation
+    Special(String, u16, u16),
+}
+
+impl Step {
+    /// The steps on A and B for this step
+    pub(crate) const fn steps(&self) -> (u16, u16) {
+        match self {
+            Self::Pair => (1, 1),
+            Self::Deletion => (1, 0),
+            Self::Insertion => (0, 1),
+            Self::Special(_, a, b) => (*a, *b),
+        }
+    }
+}
+
+/// The co-optimal alignments for an alignment
+pub struct Ambiguity<'a, A, B> {
+    /// All alignments with the same score, the first one is the original alignment
+    pub alignments: Vec<Alignment<'a, A, B>>,
+    /// The indices of the steps in the path of the original alignment that differ in at least
+    /// one of the co-optimal alignments
+    pub ambiguous_steps: HashSet<usize>,
+}
+
+/// Find co-optimal alignments by moving every gap to all alternative positions where it gives the
+/// exact same score. Only alternative gap placements are explored, so this is a subset of all
+/// possible co-optimal paths.
+pub fn find_ambiguity<'a, A: AtMax<SimpleLinear>, B: AtMax<SimpleLinear>>(
+    alignment: &'a Alignment<'_, A, B>,
+    scoring: AlignScoring,
+) -> Ambiguity<'a, A, B> {
+    let steps = path_steps(alignment);
+    let mut variants = vec![steps.clone()];
+    let mut index = 0;
+    while index < steps.len() {
+        if !matches!(steps[index], Step::Deletion | Step::Insertion) {
+            index += 1;
+            continue;
+        }
+        let start = index;
+        while index < steps.len() && steps[index] == steps[start] {
+            index += 1;
+        }
+        let end = index;
+        // Shift the gap to the left, by moving the pairs in front of the gap after it
+        for shift in (1..=start).take_while(|s| steps[start - s] == Step::Pair) {
+            let mut variant = steps[..start - shift].to_vec();
+            variant.extend_from_slice(&steps[start..end]);
+            variant.extend_from_slice(&steps[start - shift..start]);
+            variant.extend_from_slice(&steps[end..]);
+            variants.push(variant);
+        }
+        // Shift the gap to the right, by moving the pairs after the gap in front of it
+        for shift in (1..=steps.len() - end).take_while(|s| steps[end + s - 1] == Step::Pair) {
+            let mut variant = steps[..start].to_vec();
+            variant.extend_from_slice(&steps[end..end + shift]);
+            variant.extend_from_slice(&steps[start..end]);
+            variant.extend_from_slice(&steps[end + shift..]);
+            variants.push(variant);
+        }
+    }
+
+    let score = alignment.score().absolute;
+    let mut alignments = vec![alignment.clone()];
+    let mut seen = HashSet::from([steps.clone()]);
+    for variant in variants.into_iter().skip(1) {
+        if alignments.len() >= MAX_VARIANTS || !seen.insert(variant.clone()) {
+            continue;
+        }
+        if let Some(other) = Alignment::create_from_path(
+            alignment.seq_a(),
+            alignment.seq_b(),
+            alignment.start_a(),
+            alignment.start_b(),
+            &short(&variant),
+            scoring,
+            alignment.align_type(),
+            alignment.max_step(),
+        )
+        .filter(|other| other.score().absolute == score)
+        {
+            alignments.push(other);
+        }
+    }
+
+    let columns = |steps: &[(u16, u16)]| {
+        let (mut a, mut b) = alignment.start();
+        steps
+            .iter()
+            .map(|(step_a, step_b)| {
+                let column = (a, b, *step_a, *step_b);
+                a += *step_a as usize;
+                b += *step_b as usize;
+                column
+            })
+            .collect::<Vec<_>>()
+    };
+    let original = columns(&steps.iter().map(Step::steps).collect::<Vec<_>>());
+    let others: Vec<HashSet<_>> = alignments[1..]
+        .iter()
+        .map(|other| {
+            columns(
+                &other
+                    .path()
+                    .iter()
+                    .map(|p| (p.step_a, p.step_b))
+                    .collect::<Vec<_>>(),
+            )
+            .into_iter()
+            .collect()
+        })
+        .collect();
+    let ambiguous_steps = original
+        .iter()
+        .enumerate()
+        .filter(|(_, column)| others.iter().any(|other| !other.contains(column)))
+        .map(|(index, _)| index)
+        .collect();
+    Ambiguity {
+        alignments,
+        ambiguous_steps,
+    }
+}
+
+/// Get the steps for the path of this alignment
+pub(crate) fn path_steps<A, B>(alignment: &Alignment<'_, A, B>) -> Vec<Step> {
+    alignment
+        .path()
+        .iter()
+        .map(|p| match (p.match_type, p.step_a, p.step_b) {
+            (MatchType::Isobaric, a, b) if a == b => Step::Special(format!("{a}i"), a, b),
+            (MatchType::Isobaric, a, b) => Step::Special(format!("{a}:{b}i"), a, b),
+            (MatchType::Rotation, a, b) => Step::Special(format!("{a}r"), a, b),
+            (_, 1, 1) => Step::Pair,
+            (_, 1, 0) => Step::Deletion,
+            (_, 0, 1) => Step::Insertion,
+            (_, a, b) => Step::Special(format!("{a}:{b}i"), a, b),
+        })
+        .collect()
+}
+
+/// Create the short path notation for the given steps, see [`Alignment::short`]
+pub(crate) fn short(steps: &[Step]) -> String {
+    let mut output = String::new();
+    let mut last: Option<(&str, usize)> = None;
+    for step in steps {
+        let symbol = match step {
+            Step::Pair => "X",
+            Step::Deletion => "D",
+            Step::Insertion => "I",
+            Step::Special(..) => "",
+        };
+        match last {
+            Some((s, n)) if s == symbol && !symbol.is_empty() => last = Some((s, n + 1)),
+            Some((s, n)) => {
+                output += &format!("{n}{s}");
+                last = Some((symbol, 1));
+            }
+            None => last = Some((symbol, 1)),
+        }
+        if let Step::Special(special, ..) = step {
+            output += special;
+            last = None;
+        }
+    }
+    if let Some((s, n)) = last {
+        output += &format!("{n}{s}");
+    }
+    output
+}
+
+#[test]
+fn shifted_gap() {
+    use crate::test_helpers::{global, parse, scoring};
+    let (a, b) = (parse("AAAAKQRQ"), parse("AAAKQRQ"));
+    let alignment = global::<1>(&a, &b);
+    let ambiguity = find_ambiguity(&alignment, scoring());
+    assert_eq!(ambiguity.alignments.len(), 4);
+    assert_eq!(ambiguity.ambiguous_steps, HashSet::from([0, 1, 2, 3]));
+}
@@ -0,0 +1,92 @@
+use rustyms::{
+    align::AlignScoring, AminoAcid, CheckedAminoAcid, Peptidoform, SemiAmbiguous, SimpleLinear,
+};
+
+/// The maximal number of resolutions generated for a single sequence
+pub const MAX_RESOLUTIONS: usize = 4096;
+
+/// The unambiguous options for an ambiguous amino acid, or None if it is not ambiguous
+fn options(aminoacid: AminoAcid) -> Option<&'static [AminoAcid]> {
+    match aminoacid {
+        AminoAcid::AmbiguousAsparagine => Some(&[AminoAcid::Asparagine, AminoAcid::AsparticAcid]),
+        AminoAcid::AmbiguousGlutamine => Some(&[AminoAcid::Glutamine, AminoAcid::GlutamicAcid]),
+        AminoAcid::AmbiguousLeucine => Some(&[AminoAcid::Isoleucine, AminoAcid::Leucine]),
+        AminoAcid::Unknown => Some(AminoAcid::CANONICAL_AMINO_ACIDS),
+        _ => None,
+    }
+}
+
+/// Generate all resolutions of the ambiguous residues in this sequence, a sequence without any
+/// ambiguous residues gives only itself. Modifications on ambiguous residues are kept.
+pub fn resolutions(
+    peptide: &Peptidoform<SimpleLinear>,
+) -> Result<Vec<Peptidoform<SimpleLinear>>, String> {
+    let mut output = vec![peptide.clone()];
+    for (index, element) in peptide.sequence().iter().enumerate() {
+        if let Some(options) = options(element.aminoacid.aminoacid()) {
+            if output.len() * options.len() > MAX_RESOLUTIONS {
+                return Err(format!(
+                    "Too many resolutions for the ambiguous residues in {peptide}, the maximum is {MAX_RESOLUTIONS}"
+                ));
+            }
+            output = output
+                .into_iter()
+                .flat_map(|peptide| {
+                    options.iter().map(move |option| {
+                        let mut peptide = peptide.clone();
+                        peptide.sequence_mut()[index].aminoacid =
+                            CheckedAminoAcid::<SemiAmbiguous>::new(*option).into();
+                        peptide
+                    })
+                })
+                .collect();
+        }
+    }
+    Ok(output)
+}
+
+/// The scoring matrix where every ambiguous residue (B, Z, J, and X) aligned to any residue, including itself, is
+/// scored as a mismatch. This only changes the one-to-one steps, the ambiguous residues keep their masses so B, Z, and
+/// J can still be mass equivalent to their options and X (which has no mass) can still be part of mass based steps.
+pub fn mismatch_matrix(
+    scoring: &AlignScoring<'_>,
+) -> [[i8; AminoAcid::TOTAL_NUMBER]; AminoAcid::TOTAL_NUMBER] {
+    let mut matrix = *scoring.matrix;
+    for aminoacid in [
+        AminoAcid::AmbiguousAsparagine,
+        AminoAcid::AmbiguousGlutamine,
+        AminoAcid::AmbiguousLeucine,
+        AminoAcid::Unknown,
+    ] {
+        matrix[aminoacid as usize] = [scoring.mismatch; AminoAcid::TOTAL_NUMBER];
+        for row in &mut matrix {
+            row[aminoacid as usize] = scoring.mismatch;
+        }
+    }
+    matrix
+}
+
+#[test]
+fn resolve() {
+    let peptide = Peptidoform::pro_forma("ABZJ", None)
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let resolved = resolutions(&peptide).unwrap();
+    assert_eq!(resolved.len(), 8);
+    assert!(resolved.iter().any(|p| p.to_string() == "ADEL"));
+    let scoring = AlignScoring::default();
+    let matrix = mismatch_matrix(&scoring);
+    assert_eq!(
+        matrix[AminoAcid::Unknown as usize][AminoAcid::Unknown as usize],
+        scoring.mismatch
+    );
+    assert_eq!(
+        matrix[AminoAcid::Alanine as usize][AminoAcid::AmbiguousLeucine as usize],
+        scoring.mismatch
+    );
+    assert_eq!(
+        matrix[AminoAcid::Alanine as usize][AminoAcid::Alanine as usize],
+        scoring.matrix[AminoAcid::Alanine as usize][AminoAcid::Alanine as usize]
+    );
+}
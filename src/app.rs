@@ -0,0 +1,3571 @@
+use clap::Parser;
+use colored::{Color, Colorize, Styles};
+use imgt::Allele;
+use itertools::Itertools;
+use rayon::prelude::*;
+use rustyms::csv::CsvLine;
+use rustyms::identification::FastaData;
+use rustyms::imgt::{AlleleSelection, ChainType, Gene, GeneType, Selection, Species};
+use rustyms::peptidoform::{Annotation, Region};
+use rustyms::{
+    align::*,
+    imgt,
+    modification::{
+        GnoComposition, LinkerSpecificity, ModificationId, Ontology, SimpleModification,
+        SimpleModificationInner,
+    },
+    modification_search_formula, modification_search_mass,
+    placement_rule::*,
+    system::{dalton, Mass},
+    AminoAcid, Chemical, MassMode, MolecularFormula, Multi, Peptidoform, SimpleLinear, UnAmbiguous,
+};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    alignment::{align, all_hits, best_rotation, consecutive_align, path_length, DomainSettings},
+    alleles, ambiguity, ambiguous, batch, bound, cdr, checkpoint, chimera,
+    cli::*,
+    combination, compare, confidence, consensus, coverage, crosslink, custom, decoy, delta,
+    diagnostic, digest, elemental, explain, formula, glycan, humanness, index, isobaric, isotopes,
+    isotype, junction, label, ladder, liabilities, lookup, mutations, numbering, paired, placement,
+    prefilter,
+    render::*,
+    repertoire, retention, stats, stream,
+    styling::*,
+    substitution, transfer,
+    tree::{self, TreeMethod},
+    triple, validate, NUMBER_PRECISION,
+};
+
+/// Run the command line tool with the arguments of this process
+pub fn run() {
+    if let Err(err) = custom::load_from_args(std::env::args()) {
+        eprintln!("{}", err.red());
+        std::process::exit(1);
+    }
+    let mut args = Cli::parse();
+    if let Some(label) = args.label {
+        args.fixed = Modifications::Some(
+            args.fixed
+                .mods()
+                .iter()
+                .cloned()
+                .chain(label.side_chains())
+                .collect(),
+        );
+    }
+    if let Some(Command::Index { fasta }) = &args.command {
+        index_database(&args, fasta);
+    } else if let Some(Command::Replay {
+        a,
+        b,
+        path,
+        start_a,
+        start_b,
+    }) = &args.command
+    {
+        replay(&args, a, b, path, *start_a, *start_b);
+    } else if let Some(Command::Compare {
+        query,
+        first,
+        second,
+        first_path,
+        second_path,
+    }) = &args.command
+    {
+        compare_interpretations(
+            &args,
+            query,
+            first,
+            second,
+            first_path.as_deref(),
+            second_path.as_deref(),
+        );
+    } else if let Some(Command::Delta { a, b }) = &args.command {
+        show_delta(&args, a, b);
+    } else if let Some(Command::Elements { a, b }) = &args.command {
+        let [a, b] = [a, b].map(|s| {
+            Peptidoform::pro_forma(s, custom::database())
+                .unwrap()
+                .into_simple_linear()
+                .unwrap()
+        });
+        elemental::show(&a, &b, (!args.full_number).then_some(NUMBER_PRECISION));
+    } else if let Some(Command::Crosslink { mass, a, b }) = &args.command {
+        crosslink(&args, *mass, a, b.as_deref());
+    } else if let Some(Command::Validate { input }) = &args.command {
+        if !validate::show(input) {
+            std::process::exit(1);
+        }
+    } else if let Some(Command::Substitutions { delta }) = &args.command {
+        substitution::show(
+            Mass::new::<dalton>(*delta),
+            args.tolerance,
+            args.mass_mode,
+            (!args.full_number).then_some(NUMBER_PRECISION),
+        );
+    } else if let Some(Command::Digest {
+        input,
+        enzyme,
+        missed,
+        fasta,
+    }) = &args.command
+    {
+        digest_proteins(&args, input, enzyme, *missed, *fasta);
+    } else if let Some(Command::ImgtCompare {
+        first,
+        second,
+        species,
+    }) = &args.command
+    {
+        compare_germlines(&args, first, second, *species);
+    } else if let (Some(a), Some(b), Some(c)) = (&args.a, &args.second.b, &args.c) {
+        triple_alignment(&args, a, b, c);
+    } else if let (Some(threshold), Some(a), Some(b)) = (args.all_hits, &args.a, &args.second.b) {
+        show_all_hits(&args, threshold, a, b);
+    } else if let (Some(a), Some(b)) = (&args.a, &args.second.b) {
+        pairwise(&args, a, b);
+    } else if let (Some(method), Some(path)) = (args.tree, &args.second.file) {
+        database_tree(&args, method, path);
+    } else if let (Some(queries), Some(path)) = (&args.queries, &args.second.file) {
+        multi_query_search(&args, queries, path);
+    } else if let (Some(b), Some(path)) = (&args.a, &args.second.file) {
+        database_search(&args, b, path);
+    } else if let (Some(heavy), Some(light)) = (&args.heavy, &args.light) {
+        paired_chains(&args, heavy, light);
+    } else if let (Some(x), true) = (&args.a, args.isotype) {
+        show_isotype(&args, x);
+    } else if let (Some(x), true) = (&args.a, args.humanness) {
+        show_humanness(&args, x);
+    } else if let (Some(x), Some(method)) = (&args.a, args.placement) {
+        show_placement(&args, x, method);
+    } else if let (Some(x), false) = (&args.a, args.compare_species.is_empty()) {
+        compare_species(&args, x);
+    } else if let (Some(x), true) = (&args.a, &args.second.imgt) {
+        imgt_search(&args, x);
+    } else if let (Some(path), true) = (&args.domain_batch, &args.second.domain) {
+        repertoire::annotate(&args, path);
+    } else if let (Some(x), true) = (&args.a, &args.second.domain) {
+        domain_alignment(&args, x);
+    } else if let (Some(x), Some((gene, allele)), Some(species)) =
+        (&args.a, &args.second.specific_gene, &args.species)
+    {
+        specific_germline_alignment(&args, x, gene, *allele, *species);
+    } else if let Some(x) = &args.a {
+        single_stats(
+            &args,
+            Peptidoform::pro_forma(x, custom::database())
+                .unwrap()
+                .into_simple_linear()
+                .unwrap(),
+        )
+    } else if let Some(query) = &args.modification_search {
+        lookup::show(query, (!args.full_number).then_some(NUMBER_PRECISION));
+    } else if let Some(composition) = &args.glycan {
+        glycan::show(
+            composition,
+            args.gnome.then(|| gnome_filter(&args)).as_ref(),
+            (!args.full_number).then_some(NUMBER_PRECISION),
+        );
+    } else if let Some(path) = &args.modification_file {
+        batch::modification_file(
+            path,
+            args.tolerance,
+            args.mass_mode,
+            args.positions.as_deref(),
+        );
+    } else if let Some(modification) = &args.modification {
+        show_modification(&args, modification);
+    } else if let Some(path) = &args.mass_csv {
+        batch::mass_file(path, &args);
+    } else if let Some(mass) = args.isobaric_mass {
+        isobaric::show_for_mass(Mass::new::<dalton>(mass), &args);
+    } else if let Some(target) = args.formula {
+        formula::show(
+            args.formula_tolerance
+                .map_or(target, |tolerance| target.with_tolerance(tolerance)),
+            &args.adducts,
+            &args.elements,
+            args.plausibility(),
+            args.mass_defect.as_ref(),
+            args.all_mass_modes,
+            (!args.full_number).then_some(NUMBER_PRECISION),
+        );
+    } else if let Some(file) = args.second.csv.as_ref().or(args.csv_search.as_ref()) {
+        csv_file(&args, file);
+    } else if let (Some((gene, allele)), Some(species)) =
+        (&args.second.specific_gene, &args.species)
+    {
+        show_specific_germline(&args, gene, *allele, *species);
+    } else if args.second.imgt && args.list_species {
+        list_species();
+    } else if args.second.imgt && args.list_genes {
+        list_genes(&args);
+    } else if args.second.imgt && args.export_germline_json {
+        export_germline_json(&args);
+    } else if let (true, Some(path)) = (args.second.imgt, &args.export_fasta) {
+        export_fasta(&args, path);
+    } else if args.second.imgt {
+        display_germlines(&args);
+    } else {
+        println!("Please provide an argument to work with, use --help to see all options.")
+    }
+}
+
+/// Build the k-mer index for a fasta database, see `align index`
+fn index_database(args: &Cli, fasta: &str) {
+    let path = Path::new(fasta);
+    let index = index::DatabaseIndex::build(path, args.kmer_length).unwrap();
+    let index_path = index::DatabaseIndex::index_path(path);
+    index.save(&index_path).unwrap();
+    println!(
+        "Indexed {} sequences (k-mer length {}{}) into {}",
+        index.len().to_string().blue(),
+        args.kmer_length,
+        index
+            .mass_range()
+            .map(|(low, high)| format!(", masses {low:.2} to {high:.2} Da"))
+            .unwrap_or_default(),
+        index_path.to_string_lossy().dimmed()
+    );
+}
+
+/// Rebuild an alignment from a path and show it, see `align replay`
+fn replay(args: &Cli, a: &str, b: &str, path: &str, start_a: usize, start_b: usize) {
+    let parse = |sequence: &str, name: &str| {
+        Peptidoform::pro_forma(sequence, custom::database())
+            .map_err(|err| format!("Invalid sequence {name}: {err}"))?
+            .into_simple_linear()
+            .ok_or_else(|| format!("Sequence {name} has to be a simple linear peptide"))
+    };
+    let (a, b) = match (parse(a, "A"), parse(b, "B")) {
+        (Ok(a), Ok(b)) => (a, b),
+        (Err(err), _) | (_, Err(err)) => {
+            eprintln!("{}", err.red());
+            std::process::exit(1);
+        }
+    };
+    let alignment = path_length(path)
+        .ok_or_else(|| format!("Invalid path '{path}'"))
+        .and_then(|(length_a, length_b)| {
+            if start_a + length_a <= a.len() && start_b + length_b <= b.len() {
+                Ok(())
+            } else {
+                Err(format!(
+                    "The path does not fit on the given sequences, it covers {length_a} residues of A from {start_a} and {length_b} residues of B from {start_b}"
+                ))
+            }
+        })
+        .and_then(|()| {
+            Alignment::create_from_path(
+                &a,
+                &b,
+                start_a,
+                start_b,
+                path,
+                args.scoring(),
+                args.alignment_type.ty(),
+                args.alignment_kind
+                    .resolve(a.len(), b.len(), args.scoring().tolerance)
+                    .max_step(),
+            )
+            .ok_or_else(|| format!("Invalid path '{path}'"))
+        });
+    let alignment = match alignment {
+        Ok(alignment) => alignment,
+        Err(err) => {
+            eprintln!("{}", err.red());
+            std::process::exit(1);
+        }
+    };
+    show_annotated_mass_alignment(
+        &alignment,
+        annotated_regions(&a, args).as_ref(),
+        None,
+        false,
+        false,
+        ("A", "B"),
+        &args.render_options(),
+    );
+    if args.explain {
+        show_explanation(&alignment, args);
+    }
+}
+
+/// Show two alignments of the same query side by side, see `align compare`
+fn compare_interpretations(
+    args: &Cli,
+    query: &str,
+    first: &str,
+    second: &str,
+    first_path: Option<&str>,
+    second_path: Option<&str>,
+) {
+    let [query, first, second] = [query, first, second].map(|s| {
+        Peptidoform::pro_forma(s, custom::database())
+            .unwrap()
+            .into_simple_linear()
+            .unwrap()
+    });
+    let get = |reference, path: Option<&str>| {
+        if let Some(path) = path {
+            let (length_a, length_b) = path_length(path).expect("Invalid path");
+            assert!(
+                length_a <= Peptidoform::len(reference) && length_b <= query.len(),
+                "The path does not fit on the given sequences"
+            );
+            Alignment::create_from_path(
+                reference,
+                &query,
+                0,
+                0,
+                path,
+                args.scoring(),
+                args.alignment_type.ty(),
+                args.alignment_kind
+                    .resolve(reference.len(), query.len(), args.scoring().tolerance)
+                    .max_step(),
+            )
+            .expect("Invalid path")
+        } else {
+            align(
+                reference,
+                &query,
+                args.scoring(),
+                args.alignment_type.ty(),
+                args.alignment_kind,
+            )
+        }
+    };
+    let alignments = [get(&first, first_path), get(&second, second_path)];
+    for (name, alignment) in ["First", "Second"].iter().zip(&alignments) {
+        println!(
+            "{name}: Score: {} {}, Identity: {}, Path: {}",
+            format!("{:.3}", alignment.normalised_score()).green(),
+            format!("({}/{})", alignment.score().absolute, alignment.score().max).dimmed(),
+            format!("{:.3}", stats::stats(alignment, &args.stats).identity()).bright_blue(),
+            alignment.short().dimmed()
+        );
+    }
+    println!();
+    compare::show_comparison(
+        &query
+            .sequence()
+            .iter()
+            .map(|s| s.aminoacid.char())
+            .collect_vec(),
+        &compare::project(&alignments[0]),
+        &compare::project(&alignments[1]),
+        ("First", "Second"),
+        args.line_width,
+    );
+}
+
+/// Explain the mass difference between two sequences, see `align delta`
+fn show_delta(args: &Cli, a: &str, b: &str) {
+    let [a, b] = [a, b].map(|s| {
+        Peptidoform::pro_forma(s, custom::database())
+            .unwrap()
+            .into_simple_linear()
+            .unwrap()
+    });
+    delta::show(
+        &a,
+        &b,
+        args.tolerance,
+        args.mass_mode,
+        args.positions.as_deref(),
+        args.combinations,
+        (!args.full_number).then_some(NUMBER_PRECISION),
+    );
+}
+
+/// Find the crosslinkers that explain a precursor mass, see `align crosslink`
+fn crosslink(args: &Cli, mass: f64, a: &str, b: Option<&str>) {
+    let parse = |s: &str| {
+        Peptidoform::pro_forma(s, custom::database())
+            .unwrap()
+            .into_simple_linear()
+            .unwrap()
+    };
+    let candidates = if let Some(b) = b {
+        vec![crosslink::Candidate {
+            peptide: parse(b),
+            protein: None,
+        }]
+    } else {
+        let path = args
+            .second
+            .file
+            .as_ref()
+            .expect("Give a second peptide or a database with --file");
+        let digest = args.digest.unwrap_or(Digest {
+            enzyme: Enzyme::Trypsin,
+            missed_cleavages: 2,
+        });
+        let protease = digest.enzyme.protease();
+        FastaData::parse_file(path)
+            .unwrap()
+            .iter()
+            .flat_map(|fasta| {
+                digest::digest_windows(fasta.peptide(), &protease, digest.missed_cleavages)
+                    .into_iter()
+                    .map(|window| crosslink::Candidate {
+                        peptide: fasta.peptide().sub_peptide(window).into(),
+                        protein: Some(fasta.identifier().to_string()),
+                    })
+            })
+            .collect()
+    };
+    let mass = Mass::new::<dalton>(mass);
+    crosslink::show(
+        mass,
+        args.tolerance.at(mass),
+        &parse(a),
+        &candidates,
+        args.mass_mode,
+        (!args.full_number).then_some(NUMBER_PRECISION),
+    );
+}
+
+/// Digest one or more proteins, see `align digest`
+fn digest_proteins(args: &Cli, input: &str, enzyme: &Enzyme, missed: usize, fasta: bool) {
+    let proteins = if Path::new(input).is_file() {
+        FastaData::parse_file(input)
+            .unwrap()
+            .into_iter()
+            .map(|f| (f.identifier().to_string(), f.peptide().clone().into()))
+            .collect_vec()
+    } else {
+        vec![(
+            "sequence".to_string(),
+            Peptidoform::pro_forma(input, custom::database())
+                .unwrap()
+                .into_simple_linear()
+                .unwrap(),
+        )]
+    };
+    digest::show(
+        &digest::digest(&proteins, &enzyme.protease(), missed, args.fixed.mods()),
+        fasta,
+        (!args.full_number).then_some(NUMBER_PRECISION),
+    );
+}
+
+/// Align two germlines to each other, see `align imgt-compare`
+fn compare_germlines(
+    args: &Cli,
+    first: &(Gene, Option<usize>),
+    second: &(Gene, Option<usize>),
+    species: Option<Species>,
+) {
+    let species = species
+        .or(args.species)
+        .expect("The species has to be given to compare two germlines");
+    let [first, second] = [first, second].map(|(gene, allele)| {
+        imgt::get_germline(species, gene.clone(), *allele)
+            .unwrap_or_else(|| panic!("Could not find germline {gene}"))
+    });
+    let alignment = align(
+        first.sequence,
+        second.sequence,
+        args.scoring(),
+        args.alignment_type.ty(),
+        args.alignment_kind,
+    );
+    for allele in [&first, &second] {
+        println!(
+            "Selected: {} {} {}",
+            allele.species.scientific_name().to_string().purple(),
+            allele.species.common_name(),
+            format!("{} / {}", allele.name(), allele.fancy_name()).purple(),
+        );
+    }
+    show_annotated_mass_alignment(
+        &alignment,
+        Some(&first),
+        None,
+        false,
+        false,
+        (first.name(), second.name()),
+        &args.render_options(),
+    );
+    if args.explain {
+        show_explanation(&alignment, args);
+    }
+}
+
+/// Align B and C to A and show the three way alignment, see `--triple`
+fn triple_alignment(args: &Cli, a: &str, b: &str, c: &str) {
+    let [a, b, c] = [a, b, c].map(|s| {
+        Peptidoform::pro_forma(s, custom::database())
+            .unwrap()
+            .into_simple_linear()
+            .unwrap()
+    });
+    let ab = align(
+        &a,
+        &b,
+        args.scoring(),
+        args.alignment_type.ty(),
+        args.alignment_kind,
+    );
+    let ac = align(
+        &a,
+        &c,
+        args.scoring(),
+        args.alignment_type.ty(),
+        args.alignment_kind,
+    );
+    for (name, alignment) in [("B", &ab), ("C", &ac)] {
+        println!(
+            "A → {name}: Score: {} {}, Identity: {}, Path: {}",
+            format!("{:.3}", alignment.normalised_score()).green(),
+            format!("({}/{})", alignment.score().absolute, alignment.score().max).dimmed(),
+            format!("{:.3}", stats::stats(alignment, &args.stats).identity()).bright_blue(),
+            alignment.short().dimmed()
+        );
+    }
+    println!();
+    triple::show_triple(&triple::merge(&ab, &ac), args.line_width);
+}
+
+/// Show all non overlapping hits of B in A, see `--all-hits`
+fn show_all_hits(args: &Cli, threshold: f64, a: &str, b: &str) {
+    let a = Peptidoform::pro_forma(a, custom::database())
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let b = Peptidoform::pro_forma(b, custom::database())
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let hits = all_hits(&a, &b, threshold, args.align_settings());
+    println!(
+        "{} {}",
+        "All hits of B in A".underline().italic(),
+        format!("({} with normalised score ≥ {threshold})", hits.len()).dimmed()
+    );
+    let mut data = vec![[
+        String::new(),
+        "Start A".to_string(),
+        "End A".to_string(),
+        "Match".to_string(),
+        "Score".to_string(),
+        "Normalised score".to_string(),
+        "Identity".to_string(),
+        "Path".to_string(),
+    ]];
+    for (rank, (start, alignment)) in hits.iter().enumerate() {
+        let start = start + alignment.start_a();
+        data.push([
+            (rank + 1).to_string(),
+            (start + 1).to_string(),
+            (start + alignment.len_a()).to_string(),
+            a.sub_peptide(start..start + alignment.len_a()).to_string(),
+            alignment.score().absolute.to_string(),
+            format!("{:.3}", alignment.normalised_score()),
+            format!(
+                "{:.2}%",
+                stats::stats(alignment, &args.stats).identity() * 100.0
+            ),
+            alignment.short(),
+        ]);
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_style(Styles::Dimmed),
+            Styling::none(),
+            Styling::none(),
+            Styling::none(),
+            Styling::none(),
+            Styling::with_fg(Some(Color::Green)),
+            Styling::none(),
+            Styling::with_style(Styles::Dimmed),
+        ],
+    );
+}
+
+/// Align A to B
+fn pairwise(args: &Cli, a: &str, b: &str) {
+    let a = Peptidoform::pro_forma(a, custom::database())
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let b = Peptidoform::pro_forma(b, custom::database())
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let alignment = if args.circular {
+        let (rotation, alignment) = best_rotation(&a, &b, args.align_settings());
+        println!(
+            "{} {}",
+            "Best rotation of B".underline().italic(),
+            b.sequence()
+                .get(rotation)
+                .map_or_else(
+                    || "(B is empty)".to_string(),
+                    |start| format!(
+                        "(rotated by {rotation}, starting at {}{})",
+                        start.aminoacid.char(),
+                        rotation + 1
+                    )
+                )
+                .dimmed()
+        );
+        alignment
+    } else if args.ambiguous == Some(AmbiguousHandling::Mismatch) {
+        let scoring = args.scoring();
+        let matrix = ambiguous::mismatch_matrix(&scoring);
+        align(
+            &a,
+            &b,
+            AlignScoring {
+                matrix: &matrix,
+                ..scoring
+            },
+            args.alignment_type.ty(),
+            args.alignment_kind,
+        )
+        .to_owned()
+    } else if let Some(handling) = args.ambiguous {
+        let (resolved_a, resolved_b) =
+            match (ambiguous::resolutions(&a), ambiguous::resolutions(&b)) {
+                (Ok(resolved_a), Ok(resolved_b)) => (resolved_a, resolved_b),
+                (Err(error), _) | (_, Err(error)) => {
+                    eprintln!("{error}");
+                    std::process::exit(1);
+                }
+            };
+        if resolved_a.len() * resolved_b.len() > ambiguous::MAX_RESOLUTIONS {
+            eprintln!(
+                "Too many combinations of resolutions for the ambiguous residues in A and B ({} × {}), the maximum is {}",
+                resolved_a.len(),
+                resolved_b.len(),
+                ambiguous::MAX_RESOLUTIONS
+            );
+            std::process::exit(1);
+        }
+        let alignments: Vec<_> = resolved_a
+            .iter()
+            .flat_map(|a| resolved_b.iter().map(move |b| (a, b)))
+            .par_bridge()
+            .map(|(a, b)| {
+                align(
+                    a,
+                    b,
+                    args.scoring(),
+                    args.alignment_type.ty(),
+                    args.alignment_kind,
+                )
+                .to_owned()
+            })
+            .collect();
+        if handling == AmbiguousHandling::Average {
+            println!(
+                "{} {}",
+                "Average over all resolutions".underline().italic(),
+                format!(
+                    "(score {:.2}, normalised score {:.3}, {} resolutions)",
+                    alignments
+                        .iter()
+                        .map(|a| a.score().absolute as f64)
+                        .sum::<f64>()
+                        / alignments.len() as f64,
+                    alignments.iter().map(|a| a.normalised_score()).sum::<f64>()
+                        / alignments.len() as f64,
+                    alignments.len()
+                )
+                .dimmed()
+            );
+        }
+        alignments.into_iter().max().unwrap()
+    } else {
+        align(
+            &a,
+            &b,
+            args.scoring(),
+            args.alignment_type.ty(),
+            args.alignment_kind,
+        )
+    };
+    let ambiguity = args
+        .ambiguity
+        .then(|| ambiguity::find_ambiguity(&alignment, args.scoring()));
+    let confidence = args
+        .confidence
+        .then(|| confidence::confidence(&alignment, args.scoring()));
+    show_annotated_mass_alignment(
+        &alignment,
+        annotated_regions(&a, args).as_ref(),
+        ambiguity
+            .as_ref()
+            .map(|a| StepMarks::Ambiguous(&a.ambiguous_steps))
+            .or(confidence.as_deref().map(StepMarks::Confidence)),
+        false,
+        false,
+        ("A", "B"),
+        &args.render_options(),
+    );
+    if let Some(ambiguity) = &ambiguity {
+        println!(
+            "{} {}",
+            "Co-optimal alignments".underline().italic(),
+            format!(
+                "({} with score {}, {} ambiguous step{})",
+                ambiguity.alignments.len(),
+                alignment.score().absolute,
+                ambiguity.ambiguous_steps.len(),
+                if ambiguity.ambiguous_steps.len() == 1 {
+                    ""
+                } else {
+                    "s"
+                }
+            )
+            .dimmed()
+        );
+        for (index, alignment) in ambiguity.alignments.iter().enumerate() {
+            println!(
+                "{}{}",
+                alignment.short(),
+                if index == 0 { " (shown)" } else { "" }.dimmed()
+            );
+        }
+    }
+    if let Some(confidence) = &confidence {
+        show_confidence(&alignment, confidence);
+    }
+    if args.explain {
+        show_explanation(&alignment, args);
+    }
+    if let Some(direction) = args.transfer_modifications {
+        let transfer = transfer::transfer_modifications(&alignment, direction);
+        println!(
+            "{} {}",
+            "Transferred modifications".underline().italic(),
+            format!("({direction}, {} transferred)", transfer.transferred).dimmed()
+        );
+        println!("{}", transfer.peptide);
+        for lost in transfer.lost {
+            println!("{}", format!("Not transferred: {lost}").yellow());
+        }
+    }
+}
+
+/// Build a tree of all sequences of a database, see `--tree`
+fn database_tree(args: &Cli, method: TreeMethod, path: &str) {
+    let sequences = FastaData::parse_file(path).unwrap();
+    let names: Vec<_> = sequences
+        .iter()
+        .map(|s| s.identifier().to_string())
+        .collect();
+    let distances = tree::distance_matrix(
+        &sequences.iter().map(FastaData::peptide).collect_vec(),
+        args.align_settings(),
+    );
+    let mut data = vec![std::iter::once(String::new())
+        .chain(names.iter().cloned())
+        .collect_vec()];
+    for (name, row) in names.iter().zip(&distances) {
+        data.push(
+            std::iter::once(name.clone())
+                .chain(row.iter().map(|d| format!("{d:.3}")))
+                .collect(),
+        );
+    }
+    table(
+        &data,
+        true,
+        &std::iter::once(Styling::with_style(Styles::Dimmed))
+            .chain(std::iter::repeat_n(Styling::none(), names.len()))
+            .collect_vec(),
+    );
+    println!(
+        "{} {}",
+        "Tree".underline().italic(),
+        format!("({method}, distance is 1 - normalised score)").dimmed()
+    );
+    println!("{}", method.build(&names, &distances));
+}
+
+/// Search multiple queries against a database, see `--queries`
+fn multi_query_search(args: &Cli, queries: &[String], path: &str) {
+    let sequences = FastaData::parse_file(path).unwrap();
+    let queries: Vec<_> = queries
+        .iter()
+        .map(|q| {
+            Peptidoform::pro_forma(q, custom::database())
+                .unwrap()
+                .into_simple_linear()
+                .unwrap()
+        })
+        .collect();
+    let mut coverage: Vec<Option<coverage::Coverage>> =
+        (0..sequences.len()).map(|_| None).collect();
+    let mut data = vec![[
+        String::new(),
+        "Query".to_string(),
+        "Id".to_string(),
+        "Start".to_string(),
+        "Score".to_string(),
+        "Normalised score".to_string(),
+        "Identity".to_string(),
+    ]];
+    for (rank, query) in queries.iter().enumerate() {
+        let best = sequences
+            .par_iter()
+            .enumerate()
+            .map(|(index, seq)| {
+                let alignment = align(
+                    seq.peptide(),
+                    query,
+                    args.scoring(),
+                    args.alignment_type.ty(),
+                    args.alignment_kind,
+                );
+                (index, alignment)
+            })
+            .filter(|s| !s.1.normalised_score().is_nan())
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)));
+        if let Some((index, alignment)) = best {
+            data.push([
+                (rank + 1).to_string(),
+                query.to_string(),
+                sequences[index].identifier().to_string(),
+                (alignment.start_a() + 1).to_string(),
+                alignment.score().absolute.to_string(),
+                format!("{:.3}", alignment.normalised_score()),
+                format!(
+                    "{:.2}%",
+                    stats::stats(&alignment, &args.stats).identity() * 100.0
+                ),
+            ]);
+            coverage[index]
+                .get_or_insert_with(|| coverage::Coverage::new(sequences[index].peptide().len()))
+                .add(&alignment);
+        }
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_style(Styles::Dimmed),
+            Styling::none(),
+            Styling::none(),
+            Styling::none(),
+            Styling::none(),
+            Styling::with_fg(Some(Color::Green)),
+            Styling::none(),
+        ],
+    );
+    for (fasta, coverage) in sequences.iter().zip(coverage) {
+        if let Some(coverage) = coverage {
+            println!();
+            coverage.show(fasta, args.line_width);
+        }
+    }
+}
+
+/// Search a sequence against a database, see `--file`
+fn database_search(args: &Cli, b: &str, path: &str) {
+    assert!(
+        args.sort_by != SortKey::Species,
+        "Sorting by species is only possible for IMGT alignments"
+    );
+    let path = Path::new(path);
+    let search_sequence = Peptidoform::pro_forma(b, custom::database())
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let protease = args.digest.map(|d| d.enzyme.protease());
+    let align_single = |seq: FastaData| -> stream::Hit {
+        let sequence = seq.peptide().clone();
+        if let (Some(protease), Some(digest)) = (&protease, args.digest) {
+            // If no digested peptide can be aligned the whole protein is aligned instead, this
+            // is reported without a window
+            let (alignment, window) =
+                digest::digest_windows(&sequence, protease, digest.missed_cleavages)
+                    .into_iter()
+                    .map(|window| {
+                        let peptide = sequence.sub_peptide(window.clone());
+                        let alignment = align(
+                            &peptide,
+                            &search_sequence,
+                            args.scoring(),
+                            args.alignment_type.ty(),
+                            args.alignment_kind,
+                        )
+                        .to_owned();
+                        (alignment, Some(window))
+                    })
+                    .filter(|(a, _)| !a.normalised_score().is_nan())
+                    .max_by(|a, b| {
+                        a.0.cmp(&b.0).then(
+                            b.1.as_ref()
+                                .map(|w| w.start)
+                                .cmp(&a.1.as_ref().map(|w| w.start)),
+                        )
+                    })
+                    .unwrap_or_else(|| {
+                        (
+                            align(
+                                &sequence,
+                                &search_sequence,
+                                args.scoring(),
+                                args.alignment_type.ty(),
+                                args.alignment_kind,
+                            )
+                            .to_owned(),
+                            None,
+                        )
+                    });
+            (seq, alignment, window)
+        } else {
+            let alignment = align(
+                &sequence,
+                &search_sequence,
+                args.scoring(),
+                args.alignment_type.ty(),
+                args.alignment_kind,
+            );
+            (seq, alignment.to_owned(), None)
+        }
+    };
+    let skipped = std::sync::atomic::AtomicUsize::new(0);
+    let align_batch = |batch: Vec<FastaData>, threshold: f64| -> Vec<stream::Hit> {
+        let hits = if args.fast && args.sort_by == SortKey::Score {
+            let scoring = args.scoring();
+            let (hits, skip) = bound::search_bounded(
+                batch,
+                args.number_of_hits,
+                threshold,
+                |seq| {
+                    bound::normalised_score_bound(
+                        seq.peptide(),
+                        &search_sequence,
+                        &scoring,
+                        args.alignment_type.ty(),
+                    )
+                },
+                align_single,
+                |hit| hit.1.normalised_score(),
+            );
+            skipped.fetch_add(skip, std::sync::atomic::Ordering::Relaxed);
+            hits
+        } else {
+            batch.into_par_iter().map(align_single).collect()
+        };
+        hits.into_iter()
+            .filter(|s| !s.1.normalised_score().is_nan())
+            .collect()
+    };
+    let index = if args.exhaustive || args.decoy.is_some() {
+        None
+    } else {
+        match index::DatabaseIndex::load(path) {
+            Some(Ok(index)) => Some(index),
+            Some(Err(err)) => {
+                eprintln!("{}", format!("Ignoring database index: {err}").yellow());
+                None
+            }
+            None => None,
+        }
+    };
+    let mut hits = stream::TopHits::new(args.number_of_hits, args.sort_by, args.stats);
+    let tolerance = args.tolerance.for_alignment();
+    let indexed = index.is_some_and(|index| {
+        if prefilter::bin_width(tolerance, index.kmers.k) > prefilter::BIN_WIDTH {
+            println!(
+                "{}",
+                "The tolerance is too wide for the k-mers in the database index, the index is not used"
+                    .dimmed()
+            );
+            return false;
+        }
+        let candidates = index.kmers.candidates(&search_sequence);
+        if candidates.is_empty() {
+            return false;
+        }
+        println!(
+            "{}",
+            format!(
+                "Prefilter selected {} of {} database sequences (k-mer length {}, from index), use --exhaustive to align against all",
+                candidates.len(),
+                index.len(),
+                index.kmers.k
+            )
+            .dimmed()
+        );
+        for batch in &candidates.into_iter().sorted().chunks(stream::BATCH_SIZE) {
+            let threshold = hits.threshold();
+            match index.read_records(path, batch) {
+                Ok(batch) => hits.extend(align_batch(batch, threshold)),
+                Err(err) => {
+                    eprintln!(
+                        "{}",
+                        format!("Ignoring database index, searching the full database: {err}")
+                            .yellow()
+                    );
+                    hits = stream::TopHits::new(args.number_of_hits, args.sort_by, args.stats);
+                    return false;
+                }
+            }
+        }
+        true
+    });
+    if !indexed {
+        // Stream the database in batches, only keeping the best hits. If the prefilter does
+        // not select any sequence the database is searched again without prefilter.
+        let search = |seeds: Option<&prefilter::QuerySeeds>, hits: &mut stream::TopHits| {
+            let file = std::fs::File::open(path).unwrap();
+            let mut decoys = args.decoy.map(decoy::DecoyGenerator::new);
+            let (mut total, mut selected) = (0, 0);
+            for batch in
+                &stream::FastaRecords::new(std::io::BufReader::new(file)).chunks(stream::BATCH_SIZE)
+            {
+                let mut batch: Vec<_> = batch
+                    .map(|record| stream::parse_record(&record.unwrap().1).unwrap())
+                    .collect();
+                if let Some(decoys) = &mut decoys {
+                    let generated = decoys.generate(&batch);
+                    batch.extend(generated);
+                }
+                total += batch.len();
+                let batch: Vec<_> = batch
+                    .into_par_iter()
+                    .filter(|seq| seeds.is_none_or(|seeds| seeds.matches(seq.peptide())))
+                    .collect();
+                selected += batch.len();
+                let threshold = hits.threshold();
+                hits.extend(align_batch(batch, threshold));
+            }
+            (total, selected)
+        };
+        let seeds = (!args.exhaustive).then(|| {
+            prefilter::QuerySeeds::new(
+                &search_sequence,
+                args.kmer_length,
+                prefilter::bin_width(tolerance, args.kmer_length),
+            )
+        });
+        let (total, selected) = search(seeds.as_ref(), &mut hits);
+        if seeds.is_some() && selected == 0 {
+            search(None, &mut hits);
+        } else if seeds.is_some() {
+            println!(
+                "{}",
+                format!(
+                    "Prefilter selected {selected} of {total} database sequences (k-mer length {}), use --exhaustive to align against all",
+                    args.kmer_length
+                )
+                .dimmed()
+            );
+        }
+    }
+    let skipped = skipped.into_inner();
+    if skipped > 0 {
+        println!(
+            "{}",
+            format!("Skipped {skipped} database sequences based on the score bound (--fast)")
+                .dimmed()
+        );
+    }
+    let (selected, accepted) = hits.finish(0.01);
+    let (selected, q_values): (Vec<_>, Vec<_>) = selected.into_iter().unzip();
+    let accepted_targets = args.decoy.map(|_| accepted);
+    let mut data = vec![vec![
+        String::new(),
+        "Id".to_string(),
+        "Score".to_string(),
+        "Normalised score".to_string(),
+        "Identity".to_string(),
+        "Mass similarity".to_string(),
+        "Gap".to_string(),
+    ]];
+    let mut styling = vec![
+        Styling::with_style(Styles::Dimmed),
+        Styling::none(),
+        Styling::none(),
+        Styling::with_fg(Some(Color::Green)),
+        Styling::none(),
+        Styling::none(),
+        Styling::none(),
+    ];
+    if args.digest.is_some() {
+        data[0].push("Window".to_string());
+        data[0].push("Peptide".to_string());
+        styling.push(Styling::with_style(Styles::Dimmed));
+        styling.push(Styling::none());
+    }
+    let modes = mass_modes(true, args.mass_mode);
+    if args.all_mass_modes {
+        for mode in &modes {
+            data[0].push(format!("Δ {mode}"));
+            styling.push(Styling::with_fg(Some(Color::Yellow)));
+        }
+    }
+    if args.decoy.is_some() {
+        data[0].push("q-value".to_string());
+        styling.push(Styling::with_fg(Some(Color::Blue)));
+    }
+    for (rank, (fasta, alignment, window)) in selected.iter().enumerate() {
+        let stats = stats::stats(alignment, &args.stats);
+        let mut row = vec![
+            (rank + 1).to_string(),
+            fasta.identifier().to_string(),
+            alignment.score().absolute.to_string(),
+            format!("{:.3}", alignment.normalised_score()),
+            format!("{:.2}%", stats.identity() * 100.0),
+            format!("{:.2}%", stats.mass_similarity() * 100.0),
+            format!("{:.2}%", stats.gaps_fraction() * 100.0),
+        ];
+        if args.digest.is_some() {
+            if let Some(window) = window {
+                row.push(format!("{}-{}", window.start + 1, window.end));
+                row.push(digest::window_notation(fasta.peptide(), window));
+            } else {
+                row.push("whole protein".to_string());
+                row.push("-".to_string());
+            }
+        }
+        if args.all_mass_modes {
+            for mode in &modes {
+                row.push(display_dalton(
+                    mass_difference(alignment, *mode),
+                    (!args.full_number).then_some(NUMBER_PRECISION),
+                ));
+            }
+        }
+        if args.decoy.is_some() {
+            row.push(format!("{:.3}", q_values[rank]));
+        }
+        data.push(row);
+    }
+    table(&data, true, &styling);
+    if let (Some(accepted), Some(kind)) = (accepted_targets, args.decoy) {
+        println!(
+            "Target hits at 1% FDR: {} {}",
+            accepted.to_string().blue(),
+            format!("(decoys generated by {kind} the database sequences)").dimmed()
+        );
+    }
+    if args.consensus {
+        let consensus = consensus::consensus(selected.iter().map(|s| &s.1), search_sequence.len());
+        consensus::show_consensus(&consensus, selected.len(), args.line_width);
+    }
+    if let (Some(digest), Some(window)) = (args.digest, &selected[0].2) {
+        println!(
+            "{} ({} {})",
+            "Alignment for the best match".underline().italic(),
+            selected[0].0.identifier().to_string().dimmed(),
+            format!(
+                "{}-{}, digested with {} allowing {} missed cleavages",
+                window.start + 1,
+                window.end,
+                digest.enzyme,
+                digest.missed_cleavages
+            )
+            .dimmed()
+        );
+    } else if args.digest.is_some() {
+        println!(
+            "{} ({} {})",
+            "Alignment for the best match".underline().italic(),
+            selected[0].0.identifier().to_string().dimmed(),
+            "whole protein, no digested peptide could be aligned".dimmed()
+        );
+    } else {
+        println!(
+            "{} ({})",
+            "Alignment for the best match".underline().italic(),
+            selected[0].0.identifier().to_string().dimmed()
+        );
+    }
+    show_annotated_mass_alignment(
+        &selected[0].1,
+        selected[0].2.is_none().then_some(&selected[0].0),
+        None,
+        false,
+        false,
+        (&selected[0].0.identifier().to_string(), "Query"),
+        &args.render_options(),
+    );
+}
+
+/// Annotate a heavy and light chain pair, see `--heavy` and `--light`
+fn paired_chains(args: &Cli, heavy: &str, light: &str) {
+    let [heavy, light] = [heavy, light].map(|s| {
+        Peptidoform::pro_forma(s, custom::database())
+            .unwrap()
+            .into_simple_linear()
+            .unwrap()
+    });
+    let chains = [
+        ("Heavy", &heavy, HashSet::from([ChainType::Heavy])),
+        (
+            "Light",
+            &light,
+            HashSet::from([ChainType::LightKappa, ChainType::LightLambda]),
+        ),
+    ]
+    .map(|(name, query, chains)| {
+        let genes = consecutive_align(
+            query,
+            &DomainSettings {
+                chains: Some(chains),
+                return_number: 1,
+                ..args.domain_settings()
+            },
+        )
+        .0
+        .alignments
+        .into_iter()
+        .filter_map(|options| options.into_iter().next())
+        .collect_vec();
+        println!("{}", format!("{name} chain").underline().italic());
+        show_chained_annotated_mass_alignment(
+            &genes,
+            None,
+            args.generate_annotation,
+            &args.render_options(),
+        );
+        println!();
+        paired::PairedChain::new(name, query, genes)
+    });
+    paired::show_summary(&chains[0], &chains[1]);
+    println!("{}", paired::json(&chains[0], &chains[1]));
+}
+
+/// Determine the isotype of a sequence, see `--isotype`
+fn show_isotype(args: &Cli, x: &str) {
+    let query = Peptidoform::pro_forma(x, custom::database())
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let hits = isotype::per_isotype(
+        &query,
+        args.species.map(|s| HashSet::from([s])),
+        args.chains.clone(),
+        args.allele,
+        args.scoring(),
+        args.alignment_kind,
+    );
+    let mut data = vec![[
+        String::new(),
+        "Isotype".to_string(),
+        "Species".to_string(),
+        "IMGT name".to_string(),
+        "Score".to_string(),
+        "Normalised score".to_string(),
+        "Identity".to_string(),
+    ]];
+    for (rank, (isotype, imgt, alignment)) in hits.iter().take(args.number_of_hits).enumerate() {
+        data.push([
+            (rank + 1).to_string(),
+            isotype.clone(),
+            imgt.species.scientific_name().to_string(),
+            imgt.name(),
+            alignment.score().absolute.to_string(),
+            format!("{:.3}", alignment.normalised_score()),
+            format!(
+                "{:.2}%",
+                stats::stats(alignment, &args.stats).identity() * 100.0
+            ),
+        ]);
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_style(Styles::Dimmed),
+            Styling::with_fg(Some(Color::Magenta)),
+            Styling::none(),
+            Styling::none(),
+            Styling::none(),
+            Styling::with_fg(Some(Color::Green)),
+            Styling::none(),
+        ],
+    );
+    if let Some((isotype, imgt, alignment)) = hits.first() {
+        println!(
+            "Isotype: {} {}",
+            isotype.purple(),
+            format!("({} {})", imgt.species.common_name(), imgt.name()).dimmed()
+        );
+        show_annotated_mass_alignment(
+            alignment,
+            Some(imgt),
+            None,
+            false,
+            false,
+            (imgt.name(), "Query"),
+            &args.render_options(),
+        );
+        if let Some((next, next_imgt, next_alignment)) = hits.get(1) {
+            println!(
+                "{} {}",
+                "Distinguishing positions".underline().italic(),
+                format!("({isotype} versus {next})").dimmed()
+            );
+            compare::show_comparison(
+                &query
+                    .sequence()
+                    .iter()
+                    .map(|s| s.aminoacid.char())
+                    .collect_vec(),
+                &compare::project(alignment),
+                &compare::project(next_alignment),
+                (&imgt.name(), &next_imgt.name()),
+                args.line_width,
+            );
+        }
+    } else {
+        println!("Could not find any constant genes for the selection")
+    }
+}
+
+/// Score how human a sequence is, see `--humanness`
+fn show_humanness(args: &Cli, x: &str) {
+    let query = Peptidoform::pro_forma(x, custom::database())
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    if let Some(humanness) = humanness::humanness(
+        &query,
+        args.chains.clone(),
+        args.scoring(),
+        args.alignment_kind,
+    ) {
+        humanness.show();
+        println!();
+        show_annotated_mass_alignment(
+            &humanness.alignment,
+            Some(&humanness.germline),
+            None,
+            false,
+            false,
+            (humanness.germline.name(), "Query"),
+            &args.render_options(),
+        );
+    } else {
+        println!("Could not find any human germlines for the selected chains")
+    }
+}
+
+/// Place a sequence on the germline tree, see `--placement`
+fn show_placement(args: &Cli, x: &str, method: TreeMethod) {
+    let query = Peptidoform::pro_forma(x, custom::database())
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let germlines: Vec<_> = Selection {
+        species: args.species.map(|s| HashSet::from([s])),
+        chains: args.chains.clone(),
+        genes: Some(HashSet::from([GeneType::V])),
+        allele: args.allele,
+    }
+    .germlines()
+    .filter(|allele| args.matches_gene_filter(allele))
+    .collect();
+    let sequences = std::iter::once(query)
+        .chain(
+            germlines
+                .iter()
+                .map(|g| g.sequence.clone().into_simple_linear().unwrap()),
+        )
+        .collect_vec();
+    let names = std::iter::once("Query".to_string())
+        .chain(germlines.iter().map(|g| g.name()))
+        .collect_vec();
+    let distances = tree::distance_matrix(&sequences.iter().collect_vec(), args.align_settings());
+    if let Some((index, distance)) = distances[0]
+        .iter()
+        .enumerate()
+        .skip(1)
+        .min_by(|a, b| a.1.total_cmp(b.1))
+    {
+        println!(
+            "Closest germline: {} {}",
+            names[index].green(),
+            format!("(distance {distance:.3})").dimmed()
+        );
+    }
+    println!(
+        "{} {}",
+        "Placement".underline().italic(),
+        format!(
+            "({method} of the query and {} V germlines, distance is 1 - normalised score)",
+            germlines.len()
+        )
+        .dimmed()
+    );
+    if let Some(tree) = method.build_tree(&names, &distances) {
+        for line in tree.dendrogram(40, |name| {
+            if name == "Query" {
+                name.green().bold().to_string()
+            } else {
+                name.to_string()
+            }
+        }) {
+            println!("{line}");
+        }
+        println!("{}", format!("{tree};").dimmed());
+    }
+}
+
+/// Find the best germline for every species, see `--compare-species`
+fn compare_species(args: &Cli, x: &str) {
+    let query = Peptidoform::pro_forma(x, custom::database())
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let mut hits: Vec<_> = args
+        .compare_species
+        .iter()
+        .map(|species| {
+            let best = Selection {
+                species: Some(HashSet::from([*species])),
+                chains: args.chains.clone(),
+                genes: args.genes.clone(),
+                allele: args.allele,
+            }
+            .par_germlines()
+            .filter(|allele| args.matches_gene_filter(allele))
+            .map(|germline| {
+                let alignment = align(
+                    germline.sequence,
+                    &query,
+                    args.scoring(),
+                    args.alignment_type.ty(),
+                    args.alignment_kind,
+                );
+                (germline, alignment)
+            })
+            .filter(|s| !s.1.normalised_score().is_nan())
+            .min_by(|a, b| stats::compare_hits(&a.1, &b.1, args.sort_by, &args.stats));
+            (species, best)
+        })
+        .collect();
+    hits.sort_by(|a, b| match (&a.1, &b.1) {
+        (Some(a), Some(b)) => stats::compare_hits(&a.1, &b.1, args.sort_by, &args.stats),
+        (a, b) => b.is_some().cmp(&a.is_some()),
+    });
+    let mut data = vec![[
+        String::new(),
+        "Species".to_string(),
+        "Common name".to_string(),
+        "IMGT name".to_string(),
+        "Score".to_string(),
+        "Normalised score".to_string(),
+        "Identity".to_string(),
+        "Mass similarity".to_string(),
+    ]];
+    for (rank, (species, best)) in hits.iter().enumerate() {
+        let mut row = [
+            (rank + 1).to_string(),
+            species.scientific_name().to_string(),
+            species.common_name().to_string(),
+            "No germlines".to_string(),
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+        ];
+        if let Some((imgt, alignment)) = best {
+            let stats = stats::stats(alignment, &args.stats);
+            row[3] = imgt.name();
+            row[4] = alignment.score().absolute.to_string();
+            row[5] = format!("{:.3}", alignment.normalised_score());
+            row[6] = format!("{:.2}%", stats.identity() * 100.0);
+            row[7] = format!("{:.2}%", stats.mass_similarity() * 100.0);
+        }
+        data.push(row);
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_style(Styles::Dimmed),
+            Styling::with_style(Styles::Italic),
+            Styling::with_fg(Some(Color::Magenta)),
+            Styling::none(),
+            Styling::none(),
+            Styling::with_fg(Some(Color::Green)),
+            Styling::none(),
+            Styling::none(),
+        ],
+    );
+    if let [(species, Some(first)), rest @ ..] = hits.as_slice() {
+        let gap = rest
+            .first()
+            .and_then(|(_, next)| next.as_ref())
+            .map_or(String::new(), |next| {
+                format!(
+                    " (normalised score {:.3} higher than the next species)",
+                    first.1.normalised_score() - next.1.normalised_score()
+                )
+            });
+        println!(
+            "Most likely species: {}{}",
+            species.scientific_name().italic(),
+            gap.dimmed()
+        );
+    }
+}
+
+/// Align a sequence against the selected IMGT germlines, see `--imgt`
+fn imgt_search(args: &Cli, x: &str) {
+    let seq_b = Peptidoform::pro_forma(x, custom::database())
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let germlines = Selection {
+        species: args.species.map(|s| HashSet::from([s])),
+        chains: args.chains.clone(),
+        genes: args.genes.clone(),
+        allele: args.allele,
+    }
+    .par_germlines()
+    .filter(|allele| args.matches_gene_filter(allele));
+    let align_single = |seq: Allele<'static>| {
+        let alignment = align(
+            seq.sequence,
+            &seq_b,
+            args.scoring(),
+            args.alignment_type.ty(),
+            args.alignment_kind,
+        );
+        (seq, alignment)
+    };
+    let mut alignments: Vec<_> = if args.fast
+        && args.sort_by == SortKey::Score
+        && !args.group_by_gene
+        && args.region_weights.is_none()
+        && !args.per_region_hits
+    {
+        let scoring = args.scoring();
+        let (alignments, skipped) = bound::search_bounded(
+            germlines.collect(),
+            args.number_of_hits,
+            f64::NEG_INFINITY,
+            |seq| {
+                bound::normalised_score_bound(
+                    seq.sequence,
+                    &seq_b,
+                    &scoring,
+                    args.alignment_type.ty(),
+                )
+            },
+            align_single,
+            |(_, alignment)| alignment.normalised_score(),
+        );
+        if skipped > 0 {
+            println!(
+                "{}",
+                format!("Skipped {skipped} germlines based on the score bound (--fast)").dimmed()
+            );
+        }
+        alignments
+            .into_iter()
+            .filter(|s| !s.1.normalised_score().is_nan())
+            .collect()
+    } else {
+        germlines
+            .map(align_single)
+            .filter(|s| !s.1.normalised_score().is_nan())
+            .collect()
+    };
+    if args.sort_by == SortKey::Species {
+        alignments.sort_unstable_by(|a, b| {
+            a.0.species
+                .scientific_name()
+                .cmp(b.0.species.scientific_name())
+                .then_with(|| stats::compare_hits(&a.1, &b.1, args.sort_by, &args.stats))
+        });
+    } else if let (Some(weights), SortKey::Score) = (&args.region_weights, args.sort_by) {
+        alignments.sort_unstable_by(|a, b| {
+            stats::weighted_score(&b.1, b.0.regions, weights).total_cmp(&stats::weighted_score(
+                &a.1,
+                a.0.regions,
+                weights,
+            ))
+        });
+    } else {
+        alignments
+            .sort_unstable_by(|a, b| stats::compare_hits(&a.1, &b.1, args.sort_by, &args.stats));
+    }
+    if args.group_by_gene {
+        let mut seen = HashSet::new();
+        alignments.retain(|(imgt, _)| seen.insert((imgt.species, imgt.gene.to_string())));
+    }
+    let per_region = args.per_region_hits.then(|| {
+        stats::best_per_region(alignments.iter().map(|(g, a)| (g.regions, a)), &args.stats)
+            .into_iter()
+            .map(|(region, index, stats)| {
+                let overall =
+                    stats::region_stats(&alignments[0].1, alignments[0].0.regions, &args.stats)
+                        .into_iter()
+                        .find(|(r, _)| *r == region)
+                        .map(|(_, s)| s);
+                (region, alignments[index].0.clone(), stats, overall)
+            })
+            .collect_vec()
+    });
+    let selected: Vec<_> = alignments.into_iter().take(args.number_of_hits).collect();
+    // With all alleles check the positions that distinguish every hit from the other alleles of its gene
+    let support = selected
+        .iter()
+        .map(|(imgt, alignment)| {
+            (args.allele == AlleleSelection::All).then(|| {
+                let others = Selection {
+                    species: Some(HashSet::from([imgt.species])),
+                    chains: Some(HashSet::from([imgt.gene.chain])),
+                    genes: Some(HashSet::from([imgt.gene.kind])),
+                    allele: AlleleSelection::All,
+                }
+                .germlines()
+                .filter(|a| a.gene == imgt.gene && a.number != imgt.number)
+                .collect_vec();
+                let positions = alleles::distinguishing_positions(imgt, &others);
+                let support = alleles::allele_support(alignment, &positions);
+                (positions, support)
+            })
+        })
+        .collect_vec();
+    let mut data = vec![vec![
+        String::new(),
+        "Species".to_string(),
+        "IMGT name".to_string(),
+        "Alternative name".to_string(),
+        "Score".to_string(),
+        if args.region_weights.is_some() {
+            "Normalised (weighted)".to_string()
+        } else {
+            "Normalised score".to_string()
+        },
+        "Identity".to_string(),
+        "Mass similarity".to_string(),
+        "Gap".to_string(),
+    ]];
+    if args.allele == AlleleSelection::All {
+        data[0].push("Allele support".to_string());
+    }
+    for (rank, (imgt, alignment)) in selected.iter().enumerate() {
+        let stats = stats::stats(alignment, &args.stats);
+        let weighted = args
+            .region_weights
+            .as_ref()
+            .map_or(String::new(), |weights| {
+                format!(
+                    " ({:.3})",
+                    stats::weighted_score(alignment, imgt.regions, weights)
+                )
+            });
+        let mut row = vec![
+            (rank + 1).to_string(),
+            imgt.species.scientific_name().to_string(),
+            imgt.name(),
+            imgt.fancy_name(),
+            alignment.score().absolute.to_string(),
+            format!("{:.3}{weighted}", alignment.normalised_score()),
+            format!("{:.2}%", stats.identity() * 100.0),
+            format!("{:.2}%", stats.mass_similarity() * 100.0),
+            format!("{:.2}%", stats.gaps_fraction() * 100.0),
+        ];
+        if let Some((positions, support)) = &support[rank] {
+            row.push(if positions.is_empty() {
+                "single allele".to_string()
+            } else {
+                format!(
+                    "{}/{}",
+                    support.iter().filter(|s| s.2).count(),
+                    positions.len()
+                )
+            });
+        }
+        data.push(row);
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_style(Styles::Dimmed),
+            Styling::none(),
+            Styling::none(),
+            Styling::with_style(Styles::Dimmed),
+            Styling::none(),
+            Styling::with_fg(Some(Color::Green)),
+            Styling::none(),
+            Styling::none(),
+            Styling::none(),
+            Styling::none(),
+        ],
+    );
+    if args.consensus {
+        let consensus = consensus::consensus(selected.iter().map(|s| &s.1), seq_b.len());
+        consensus::show_consensus(&consensus, selected.len(), args.line_width);
+    }
+    println!(
+        "{} ({} {} {})",
+        "Alignment for the best match".underline().italic(),
+        selected[0].0.species.scientific_name().dimmed(),
+        selected[0].0.species.common_name().dimmed(),
+        format!("{} / {}", selected[0].0.name(), selected[0].0.fancy_name()).dimmed(),
+    );
+    let marks: Option<HashMap<usize, bool>> = support[0].as_ref().map(|(_, support)| {
+        support
+            .iter()
+            .fold(HashMap::new(), |mut marks, (step, _, supported)| {
+                *marks.entry(*step).or_insert(true) &= supported;
+                marks
+            })
+    });
+    show_annotated_mass_alignment(
+        &selected[0].1,
+        Some(&selected[0].0),
+        marks.as_ref().map(StepMarks::Alleles),
+        false,
+        false,
+        (selected[0].0.name(), "Query"),
+        &args.render_options(),
+    );
+    if let Some((positions, support)) = support[0].as_ref().filter(|(p, _)| !p.is_empty()) {
+        println!(
+            "{} {}",
+            "Distinguishing positions".underline().italic(),
+            format!(
+                "({} of the {} positions that distinguish {} from the other alleles have the residue of this allele, marked with *)",
+                support.iter().filter(|s| s.2).count(),
+                positions.len(),
+                selected[0].0.name()
+            )
+            .dimmed()
+        );
+        println!(
+            "{}",
+            positions
+                .iter()
+                .map(|(position, residue, others)| {
+                    let text = format!(
+                        "{residue}{} (other alleles: {})",
+                        position + 1,
+                        others.iter().join("")
+                    );
+                    match support.iter().find(|s| s.1 == *position) {
+                        Some((_, _, true)) => text.green().to_string(),
+                        Some((_, _, false)) => text.red().to_string(),
+                        None => text.dimmed().to_string(),
+                    }
+                })
+                .join(", ")
+        );
+    }
+    if args.mutations {
+        mutations::show_mutations(
+            &selected[0].0,
+            &mutations::mutations(&selected[0].0, &selected[0].1),
+            0,
+        );
+    }
+    if let Some(per_region) = per_region {
+        println!("{}", "Best germline per region".underline().italic());
+        let mut data = vec![[
+            "Region".to_string(),
+            "Germline".to_string(),
+            "Identity".to_string(),
+            format!("Identity {}", selected[0].0.name()),
+            String::new(),
+        ]];
+        let identity = |stats: &rustyms::align::Stats| {
+            format!(
+                "{:.2}% ({}/{})",
+                stats.identity() * 100.0,
+                stats.identical,
+                stats.length
+            )
+        };
+        for (region, germline, stats, overall) in &per_region {
+            data.push([
+                region.to_string(),
+                germline.name(),
+                identity(stats),
+                overall.as_ref().map_or(String::new(), identity),
+                if germline.gene == selected[0].0.gene {
+                    String::new()
+                } else {
+                    "other gene".to_string()
+                },
+            ]);
+        }
+        table(
+            &data,
+            true,
+            &[
+                Styling::none(),
+                Styling::none(),
+                Styling::with_fg(Some(Color::Green)),
+                Styling::none(),
+                Styling::with_fg(Some(Color::Yellow)),
+            ],
+        );
+        if per_region.iter().any(|(region, germline, _, _)| {
+            matches!(region, Region::Framework(_)) && germline.gene != selected[0].0.gene
+        }) {
+            println!(
+                "{}",
+                "Some frameworks are closest to another gene than the best overall hit, this could indicate a hybrid or recombined sequence"
+                    .yellow()
+            );
+        }
+    }
+}
+
+/// Align a sequence against all segments of the selected IMGT germlines, see `--domain`
+fn domain_alignment(args: &Cli, x: &str) {
+    let query = Peptidoform::pro_forma(x, custom::database())
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let settings = args.domain_settings();
+    let (scores, absent) = consecutive_align(&query, &settings);
+    if !absent.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "Absent segments: {} (no alignment with a normalised score of at least {})",
+                absent.iter().join(", "),
+                args.segment_threshold
+            )
+            .yellow()
+        );
+    }
+    if scores.alignments.is_empty() {
+        println!("{}", "None of the segments could be aligned".red());
+        return;
+    }
+
+    for gene in &scores.alignments {
+        let mut data = vec![[
+            String::new(),
+            "Species".to_string(),
+            "IMGT name".to_string(),
+            "Alternative name".to_string(),
+            "Score".to_string(),
+            "Normalised score".to_string(),
+            "Identity".to_string(),
+            "Mass similarity".to_string(),
+            "Gap".to_string(),
+        ]];
+        for (rank, (imgt, alignment)) in gene.iter().enumerate() {
+            let stats = stats::stats(alignment, &args.stats);
+            data.push([
+                (rank + 1).to_string(),
+                imgt.species.scientific_name().to_string(),
+                imgt.name(),
+                imgt.fancy_name(),
+                alignment.score().absolute.to_string(),
+                format!("{:.3}", alignment.normalised_score()),
+                format!("{:.2}%", stats.identity() * 100.0),
+                format!("{:.2}%", stats.mass_similarity() * 100.0),
+                format!("{:.2}%", stats.gaps_fraction() * 100.0),
+            ]);
+        }
+        table(
+            &data,
+            true,
+            &[
+                Styling::with_style(Styles::Dimmed),
+                Styling::none(),
+                Styling::none(),
+                Styling::with_style(Styles::Dimmed),
+                Styling::none(),
+                Styling::with_fg(Some(Color::Green)),
+                Styling::none(),
+                Styling::none(),
+                Styling::none(),
+            ],
+        );
+    }
+
+    let tops = scores
+        .alignments
+        .into_iter()
+        .map(|options| options[0].clone())
+        .collect_vec();
+    let numbering = args
+        .numbering
+        .map(|scheme| numbering::number(&generate_regions(&tops), tops[0].0.gene.chain, scheme));
+    show_chained_annotated_mass_alignment(
+        &tops,
+        numbering.as_deref(),
+        args.generate_annotation,
+        &args.render_options(),
+    );
+    if let Some(ty) = settings
+        .segments
+        .iter()
+        .find_map(|(gene, ty)| matches!(gene, GeneType::C(_)).then_some(*ty))
+    {
+        if let Some(chimera) = chimera::detect(
+            &query,
+            &tops,
+            ty,
+            args.allele,
+            args.scoring(),
+            args.alignment_kind,
+            args.chimera_margin,
+        ) {
+            chimera.show();
+            if args.generate_annotation {
+                println!("CHIMERA={}", chimera.json());
+            }
+        }
+    }
+    if args.imgt_gaps {
+        let germline = tops
+            .iter()
+            .filter(|(a, _)| !matches!(a.gene.kind, GeneType::C(_)))
+            .flat_map(|(a, _)| {
+                a.sequence
+                    .sequence()
+                    .iter()
+                    .map(|s| s.aminoacid.char())
+                    .zip(numbering::germline_numbering(a))
+                    .collect_vec()
+            })
+            .fold(Vec::<(char, Option<String>)>::new(), |mut acc, (c, l)| {
+                // Where the V and J germlines overlap in the CDR3 the V is used
+                if l.is_none() || !acc.iter().any(|(_, a)| *a == l) {
+                    acc.push((c, l));
+                }
+                acc
+            });
+        let query_row = query
+            .sequence()
+            .iter()
+            .map(|s| s.aminoacid.char())
+            .zip(numbering::number(
+                &generate_regions(&tops),
+                tops[0].0.gene.chain,
+                NumberingScheme::Imgt,
+            ))
+            .collect_vec();
+        let germline_name = tops
+            .iter()
+            .filter(|(a, _)| !matches!(a.gene.kind, GeneType::C(_)))
+            .map(|(a, _)| a.name())
+            .join(" / ");
+        let (columns, padded) = numbering::imgt_gapped(&[germline, query_row]);
+        show_imgt_gapped(
+            &columns,
+            &padded,
+            &[&germline_name, "Query"],
+            args.line_width,
+        );
+    }
+    if args.mutations {
+        let mut offset = 0;
+        for (allele, alignment) in &tops {
+            mutations::show_mutations(allele, &mutations::mutations(allele, alignment), offset);
+            offset += alignment.len_b() + alignment.start_b();
+        }
+    }
+    if args.junction {
+        match junction::analyse(&query, &tops, &generate_regions(&tops)) {
+            Some(junction) => {
+                junction.show();
+                if args.generate_annotation {
+                    println!("JUNCTION={}", junction.json());
+                }
+            }
+            None => println!("{}", "No CDR3 found in the query".red()),
+        }
+    }
+    if args.liabilities {
+        let regions = generate_regions(&tops);
+        let found = liabilities::scan(
+            &query
+                .sequence()
+                .iter()
+                .map(|s| s.aminoacid.char())
+                .collect_vec(),
+            &regions,
+            &numbering::number(&regions, tops[0].0.gene.chain, NumberingScheme::Imgt),
+        );
+        liabilities::show(&found);
+        if args.generate_annotation {
+            println!("LIABILITIES={}", liabilities::annotation(&found));
+        }
+    }
+    if args.extract_cdrs {
+        let regions = cdr::extract_regions(&query, &generate_regions(&tops));
+        let mut data = vec![[
+            "Region".to_string(),
+            "Start".to_string(),
+            "End".to_string(),
+            "Length".to_string(),
+            "Sequence".to_string(),
+        ]];
+        for region in &regions {
+            data.push([
+                region.region.to_string(),
+                (region.range.start + 1).to_string(),
+                region.range.end.to_string(),
+                region.range.len().to_string(),
+                region.peptide.to_string(),
+            ]);
+        }
+        table(
+            &data,
+            true,
+            &[
+                Styling::none(),
+                Styling::with_style(Styles::Dimmed),
+                Styling::with_style(Styles::Dimmed),
+                Styling::with_style(Styles::Dimmed),
+                Styling::none(),
+            ],
+        );
+        for region in &regions {
+            println!(">Query|{}\n{}", region.region, region.peptide);
+        }
+    }
+    if let Some((gene, allele)) = &args.graft {
+        let acceptor = imgt::get_germline(Species::HomoSapiens, gene.clone(), *allele)
+            .unwrap_or_else(|| panic!("Could not find human germline {gene}"));
+        let regions = cdr::extract_regions(&query, &generate_regions(&tops));
+        match cdr::graft(&regions, &acceptor) {
+            Some(graft) => {
+                println!(
+                    "{} {}",
+                    "Graft".underline().italic(),
+                    format!("(CDRs of the query on {})", acceptor.name()).dimmed()
+                );
+                println!("ProForma: {graft}");
+                println!(
+                    ">Graft|{}\n{}",
+                    acceptor.name(),
+                    graft
+                        .sequence()
+                        .iter()
+                        .map(|s| s.aminoacid.char())
+                        .collect::<String>()
+                );
+                println!();
+                let to_acceptor = align(
+                    acceptor.sequence,
+                    &graft,
+                    args.scoring(),
+                    args.alignment_type.ty(),
+                    args.alignment_kind,
+                );
+                show_annotated_mass_alignment(
+                    &to_acceptor,
+                    Some(&acceptor),
+                    None,
+                    false,
+                    false,
+                    (acceptor.name(), "Graft"),
+                    &args.render_options(),
+                );
+                println!();
+                let to_query = align(
+                    &query,
+                    &graft,
+                    args.scoring(),
+                    args.alignment_type.ty(),
+                    args.alignment_kind,
+                );
+                show_annotated_mass_alignment::<_, _, Allele>(
+                    &to_query,
+                    None,
+                    None,
+                    false,
+                    false,
+                    ("Query", "Graft"),
+                    &args.render_options(),
+                );
+            }
+            None => println!(
+                "{}",
+                "Could not graft, the query misses a CDR or the acceptor misses a framework".red()
+            ),
+        }
+    }
+}
+
+/// Align a sequence against one germline, see `--specific-gene`
+fn specific_germline_alignment(
+    args: &Cli,
+    x: &str,
+    gene: &Gene,
+    allele: Option<usize>,
+    species: Species,
+) {
+    if let Some(allele) = imgt::get_germline(species, gene.clone(), allele) {
+        let b = Peptidoform::pro_forma(x, custom::database())
+            .unwrap()
+            .into_simple_linear()
+            .unwrap();
+        let alignment = align(
+            allele.sequence,
+            &b,
+            args.scoring(),
+            args.alignment_type.ty(),
+            args.alignment_kind,
+        );
+        println!(
+            "Selected: {} {} {}",
+            allele.species.scientific_name().to_string().purple(),
+            allele.species.common_name(),
+            format!("{} / {}", allele.name(), allele.fancy_name()).purple(),
+        );
+        show_annotated_mass_alignment(
+            &alignment,
+            Some(&allele),
+            None,
+            false,
+            false,
+            (allele.name(), "Query"),
+            &args.render_options(),
+        );
+        if args.mutations {
+            mutations::show_mutations(&allele, &mutations::mutations(&allele, &alignment), 0);
+        }
+    } else {
+        println!("Could not find specified germline")
+    }
+}
+
+/// Show the details of a modification, see `--modification`
+fn show_modification(args: &Cli, modification: &SimpleModification) {
+    modification_stats(
+        modification,
+        args.tolerance,
+        args.full_number,
+        args.mass_mode,
+        args.all_mass_modes,
+        args.positions.as_deref(),
+        args.combinations,
+        args.snfg,
+        &gnome_filter(args),
+    );
+    if args.isotopes {
+        println!();
+        if modification.formula().is_empty() {
+            println!(
+                "{}",
+                "No isotope distribution for a modification without a formula".red()
+            );
+        } else {
+            isotopes::show(
+                &modification.formula(),
+                (!args.full_number).then_some(NUMBER_PRECISION),
+            );
+        }
+    }
+}
+
+/// Score every row of a csv file, see `--csv` and `--csv-search`
+fn csv_file(args: &Cli, file: &str) {
+    // For --csv-search all rows are searched against the same database
+    let targets: Option<Vec<(String, Peptidoform<SimpleLinear>)>> =
+        args.csv_search.as_ref().map(|_| {
+            if let Some(path) = &args.second.file {
+                FastaData::parse_file(path)
+                    .unwrap()
+                    .into_iter()
+                    .map(|fasta| {
+                        (
+                            fasta.identifier().to_string(),
+                            fasta.peptide().clone().cast(),
+                        )
+                    })
+                    .collect()
+            } else if args.second.imgt {
+                Selection {
+                    species: args.species.map(|s| HashSet::from([s])),
+                    chains: args.chains.clone(),
+                    genes: args.genes.clone(),
+                    allele: args.allele,
+                }
+                .germlines()
+                .filter(|allele| args.matches_gene_filter(allele))
+                .map(|allele| {
+                    (
+                        format!("{} {}", allele.species.scientific_name(), allele.name()),
+                        allele.sequence.clone().cast(),
+                    )
+                })
+                .collect()
+            } else {
+                panic!("--csv-search needs a database to search in, give --file or --imgt")
+            }
+        });
+    let delimiter = args.delimiter.unwrap_or_else(|| file_delimiter(file));
+    let csv =
+        rustyms::csv::parse_csv(file, delimiter as u8, None).expect("Failed to parse CSV file");
+    let stdout = args.output.as_deref() == Some("-");
+    if stdout && args.resume {
+        panic!("A --csv run written to stdout cannot be resumed, give a file with --output");
+    }
+    let output_path = args.output.as_ref().filter(|_| !stdout).map_or_else(
+        || {
+            Path::new(file).with_file_name(
+                Path::new(file)
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string()
+                    + "_output.csv",
+            )
+        },
+        PathBuf::from,
+    );
+    let fingerprint = match csv_fingerprint(args, file) {
+        Ok(fingerprint) => fingerprint,
+        Err(err) => {
+            eprintln!("{}", err.red());
+            std::process::exit(1);
+        }
+    };
+    let checkpoint = if args.resume {
+        match checkpoint::Checkpoint::load(&output_path, fingerprint.clone()) {
+            Some(Ok(checkpoint)) => Some(checkpoint),
+            Some(Err(err)) => {
+                eprintln!("{}", err.red());
+                std::process::exit(1);
+            }
+            None => None,
+        }
+    } else {
+        None
+    };
+    let output: Box<dyn Write> = if stdout {
+        Box::new(std::io::stdout().lock())
+    } else if let Some(checkpoint) = &checkpoint {
+        println!(
+            "{}",
+            format!("Resuming after {} rows", checkpoint.rows).dimmed()
+        );
+        Box::new(checkpoint.resume(&output_path).unwrap())
+    } else {
+        Box::new(std::fs::File::create(&output_path).unwrap())
+    };
+    let mut checkpoint =
+        checkpoint.unwrap_or_else(|| checkpoint::Checkpoint::new(&output_path, fingerprint));
+    let mut writer = BufWriter::new(output);
+    let mut rows = checkpoint.rows;
+    let mut first = checkpoint.bytes == 0;
+    // The rows are scored in parallel per checkpoint interval, and written in the input order
+    let lines = csv
+        .map(|line| line.expect("Failed to read CSV line"))
+        .skip(checkpoint.rows);
+    for chunk in &lines.chunks(checkpoint::CHECKPOINT_INTERVAL) {
+        let chunk = chunk.collect_vec();
+        if first {
+            let header = csv_header(args, chunk[0].headers(), targets.is_some());
+            writeln!(writer, "{}", header.join(&delimiter.to_string())).unwrap();
+            first = false;
+        }
+        let scored: Vec<String> = chunk
+            .par_iter()
+            .map(|line| match &targets {
+                Some(targets) => search_csv_line(args, line, targets, delimiter),
+                None => score_csv_line(args, line, delimiter),
+            })
+            .collect();
+        for row in &scored {
+            writeln!(writer, "{row}").unwrap();
+        }
+        rows += chunk.len();
+        if !stdout && rows % checkpoint::CHECKPOINT_INTERVAL == 0 {
+            writer.flush().unwrap();
+            checkpoint.rows = rows;
+            checkpoint.bytes = std::fs::metadata(&output_path).unwrap().len();
+            checkpoint.save().unwrap();
+        }
+    }
+    writer.flush().unwrap();
+    if stdout {
+        eprintln!("{}", format!("Written {rows} rows to stdout").dimmed());
+    } else {
+        checkpoint.remove().unwrap();
+        println!(
+            "{}",
+            format!("Written {rows} rows to {}", output_path.to_string_lossy()).dimmed()
+        );
+    }
+}
+
+/// The fingerprint of a `--csv` run, from the csv file, the `--csv-search` database, and all settings that change the
+/// output, so `--resume` only continues the same run
+fn csv_fingerprint(args: &Cli, file: &str) -> Result<String, String> {
+    let mut files = vec![Path::new(file)];
+    if args.csv_search.is_some() {
+        files.extend(args.second.file.as_deref().map(Path::new));
+    }
+    let settings = format!(
+        "{:?}",
+        (
+            (
+                args.score_mismatch,
+                args.score_mass_mismatch,
+                args.score_mass_base,
+                args.score_rotated,
+                args.score_isobaric,
+                args.score_gap_start,
+                args.score_gap_extend,
+                args.tolerance,
+                args.mass_mode,
+            ),
+            &args.scoring_matrix,
+            args.alignment_type.ty(),
+            args.alignment_kind,
+            args.stats,
+            &args.csv_search,
+            &args.csv_extra_columns,
+            &args.csv_stats,
+            &args.predict_rt,
+            (
+                args.isotopes,
+                args.full_number,
+                args.sort_by,
+                args.delimiter
+            ),
+            (
+                args.second.imgt,
+                args.species,
+                args.chains.as_ref().map(|c| c
+                    .iter()
+                    .map(|c| format!("{c:?}"))
+                    .sorted()
+                    .collect_vec()),
+                args.genes.as_ref().map(|g| g
+                    .iter()
+                    .map(|g| format!("{g:?}"))
+                    .sorted()
+                    .collect_vec()),
+                args.allele,
+                &args.gene_filter,
+            ),
+        )
+    );
+    checkpoint::fingerprint(&files, &settings)
+}
+
+/// Show one germline, or all alleles of a gene, see `--specific-gene`
+fn show_specific_germline(args: &Cli, gene: &Gene, allele: Option<usize>, species: Species) {
+    if allele.is_none() {
+        let all = Selection {
+            species: Some(HashSet::from([species])),
+            chains: Some(HashSet::from([gene.chain])),
+            genes: Some(HashSet::from([gene.kind])),
+            allele: AlleleSelection::All,
+        }
+        .germlines()
+        .filter(|a| *a.gene == *gene)
+        .collect_vec();
+        if all.is_empty() {
+            println!("Could not find specified germline")
+        } else if args.export_germline_json {
+            println!("[{}]", all.iter().map(germline_json).join(","));
+        } else if all.len() == 1 || args.display_fasta {
+            for allele in all {
+                display_germline(allele, args);
+            }
+        } else {
+            alleles::show_alleles(&all, args.line_width);
+        }
+    } else if let Some(allele) = imgt::get_germline(species, gene.clone(), allele) {
+        if args.export_germline_json {
+            println!("{}", germline_json(&allele));
+        } else {
+            display_germline(allele, args);
+        }
+    } else {
+        println!("Could not find specified germline")
+    }
+}
+
+/// Export the selected germlines as JSON, see `--export-germline-json`
+fn export_germline_json(args: &Cli) {
+    let selection = Selection {
+        species: args.species.map(|s| HashSet::from([s])),
+        chains: args.chains.clone(),
+        genes: args.genes.clone(),
+        allele: args.allele,
+    };
+    println!(
+        "[{}]",
+        selection
+            .germlines()
+            .filter(|allele| args.matches_gene_filter(allele))
+            .map(|allele| germline_json(&allele))
+            .join(",")
+    );
+}
+
+/// Export the selected germlines as a fasta file, see `--export-fasta`
+fn export_fasta(args: &Cli, path: &str) {
+    let selection = Selection {
+        species: args.species.map(|s| HashSet::from([s])),
+        chains: args.chains.clone(),
+        genes: args.genes.clone(),
+        allele: args.allele,
+    };
+    let mut writer = BufWriter::new(std::fs::File::create(path).unwrap());
+    let mut count = 0;
+    for allele in selection
+        .germlines()
+        .filter(|allele| args.matches_gene_filter(allele))
+    {
+        write!(
+            writer,
+            ">{} {} {}",
+            allele.name(),
+            allele.species.scientific_name(),
+            allele.species.common_name()
+        )
+        .unwrap();
+        if args.generate_annotation {
+            write!(
+                writer,
+                " REGIONS={}",
+                allele
+                    .regions
+                    .iter()
+                    .map(|(r, l)| format!("{r}:{l}"))
+                    .join(";")
+            )
+            .unwrap();
+        }
+        writeln!(
+            writer,
+            "\n{}",
+            allele
+                .sequence
+                .sequence()
+                .iter()
+                .map(|s| s.aminoacid.char())
+                .collect::<String>()
+        )
+        .unwrap();
+        count += 1;
+    }
+    writer.flush().unwrap();
+    println!(
+        "Exported {} germlines to {}",
+        count.to_string().blue(),
+        path.dimmed()
+    );
+}
+
+/// Show all selected germlines, see `--imgt`
+fn display_germlines(args: &Cli) {
+    let mut first = true;
+    let selection = Selection {
+        species: args.species.map(|s| HashSet::from([s])),
+        chains: args.chains.clone(),
+        genes: args.genes.clone(),
+        allele: args.allele,
+    };
+    for allele in selection
+        .germlines()
+        .filter(|allele| args.matches_gene_filter(allele))
+    {
+        if !first {
+            println!();
+        } else {
+            first = false;
+        }
+        display_germline(allele, args);
+    }
+}
+
+fn show_explanation<A, B>(alignment: &Alignment<'_, A, B>, args: &Cli) {
+    println!(
+        "{} {}",
+        "Score explanation".underline().italic(),
+        format!(
+            "(total {} of maximal {})",
+            alignment.score().absolute,
+            alignment.score().max
+        )
+        .dimmed()
+    );
+    let mut data = vec![[
+        String::new(),
+        "A".to_string(),
+        "B".to_string(),
+        "Step".to_string(),
+        "Components".to_string(),
+        "Score".to_string(),
+        "Total".to_string(),
+    ]];
+    data.extend(explain::explain_score(alignment, args.scoring()));
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_style(Styles::Dimmed),
+            Styling::none(),
+            Styling::none(),
+            Styling::none(),
+            Styling::with_style(Styles::Dimmed),
+            Styling::with_fg(Some(Color::Green)),
+            Styling::none(),
+        ],
+    );
+}
+
+fn show_confidence<A, B>(alignment: &Alignment<'_, A, B>, confidence: &[f64]) {
+    println!(
+        "{} {}",
+        "Confidence".underline().italic(),
+        format!(
+            "(mean {:.3}, minimal {:.3})",
+            confidence.iter().sum::<f64>() / confidence.len().max(1) as f64,
+            confidence.iter().copied().fold(1.0, f64::min)
+        )
+        .dimmed()
+    );
+    let (mut a, mut b) = alignment.start();
+    let mut shaky = Vec::new();
+    for (step, confidence) in alignment.path().iter().zip(confidence) {
+        if matches!(step.match_type, MatchType::Isobaric | MatchType::Rotation)
+            && *confidence < confidence::LOW_CONFIDENCE
+        {
+            shaky.push(format!(
+                "{}{} ⇔ {}{}: {confidence:.3}",
+                alignment.seq_a()[a..a + step.step_a as usize]
+                    .iter()
+                    .map(|s| s.aminoacid.char())
+                    .collect::<String>(),
+                a + 1,
+                alignment.seq_b()[b..b + step.step_b as usize]
+                    .iter()
+                    .map(|s| s.aminoacid.char())
+                    .collect::<String>(),
+                b + 1
+            ));
+        }
+        a += step.step_a as usize;
+        b += step.step_b as usize;
+    }
+    if shaky.is_empty() {
+        println!("{}", "No unreliable isobaric or rotated sets".dimmed());
+    } else {
+        println!("Unreliable isobaric or rotated sets");
+        for set in shaky {
+            println!("  {}", set.red());
+        }
+    }
+}
+
+/// The header of the `--csv` and `--csv-search` output, the input columns followed by the statistics
+fn csv_header<'a>(args: &Cli, input: impl Iterator<Item = &'a str>, search: bool) -> Vec<String> {
+    input
+        .chain(search.then_some("id"))
+        .chain(
+            args.csv_stats
+                .as_deref()
+                .unwrap_or(CsvStat::DEFAULT)
+                .iter()
+                .map(|stat| stat.header()),
+        )
+        .chain(
+            match args.predict_rt {
+                Some(None) => &["hydrophobicity a", "hydrophobicity b"][..],
+                Some(Some(_)) => &["hydrophobicity a", "hydrophobicity b", "rt a", "rt b"],
+                None => &[],
+            }
+            .iter()
+            .copied(),
+        )
+        .chain(args.csv_extra_columns.unwrap_or_default().header())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parse a sequence from a column of a `--csv` row
+fn csv_sequence(line: &CsvLine, column: &str) -> Peptidoform<SimpleLinear> {
+    Peptidoform::pro_forma(line.index_column(column).unwrap().0, custom::database())
+        .unwrap()
+        .into_simple_linear()
+        .unwrap()
+}
+
+/// The scoring and alignment type for one `--csv` row, the optional columns "tolerance", "matrix",
+/// and "type" override the global settings for this row
+fn csv_settings(args: &Cli, line: &CsvLine) -> Result<(AlignScoring<'static>, AlignType), String> {
+    let column = |name: &str| {
+        line.index_column(name)
+            .ok()
+            .map(|(value, _)| value.trim())
+            .filter(|value| !value.is_empty())
+    };
+    let invalid = |name: &str, error: String| {
+        format!(
+            "Invalid {name} on line {} of the csv file: {error}",
+            line.line_index() + 1
+        )
+    };
+    let mut scoring = args.scoring();
+    if let Some(tolerance) = column("tolerance") {
+        scoring.tolerance = mass_tolerance_parse(tolerance)
+            .map_err(|e| invalid("tolerance", e.to_string()))?
+            .for_alignment()
+            .convert();
+    }
+    if let Some(matrix) = column("matrix") {
+        scoring.matrix = ScoringMatrix::by_name(matrix).map_err(|e| invalid("matrix", e))?;
+    }
+    let ty = match column("type") {
+        Some(ty) => AlignmentType::by_name(ty).map_err(|e| invalid("type", e))?,
+        None => args.alignment_type.ty(),
+    };
+    Ok((scoring, ty))
+}
+
+/// The output row for a `--csv` row that could not be aligned, the input row followed by empty
+/// columns so that the output keeps the same number of columns on every row
+fn csv_empty_row(args: &Cli, line: &CsvLine, search: bool, delimiter: char) -> String {
+    std::iter::once(line.line())
+        .chain(
+            csv_header(args, std::iter::empty(), search)
+                .iter()
+                .map(|_| ""),
+        )
+        .join(&delimiter.to_string())
+}
+
+/// The output row for a `--csv` row, the input row followed by the statistics of the alignment
+/// and the hit id for `--csv-search`, using the same delimiter as the input
+fn csv_row(
+    args: &Cli,
+    line: &CsvLine,
+    hit: Option<&str>,
+    alignment: &Alignment<'_, SimpleLinear, SimpleLinear>,
+    delimiter: char,
+) -> String {
+    let stats = stats::stats(alignment, &args.stats);
+    let score = alignment.score();
+    let mut fields = vec![line.line().to_string()];
+    fields.extend(hit.map(str::to_string));
+    fields.extend(
+        args.csv_stats
+            .as_deref()
+            .unwrap_or(CsvStat::DEFAULT)
+            .iter()
+            .map(|stat| match stat {
+                CsvStat::Path => alignment.short(),
+                CsvStat::Score => score.normalised.to_string(),
+                CsvStat::AbsoluteScore => score.absolute.to_string(),
+                CsvStat::MaximalScore => score.max.to_string(),
+                CsvStat::Identical => stats.identical.to_string(),
+                CsvStat::MassSimilar => stats.mass_similar.to_string(),
+                CsvStat::Gaps => stats.gaps.to_string(),
+                CsvStat::Length => stats.length.to_string(),
+                CsvStat::Identity => stats.identity().to_string(),
+                CsvStat::MassSimilarity => stats.mass_similarity().to_string(),
+                CsvStat::Similarity => stats.similarity().to_string(),
+                CsvStat::MassDifference => alignment.mass_difference().value.to_string(),
+                CsvStat::Ppm => (alignment.ppm().value * 1e6).to_string(),
+            }),
+    );
+    if let Some(calibration) = args.predict_rt {
+        let (a, b) = (
+            retention::hydrophobicity(alignment.seq_a()),
+            retention::hydrophobicity(alignment.seq_b()),
+        );
+        fields.extend([format!("{a:.2}"), format!("{b:.2}")]);
+        if let Some(calibration) = calibration {
+            fields.extend([
+                format!("{:.2}", calibration.retention_time(a)),
+                format!("{:.2}", calibration.retention_time(b)),
+            ]);
+        }
+    }
+    let columns = args.csv_extra_columns.unwrap_or_default();
+    if columns.aligned {
+        let (a, b) = gapped(alignment);
+        fields.extend([a, b]);
+    }
+    if columns.coordinates {
+        fields.extend(
+            [
+                alignment.start_a() + 1,
+                alignment.start_a() + alignment.len_a(),
+                alignment.start_b() + 1,
+                alignment.start_b() + alignment.len_b(),
+            ]
+            .map(|n| n.to_string()),
+        );
+    }
+    if columns.components {
+        fields.extend(
+            [
+                stats.identity(),
+                stats.mass_similarity(),
+                stats.similarity(),
+                stats.gaps_fraction(),
+            ]
+            .map(|n| n.to_string()),
+        );
+    }
+    fields.join(&delimiter.to_string())
+}
+
+/// Score one row of a `--csv` file by aligning the columns "a" and "b"
+fn score_csv_line(args: &Cli, line: &CsvLine, delimiter: char) -> String {
+    let (a, b) = (csv_sequence(line, "a"), csv_sequence(line, "b"));
+    let (scoring, ty) = match csv_settings(args, line) {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("{}", err.red());
+            return csv_empty_row(args, line, false, delimiter);
+        }
+    };
+    let alignment = align(&a, &b, scoring, ty, args.alignment_kind);
+    csv_row(args, line, None, &alignment, delimiter)
+}
+
+/// Search the column "a" of one row of a `--csv-search` file against all targets and give the row
+/// for the best hit, or the input row with empty columns if there is no hit. As in `--file` mode
+/// the target is A in the alignment and the query is B.
+fn search_csv_line(
+    args: &Cli,
+    line: &CsvLine,
+    targets: &[(String, Peptidoform<SimpleLinear>)],
+    delimiter: char,
+) -> String {
+    let query = csv_sequence(line, "a");
+    let (scoring, ty) = match csv_settings(args, line) {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("{}", err.red());
+            return csv_empty_row(args, line, true, delimiter);
+        }
+    };
+    targets
+        .iter()
+        .map(|(id, target)| (id, align(target, &query, scoring, ty, args.alignment_kind)))
+        .filter(|(_, alignment)| !alignment.normalised_score().is_nan())
+        .min_by(|x, y| stats::compare_hits(&x.1, &y.1, args.sort_by, &args.stats))
+        .map_or_else(
+            || csv_empty_row(args, line, true, delimiter),
+            |(id, alignment)| csv_row(args, line, Some(id), &alignment, delimiter),
+        )
+}
+
+fn single_stats(args: &Cli, seq: Peptidoform<SimpleLinear>) {
+    let full_formulas = seq.formulas().unique();
+    let bare_formulas = seq.bare_formulas().unique();
+    print_multi_formula(&full_formulas, "Full", "", args.full_number);
+    print_multi_formula(
+        &bare_formulas,
+        "Bare",
+        "no N/C terminal taken into account",
+        args.full_number,
+    );
+    let multiple = full_formulas.len() > 1;
+    if args.isotopes {
+        for formula in full_formulas.iter() {
+            println!();
+            if multiple {
+                println!("{}", formula.hill_notation_fancy().green());
+            }
+            isotopes::show(formula, (!args.full_number).then_some(NUMBER_PRECISION));
+        }
+    }
+    if let Some(calibration) = args.predict_rt {
+        println!();
+        retention::show(&seq, calibration);
+    }
+    if let Some(modification) = &args.where_modification {
+        println!();
+        placement::show(&seq, modification);
+    }
+    if args.ladder {
+        println!();
+        ladder::show(
+            &seq,
+            args.mass_mode,
+            (!args.full_number).then_some(NUMBER_PRECISION),
+        );
+    }
+    if args.diagnostic_ions {
+        println!();
+        diagnostic::show(
+            &seq,
+            args.mass_mode,
+            (!args.full_number).then_some(NUMBER_PRECISION),
+        );
+    }
+    // With a label the isobaric sets are generated for the labelled peptide
+    let seq = if let Some(label) = args.label {
+        println!();
+        label::show(
+            &seq,
+            label,
+            args.mass_mode,
+            (!args.full_number).then_some(NUMBER_PRECISION),
+        );
+        label.apply(&seq)
+    } else {
+        seq
+    };
+
+    let bare = seq
+        .bare_formulas()
+        .mass_bounds()
+        .into_option()
+        .expect("No masses for peptide")
+        .0
+        .mass(args.mass_mode);
+    println!();
+    if multiple {
+        println!("{}", "Multiple precursor masses found, it will generate isobaric options based on the lowest bare mass".dimmed().italic());
+    }
+    if !matches!(args.isobaric, IsobaricNumber::Limited(0)) {
+        isobaric::show(bare, isobaric::termini(&seq), args);
+    }
+}
+
+fn print_multi_formula(
+    formulas: &Multi<MolecularFormula>,
+    prefix: &str,
+    suffix: &str,
+    full_number: bool,
+) {
+    let precision = if full_number {
+        None
+    } else {
+        Some(NUMBER_PRECISION)
+    };
+    let multiple = formulas.len() > 1;
+    print!("{}: ", prefix);
+    if multiple {
+        println!(
+            "{}",
+            if suffix.is_empty() {
+                String::new()
+            } else {
+                format!("({suffix})").dimmed().to_string()
+            }
+        )
+    }
+    let mut lengths = (0, 0, 0, 0);
+    let mut rows = Vec::with_capacity(formulas.len());
+    for formula in formulas.iter() {
+        let row = (
+            formula.hill_notation_fancy().green(),
+            display_mass(formula.monoisotopic_mass(), true, precision),
+            display_mass(formula.average_weight(), true, precision),
+            display_mass(formula.most_abundant_mass(), true, precision),
+        );
+        lengths = (
+            lengths.0.max(row.0.chars().count()),
+            lengths.1.max(row.1.chars().count()),
+            lengths.2.max(row.2.chars().count()),
+            lengths.3.max(row.3.chars().count()),
+        );
+        rows.push(row);
+    }
+    for formula in formulas.iter() {
+        if multiple {
+            print!("  ");
+        }
+        print!(
+            "{:4$} {:5$} {:6$} {:7$}",
+            formula.hill_notation_fancy().green(),
+            display_mass(formula.monoisotopic_mass(), true, precision),
+            display_mass(formula.average_weight(), true, precision),
+            display_mass(formula.most_abundant_mass(), true, precision),
+            lengths.0,
+            lengths.1,
+            lengths.2,
+            lengths.3,
+        );
+        if multiple {
+            println!();
+        }
+    }
+    if multiple {
+        print!("  ");
+    } else {
+        print!(" ");
+    }
+    println!(
+        "{}{}",
+        "(formula | monoisotopic mass | average weight | most abundant mass)".dimmed(),
+        if suffix.is_empty() || multiple {
+            String::new()
+        } else {
+            format!(" ({suffix})").dimmed().to_string()
+        }
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn modification_stats(
+    modification: &SimpleModification,
+    tolerance: MassTolerance,
+    full_number: bool,
+    mass_mode: MassMode,
+    all_mass_modes: bool,
+    positions: Option<&[(Vec<AminoAcid>, Position)]>,
+    combinations: usize,
+    snfg: bool,
+    filter: &glycan::GnomeFilter,
+) {
+    let precision = if full_number {
+        None
+    } else {
+        Some(NUMBER_PRECISION)
+    };
+    match &**modification {
+        SimpleModificationInner::Mass(m)
+        | SimpleModificationInner::Gno {
+            composition: GnoComposition::Weight(m),
+            ..
+        } => {
+            println!(
+                "All ontology modifications close to the given {mass_mode}: {}",
+                format!("tolerance: {tolerance}").dimmed()
+            );
+            let modes = mass_modes(all_mass_modes, mass_mode);
+            let mut data = vec![["Name".to_string(), "Id".to_string()]
+                .into_iter()
+                .chain(modes.iter().map(ToString::to_string))
+                .chain(["Formula".to_string()])
+                .collect_vec()];
+            for (ontology, id, _name, modification) in modification_search_mass(
+                m.into_inner(),
+                tolerance.at(m.into_inner()),
+                positions,
+                mass_mode,
+                custom::database(),
+            ) {
+                data.push(
+                    [
+                        modification.to_string(),
+                        format!(
+                            "{}{}",
+                            ontology.name(),
+                            id.map_or(String::new(), |id| format!(":{id}")),
+                        ),
+                    ]
+                    .into_iter()
+                    .chain(modes.iter().map(|mode| {
+                        display_mass(modification.formula().mass(*mode), false, precision)
+                    }))
+                    .chain([modification.formula().hill_notation_fancy()])
+                    .collect_vec(),
+                )
+            }
+            if data.len() > 1 {
+                table(
+                    &data,
+                    true,
+                    &[
+                        Styling::with_fg(Some(Color::Magenta)),
+                        Styling::with_style(Styles::Dimmed),
+                    ]
+                    .into_iter()
+                    .chain(modes.iter().map(|_| Styling::with_fg(Some(Color::Yellow))))
+                    .chain([Styling::with_fg(Some(Color::Green))])
+                    .collect_vec(),
+                );
+            } else {
+                println!("{}", "No modifications found".red())
+            }
+            if combinations > 1 {
+                combination::show(
+                    m.into_inner(),
+                    tolerance.at(m.into_inner()),
+                    combinations,
+                    positions,
+                    mass_mode,
+                    precision,
+                );
+            }
+        }
+        SimpleModificationInner::Formula(f) => {
+            display_single_mod(modification, precision, snfg);
+
+            println!("\nAll ontology modifications with the same formula:");
+            let mut data = vec![["Name".to_string(), "Id".to_string()]];
+            for (ontology, id, _name, modification) in
+                modification_search_formula(f, custom::database())
+            {
+                data.push([
+                    modification.to_string(),
+                    format!(
+                        "{}{}",
+                        id.map_or(String::new(), |id| format!("{id}:")),
+                        ontology.name()
+                    ),
+                ])
+            }
+            if data.len() > 1 {
+                table(
+                    &data,
+                    true,
+                    &[
+                        Styling::with_fg(Some(Color::Magenta)),
+                        Styling::with_style(Styles::Dimmed),
+                    ],
+                );
+            } else {
+                println!("{}", "No modifications found".red())
+            }
+        }
+        SimpleModificationInner::Glycan(ref g)
+        | SimpleModificationInner::Gno {
+            composition: GnoComposition::Composition(ref g),
+            ..
+        } => {
+            display_single_mod(modification, precision, snfg);
+
+            println!();
+            glycan::structures(g, filter);
+        }
+        modification => display_single_mod(modification, precision, snfg),
+    }
+}
+
+/// The filter for GNOme searches from `--taxonomy` and `--tissue`
+fn gnome_filter(args: &Cli) -> glycan::GnomeFilter {
+    glycan::GnomeFilter {
+        taxonomy: args.taxonomy.clone(),
+        tissue: args.tissue.clone(),
+    }
+}
+
+fn display_single_mod(
+    modification: &SimpleModificationInner,
+    precision: Option<usize>,
+    snfg: bool,
+) {
+    println!(
+        "Full mass: {} {} {} {}",
+        display_mass(modification.formula().monoisotopic_mass(), true, precision),
+        display_mass(modification.formula().average_weight(), true, precision),
+        display_mass(modification.formula().most_abundant_mass(), true, precision),
+        "(monoisotopic | average | most abundant)".dimmed(),
+    );
+    if !modification.formula().is_empty() {
+        println!(
+            "Composition: {}",
+            modification.formula().hill_notation_fancy().green(),
+        );
+    }
+    match modification {
+        SimpleModificationInner::Database {
+            specificities, id, ..
+        } => {
+            display_id(id);
+            println!("Placement rules: ");
+
+            for rule in specificities {
+                print!("  Locations: ");
+                // Print locations
+                display_placement_rules(&rule.0);
+                // Print neutral losses
+                if !rule.1.is_empty() {
+                    print!(
+                        ", Neutral losses: {}",
+                        rule.1
+                            .iter()
+                            .map(|n| n.hill_notation_fancy().yellow())
+                            .join(", ")
+                    );
+                }
+                // Print diagnostic ions
+                if !rule.2.is_empty() {
+                    print!(
+                        ", Diagnostic ions: {}",
+                        rule.2
+                            .iter()
+                            .map(|d| d.0.hill_notation_fancy().green())
+                            .join(", ")
+                    );
+                }
+                println!();
+            }
+        }
+        SimpleModificationInner::Linker {
+            specificities,
+            id,
+            length,
+            ..
+        } => {
+            display_id(id);
+            if let Some(length) = length {
+                println!("Length: {}", length);
+            }
+            println!("Placement rules: ");
+            for specificity in specificities {
+                match specificity {
+                    LinkerSpecificity::Symmetric(locations, stubs, diagnostic) => {
+                        print!("  Locations: ");
+                        display_placement_rules(locations);
+                        if !stubs.is_empty() {
+                            print!(
+                                ", Cleave points: {}",
+                                stubs
+                                    .iter()
+                                    .map(|(a, b)| format!(
+                                        "{} + {}",
+                                        a.hill_notation_fancy().yellow(),
+                                        b.hill_notation_fancy().yellow()
+                                    ))
+                                    .join(", ")
+                            );
+                        }
+                        if !diagnostic.is_empty() {
+                            print!(
+                                ", Diagnostic ions: {}",
+                                diagnostic
+                                    .iter()
+                                    .map(|d| d.0.hill_notation_fancy().green())
+                                    .join(", ")
+                            );
+                        }
+                    }
+                    LinkerSpecificity::Asymmetric(locations, stubs, diagnostic) => {
+                        print!("  Left: ");
+                        display_placement_rules(&locations.0);
+                        print!(", Right: ");
+                        display_placement_rules(&locations.1);
+
+                        if !stubs.is_empty() {
+                            print!(
+                                ", Cleave points: {}",
+                                stubs
+                                    .iter()
+                                    .map(|(a, b)| format!(
+                                        "{} + {}",
+                                        a.hill_notation_fancy().yellow(),
+                                        b.hill_notation_fancy().yellow()
+                                    ))
+                                    .join(", ")
+                            );
+                        }
+                        if !diagnostic.is_empty() {
+                            print!(
+                                ", Diagnostic ions: {}",
+                                diagnostic
+                                    .iter()
+                                    .map(|d| d.0.hill_notation_fancy().green())
+                                    .join(", ")
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        SimpleModificationInner::Gno {
+            composition,
+            id,
+            structure_score,
+            subsumption_level,
+            motif,
+            taxonomy,
+            glycomeatlas,
+        } => {
+            display_id(id);
+            if let Some(score) = structure_score {
+                println!("Structure score: {}", score.to_string().blue());
+            }
+            println!("Subsumption: {}", subsumption_level.to_string().green());
+            println!(
+                "Motif: {}",
+                motif
+                    .iter()
+                    .map(|(name, id)| format!("{name}:{id}"))
+                    .join(", ")
+            );
+            println!(
+                "Taxonomy: {}",
+                taxonomy
+                    .iter()
+                    .map(|(name, id)| format!("{name}:{id}"))
+                    .join(", ")
+            );
+            println!(
+                "Glycomeatlas: {}",
+                glycomeatlas
+                    .iter()
+                    .map(|(species, places)| format!(
+                        "{species}:{}",
+                        places
+                            .iter()
+                            .map(|(place, id)| format!("{place}({id})"))
+                            .join(", ")
+                    ))
+                    .join("\n")
+            );
+            match composition {
+                GnoComposition::Weight(mass) => {
+                    println!(
+                        "Average weight: {}",
+                        display_mass(mass.into_inner(), true, precision)
+                    )
+                }
+                GnoComposition::Composition(composition) => {
+                    println!(
+                        "Composition: {}",
+                        composition
+                            .iter()
+                            .map(|(sug, amount)| format!("{}{amount}", sug.to_string().green()))
+                            .join("")
+                    )
+                }
+                GnoComposition::Topology(structure) => {
+                    println!("Structure: {}", structure.to_string().green());
+                    for line in glycan::tree(structure, snfg) {
+                        println!("  {line}");
+                    }
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+fn display_placement_rules(rules: &[PlacementRule]) {
+    let mut first = true;
+    for rule in rules {
+        match rule {
+            PlacementRule::AminoAcid(aa, pos) => {
+                print!(
+                    "{}{}@{}",
+                    if first { "" } else { ", " },
+                    aa.iter().map(|a| a.char()).collect::<String>().yellow(),
+                    pos.to_string().green()
+                )
+            }
+            PlacementRule::PsiModification(index, pos) => {
+                print!(
+                    "{}{}@{}",
+                    if first { "" } else { ", " },
+                    Ontology::Psimod
+                        .find_id(*index, None)
+                        .unwrap()
+                        .to_string()
+                        .blue(),
+                    pos.to_string().green()
+                )
+            }
+            PlacementRule::Terminal(pos) => {
+                print!(
+                    "{}{}",
+                    if first { "" } else { ", " },
+                    pos.to_string().green()
+                )
+            }
+            PlacementRule::Anywhere => print!("{}", "Anywhere".green()),
+        }
+        first = false;
+    }
+}
+
+fn display_id(id: &ModificationId) {
+    println!(
+        "Ontology: {}, name: {}{}",
+        id.ontology.to_string().purple(),
+        id.name.green(),
+        id.id.map_or(String::new(), |id| format!(
+            ", index: {}",
+            id.to_string().blue()
+        ))
+    );
+    if !id.description.is_empty() {
+        println!("{}", id.description);
+    }
+    if !id.cross_ids.is_empty() {
+        println!(
+            "IDs: {}",
+            id.cross_ids
+                .iter()
+                .map(|(r, i)| format!("{}{}{i}", r.dimmed(), ":".dimmed()))
+                .join(", ")
+        );
+    }
+    if !id.synonyms.is_empty() {
+        println!("Synonyms: {}", id.synonyms.join(", "));
+    }
+}
+
+/// The JSON representation of a germline, see [`germline_json`]
+#[derive(Serialize)]
+struct GermlineJson {
+    name: String,
+    fancy_name: String,
+    species: String,
+    chain: String,
+    gene_type: String,
+    sequence: String,
+    regions: Vec<RegionJson>,
+    annotations: Vec<AnnotationJson>,
+}
+
+#[derive(Serialize)]
+struct RegionJson {
+    region: String,
+    start: usize,
+    end: usize,
+}
+
+#[derive(Serialize)]
+struct AnnotationJson {
+    annotation: String,
+    position: usize,
+}
+
+/// A JSON object with the sequence, regions (0 based, exclusive end), and annotations (0 based) of a
+/// germline
+fn germline_json(allele: &Allele) -> String {
+    let mut start = 0;
+    serde_json::to_string(&GermlineJson {
+        name: allele.name(),
+        fancy_name: allele.fancy_name(),
+        species: allele.species.scientific_name().to_string(),
+        chain: allele.gene.chain.to_string(),
+        gene_type: allele.gene.kind.to_string(),
+        sequence: allele
+            .sequence
+            .sequence()
+            .iter()
+            .map(|s| s.aminoacid.char())
+            .collect(),
+        regions: allele
+            .regions
+            .iter()
+            .map(|(region, length)| {
+                start += length;
+                RegionJson {
+                    region: region.to_string(),
+                    start: start - length,
+                    end: start,
+                }
+            })
+            .collect(),
+        annotations: allele
+            .annotations
+            .iter()
+            .map(|(annotation, position)| AnnotationJson {
+                annotation: match annotation {
+                    Annotation::Conserved => "Conserved".to_string(),
+                    Annotation::NGlycan => "NGlycan".to_string(),
+                    Annotation::Other(other) => other.to_string(),
+                },
+                position: *position,
+            })
+            .collect(),
+    })
+    .unwrap()
+}
+
+/// Show sequences padded to the IMGT unique numbering with a ruler, followed by the fasta records
+fn show_imgt_gapped(columns: &[String], padded: &[String], names: &[&str], line_width: usize) {
+    println!(
+        "{} {}",
+        "IMGT gapped".underline().italic(),
+        format!("({} positions)", columns.len()).dimmed()
+    );
+    let rows = padded.iter().map(|p| p.chars().collect_vec()).collect_vec();
+    for start in (0..columns.len()).step_by(line_width.max(1)) {
+        let end = (start + line_width.max(1)).min(columns.len());
+        let mut ruler = String::new();
+        for (index, label) in columns.iter().enumerate().take(end).skip(start) {
+            if ruler.chars().count() > index - start {
+                continue;
+            }
+            if label.parse::<usize>().is_ok_and(|p| p % 10 == 0) && index + label.len() <= end {
+                ruler += label;
+            } else {
+                ruler.push(' ');
+            }
+        }
+        println!("{}", ruler.dimmed());
+        for (row, name) in rows.iter().zip(names) {
+            println!(
+                "{} {}",
+                row[start..end].iter().collect::<String>(),
+                name.dimmed()
+            );
+        }
+    }
+    for (row, name) in padded.iter().zip(names) {
+        println!(">{name}\n{row}");
+    }
+}
+
+/// The regions given with `--regions` for this sequence, with a warning if they do not cover the sequence
+fn annotated_regions<'a>(
+    sequence: &'a Peptidoform<SimpleLinear>,
+    args: &'a Cli,
+) -> Option<RegionAnnotated<'a, SimpleLinear>> {
+    let regions = args.regions.as_deref()?;
+    let length: usize = regions.iter().map(|(_, l)| l).sum();
+    if length != sequence.len() {
+        println!(
+            "{}",
+            format!(
+                "The regions cover {length} residues but the sequence has {} residues",
+                sequence.len()
+            )
+            .yellow()
+        );
+    }
+    Some(RegionAnnotated::new(sequence, regions))
+}
+
+/// Show all species in the IMGT data with the number of germlines (all alleles) per chain
+fn list_species() {
+    let chains = [
+        ChainType::Heavy,
+        ChainType::LightKappa,
+        ChainType::LightLambda,
+        ChainType::Iota,
+    ];
+    let mut counts: Vec<(Species, [usize; 4])> = Vec::new();
+    let selection = Selection::<std::hash::RandomState, std::hash::RandomState> {
+        allele: AlleleSelection::All,
+        ..Default::default()
+    };
+    for allele in selection.germlines() {
+        let index = counts
+            .iter()
+            .position(|(s, _)| *s == allele.species)
+            .unwrap_or_else(|| {
+                counts.push((allele.species, [0; 4]));
+                counts.len() - 1
+            });
+        counts[index].1[chains.iter().position(|c| *c == allele.gene.chain).unwrap()] += 1;
+    }
+    let mut data = vec![[
+        "Scientific name".to_string(),
+        "Common name".to_string(),
+        "H".to_string(),
+        "K".to_string(),
+        "L".to_string(),
+        "I".to_string(),
+    ]];
+    for (species, counts) in counts {
+        data.push([
+            species.scientific_name().to_string(),
+            species.common_name().to_string(),
+            counts[0].to_string(),
+            counts[1].to_string(),
+            counts[2].to_string(),
+            counts[3].to_string(),
+        ]);
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_style(Styles::Italic),
+            Styling::with_fg(Some(Color::Magenta)),
+            Styling::none(),
+            Styling::none(),
+            Styling::none(),
+            Styling::none(),
+        ],
+    );
+}
+
+/// Show all genes in the IMGT selection with the number of alleles
+fn list_genes(args: &Cli) {
+    let selection = Selection {
+        species: args.species.map(|s| HashSet::from([s])),
+        chains: args.chains.clone(),
+        genes: args.genes.clone(),
+        allele: AlleleSelection::All,
+    };
+    let mut data = vec![[
+        "Species".to_string(),
+        "Gene".to_string(),
+        "Alleles".to_string(),
+        "Chain".to_string(),
+        "Type".to_string(),
+    ]];
+    for ((species, gene), alleles) in &selection
+        .germlines()
+        .filter(|allele| args.matches_gene_filter(allele))
+        .chunk_by(|a| (a.species, a.gene.clone().into_owned()))
+    {
+        data.push([
+            species.common_name().to_string(),
+            gene.to_string(),
+            alleles.count().to_string(),
+            gene.chain.to_string(),
+            gene.kind.to_string(),
+        ]);
+    }
+    println!(
+        "{} {}",
+        "Genes".underline().italic(),
+        format!("({})", data.len() - 1).dimmed()
+    );
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_fg(Some(Color::Magenta)),
+            Styling::with_fg(Some(Color::Magenta)),
+            Styling::none(),
+            Styling::none(),
+            Styling::none(),
+        ],
+    );
+}
+
+fn display_germline(allele: Allele, args: &Cli) {
+    let scoring = AlignScoring::<'static> {
+        matrix: rustyms::align::matrix::BLOSUM90,
+        ..Default::default()
+    };
+    let alignment = rustyms::align::align::<1, UnAmbiguous, UnAmbiguous>(
+        allele.sequence,
+        allele.sequence,
+        scoring,
+        rustyms::align::AlignType::GLOBAL,
+    );
+    if args.display_fasta {
+        println!(
+            ">{} {} {}",
+            allele.name().purple(),
+            allele.species.scientific_name(),
+            allele.species.common_name().purple(),
+        );
+    } else {
+        println!(
+            "{} {} {}",
+            allele.species.scientific_name().to_string().purple(),
+            allele.species.common_name(),
+            format!("{} / {}", allele.name(), allele.fancy_name()).purple(),
+        );
+    }
+    show_annotated_mass_alignment(
+        &alignment,
+        Some(&allele),
+        None,
+        true,
+        args.display_fasta,
+        ("", ""),
+        &args.render_options(),
+    );
+}
@@ -0,0 +1,335 @@
+use std::io::{BufWriter, Write};
+
+use colored::{Color, Colorize};
+use itertools::Itertools;
+use rayon::prelude::*;
+use rustyms::{
+    modification::{GnoComposition, Ontology, SimpleModification, SimpleModificationInner},
+    modification_search_formula, modification_search_glycan, modification_search_mass,
+    molecular_formula,
+    placement_rule::Position,
+    system::{dalton, Mass},
+    AminoAcid, Chemical, MassMode,
+};
+
+use crate::{
+    cli::{
+        file_delimiter, formula_target_parser, modification_parse, Cli, IsobaricNumber,
+        MassTolerance,
+    },
+    custom, formula, isobaric,
+    render::table,
+    styling::Styling,
+};
+
+/// A modification found for a query
+#[derive(Clone, Debug)]
+pub struct Hit {
+    pub ontology: Option<Ontology>,
+    pub id: Option<usize>,
+    pub modification: SimpleModification,
+}
+
+/// Find all modifications for a query as in `--modification`: modifications close to a mass,
+/// modifications with the same formula or monosaccharide composition, or the modification itself
+/// if it is defined by name.
+pub fn matches(
+    query: &SimpleModification,
+    tolerance: MassTolerance,
+    mass_mode: MassMode,
+    positions: Option<&[(Vec<AminoAcid>, Position)]>,
+) -> Vec<Hit> {
+    let hit = |(ontology, id, _name, modification): (Ontology, Option<usize>, String, _)| Hit {
+        ontology: Some(ontology),
+        id,
+        modification,
+    };
+    match &**query {
+        SimpleModificationInner::Mass(m)
+        | SimpleModificationInner::Gno {
+            composition: GnoComposition::Weight(m),
+            ..
+        } => modification_search_mass(
+            m.into_inner(),
+            tolerance.at(m.into_inner()),
+            positions,
+            mass_mode,
+            custom::database(),
+        )
+        .map(hit)
+        .collect(),
+        SimpleModificationInner::Formula(f) => modification_search_formula(f, custom::database())
+            .map(hit)
+            .collect(),
+        SimpleModificationInner::Glycan(g) => {
+            modification_search_glycan(g, true).map(hit).collect()
+        }
+        _ => vec![Hit {
+            ontology: None,
+            id: None,
+            modification: query.clone(),
+        }],
+    }
+}
+
+/// Quote a field for a csv file if needed
+fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Run the modification search for every entry in the file (one mass, formula, or name per line,
+/// empty lines and lines starting with `#` are skipped) and write all matches to one csv file next
+/// to the input. Queries without matches get a single row with empty match columns.
+pub fn modification_file(
+    path: &str,
+    tolerance: MassTolerance,
+    mass_mode: MassMode,
+    positions: Option<&[(Vec<AminoAcid>, Position)]>,
+) {
+    let output_path = format!("{path}_output.csv");
+    let mut writer = BufWriter::new(std::fs::File::create(&output_path).unwrap());
+    writeln!(
+        writer,
+        "query,name,id,formula,{},error",
+        mass_mode.to_string().replace(' ', "_")
+    )
+    .unwrap();
+    let mut summary = vec![["Query".to_string(), "Matches".to_string()]];
+    let mut invalid = Vec::new();
+    let mut total = 0;
+    for line in std::fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    {
+        let query = match modification_parse(line) {
+            Ok(query) => query,
+            Err(err) => {
+                summary.push([line.to_string(), "invalid".red().to_string()]);
+                invalid.push(err);
+                continue;
+            }
+        };
+        let query_mass = query.formula().mass(mass_mode);
+        let hits = matches(&query, tolerance, mass_mode, positions);
+        if hits.is_empty() {
+            writeln!(writer, "{},,,,,", field(line)).unwrap();
+        }
+        for hit in &hits {
+            let formula = hit.modification.formula();
+            let mass = formula.mass(mass_mode);
+            writeln!(
+                writer,
+                "{},{},{},{},{},{}",
+                field(line),
+                field(&hit.modification.to_string()),
+                hit.ontology.map_or(String::new(), |ontology| format!(
+                    "{}{}",
+                    ontology.name(),
+                    hit.id.map_or(String::new(), |id| format!(":{id}"))
+                )),
+                formula.hill_notation(),
+                mass.value,
+                (mass - query_mass).value
+            )
+            .unwrap();
+        }
+        total += hits.len();
+        summary.push([line.to_string(), hits.len().to_string()]);
+    }
+    writer.flush().unwrap();
+    table(
+        &summary,
+        true,
+        &[
+            Styling::with_fg(Some(Color::Magenta)),
+            Styling::with_fg(Some(Color::Yellow)),
+        ],
+    );
+    for err in &invalid {
+        println!("{err}");
+    }
+    println!(
+        "{}",
+        format!(
+            "Written {total} matches for {} queries to {output_path} (tolerance: {tolerance})",
+            summary.len() - 1
+        )
+        .dimmed()
+    );
+}
+
+/// What is looked up for every mass in `--mass-csv`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MassLookup {
+    /// The molecular formulas, as `--formula`
+    #[default]
+    Formula,
+    /// The isobaric sets, as `--isobaric-mass`
+    Isobaric,
+}
+
+/// The columns added for every mass in `--mass-csv`
+fn mass_columns(lookup: MassLookup) -> [&'static str; 3] {
+    match lookup {
+        MassLookup::Formula => ["formulas", "top formulas", "top errors"],
+        MassLookup::Isobaric => ["isobaric sets", "top sets", "top errors"],
+    }
+}
+
+/// Look up the formulas or isobaric sets for one mass, gives the number found and the best options
+/// with their mass errors in Dalton (separated by spaces)
+fn lookup_mass(value: &str, args: &Cli) -> Result<[String; 3], String> {
+    let lookup = args.mass_lookup.unwrap_or_default();
+    let (count, found): (usize, Vec<(String, f64)>) = match lookup {
+        MassLookup::Formula => {
+            let target = formula_target_parser(value)?;
+            let target = args
+                .formula_tolerance
+                .map_or(target, |tolerance| target.with_tolerance(tolerance));
+            let results = formula::find(
+                target,
+                &args.adducts,
+                &args.elements,
+                args.plausibility(),
+                args.mass_defect.as_ref(),
+            );
+            (
+                results.len(),
+                results
+                    .into_iter()
+                    .take(args.number_of_hits)
+                    .map(|(adduct, formula, error)| {
+                        (
+                            formula.hill_notation()
+                                + &adduct.map_or(String::new(), formula::Adduct::label),
+                            error,
+                        )
+                    })
+                    .collect(),
+            )
+        }
+        MassLookup::Isobaric => {
+            let mass: f64 = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid mass: '{value}'"))?;
+            let bare = Mass::new::<dalton>(mass) - molecular_formula!(H 2 O 1).mass(args.mass_mode);
+            let (count, sets) = isobaric::sets(
+                bare,
+                (Vec::new(), Vec::new()),
+                args,
+                IsobaricNumber::Limited(args.number_of_hits),
+            );
+            (
+                count,
+                sets.into_iter()
+                    .map(|(set, _, error)| (set, error.value))
+                    .collect(),
+            )
+        }
+    };
+    Ok([
+        count.to_string(),
+        found.iter().map(|(name, _)| name).join(" "),
+        found
+            .iter()
+            .map(|(_, error)| format!("{error:.5}"))
+            .join(" "),
+    ])
+}
+
+/// Look up the formulas or isobaric sets (see [`MassLookup`]) for every mass in the column "mass" of
+/// the csv file and write the rows with the results appended, using the same delimiter as the input
+pub fn mass_file(path: &str, args: &Cli) {
+    let delimiter = args.delimiter.unwrap_or_else(|| file_delimiter(path));
+    let lines = rustyms::csv::parse_csv(path, delimiter as u8, None)
+        .expect("Failed to parse CSV file")
+        .map(|line| line.expect("Failed to read CSV line"))
+        .collect_vec();
+    let separator = delimiter.to_string();
+    let rows: Vec<String> = lines
+        .par_iter()
+        .map(|line| {
+            let value = line.index_column("mass").unwrap().0;
+            let columns = lookup_mass(value, args).unwrap_or_else(|error| {
+                panic!(
+                    "Invalid mass on line {} of the csv file: {error}",
+                    line.line_index() + 1
+                )
+            });
+            std::iter::once(line.line())
+                .chain(columns.iter().map(String::as_str))
+                .join(&separator)
+        })
+        .collect();
+    let stdout = args.output.as_deref() == Some("-");
+    let output_path = args
+        .output
+        .clone()
+        .unwrap_or_else(|| format!("{path}_output.csv"));
+    let mut writer: BufWriter<Box<dyn Write>> = BufWriter::new(if stdout {
+        Box::new(std::io::stdout())
+    } else {
+        Box::new(std::fs::File::create(&output_path).unwrap())
+    });
+    if let Some(line) = lines.first() {
+        writeln!(
+            writer,
+            "{}",
+            line.headers()
+                .chain(mass_columns(args.mass_lookup.unwrap_or_default()))
+                .join(&separator)
+        )
+        .unwrap();
+    }
+    for row in &rows {
+        writeln!(writer, "{row}").unwrap();
+    }
+    writer.flush().unwrap();
+    let message = format!(
+        "Written {} rows to {}",
+        rows.len(),
+        if stdout { "stdout" } else { &output_path }
+    );
+    if stdout {
+        eprintln!("{}", message.dimmed());
+    } else {
+        println!("{}", message.dimmed());
+    }
+}
+
+#[test]
+fn batch_matches() {
+    use rustyms::{system::dalton, system::Mass, Tolerance};
+    let tolerance = MassTolerance::Single(Tolerance::new_absolute(Mass::new::<dalton>(0.01)));
+    let oxidation = matches(
+        &SimpleModificationInner::Mass(Mass::new::<dalton>(15.995).into()).into(),
+        tolerance,
+        MassMode::Monoisotopic,
+        None,
+    );
+    assert!(oxidation
+        .iter()
+        .any(|hit| hit.modification.to_string() == "U:Oxidation"));
+    let named = Ontology::Unimod.find_id(21, None).unwrap();
+    let found = matches(&named, tolerance, MassMode::Monoisotopic, None);
+    assert_eq!(found.len(), 1);
+    assert_eq!(field("a,b"), "\"a,b\"");
+}
+
+#[test]
+fn mass_lookup() {
+    use clap::Parser;
+    let args = Cli::parse_from(["align", "--mass-csv", "masses.csv"]);
+    // Glycine residue C2H3NO
+    let [count, formulas, _] = lookup_mass("57.02146", &args).unwrap();
+    assert_eq!(count, "1");
+    assert_eq!(formulas, "C2H3N1O1");
+    assert!(lookup_mass("heavy", &args).is_err());
+}
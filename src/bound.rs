@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+use rustyms::{
+    align::{AlignScoring, AlignType},
+    AminoAcid, Peptidoform,
+};
+
+/// The number of items evaluated in parallel before the bound is updated
+const CHUNK_SIZE: usize = 256;
+
+/// Get an upper bound for the normalised score of aligning `a` to `b` without running the
+/// alignment. Every step splits its score evenly over its residues, so every residue contributes at
+/// most the best score per residue of any step it could be part of: an identity step for amino acid
+/// X (at most as often as the lowest count of X in the two sequences), or a mismatch, mass mismatch,
+/// isobaric, or rotated step, or nothing if it is not aligned. The normalised score divides by the
+/// maximal score of the aligned region only, so the bound divides by the smallest maximal score the
+/// aligned region can have for this alignment type: the full sequence for a sequence that is aligned
+/// globally on both sides. If no sequence has to be aligned fully the bound is infinite.
+pub fn normalised_score_bound<A, B>(
+    a: &Peptidoform<A>,
+    b: &Peptidoform<B>,
+    scoring: &AlignScoring,
+    ty: AlignType,
+) -> f64 {
+    let identity = |aa: AminoAcid| scoring.matrix[aa as usize][aa as usize] as isize;
+    let composition = |residues: &mut dyn Iterator<Item = AminoAcid>| {
+        let mut counts: HashMap<AminoAcid, isize> = HashMap::new();
+        for aa in residues {
+            *counts.entry(aa).or_default() += 1;
+        }
+        counts
+    };
+    let counts_a = composition(&mut a.sequence().iter().map(|s| s.aminoacid.aminoacid()));
+    let counts_b = composition(&mut b.sequence().iter().map(|s| s.aminoacid.aminoacid()));
+    // The best score per residue for a residue that is not part of an identity step, an isobaric
+    // step has at least two residues and a rotated step at least four
+    let mass_base = f64::from(scoring.mass_base.max(0));
+    let unmatched = [
+        0.0,
+        f64::from(scoring.mismatch) / 2.0,
+        f64::from(scoring.mass_mismatch) / 2.0,
+        mass_base / 2.0 + f64::from(scoring.isobaric) / 2.0,
+        mass_base / 4.0 + f64::from(scoring.rotated) / 2.0,
+    ]
+    .into_iter()
+    .fold(0.0, f64::max);
+    let total = (a.len() + b.len()) as f64;
+    let bound = total * unmatched
+        + counts_a
+            .iter()
+            .map(|(aa, count_a)| {
+                let count = (*count_a).min(counts_b.get(aa).copied().unwrap_or_default());
+                count as f64 * (identity(*aa) as f64 - 2.0 * unmatched).max(0.0)
+            })
+            .sum::<f64>();
+    // The smallest possible maximal score of the aligned region, a sequence that is not fully
+    // aligned contributes at least all its negative identity scores
+    let region = |counts: &HashMap<AminoAcid, isize>, full: bool| {
+        counts
+            .iter()
+            .map(|(aa, count)| {
+                if full {
+                    identity(*aa) * count
+                } else {
+                    identity(*aa).min(0) * count
+                }
+            })
+            .sum::<isize>()
+    };
+    let maximal_score = (region(&counts_a, ty.left.global_a() && ty.right.global_a())
+        + region(&counts_b, ty.left.global_b() && ty.right.global_b()))
+        / 2;
+    if maximal_score <= 0 {
+        f64::INFINITY
+    } else {
+        bound / maximal_score as f64
+    }
+}
+
+/// Evaluate all items, from the highest to the lowest bound, and stop as soon as the bound for
+/// the next item is lower than the score of the `size`th best result so far (or the given
+/// threshold). Returns all evaluated results and the number of skipped items.
+pub fn search_bounded<T: Send, R: Send>(
+    items: Vec<T>,
+    size: usize,
+    mut threshold: f64,
+    bound: impl Fn(&T) -> f64 + Sync,
+    evaluate: impl Fn(T) -> R + Sync + Send,
+    score: impl Fn(&R) -> f64,
+) -> (Vec<R>, usize) {
+    let mut items: Vec<_> = items.into_par_iter().map(|i| (bound(&i), i)).collect();
+    items.sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+    let total = items.len();
+    let mut results = Vec::new();
+    let mut scores = Vec::new();
+    let mut items = items.into_iter().peekable();
+    while items.peek().is_some_and(|(bound, _)| *bound >= threshold) {
+        let chunk: Vec<_> = items
+            .by_ref()
+            .take(CHUNK_SIZE)
+            .filter(|(bound, _)| *bound >= threshold)
+            .map(|(_, item)| item)
+            .collect();
+        let evaluated: Vec<_> = chunk.into_par_iter().map(&evaluate).collect();
+        scores.extend(evaluated.iter().map(&score).filter(|s| !s.is_nan()));
+        results.extend(evaluated);
+        if size > 0 && scores.len() >= size {
+            scores.sort_unstable_by(|a, b| b.total_cmp(a));
+            scores.truncate(size);
+            threshold = threshold.max(scores[size - 1]);
+        }
+    }
+    let skipped = total - results.len();
+    (results, skipped)
+}
+
+#[test]
+fn bound_holds() {
+    use crate::test_helpers::{parse, scoring};
+    use rustyms::align::align;
+    let scoring = scoring();
+    let query = parse("WGQGTLVTVSS");
+    for (database, ty) in [
+        // A perfect semi-global hit in a long database sequence
+        (
+            "EVQLVESGGGLVQPGGSLRLSCAASGFTFSDYWGQGTLVTVSS",
+            AlignType::GLOBAL_B,
+        ),
+        ("ANGTEWQ", AlignType::GLOBAL),
+        // N is isobaric with GG
+        ("WNQGTLVTVSS", AlignType::GLOBAL),
+    ] {
+        let database = parse(database);
+        let alignment = align::<4, _, _>(&database, &query, scoring, ty);
+        assert!(
+            normalised_score_bound(&database, &query, &scoring, ty) >= alignment.normalised_score()
+        );
+    }
+    assert!(normalised_score_bound(&query, &query, &scoring, AlignType::LOCAL).is_infinite());
+}
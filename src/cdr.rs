@@ -0,0 +1,111 @@
+use std::ops::Range;
+
+use itertools::Itertools;
+use rustyms::{imgt::Allele, peptidoform::Region, Peptidoform, SimpleLinear};
+
+/// A framework or CDR region of the query
+pub struct ExtractedRegion {
+    /// The region, always a [`Region::Framework`] or [`Region::ComplementarityDeterminingRegion`]
+    pub region: Region,
+    /// The location in the query (0 based, exclusive end)
+    pub range: Range<usize>,
+    /// The subsequence of the query, with all modifications in this range
+    pub peptide: Peptidoform<SimpleLinear>,
+}
+
+/// Extract the FR1-4 and CDR1-3 subsequences from the query based on the regions generated from a
+/// domain alignment (see [`crate::render::generate_regions`]). The regions are given in the order
+/// they appear in the query, regions that have no residues in the query are left out.
+pub fn extract_regions(
+    query: &Peptidoform<SimpleLinear>,
+    regions: &[(Region, usize)],
+) -> Vec<ExtractedRegion> {
+    let mut start = 0;
+    let mut output = Vec::new();
+    for (region, length) in regions {
+        let range = start..(start + length).min(query.len());
+        start += length;
+        if range.is_empty()
+            || !matches!(
+                region,
+                Region::Framework(1..=4) | Region::ComplementarityDeterminingRegion(1..=3)
+            )
+        {
+            continue;
+        }
+        output.push(ExtractedRegion {
+            region: region.clone(),
+            peptide: query.sub_peptide(range.clone()),
+            range,
+        });
+    }
+    output
+}
+
+/// Graft the CDRs of the query (as extracted with [`extract_regions`]) onto the frameworks of the
+/// acceptor germline, which has to be a V gene. As V genes end in the CDR3 the FR4 is taken from
+/// the query. Returns None if the query misses one of the CDRs or the acceptor one of FR1 to FR3.
+pub fn graft(query: &[ExtractedRegion], acceptor: &Allele) -> Option<Peptidoform<SimpleLinear>> {
+    let residues = acceptor
+        .sequence
+        .sequence()
+        .iter()
+        .map(|s| s.aminoacid.char())
+        .collect_vec();
+    let mut frameworks = Vec::new();
+    let mut start = 0;
+    for (region, length) in acceptor.regions {
+        if matches!(region, Region::Framework(1..=3)) {
+            frameworks.push(residues[start..start + length].iter().collect::<String>());
+        }
+        start += length;
+    }
+    let cdr = |n| {
+        query
+            .iter()
+            .find(|r| r.region == Region::ComplementarityDeterminingRegion(n))
+            .map(|r| r.peptide.to_string())
+    };
+    let fr4 = query
+        .iter()
+        .find(|r| r.region == Region::Framework(4))
+        .map(|r| r.peptide.to_string())
+        .unwrap_or_default();
+    if frameworks.len() != 3 {
+        return None;
+    }
+    let sequence = format!(
+        "{}{}{}{}{}{}{fr4}",
+        frameworks[0],
+        cdr(1)?,
+        frameworks[1],
+        cdr(2)?,
+        frameworks[2],
+        cdr(3)?
+    );
+    Peptidoform::pro_forma(&sequence, None)
+        .ok()
+        .and_then(|p| p.into_simple_linear())
+}
+
+#[test]
+fn extract() {
+    let query = Peptidoform::pro_forma("AAACCCDDDEE", None)
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let regions = extract_regions(
+        &query,
+        &[
+            (Region::Other("Unknown".to_string()), 1),
+            (Region::Framework(1), 2),
+            (Region::ComplementarityDeterminingRegion(1), 3),
+            (Region::Framework(2), 3),
+            (Region::ConstantLight, 2),
+        ],
+    );
+    assert_eq!(regions.len(), 3);
+    assert_eq!(regions[0].range, 1..3);
+    assert_eq!(regions[1].peptide.to_string(), "CCC");
+    assert_eq!(regions[2].range, 6..9);
+}
@@ -0,0 +1,172 @@
+use std::{
+    fs::File,
+    io::{Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use crate::index::file_stamp;
+
+/// The number of rows written between two checkpoints
+pub const CHECKPOINT_INTERVAL: usize = 100;
+
+/// The progress of a batch run. The checkpoint is saved next to the output file as
+/// `<OUTPUT>.checkpoint` and contains the number of finished rows and the size of the output file
+/// at that point, so any partially written row after the checkpoint can be discarded on resume.
+/// It also contains a fingerprint of the input files and settings, so a checkpoint is never
+/// applied to a run with other input.
+pub struct Checkpoint {
+    path: PathBuf,
+    /// The number of input rows already handled
+    pub rows: usize,
+    /// The number of bytes of the output file that contain the handled rows
+    pub bytes: u64,
+    fingerprint: String,
+}
+
+/// Identify the input of a batch run by the path, size, and modification time of all input files
+/// and a description of all settings that influence the output
+pub fn fingerprint(files: &[&Path], settings: &str) -> Result<String, String> {
+    let mut fingerprint = String::new();
+    for file in files {
+        let (size, modified) = file_stamp(file)?;
+        fingerprint += &format!("{}\t{size}\t{modified}\n", file.to_string_lossy());
+    }
+    Ok(fingerprint + settings)
+}
+
+impl Checkpoint {
+    /// Start a new checkpoint for the given output file
+    pub fn new(output: &Path, fingerprint: String) -> Self {
+        let mut path = output.as_os_str().to_owned();
+        path.push(".checkpoint");
+        Self {
+            path: PathBuf::from(path),
+            rows: 0,
+            bytes: 0,
+            fingerprint,
+        }
+    }
+
+    /// Load the checkpoint for the given output file, returns None if there is no checkpoint and an
+    /// error if it is invalid or made for a run with another fingerprint
+    pub fn load(output: &Path, fingerprint: String) -> Option<Result<Self, String>> {
+        let mut checkpoint = Self::new(output, fingerprint);
+        let content = std::fs::read_to_string(&checkpoint.path).ok()?;
+        let path = checkpoint.path.to_string_lossy().to_string();
+        let (progress, fingerprint) = content.split_once('\n').unwrap_or((&content, ""));
+        let Some((rows, bytes)) = progress
+            .trim()
+            .split_once(',')
+            .and_then(|(rows, bytes)| Some((rows.parse().ok()?, bytes.parse().ok()?)))
+        else {
+            return Some(Err(format!("Invalid checkpoint file: {path}")));
+        };
+        if fingerprint != checkpoint.fingerprint {
+            return Some(Err(format!(
+                "The input files or settings changed since the checkpoint {path} was saved, rerun without --resume"
+            )));
+        }
+        if std::fs::metadata(output).map_or(true, |m| m.len() < bytes) {
+            return Some(Err(format!(
+                "The output file {} is smaller than recorded in the checkpoint, rerun without --resume",
+                output.to_string_lossy()
+            )));
+        }
+        checkpoint.rows = rows;
+        checkpoint.bytes = bytes;
+        Some(Ok(checkpoint))
+    }
+
+    /// Open the output file to continue after the checkpoint, anything written after the checkpoint
+    /// is removed
+    pub fn resume(&self, output: &Path) -> std::io::Result<File> {
+        let mut file = std::fs::OpenOptions::new().write(true).open(output)?;
+        file.set_len(self.bytes)?;
+        file.seek(SeekFrom::End(0))?;
+        Ok(file)
+    }
+
+    /// Save the current progress
+    pub fn save(&self) -> std::io::Result<()> {
+        std::fs::write(
+            &self.path,
+            format!("{},{}\n{}", self.rows, self.bytes, self.fingerprint),
+        )
+    }
+
+    /// Remove the checkpoint after the run finished
+    pub fn remove(self) -> std::io::Result<()> {
+        std::fs::remove_file(&self.path).or_else(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                Ok(())
+            } else {
+                Err(e)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+/// A fresh directory for the files of a test
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("align-cli-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn round_trip() {
+    let dir = test_dir("checkpoint-round-trip");
+    let input = dir.join("input.csv");
+    let output = dir.join("output.csv");
+    std::fs::write(&input, "a,b\nAGK,AGK\n").unwrap();
+    std::fs::write(&output, "a,b,score\nAGK,AGK,1\n").unwrap();
+    let print = fingerprint(&[&input], "global").unwrap();
+    assert!(Checkpoint::load(&output, print.clone()).is_none());
+    let mut checkpoint = Checkpoint::new(&output, print.clone());
+    checkpoint.rows = 1;
+    checkpoint.bytes = 10;
+    checkpoint.save().unwrap();
+    let loaded = Checkpoint::load(&output, print.clone()).unwrap().unwrap();
+    assert_eq!((loaded.rows, loaded.bytes), (1, 10));
+    assert!(
+        Checkpoint::load(&output, fingerprint(&[&input], "local").unwrap())
+            .unwrap()
+            .is_err()
+    );
+    std::fs::write(&input, "a,b\nAGK,AGK\nWWW,WWW\n").unwrap();
+    assert!(
+        Checkpoint::load(&output, fingerprint(&[&input], "global").unwrap())
+            .unwrap()
+            .is_err()
+    );
+    loaded.remove().unwrap();
+    assert!(Checkpoint::load(&output, print).is_none());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn truncate_and_resume() {
+    use std::io::Write;
+    let dir = test_dir("checkpoint-resume");
+    let output = dir.join("output.csv");
+    std::fs::write(&output, "score\n1\n").unwrap();
+    let mut checkpoint = Checkpoint::new(&output, String::new());
+    checkpoint.rows = 1;
+    checkpoint.bytes = std::fs::metadata(&output).unwrap().len();
+    checkpoint.save().unwrap();
+    // A partially written row after the checkpoint
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&output)
+        .unwrap()
+        .write_all(b"0.")
+        .unwrap();
+    let checkpoint = Checkpoint::load(&output, String::new()).unwrap().unwrap();
+    let mut file = checkpoint.resume(&output).unwrap();
+    file.write_all(b"2\n").unwrap();
+    drop(file);
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "score\n1\n2\n");
+    std::fs::remove_dir_all(dir).unwrap();
+}
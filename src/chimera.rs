@@ -0,0 +1,175 @@
+use std::{collections::HashSet, hash::RandomState};
+
+use colored::Colorize;
+use rayon::prelude::*;
+use rustyms::{
+    align::{AlignScoring, AlignType, Alignment},
+    imgt::{Allele, AlleleSelection, GeneType, Selection},
+    Peptidoform, SimpleLinear,
+};
+use serde::Serialize;
+
+use crate::{alignment::align, cli::AlignmentKind};
+
+/// A constant gene from another species or chain than the V gene of a domain alignment
+pub struct Chimera {
+    /// The V gene of the domain alignment
+    pub v: Allele<'static>,
+    /// The best constant gene over all species and chains
+    pub c: Allele<'static>,
+    /// The normalised score of the best constant gene
+    pub score: f64,
+    /// The best constant gene from the same species and chain as the V gene, with its normalised score
+    pub same_species: Option<(Allele<'static>, f64)>,
+}
+
+/// Detect if a query is chimeric based on a chained domain alignment. As the chained alignment only
+/// uses germlines from the species and chain of the V gene, the part of the query aligned to the
+/// constant gene (or when no constant gene was found the part after the last segment) is aligned
+/// again to the constant genes of all species and chains. Returns None if the best of these is
+/// from the same species and chain as the V gene, if it does not score at least the margin higher
+/// than the best constant gene from that species and chain, or if there is no V gene or nothing
+/// left to align.
+pub fn detect<A>(
+    query: &Peptidoform<SimpleLinear>,
+    alignments: &[(Allele<'static>, Alignment<'_, A, SimpleLinear>)],
+    ty: AlignType,
+    allele: AlleleSelection,
+    scoring: AlignScoring,
+    kind: AlignmentKind,
+    margin: f64,
+) -> Option<Chimera> {
+    let v = alignments
+        .iter()
+        .find(|(a, _)| a.gene.kind == GeneType::V)?
+        .0
+        .clone();
+    let offset: usize = alignments
+        .iter()
+        .take_while(|(a, _)| !matches!(a.gene.kind, GeneType::C(_)))
+        .map(|(_, a)| a.start_b() + a.len_b())
+        .sum();
+    if offset >= query.len() {
+        return None;
+    }
+    let left = query.sub_peptide(offset..);
+    let mut hits: Vec<(Allele<'static>, f64)> = Selection::<RandomState, RandomState> {
+        species: None,
+        chains: None,
+        allele,
+        genes: Some(HashSet::from([GeneType::C(None)])),
+    }
+    .par_germlines()
+    .map(|germline| {
+        let score = align(germline.sequence, &left, scoring, ty, kind).normalised_score();
+        (germline, score)
+    })
+    .filter(|(_, score)| !score.is_nan())
+    .collect();
+    hits.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let same = |c: &Allele| c.species == v.species && c.gene.chain == v.gene.chain;
+    let (c, score) = hits.first()?.clone();
+    if same(&c) {
+        return None;
+    }
+    let same_species = hits.into_iter().find(|(c, _)| same(c));
+    let chimera = Chimera {
+        v,
+        c,
+        score,
+        same_species,
+    };
+    chimera
+        .gap()
+        .is_none_or(|gap| gap >= margin)
+        .then_some(chimera)
+}
+
+/// The JSON representation of a [`Chimera`]
+#[derive(Serialize)]
+struct ChimeraJson {
+    v: String,
+    v_species: String,
+    v_chain: String,
+    c: String,
+    c_species: String,
+    c_chain: String,
+    score: f64,
+    same_species: Option<SameSpeciesJson>,
+    gap: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct SameSpeciesJson {
+    c: String,
+    score: f64,
+}
+
+impl Chimera {
+    /// The normalised score gap between the best constant gene and the best same species alternative
+    pub fn gap(&self) -> Option<f64> {
+        self.same_species.as_ref().map(|(_, s)| self.score - s)
+    }
+
+    /// Print a warning
+    pub fn show(&self) {
+        println!(
+            "{}",
+            format!(
+                "Possible chimera: the V gene {} is from {} ({}), but the best constant gene {} is from {} ({}) with a normalised score of {:.3}",
+                self.v.name(),
+                self.v.species.scientific_name(),
+                self.v.gene.chain,
+                self.c.name(),
+                self.c.species.scientific_name(),
+                self.c.gene.chain,
+                self.score,
+            )
+            .yellow()
+        );
+        match &self.same_species {
+            Some((c, score)) => println!(
+                "{}",
+                format!(
+                    "The best {} constant gene is {} with a normalised score of {score:.3} ({:.3} lower)",
+                    self.v.species.scientific_name(),
+                    c.name(),
+                    self.score - score,
+                )
+                .dimmed()
+            ),
+            None => println!(
+                "{}",
+                format!(
+                    "There are no constant genes for {} {}",
+                    self.v.species.scientific_name(),
+                    self.v.gene.chain
+                )
+                .dimmed()
+            ),
+        }
+    }
+
+    /// The chimera as a JSON object, the scores are rounded to three decimals
+    pub fn json(&self) -> String {
+        let round = |score: f64| (score * 1000.0).round() / 1000.0;
+        serde_json::to_string(&ChimeraJson {
+            v: self.v.name(),
+            v_species: self.v.species.scientific_name().to_string(),
+            v_chain: self.v.gene.chain.to_string(),
+            c: self.c.name(),
+            c_species: self.c.species.scientific_name().to_string(),
+            c_chain: self.c.gene.chain.to_string(),
+            score: round(self.score),
+            same_species: self
+                .same_species
+                .as_ref()
+                .map(|(c, score)| SameSpeciesJson {
+                    c: c.name(),
+                    score: round(*score),
+                }),
+            gap: self.gap().map(round),
+        })
+        .unwrap()
+    }
+}
@@ -1,24 +1,44 @@
-use clap::{Args, Parser};
+use clap::{ArgGroup, Args, Parser, Subcommand};
+use itertools::Itertools;
 use rustyms::align::AlignScoring;
-use rustyms::imgt::{AlleleSelection, ChainType, Gene, GeneType, Species};
-use rustyms::modification::{SimpleModification, SimpleModificationInner};
-use rustyms::system::Mass;
+use rustyms::glycan::MonoSaccharide;
+use rustyms::imgt::{Allele, AlleleSelection, ChainType, Gene, GeneType, Species};
+use rustyms::modification::{Ontology, SimpleModification, SimpleModificationInner};
+use rustyms::system::{Mass, OrderedMass};
 use rustyms::{
-    align::{self, AlignType},
+    align::{self, AlignType, Side},
+    peptidoform::Region,
     placement_rule::*,
-    AminoAcid, MassMode, Peptidoform, Tolerance,
+    AminoAcid, Element, MassMode, MolecularFormula, Peptidoform, Protease, Tolerance,
 };
 use rustyms::{ReturnModification, SimpleLinear};
 use std::str::FromStr;
+
+use crate::alignment::{AlignSettings, DomainSettings};
+use crate::batch::MassLookup;
+use crate::custom;
+use crate::formula::{Adduct, ElementBound, FormulaTarget, MassDefect, Plausibility};
+use crate::glycan::Taxonomy;
+use crate::highlight::{Highlight, Motif, MotifElement};
+use crate::isobaric::{PatternElement, Terminus};
+use crate::label::Label;
+use crate::render::RenderOptions;
+use crate::retention::Calibration;
+use crate::tree::TreeMethod;
 use std::{collections::HashSet, fmt::Display};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
+#[command(group(ArgGroup::new("batch").args(["csv", "csv_search", "domain_batch", "mass_csv"])))]
+#[command(group(ArgGroup::new("formula_search").args(["formula", "mass_csv"])))]
 #[command(long_about = "It supports multiple use cases:
 
 1. Pairwise alignment
    - Align two sequences `align <A> <B>`, this shows the best alignment for these two sequences.
+   - Align three sequences at once `align <A> <B> <C> --triple`, this aligns B and C to A and shows them as one alignment.
    - Align a single peptide to a database `align <A> --file <FILE.fasta>`.
+   - Map multiple peptides onto a database and show the protein coverage `align --queries <A>,<B> --file <FILE.fasta>`.
+   - Align all sequences in a database against each other and build a tree `align --tree --file <FILE.fasta>`.
    - Align a single peptide to the IMGT database `align <A> --imgt`.
    - Align a single peptide to the V-J-C domains in the IMGT database `align <A> --domain`.
    - Align a single peptide to a specific gene in IMGT database `align <A> --specific-gene <GENE>`.
@@ -31,16 +51,35 @@ use std::{collections::HashSet, fmt::Display};
    - Use a formula to find all modifications with that formula eg `--modification Formula:O`
    - Use a mass to find all modifications with that mass eg `--modification +15.995`
 
-4. List IMGT genes `align --imgt` or `align --specific-gene <GENE>`.")]
+4. List IMGT genes `align --imgt` or `align --specific-gene <GENE>`.
+
+5. Build a persistent index for a fasta database `align index <FILE.fasta>`, later `--file` searches against this database use the index.")]
 pub struct Cli {
+    /// Additional tools, when a subcommand is given the other modes are not used
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// First sequence
     #[arg()]
     pub a: Option<String>,
 
+    /// The heavy chain of a paired antibody, both chains are aligned with a domain alignment and summarised together
+    /// (with a JSON object describing the pair), use together with --light
+    #[arg(long, requires = "light", conflicts_with = "a")]
+    pub heavy: Option<String>,
+
+    /// The light chain of a paired antibody, use together with --heavy
+    #[arg(long, requires = "heavy", conflicts_with = "a")]
+    pub light: Option<String>,
+
     /// The selection of second sequence, can only be one of these
     #[command(flatten)]
     pub second: SecondSelection,
 
+    /// Third sequence, only used for a three way alignment with `--triple`
+    #[arg(requires = "triple")]
+    pub c: Option<String>,
+
     /// The kind of alignment (normal/mass based/etc), can only be one of these
     #[command(flatten)]
     pub alignment_kind: AlignmentKind,
@@ -53,6 +92,10 @@ pub struct Cli {
     #[command(flatten)]
     pub scoring_matrix: ScoringMatrix,
 
+    /// The conventions used for the identity and gap statistics
+    #[command(flatten)]
+    pub stats: StatsConventions,
+
     /// The number of characters to show on a single line in the alignment
     #[arg(short = 'n', long, default_value_t = 50)]
     pub line_width: usize,
@@ -61,14 +104,105 @@ pub struct Cli {
     #[arg(short = 'c', long)]
     pub context: bool,
 
+    /// Highlight all residues matching this motif in both sequences of the alignment, can be given multiple times. A
+    /// letter is that residue, `X` or `.` is any residue, `[ST]` is any of the residues in the brackets, and `[^P]` is
+    /// any residue except those in the brackets. For example `--highlight "N[^P][ST]" --highlight DG` for glycosylation
+    /// sequons and isomerization sites.
+    #[arg(long, value_parser=motif_parser)]
+    pub highlight: Vec<Motif>,
+
+    /// Highlight the residues at these positions (1 based, comma separated) in both sequences of the alignment, eg
+    /// `--highlight-positions 34,52,96`. For a domain alignment the positions are in the query.
+    #[arg(long, value_delimiter = ',')]
+    pub highlight_positions: Vec<usize>,
+
+    /// The layout of the alignment, 'stacked' (default) shows A above B interleaved over multiple lines, 'side-by-side'
+    /// (or 'vertical') shows A and B in two columns with the kind of step in between and one step per row, which can be
+    /// easier to read for short peptides.
+    #[arg(long, value_parser=layout_parser, default_value = "stacked")]
+    pub layout: Layout,
+
+    /// Only show the differences of the alignment, like a unified diff. Every run of mismatches, gaps, and isobaric
+    /// steps is shown with its position in A and B (`@@ -<START>,<LENGTH> +<START>,<LENGTH> @@`) followed by the
+    /// residues of A (`-`) and B (`+`). This is easier to read than the full alignment for nearly identical sequences.
+    #[arg(long)]
+    pub diff: bool,
+
+    /// Colour every column of the alignment by its local score on a gradient from red (the lowest score in the
+    /// alignment) via yellow to green (the highest score), this overrides the colours for the kind of step.
+    #[arg(long)]
+    pub heatmap: bool,
+
+    /// Show a second ruler below the B sequence with the positions in B, next to the ruler with the positions in A. This
+    /// is useful when B is the query, as for a domain alignment.
+    #[arg(long)]
+    pub b_ruler: bool,
+
+    /// Show an additional line under the alignment with the local mass difference (B - A) of every isobaric, rotated,
+    /// or mass mismatch step. Give the unit as 'mda' (milli Dalton, default) or 'ppm' (relative to the mass of A).
+    #[arg(long, value_parser=mass_delta_unit_parser, num_args = 0..=1, default_missing_value = "mda")]
+    pub show_mass_deltas: Option<MassDeltaUnit>,
+
     /// The number of hits to show in the tables for file and IMGT alignment
     #[arg(short = 'N', long, default_value_t = 10)]
     pub number_of_hits: usize,
 
-    /// The maximal number of isobaric sets the generate, use `all` to generate all options
+    /// The column to rank the hits in the tables for file and IMGT alignment on, the ranking is applied before the
+    /// `--number-of-hits` cut. Use 'score' (normalised score, default), 'identity', 'mass-similarity', 'gaps'
+    /// (fewest gaps first), or 'species' (IMGT only). Ties are broken on the normalised score.
+    #[arg(long, value_parser=sort_key_parser, default_value_t = SortKey::Score)]
+    pub sort_by: SortKey,
+
+    /// The maximal number of isobaric sets to show, use `all` to show all options. All sets are generated and the ones
+    /// closest in mass are shown.
     #[arg(short, long, default_value_t = IsobaricNumber::Limited(25), value_parser=options_parse)]
     pub isobaric: IsobaricNumber,
 
+    /// Write the generated isobaric sets to this file instead, one ProForma sequence per line or if the file ends in
+    /// `.csv` as a csv file with the mass and error. Only the first few sets are shown in the terminal. The number of
+    /// sets is still set by `--isobaric`, use `--isobaric all` to write all sets.
+    #[arg(long, value_name = "PATH")]
+    pub isobaric_output: Option<String>,
+
+    /// Only keep one of the generated isobaric sets with the same residues in a different order.
+    #[arg(long)]
+    pub compositions_only: bool,
+
+    /// Generate isobaric sets for this neutral peptide mass (in Dalton, including the water of the termini) instead of
+    /// for a given sequence, eg `--isobaric-mass 1234.567`. The mass is taken as `--mass-mode` and `--tolerance` is used.
+    #[arg(long, conflicts_with = "a")]
+    pub isobaric_mass: Option<f64>,
+
+    /// A csv file with a column "mass" of neutral masses in Dalton (for example an MS1 feature list), for every row the
+    /// formulas (as `--formula`, using its options) or the isobaric sets (as `--isobaric-mass`) are looked up, see
+    /// `--mass-lookup`. The number found and the `-N` best formulas with their errors, or the first `-N` sets, are
+    /// appended as columns and written to `<FILE>_output.csv` (or `--output`).
+    #[arg(long, conflicts_with_all = ["a", "csv", "csv_search", "domain_batch"])]
+    pub mass_csv: Option<String>,
+
+    /// What to look up for every mass in `--mass-csv`: 'formula' (the default) or 'isobaric'
+    #[arg(long, value_parser=mass_lookup_parser, requires = "mass_csv")]
+    pub mass_lookup: Option<MassLookup>,
+
+    /// The N terminal modification of the generated isobaric sets, eg `--isobaric-n-term Acetyl`, or `free` for an
+    /// unmodified N terminus. By default the N terminal modification of the sequence is kept, when a different
+    /// modification is given the mass difference is taken into account.
+    #[arg(long, value_parser=terminus_parser, allow_hyphen_values=true)]
+    pub isobaric_n_term: Option<Terminus>,
+
+    /// The C terminal modification of the generated isobaric sets, eg `--isobaric-c-term Amidated`, or `free` for an
+    /// unmodified C terminus. By default the C terminal modification of the sequence is kept, when a different
+    /// modification is given the mass difference is taken into account.
+    #[arg(long, value_parser=terminus_parser, allow_hyphen_values=true)]
+    pub isobaric_c_term: Option<Terminus>,
+
+    /// Only generate isobaric sets that follow this pattern, separated by whitespace: `X` is any residue, `*` is any number
+    /// of residues, a letter is that residue, and `[ST]` is any of the residues in the brackets. For example `"* [KR]"`
+    /// for tryptic peptides or `"X X N [ST] *"` for a glycosylation motif at the third position. The fixed residues are
+    /// taken into account during the generation.
+    #[arg(long, value_parser=isobaric_pattern_parser)]
+    pub isobaric_pattern: Option<IsobaricPattern>,
+
     /// All possible fixed modifications that will be used in the isobaric sets generation, separated by commas `,`, commas can be
     /// escaped by wrapping the entire modification in square brackets `[..]`.
     /// You can overwrite the default placement rules in the same way as for variable modifications.
@@ -92,14 +226,126 @@ pub struct Cli {
     #[arg(long, value_parser=amino_acids_parser)]
     pub amino_acids: Option<AminoAcids>,
 
-    /// The tolerance for the isobaric set search and the definition for isobaric sets in the alignment, use `<x>ppm` or `<x>da` to control the unit, e.g. `10.0ppm` or `2.3da`
-    #[arg(short, long, default_value_t = Tolerance::new_ppm(10.0.into()), value_parser=mass_tolerance_parse)]
-    pub tolerance: Tolerance<Mass>,
+    /// The tolerance for the isobaric set search and the definition for isobaric sets in the alignment, use `<x>ppm` or `<x>da` to control the unit, e.g. `10.0ppm` or `2.3da`.
+    /// Use a combination like `10ppm+0.002da` to use whichever of the two gives the larger bound at a given mass. Alignments use a single tolerance for all steps, so there the one of the two that is larger for a single residue (111.1 Da) is used.
+    #[arg(short, long, global = true, default_value_t = MassTolerance::Single(Tolerance::new_ppm(10.0.into())), value_parser=mass_tolerance_parse)]
+    pub tolerance: MassTolerance,
 
-    /// A modification you want details on, if it is a mass shift modification e.g. `+58.01` it will show all predefined modifications that are within the tolerance of this mass
+    /// A modification you want details on, if it is a mass shift modification e.g. `+58.01` it will show all predefined modifications that are within the tolerance of this mass.
+    /// A modification can also be given by its accession e.g. `U:35`, `UNIMOD:35`, `MOD:00046`, `XLMOD:02001`, or `RESID:AA0037`.
     #[arg(short, long, value_parser=modification_parse, allow_hyphen_values=true)]
     pub modification: Option<SimpleModification>,
 
+    /// A file with one modification, formula, or mass per line (as in `--modification`), the modification search is run
+    /// for every line and all matches are written to one csv file (`<FILE>_output.csv`) with the query, the matched
+    /// modifications, and their mass error. Empty lines and lines starting with `#` are skipped.
+    #[arg(long, conflicts_with = "modification")]
+    pub modification_file: Option<String>,
+
+    /// A monosaccharide composition you want the formula and masses of, e.g. `HexNAc4Hex5NeuAc2`
+    #[arg(long, value_parser=glycan_parser)]
+    pub glycan: Option<Glycan>,
+
+    /// Also list all GNOme structures with the same monosaccharide composition as `--glycan`
+    #[arg(long, requires = "glycan")]
+    pub gnome: bool,
+
+    /// Only list the GNOme structures (for `--gnome` or a glycan `--modification`) found in this species, give the
+    /// common name (e.g. `human`), scientific name (e.g. `Mus musculus`), or NCBI taxonomy ID (e.g. `9606`)
+    #[arg(long, value_parser=taxonomy_parser)]
+    pub taxonomy: Option<Taxonomy>,
+
+    /// Only list the GNOme structures (for `--gnome` or a glycan `--modification`) found in this tissue according to
+    /// GlycomeAtlas, give (part of) the name (e.g. `brain`) or the UBERON ID (e.g. `UBERON_0000955`). Combined with
+    /// `--taxonomy` the tissue has to be reported for that species.
+    #[arg(long)]
+    pub tissue: Option<String>,
+
+    /// Show the SNFG symbols of the monosaccharides in the tree of a glycan structure (shown for GNOme modifications with a
+    /// known topology in `--modification`)
+    #[arg(long)]
+    pub snfg: bool,
+
+    /// Search the names and synonyms of all modifications in Unimod, PSI-MOD, GNOme, XL-MOD, RESID, and `--mod-db` for this text
+    /// (case insensitive), eg `--modification-search phospho`. This helps to find the name to use for `--modification`.
+    #[arg(long)]
+    pub modification_search: Option<String>,
+
+    /// A file with custom modifications (TSV, OBO, or the rustyms JSON format), these can be used everywhere a
+    /// modification is accepted, both in the options and in ProForma sequences, with `C:<name>` or `CUSTOM:<id>`.
+    /// The TSV format has the columns name, formula or mass, placement rules (as in `--fixed`), and description.
+    #[arg(long, value_name = "PATH")]
+    pub mod_db: Option<String>,
+
+    /// For a single sequence or a modification show the theoretical isotope distribution of the molecular formula,
+    /// with the abundances relative to the highest peak as a table and a bar chart.
+    #[arg(long)]
+    pub isotopes: bool,
+
+    /// Predict the reversed phase hydrophobicity index of a single sequence, or of both sequences in `--csv` mode, with
+    /// a simple SSRCalc style model. Give a calibration of the gradient as `<SLOPE>,<INTERCEPT>` (minutes per index unit,
+    /// minutes) to also predict the retention time, eg `--predict-rt 0.62,3.1`.
+    #[arg(long, value_parser=rt_calibration_parser, num_args = 0..=1)]
+    pub predict_rt: Option<Option<Calibration>>,
+
+    /// For a single sequence show the cumulative N terminal and C terminal residue masses (the b and y ladder without
+    /// charge), to check a sequence against a spectrum by hand.
+    #[arg(long)]
+    pub ladder: bool,
+
+    /// For a single sequence list the singly charged immonium ions (including modifications) and the diagnostic ions of
+    /// its modifications (from the ontology definitions) with their m/z, to guide the manual inspection of a spectrum.
+    #[arg(long)]
+    pub diagnostic_ions: bool,
+
+    /// For a single sequence apply a standard labelling chemistry and show the unlabelled and labelled masses with the
+    /// difference: 'silac-k8r10' (heavy lysine and arginine), 'tmt16' (TMTpro), or 'dimethyl' (both on lysine and the N
+    /// terminus). The label is also used as fixed modification in the isobaric generation.
+    #[arg(long, value_parser=label_parser)]
+    pub label: Option<Label>,
+
+    /// For a single sequence list every position where this modification is allowed according to its placement rules,
+    /// with the rules that allow it, eg `align PEPSTIDE --where Phospho`.
+    #[arg(long = "where", value_parser=modification_parse, allow_hyphen_values=true)]
+    pub where_modification: Option<SimpleModification>,
+
+    /// Find all molecular formulas (by default of C, H, N, O, and S, see `--elements`) with this monoisotopic mass, the
+    /// tolerance is half a unit in the last digit given, so `146.058` searches from 146.0575 to 146.0585 Da. The results are ranked by the mass error.
+    #[arg(long, value_parser=formula_target_parser)]
+    pub formula: Option<FormulaTarget>,
+
+    /// For `--formula` interpret the mass as that of a singly charged ion with one of these adducts, eg `Na,K,NH4,H-1`
+    /// searches for [M+Na]+, [M+K]+, [M+NH4]+, and [M-H]-. The neutral formulas are searched after removing the adduct.
+    #[arg(long, value_parser=adduct_parser, value_delimiter=',', requires = "formula_search")]
+    pub adducts: Vec<Adduct>,
+
+    /// For `--formula` the elements to use, with optional bounds on the count, eg `C,H,N,O,S,P,Se,Fe[0-2]`. A bound can
+    /// be given as `[2]` for an exact count, `[0-2]` for a range, or `[1-]` for only a minimum.
+    #[arg(long, value_parser=element_bound_parser, value_delimiter=',', requires = "formula_search")]
+    pub elements: Vec<ElementBound>,
+
+    /// For `--formula` use this tolerance instead of the one derived from the number of digits given, eg `3ppm` or
+    /// `0.002da`.
+    #[arg(long, value_parser=mass_tolerance_parse, requires = "formula_search")]
+    pub formula_tolerance: Option<MassTolerance>,
+
+    /// For `--formula` only show formulas with the common element ratios (H/C 0.2-3.1, N/C <= 1.3, O/C <= 1.2,
+    /// P/C <= 0.3, S/C <= 0.8) and an integer ring and double bond equivalent (RDBE), instead of the default of the
+    /// extended ratios (H/C 0.1-6, N/C <= 4, O/C <= 3, P/C <= 2, S/C <= 3) and a non negative RDBE.
+    #[arg(long, requires = "formula_search", conflicts_with = "lenient")]
+    pub strict: bool,
+
+    /// For `--formula` only show formulas with a mass defect (the monoisotopic mass minus the nominal mass of the
+    /// neutral formula) in this window, eg `0..0.3`. Prefix a repeating unit to use the Kendrick mass defect instead,
+    /// eg `CH2:-0.1..0.1`, this helps to separate peptide like formulas from lipid or polymer like formulas.
+    #[arg(long, value_parser=mass_defect_parser, requires = "formula_search", allow_hyphen_values=true)]
+    pub mass_defect: Option<MassDefect>,
+
+    /// For `--formula` show all formulas with a non negative ring and double bond equivalent, without filtering on the
+    /// element ratios.
+    #[arg(long, requires = "formula_search")]
+    pub lenient: bool,
+
     /// The species selected for any IMGT based alignments, you can use either the common or scientific name for the species.
     #[arg(long)]
     pub species: Option<Species>,
@@ -112,6 +358,12 @@ pub struct Cli {
     #[arg(long, value_parser=genes_parser)]
     pub genes: Option<HashSet<GeneType>>,
 
+    /// Only use the IMGT genes whose name matches one of these patterns (comma separated) for --imgt searches and
+    /// listings, `*` matches any number of characters and `?` a single character, eg `IGHV3*` or `IGHV1-?9`. The
+    /// pattern is matched to the gene name (`IGHV3-23`) and the allele name (`IGHV3-23*01`).
+    #[arg(long, value_delimiter = ',')]
+    pub gene_filter: Vec<String>,
+
     /// The genes selected for any IMGT based alignments, you can use either 'all' or 'first'.
     #[arg(long, value_parser=allele_parser, default_value = "first")]
     pub allele: AlleleSelection,
@@ -124,14 +376,146 @@ pub struct Cli {
     #[arg(long)]
     pub display_fasta: bool,
 
-    /// Generate annotation for a sequence based on domain gap align
+    /// List all species in the IMGT data (with --imgt) with the number of germlines per chain
+    #[arg(long, requires = "imgt")]
+    pub list_species: bool,
+
+    /// List all genes in the IMGT selection (with --imgt and species/chains/genes) with the number of alleles, the chain,
+    /// and the gene type
+    #[arg(long, requires = "imgt")]
+    pub list_genes: bool,
+
+    /// Print the listed IMGT germlines (with --specific-gene or --imgt) as JSON, with the sequence, the regions, and the
+    /// conserved and N-glycan annotations
+    #[arg(long)]
+    pub export_germline_json: bool,
+
+    /// Write the full IMGT selection (with --imgt and species/chains/genes/allele) to this fasta file instead of
+    /// showing the germlines, use --generate-annotation to add the regions to the headers
+    #[arg(long)]
+    pub export_fasta: Option<String>,
+
+    /// Generate annotation for a sequence based on domain gap align, or add the regions to the headers of --export-fasta
     #[arg(long)]
     pub generate_annotation: bool,
 
+    /// Align the query against the constant genes (use species/chains/allele to further specify the IMGT selection) and
+    /// show the determined isotype and subclass (eg IgG1 or kappa) with the positions that distinguish it from the
+    /// next best isotype
+    #[arg(long)]
+    pub isotype: bool,
+
+    /// Align the query to all human V germlines (use --chains to limit the chains) and show the humanness based on the
+    /// nearest human germline: the V identity and the germinality index (the identity over the frameworks)
+    #[arg(long)]
+    pub humanness: bool,
+
+    /// For --imgt searches collapse all alleles of the same gene into the best scoring allele, so that the table shows
+    /// distinct genes. This is only useful together with `--allele all`. This disables --fast.
+    #[arg(long)]
+    pub group_by_gene: bool,
+
+    /// For --imgt searches also show the germline with the highest identity for every framework and CDR separately,
+    /// to detect hybrid or recombined sequences. This uses all germlines of the selection and disables --fast.
+    #[arg(long, requires = "imgt")]
+    pub per_region_hits: bool,
+
+    /// Run the --imgt search separately for each of these species (comma separated) and show the best hit for every
+    /// species in one table, to determine the likely source organism of a sequence, eg `human,mouse,alpaca`
+    #[arg(
+        long,
+        value_delimiter = ',',
+        requires = "imgt",
+        conflicts_with = "species"
+    )]
+    pub compare_species: Vec<Species>,
+
+    /// Place the query among the V genes of the --imgt selection: align the query and all V germlines against each
+    /// other and show a tree (distance is 1 - normalised score) as a dendrogram and in Newick format. Use 'upgma'
+    /// (default) or 'nj' for neighbour joining. All pairs of germlines are aligned, so use --chains, --species, or
+    /// --gene-filter to limit the selection.
+    #[arg(long, value_parser=tree_parser, num_args = 0..=1, default_missing_value = "upgma", requires = "imgt")]
+    pub placement: Option<TreeMethod>,
+
+    /// List every position where the query differs from the germline for `--imgt` (the best match), `--domain` (all
+    /// genes), and `--specific-gene`. Every difference is given with the IMGT position, the germline and query
+    /// residues, the region, and if the residues have the same mass.
+    #[arg(long)]
+    pub mutations: bool,
+
+    /// The segments used for a domain alignment, in the order they are aligned, eg `VJ` for an Fv without constant
+    /// region [default: VJC]. The alignment type of every segment can be given in the same format as `--type`, eg
+    /// `V:11-,J:10-,C:11-` (the defaults), with `D` for the IgD constant gene as IMGT has no diversity genes. An
+    /// unspecified type uses the default: global for the germline on the left, free for the query on the left only for
+    /// the J, and either global on the right.
+    /// For example a VHH-Fc fusion with a linker between the domains can be aligned with `V,J,G:10-`.
+    #[arg(long, value_parser=segments_parser, requires = "domain")]
+    pub segments: Option<Segments>,
+
+    /// Segments of a domain alignment whose best alignment has a normalised score below this threshold are reported
+    /// as absent and the next segment is aligned at the same location in the query instead
+    #[arg(long, default_value_t = 0.25)]
+    pub segment_threshold: f64,
+
+    /// After a domain alignment show the germline and query padded to the IMGT unique numbering (positions 1 to 128
+    /// with the insertions, unoccupied positions are shown as `.`), also given in fasta format for downstream tools
+    #[arg(long, requires = "domain")]
+    pub imgt_gaps: bool,
+
+    /// Annotate the first sequence of a pairwise alignment with these regions, in the same format as generated by
+    /// `--generate-annotation`, eg `FR1:25;CDR1:8;FR2:17`. The alignment is then coloured by region as for
+    /// alignments against an IMGT germline, so an annotation can be refined without needing a germline.
+    #[arg(long, value_parser=regions_parser)]
+    pub regions: Option<RegionList>,
+
+    /// Weight the score of the steps of an alignment against a germline by the region of the germline, given as a
+    /// comma separated list of regions with their weight, eg `CDR3:0.2` to down weight the CDR3 so that a divergent
+    /// CDR3 does not pull the V assignment towards another family. Regions that are not listed get weight 1. The hits
+    /// of `--imgt` searches (this disables --fast) and of every segment of `--domain` alignments are ranked on the
+    /// weighted score.
+    #[arg(long, value_parser=region_weights_parser)]
+    pub region_weights: Option<RegionWeights>,
+
+    /// After a domain alignment graft the CDRs of the query onto the frameworks of this human V gene (eg `IGHV3-23*01`),
+    /// FR4 is taken from the query. The grafted sequence is shown as ProForma and fasta, together with its alignments
+    /// to the acceptor germline and the query.
+    #[arg(long, requires = "domain", value_parser=parse_specific_gene)]
+    pub graft: Option<(Gene, Option<usize>)>,
+
+    /// After a domain alignment analyse the junction: the CDR3 sequence and length, which residues are templated by the
+    /// V and J genes and which are not, and if the conserved anchors (C104 and W/F118) are present. With
+    /// `--generate-annotation` the analysis is also given as JSON as `JUNCTION=`.
+    #[arg(long, requires = "domain")]
+    pub junction: bool,
+
+    /// After a domain alignment scan the query for developability liabilities: NG/NS deamidation, DG isomerization,
+    /// DP cleavage, cysteines in the variable domain outside the conserved C23 and C104, and N-glycosylation sequons
+    /// and methionine oxidation in the CDRs. With `--generate-annotation` the motifs are also given as `LIABILITIES=`.
+    #[arg(long, requires = "domain")]
+    pub liabilities: bool,
+
+    /// Number the query of a domain alignment with an antibody numbering scheme instead of the plain residue ruler,
+    /// use 'imgt', 'kabat', 'chothia', or 'martin'. The numbers are based on the regions of the aligned IMGT genes, so
+    /// for unusual sequences these are an approximation of the scheme. With `--generate-annotation` the full
+    /// numbering is given as `NUMBERING=`.
+    #[arg(long, value_parser=numbering_parser, requires = "domain")]
+    pub numbering: Option<NumberingScheme>,
+
+    /// After a domain alignment show the FR1-4 and CDR1-3 subsequences of the query (as ProForma), based on the
+    /// regions of the best V, J, and C genes, both as a table and in fasta format.
+    #[arg(long, requires = "domain")]
+    pub extract_cdrs: bool,
+
     /// Set the mass mode for appropriate steps, use 'monoisotopic', 'average', or 'mostabundant'
     #[arg(long, value_parser=mass_mode_parser, default_value = "monoisotopic")]
     pub mass_mode: MassMode,
 
+    /// Show the monoisotopic mass, average weight, and most abundant mass side by side in the tables of the formula
+    /// search, the mass based modification search, and the database hits (as the mass difference of each hit), instead
+    /// of only the `--mass-mode`, to cross check against instruments that report a different mass.
+    #[arg(long)]
+    pub all_mass_modes: bool,
+
     /// The score for a mismatch, this is used as the full score of that step.
     #[arg(long, default_value = "-1", allow_hyphen_values = true)]
     pub score_mismatch: i8,
@@ -170,9 +554,180 @@ pub struct Cli {
     /// Multiple positions can be specified by using this argument multiple times.
     #[arg(long, value_parser=positions_parser)]
     pub positions: Option<Vec<(Vec<AminoAcid>, Position)>>,
+
+    /// For mass based modification searching (and `align delta`) also explain the mass as a combination of up to this
+    /// many Unimod modifications, eg Oxidation + Deamidated. At most 3 modifications can be combined.
+    #[arg(long, value_parser=combinations_parser, default_value_t = 1)]
+    pub combinations: usize,
+
+    /// Align multiple peptides to the `--file` database at once, separated by commas `,`. Every peptide is placed on its
+    /// best matching database protein and a coverage report is shown for every protein with at least one peptide.
+    #[arg(long, value_delimiter = ',', num_args = 1.., conflicts_with = "a", requires = "file")]
+    pub queries: Option<Vec<String>>,
+
+    /// Align all sequences in the `--file` database against each other and build a tree from the distances
+    /// (1 - normalised score). Use 'upgma' (default) or 'nj' for neighbour joining, the tree is given in Newick format.
+    #[arg(long, value_parser=tree_parser, num_args = 0..=1, default_missing_value = "upgma", conflicts_with = "a", requires = "file")]
+    pub tree: Option<TreeMethod>,
+
+    /// After a pairwise alignment transfer all modifications from one sequence onto the aligned positions of the other
+    /// sequence and show the resulting ProForma, use 'a-to-b' or 'b-to-a'.
+    #[arg(long, value_parser=transfer_parser)]
+    pub transfer_modifications: Option<TransferDirection>,
+
+    /// For a pairwise alignment find all alternative gap placements that give the exact same score. The steps
+    /// that differ between these co-optimal alignments are marked with '?' and all co-optimal paths are listed.
+    #[arg(long)]
+    pub ambiguity: bool,
+
+    /// For a pairwise alignment estimate the reliability of every step by scoring forced alternatives (breaking up
+    /// pairs and isobaric sets and shifting gaps). The alignment is coloured by this confidence: green is reliable,
+    /// yellow is uncertain, and red is unreliable (also marked with '!'). Unreliable isobaric sets are listed.
+    #[arg(long, conflicts_with = "ambiguity")]
+    pub confidence: bool,
+
+    /// Treat sequence B as circular, a pairwise alignment is made against all rotations of B and the best
+    /// rotation is shown. Useful for cyclic peptides or to check for head-to-tail rearrangements.
+    #[arg(long)]
+    pub circular: bool,
+
+    /// Align three sequences `align <A> <B> <C> --triple`. B and C are both aligned to A, and these alignments are
+    /// combined into a single three way alignment. The line below the alignment shows which sequence differs in every
+    /// column: 'b' or 'c' if only that sequence differs, 'a' if B and C agree but A differs, and 'x' if all differ.
+    #[arg(long, requires = "c", requires = "b")]
+    pub triple: bool,
+
+    /// Control how the ambiguous residues B, Z, J, and X in a pairwise alignment are scored. Use 'expand' to try all
+    /// possible resolutions (B = N/D, Z = Q/E, J = I/L, X = any) and show the best, 'average' to also show the
+    /// average score over all resolutions, or 'mismatch' to score ambiguous residues as mismatches whenever they are
+    /// aligned one-to-one, also to themselves. 'expand' and 'average' try all combinations of the resolutions of A
+    /// and B, with at most 4096 combinations. With 'mismatch' or without this option the ambiguous residues keep their
+    /// masses, so B/Z/J can be mass equivalent to their options and X (no mass) can be taken up in mass based steps.
+    #[arg(long, value_parser=ambiguous_parser, conflicts_with = "circular")]
+    pub ambiguous: Option<AmbiguousHandling>,
+
+    /// Report all non overlapping hits of sequence B in sequence A instead of only the best alignment. After every hit
+    /// the aligned region of A is masked and the search is repeated until no hit with at least the given normalised
+    /// score (default 0.5) is found. Use this with `--local` or `--semi-global` to find all occurrences of a peptide in a protein.
+    #[arg(long, num_args = 0..=1, default_missing_value = "0.5", requires = "b")]
+    pub all_hits: Option<f64>,
+
+    /// For a pairwise alignment show for every step how the score was built up from the scoring parameters (matrix
+    /// score, mass mismatch, isobaric/rotated scores, and gap penalties), to help to understand and tune the scoring.
+    #[arg(long, global = true)]
+    pub explain: bool,
+
+    /// Add a decoy for every sequence in the `--file` database, use 'reverse' or 'shuffle' to control how the decoys are made.
+    /// The hit table will then contain a q-value for every hit based on target-decoy competition.
+    #[arg(long, value_parser=decoy_parser)]
+    pub decoy: Option<DecoyKind>,
+
+    /// Speed up `--file` and `--imgt` searches by skipping any sequence for which an upper bound of the normalised score
+    /// (based on the amino acid composition) is lower than the score of the Nth best hit so far, this never skips a hit
+    /// that would be shown. The bound is only useful if one of the sequences is aligned globally (eg `--semi-global`
+    /// with the query as B), for local alignments nothing can be skipped. This cannot be combined with `--decoy` as the
+    /// q-values need all hits, nor with `--digest`. This is ignored if the hits are sorted on anything else than the
+    /// score (`--sort-by`).
+    #[arg(long, conflicts_with_all = ["decoy", "digest"])]
+    pub fast: bool,
+
+    /// A csv file with a column "a" of sequences, every sequence is searched against the `--file` database or the
+    /// `--imgt` selection and the best hit is written with its id and statistics, as in `--csv` (including the
+    /// optional override columns). As in `--file` mode the database sequence is A and the query is B in the alignment,
+    /// so use `--semi-global` to align peptides to proteins. This is meant to annotate a list of (de novo) peptides.
+    #[arg(long, conflicts_with_all = ["a", "b", "csv"])]
+    pub csv_search: Option<String>,
+
+    /// Annotate many sequences with a `--domain` alignment, from a fasta file or a csv file with the sequences in the
+    /// column "a" (and optionally the names in the column "id"). For every sequence the best V/J/C calls (or the
+    /// `--segments`) with their normalised score and identity and the `REGIONS=` annotation are written as columns to
+    /// `<FILE>_domains.csv` (or `--output`) without showing the alignments.
+    #[arg(long, requires = "domain", conflicts_with = "a")]
+    pub domain_batch: Option<String>,
+
+    /// For `--domain` only warn about a possible chimera (a constant gene from another species or chain than the V
+    /// gene) if its normalised score is at least this much higher than the best constant gene from the same species
+    /// and chain as the V gene.
+    #[arg(long, default_value_t = 0.05, requires = "domain")]
+    pub chimera_margin: f64,
+
+    /// Resume an interrupted `--csv` or `--csv-search` run from its checkpoint, rows that were already scored are not recomputed.
+    /// The checkpoint is saved every 100 rows next to the output file and removed when the run finishes. A checkpoint is
+    /// only used if the csv file, the `--csv-search` database, and the settings did not change since it was saved.
+    /// `--queries` runs are not checkpointed, they only show a summary at the end and do not write an output file that
+    /// could be continued, use `--csv-search` for large numbers of queries instead.
+    #[arg(long)]
+    pub resume: bool,
+
+    /// The output file for `--csv`, `--csv-search`, `--domain-batch`, and `--mass-csv` mode, use `-` to write to
+    /// stdout. By default the output is written next to the input as `<FILE>_output.csv`. Writing to stdout cannot be
+    /// combined with `--resume`.
+    #[arg(long, requires = "batch")]
+    pub output: Option<String>,
+
+    /// Add extra columns to the `--csv` and `--csv-search` output, give a comma separated list of 'aligned' (the gapped
+    /// aligned residues of a and b), 'coordinates' (the 1 based inclusive start and end of the alignment in a and b),
+    /// and 'components' (the identity, mass similarity, similarity, and gaps as fractions of the alignment length), or
+    /// 'all'.
+    #[arg(long, value_parser=csv_extra_columns_parser, requires = "batch", conflicts_with_all = ["domain_batch", "mass_csv"])]
+    pub csv_extra_columns: Option<CsvExtraColumns>,
+
+    /// The statistics written for every row in `--csv` and `--csv-search` mode, as a comma separated list of 'path',
+    /// 'score' (normalised), 'absolute_score', 'maximal_score', 'identical', 'mass_similar', 'gaps', 'length' (of the
+    /// alignment), 'identity', 'mass_similarity', 'similarity' (as fractions), 'mass_difference' (in Da), and 'ppm'.
+    /// Defaults to `path,score,absolute_score,maximal_score,identical,mass_similar,gaps,length`.
+    #[arg(long = "stats", value_parser=csv_stats_parser, requires = "batch", conflicts_with_all = ["domain_batch", "mass_csv"])]
+    pub csv_stats: Option<CsvStats>,
+
+    /// The delimiter of the `--csv`, `--csv-search`, `--domain-batch`, or `--mass-csv` file, use 'tab', 'comma',
+    /// 'semicolon', or any single character. By default this is detected from the header line (tab, semicolon, or
+    /// comma). The output uses the same delimiter as the input.
+    #[arg(long, value_parser=delimiter_parser, requires = "batch")]
+    pub delimiter: Option<char>,
+
+    /// Digest the `--file` database proteins before aligning, the query is aligned against all peptides and for every
+    /// protein the best peptide is reported with its cleavage window. Use `<ENZYME>[,missed=<N>]` with one of 'trypsin',
+    /// 'lys-c', 'arg-c', 'asp-n', 'glu-c', or 'chymotrypsin', the number of missed cleavages defaults to 2.
+    #[arg(long, value_parser=digest_parser)]
+    pub digest: Option<Digest>,
+
+    /// Show the consensus sequence of the top hits of a `--file` or `--imgt` search, with the agreement for every
+    /// position of the query. Use `-N` to control the number of hits used.
+    #[arg(long)]
+    pub consensus: bool,
+
+    /// Disable the k-mer prefilter for `--file` searches and align against every sequence in the database.
+    /// By default only database sequences that share at least one mass-equivalent k-mer with the query are aligned,
+    /// the k-mers are mass-equivalent if they are within 0.05 Da or the tolerance (whichever is larger).
+    #[arg(long)]
+    pub exhaustive: bool,
+
+    /// The length of the mass-equivalent k-mers used by the prefilter for `--file` searches, when a persistent index
+    /// is used the k-mer length of the index is used instead.
+    #[arg(long, default_value_t = 4, global = true)]
+    pub kmer_length: usize,
 }
 
 impl Cli {
+    /// The residues to highlight in the alignment, if any are given
+    pub fn highlight(&self) -> Option<Highlight> {
+        (!self.highlight.is_empty() || !self.highlight_positions.is_empty()).then(|| Highlight {
+            motifs: self.highlight.clone(),
+            positions: self.highlight_positions.clone(),
+        })
+    }
+
+    /// The plausibility filter for formula searches, see `--strict` and `--lenient`
+    pub const fn plausibility(&self) -> Plausibility {
+        if self.strict {
+            Plausibility::Strict
+        } else if self.lenient {
+            Plausibility::Lenient
+        } else {
+            Plausibility::Normal
+        }
+    }
+
     pub fn scoring(&self) -> AlignScoring<'static> {
         AlignScoring::<'static> {
             mismatch: self.score_mismatch,
@@ -183,10 +738,92 @@ impl Cli {
             gap_start: self.score_gap_start,
             gap_extend: self.score_gap_extend,
             matrix: self.scoring_matrix.matrix(),
-            tolerance: self.tolerance.convert(),
+            tolerance: self.tolerance.for_alignment().convert(),
             mass_mode: self.mass_mode,
         }
     }
+
+    /// The settings for a pairwise alignment
+    pub fn align_settings(&self) -> AlignSettings<'static> {
+        AlignSettings {
+            scoring: self.scoring(),
+            ty: self.alignment_type.ty(),
+            kind: self.alignment_kind,
+        }
+    }
+
+    /// The settings for rendering alignments
+    pub fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            tolerance: self.tolerance,
+            auto_kind: self.alignment_kind.auto_kind,
+            full_number: self.full_number,
+            stats: self.stats,
+            line_width: self.line_width,
+            context: self.context,
+            b_ruler: self.b_ruler,
+            heatmap: self.heatmap,
+            mass_deltas: self.show_mass_deltas.map(|unit| (unit, self.mass_mode)),
+            highlight: self.highlight(),
+            layout: self.layout,
+            diff: self.diff,
+        }
+    }
+
+    /// The settings for a `--domain` alignment against the selected germlines
+    pub fn domain_settings(&self) -> DomainSettings<'static> {
+        DomainSettings {
+            segments: self.segments.clone().unwrap_or_else(default_segments),
+            threshold: self.segment_threshold,
+            weights: self.region_weights.clone().unwrap_or_default(),
+            species: self.species.map(|s| HashSet::from([s])),
+            chains: self.chains.clone(),
+            allele: self.allele,
+            scoring: self.scoring(),
+            return_number: self.number_of_hits,
+            kind: self.alignment_kind,
+        }
+    }
+
+    /// Check if the allele matches the --gene-filter patterns, it always matches if there is no pattern
+    pub fn matches_gene_filter(&self, allele: &Allele) -> bool {
+        self.gene_filter.is_empty()
+            || self.gene_filter.iter().any(|pattern| {
+                glob(pattern, &allele.gene.to_string()) || glob(pattern, &allele.name())
+            })
+    }
+}
+
+/// Match a glob pattern (`*` for any number of characters, `?` for a single character) to a name
+fn glob(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.chars().collect_vec(), name.chars().collect_vec());
+    // The positions in the pattern that can be reached after the processed characters of the name
+    let mut states = vec![false; pattern.len() + 1];
+    states[0] = true;
+    let close = |states: &mut Vec<bool>| {
+        for i in 0..pattern.len() {
+            if states[i] && pattern[i] == '*' {
+                states[i + 1] = true;
+            }
+        }
+    };
+    close(&mut states);
+    for c in name {
+        let mut next = vec![false; pattern.len() + 1];
+        for i in 0..pattern.len() {
+            if states[i] {
+                match pattern[i] {
+                    '*' => next[i] = true,
+                    '?' => next[i + 1] = true,
+                    p if p == c => next[i + 1] = true,
+                    _ => (),
+                }
+            }
+        }
+        close(&mut next);
+        states = next;
+    }
+    states[pattern.len()]
 }
 
 fn positions_parser(value: &str) -> Result<(Vec<AminoAcid>, Position), String> {
@@ -208,6 +845,22 @@ fn positions_parser(value: &str) -> Result<(Vec<AminoAcid>, Position), String> {
         })
 }
 
+/// The layout of a pairwise alignment, see `--layout`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Layout {
+    #[default]
+    Stacked,
+    SideBySide,
+}
+
+fn layout_parser(value: &str) -> Result<Layout, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "stacked" => Ok(Layout::Stacked),
+        "side-by-side" | "vertical" => Ok(Layout::SideBySide),
+        _ => Err("Invalid layout, use 'stacked', 'side-by-side', or 'vertical'".to_string()),
+    }
+}
+
 fn mass_mode_parser(value: &str) -> Result<MassMode, String> {
     match value.trim().to_ascii_lowercase().as_str() {
         "monoisotopic" => Ok(MassMode::Monoisotopic),
@@ -217,6 +870,275 @@ fn mass_mode_parser(value: &str) -> Result<MassMode, String> {
     }
 }
 
+fn decoy_parser(value: &str) -> Result<DecoyKind, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "reverse" => Ok(DecoyKind::Reverse),
+        "shuffle" => Ok(DecoyKind::Shuffle),
+        _ => Err("Invalid decoy kind, use 'reverse' or 'shuffle'".to_string()),
+    }
+}
+
+fn enzyme_parser(value: &str) -> Result<Enzyme, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "trypsin" => Ok(Enzyme::Trypsin),
+        "lys-c" | "lysc" => Ok(Enzyme::LysC),
+        "arg-c" | "argc" => Ok(Enzyme::ArgC),
+        "asp-n" | "aspn" => Ok(Enzyme::AspN),
+        "glu-c" | "gluc" => Ok(Enzyme::GluC),
+        "chymotrypsin" => Ok(Enzyme::Chymotrypsin),
+        _ => Err(format!("Invalid enzyme: {value}, use 'trypsin', 'lys-c', 'arg-c', 'asp-n', 'glu-c', or 'chymotrypsin'")),
+    }
+}
+
+fn digest_parser(value: &str) -> Result<Digest, String> {
+    let (enzyme, missed) = value
+        .split_once(',')
+        .map_or((value, None), |(e, m)| (e, Some(m)));
+    let enzyme = enzyme_parser(enzyme)?;
+    let missed_cleavages = missed
+        .map(|m| {
+            m.trim()
+                .strip_prefix("missed=")
+                .and_then(|n| n.parse().ok())
+                .ok_or(format!(
+                    "Invalid missed cleavages definition: {m}, use 'missed=<N>'"
+                ))
+        })
+        .transpose()?
+        .unwrap_or(2);
+    Ok(Digest {
+        enzyme,
+        missed_cleavages,
+    })
+}
+
+fn transfer_parser(value: &str) -> Result<TransferDirection, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "a-to-b" => Ok(TransferDirection::AToB),
+        "b-to-a" => Ok(TransferDirection::BToA),
+        _ => Err("Invalid transfer direction, use 'a-to-b' or 'b-to-a'".to_string()),
+    }
+}
+
+fn ambiguous_parser(value: &str) -> Result<AmbiguousHandling, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "expand" => Ok(AmbiguousHandling::Expand),
+        "average" => Ok(AmbiguousHandling::Average),
+        "mismatch" => Ok(AmbiguousHandling::Mismatch),
+        _ => Err("Invalid ambiguous handling, use 'expand', 'average', or 'mismatch'".to_string()),
+    }
+}
+
+fn sort_key_parser(value: &str) -> Result<SortKey, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "score" => Ok(SortKey::Score),
+        "identity" => Ok(SortKey::Identity),
+        "mass-similarity" => Ok(SortKey::MassSimilarity),
+        "gaps" => Ok(SortKey::Gaps),
+        "species" => Ok(SortKey::Species),
+        _ => Err(
+            "Invalid sort key, use 'score', 'identity', 'mass-similarity', 'gaps', or 'species'"
+                .to_string(),
+        ),
+    }
+}
+
+fn numbering_parser(value: &str) -> Result<NumberingScheme, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "imgt" => Ok(NumberingScheme::Imgt),
+        "kabat" => Ok(NumberingScheme::Kabat),
+        "chothia" => Ok(NumberingScheme::Chothia),
+        "martin" => Ok(NumberingScheme::Martin),
+        _ => {
+            Err("Invalid numbering scheme, use 'imgt', 'kabat', 'chothia', or 'martin'".to_string())
+        }
+    }
+}
+
+pub(crate) fn formula_target_parser(value: &str) -> Result<FormulaTarget, String> {
+    let value = value.trim();
+    let mass: f64 = value
+        .parse()
+        .map_err(|_| format!("Invalid formula mass: '{value}'"))?;
+    if mass <= 0.0 {
+        return Err("The formula mass has to be positive".to_string());
+    }
+    let digits = value.split_once('.').map_or(0, |(_, d)| d.len());
+    Ok(FormulaTarget {
+        mass,
+        tolerance: 0.5 * 10_f64.powi(-(digits as i32)),
+    })
+}
+
+fn mass_defect_parser(value: &str) -> Result<MassDefect, String> {
+    let (base, window) = match value.trim().rsplit_once(':') {
+        Some((base, window)) => (Some(base.trim()), window),
+        None => (None, value.trim()),
+    };
+    let base = base
+        .map(|base| {
+            MolecularFormula::from_pro_forma(base, .., false, false, true)
+                .map_err(|err| format!("Invalid Kendrick base '{base}': {err}"))
+                .and_then(|formula| {
+                    if formula.monoisotopic_mass().value >= 0.5 {
+                        Ok(formula)
+                    } else {
+                        Err(format!(
+                            "The Kendrick base '{base}' needs a nominal mass of at least 1 Da"
+                        ))
+                    }
+                })
+        })
+        .transpose()?;
+    let (min, max) = window.split_once("..").ok_or_else(|| {
+        format!("Invalid mass defect window '{window}', use 'MIN..MAX' eg '0..0.3'")
+    })?;
+    let bound = |n: &str| {
+        n.trim()
+            .parse::<f64>()
+            .map_err(|_| format!("Invalid mass defect '{n}'"))
+    };
+    let (min, max) = (bound(min)?, bound(max)?);
+    if max < min {
+        return Err("Invalid mass defect window, the maximum is below the minimum".to_string());
+    }
+    Ok(MassDefect { base, min, max })
+}
+
+fn adduct_parser(value: &str) -> Result<Adduct, String> {
+    let mut elements = Vec::new();
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        let symbol = rest
+            .char_indices()
+            .skip(1)
+            .find(|(_, c)| !c.is_ascii_lowercase())
+            .map_or(rest.len(), |(i, _)| i);
+        let number = rest[symbol..]
+            .char_indices()
+            .find(|(i, c)| !(c.is_ascii_digit() || (*i == 0 && *c == '-')))
+            .map_or(rest.len() - symbol, |(i, _)| i);
+        let element = rest
+            .starts_with(|c: char| c.is_ascii_uppercase())
+            .then(|| Element::try_from(&rest[..symbol]).ok())
+            .flatten()
+            .filter(|e| *e != Element::Electron && e.mass(None).is_some())
+            .ok_or_else(|| format!("Invalid element '{}' in adduct '{value}'", &rest[..symbol]))?;
+        let count = match &rest[symbol..symbol + number] {
+            "" => 1,
+            "-" => -1,
+            n => n
+                .parse()
+                .map_err(|_| format!("Invalid count '{n}' in adduct '{value}'"))?,
+        };
+        if count != 0 {
+            elements.push((element, count));
+        }
+        rest = &rest[symbol + number..];
+    }
+    if elements.is_empty() {
+        return Err(format!("Empty adduct '{value}'"));
+    }
+    Ok(Adduct { elements })
+}
+
+fn element_bound_parser(value: &str) -> Result<ElementBound, String> {
+    let value = value.trim();
+    let (symbol, bounds) = match value.split_once('[') {
+        Some((symbol, bounds)) => (
+            symbol,
+            Some(
+                bounds
+                    .strip_suffix(']')
+                    .ok_or_else(|| format!("Missing closing bracket in '{value}'"))?,
+            ),
+        ),
+        None => (value, None),
+    };
+    let element = symbol
+        .starts_with(|c: char| c.is_ascii_uppercase())
+        .then(|| Element::try_from(symbol).ok())
+        .flatten()
+        .filter(|e| *e != Element::Electron && e.mass(None).is_some())
+        .ok_or_else(|| format!("Invalid element '{symbol}'"))?;
+    let count = |n: &str| {
+        n.trim()
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid count '{n}' for element '{symbol}'"))
+    };
+    let (min, max) = match bounds.map(|b| b.split_once('-')) {
+        None => (0, None),
+        Some(None) => {
+            let n = count(bounds.unwrap())?;
+            (n, Some(n))
+        }
+        Some(Some((min, max))) => (
+            if min.trim().is_empty() {
+                0
+            } else {
+                count(min)?
+            },
+            if max.trim().is_empty() {
+                None
+            } else {
+                Some(count(max)?)
+            },
+        ),
+    };
+    if max.is_some_and(|max| max < min) {
+        return Err(format!(
+            "Invalid bounds for element '{symbol}', the maximum is below the minimum"
+        ));
+    }
+    Ok(ElementBound { element, min, max })
+}
+
+fn combinations_parser(value: &str) -> Result<usize, String> {
+    match value.trim().parse::<usize>() {
+        Ok(n @ 1..=3) => Ok(n),
+        _ => Err("The number of combined modifications has to be 1, 2, or 3".to_string()),
+    }
+}
+
+fn rt_calibration_parser(value: &str) -> Result<Calibration, String> {
+    let (slope, intercept) = value
+        .split_once(',')
+        .ok_or("Give the calibration as <SLOPE>,<INTERCEPT>".to_string())?;
+    Ok(Calibration {
+        slope: slope
+            .trim()
+            .parse()
+            .map_err(|_| format!("'{slope}' is not a valid slope"))?,
+        intercept: intercept
+            .trim()
+            .parse()
+            .map_err(|_| format!("'{intercept}' is not a valid intercept"))?,
+    })
+}
+
+/// The unit of the local mass differences shown with `--show-mass-deltas`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MassDeltaUnit {
+    MilliDalton,
+    Ppm,
+}
+
+fn mass_delta_unit_parser(value: &str) -> Result<MassDeltaUnit, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "mda" => Ok(MassDeltaUnit::MilliDalton),
+        "ppm" => Ok(MassDeltaUnit::Ppm),
+        _ => Err("Invalid mass delta unit, use 'mda' or 'ppm'".to_string()),
+    }
+}
+
+fn tree_parser(value: &str) -> Result<TreeMethod, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "upgma" => Ok(TreeMethod::Upgma),
+        "nj" | "neighbour-joining" | "neighbor-joining" => Ok(TreeMethod::NeighbourJoining),
+        _ => Err("Invalid tree method, use 'upgma' or 'nj'".to_string()),
+    }
+}
+
 fn chains_parser(value: &str) -> Result<HashSet<ChainType>, String> {
     let mut set = HashSet::new();
     for c in value.chars() {
@@ -239,6 +1161,123 @@ fn genes_parser(value: &str) -> Result<HashSet<GeneType>, String> {
     Ok(set)
 }
 
+/// The segments used for a domain alignment if `--segments` is not given, V, J, and C with their
+/// default alignment types (see [`default_segment_type`])
+pub fn default_segments() -> Segments {
+    [GeneType::V, GeneType::J, GeneType::C(None)]
+        .map(|gene| (gene, default_segment_type(gene)))
+        .to_vec()
+}
+
+/// The alignment type used for a segment of a domain alignment if no type is specified, all
+/// segments are global on the left for the germline and continue directly after the previous
+/// segment, except the J which is free on the left for the query to allow for the non templated
+/// part of the CDR3. All segments are either global on the right.
+pub fn default_segment_type(gene: GeneType) -> AlignType {
+    AlignType {
+        left: Side::Specified {
+            a: true,
+            b: gene != GeneType::J,
+        },
+        right: Side::EitherGlobal,
+    }
+}
+
+fn segments_parser(value: &str) -> Result<Segments, String> {
+    let gene = |c: &str| {
+        GeneType::from_str(&c.to_ascii_uppercase()).map_err(|()| {
+            format!(
+                "Not a valid segment: {c}, use V, J, C, or a constant gene (A, D, E, G, M, O, T)"
+            )
+        })
+    };
+    let segments = if value.contains([':', ',']) {
+        value
+            .split(',')
+            .map(|segment| match segment.split_once(':') {
+                Some((g, ty)) => Ok((gene(g.trim())?, type_parser(ty.trim())?)),
+                None => gene(segment.trim()).map(|g| (g, default_segment_type(g))),
+            })
+            .collect::<Result<Segments, String>>()?
+    } else {
+        value
+            .chars()
+            .map(|c| gene(&c.to_string()).map(|g| (g, default_segment_type(g))))
+            .collect::<Result<Segments, String>>()?
+    };
+    if segments.is_empty() {
+        Err("At least one segment has to be given".to_string())
+    } else {
+        Ok(segments)
+    }
+}
+type Segments = Vec<(GeneType, AlignType)>;
+
+fn region_weights_parser(value: &str) -> Result<RegionWeights, String> {
+    let weights = value
+        .split(',')
+        .map(|pair| {
+            let (region, weight) = pair
+                .split_once(':')
+                .ok_or_else(|| format!("Not a valid region weight: {pair}, use eg 'CDR3:0.2'"))?;
+            let region = Region::from_str(&region.trim().to_ascii_uppercase()).unwrap();
+            if matches!(region, Region::Other(_) | Region::None) {
+                return Err(format!(
+                    "Not a valid region: {region}, use FR1-4, CDR1-3, CH1-4, CL, or H"
+                ));
+            }
+            let weight = weight
+                .trim()
+                .parse::<f64>()
+                .map_err(|err| format!("Not a valid weight: {err}"))?;
+            if weight.is_finite() && weight >= 0.0 {
+                Ok((region, weight))
+            } else {
+                Err(format!(
+                    "The weight for {region} has to be zero or positive"
+                ))
+            }
+        })
+        .collect::<Result<RegionWeights, String>>()?;
+    if weights.iter().all(|(_, weight)| *weight == 0.0) {
+        Err("At least one region weight has to be positive".to_string())
+    } else {
+        Ok(weights)
+    }
+}
+type RegionWeights = Vec<(Region, f64)>;
+
+fn regions_parser(value: &str) -> Result<RegionList, String> {
+    let value = value.trim();
+    let regions = value
+        .strip_prefix("REGIONS=")
+        .unwrap_or(value)
+        .split(';')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            let (region, length) = pair
+                .split_once(':')
+                .ok_or_else(|| format!("Not a valid region: {pair}, use eg 'FR1:25'"))?;
+            let length = length
+                .trim()
+                .parse::<usize>()
+                .map_err(|err| format!("Not a valid length for {region}: {err}"))?;
+            match Region::from_str(region.trim()).unwrap() {
+                Region::Other(name) => Err(format!(
+                    "Not a valid region: {name}, use FR1-4, CDR1-3, CH1-4, CHS, CL, H, or M"
+                )),
+                region => Ok((region, length)),
+            }
+        })
+        .collect::<Result<RegionList, String>>()?;
+    if regions.is_empty() {
+        Err("At least one region has to be given".to_string())
+    } else {
+        Ok(regions)
+    }
+}
+type RegionList = Vec<(Region, usize)>;
+
 fn allele_parser(value: &str) -> Result<AlleleSelection, String> {
     match value.trim().to_lowercase().as_str() {
         "all" => Ok(AlleleSelection::All),
@@ -255,22 +1294,141 @@ fn verify_cli() {
     Cli::command().debug_assert()
 }
 
-#[derive(Args, Debug, Clone, Copy)]
+#[derive(Args, Debug, Clone, Copy, Default)]
 #[group(multiple = false)]
 pub struct AlignmentKind {
+    /// The maximal length of isobaric sets in mass based alignment (default 4). Use 1 for normal alignment, any of
+    /// 1-16, 20, 24, 32, 48, 64, or 'unbounded'. Longer sets find more isobaric sets but take more time.
+    #[arg(long, value_parser=max_isobaric_length_parser)]
+    pub max_isobaric_length: Option<u16>,
+
     /// Use normal alignment (instead of the default of Mass alignment) this uses Smith Waterman or Needleman-Wunsch algorithms (based on the alignment mode)
     /// using the same modified BLOSUM62 scoring table as used in mass based alignment. Note: this is the same mass based alignment algorithm but set to a
     /// maximal length of isobaric sets of 1, meaning it will still handle modifications and show I/L as isobaric.
+    /// Alias for `--max-isobaric-length 1`.
     #[arg(long)]
     pub normal: bool,
 
     /// Do mass based alignment but allow for a maximal isobaric set length of 8 instead of the default 4.
+    /// Alias for `--max-isobaric-length 8`.
     #[arg(long)]
     pub mass_based_long: bool,
 
     /// Do mass based alignment but allow for an unbounded maximal isobaric set instead of the default 4.
+    /// Alias for `--max-isobaric-length unbounded`.
     #[arg(long)]
     pub mass_based_huge: bool,
+
+    /// Pick the maximal isobaric set length (1, 4, 8, or unbounded) for every alignment based on the length of the
+    /// sequences and the tolerance. The largest set length is picked that keeps the runtime predictable and for which
+    /// the tolerance at the mass of the set is still narrow enough to distinguish K and Q. The chosen length is shown
+    /// in the header of the alignment.
+    #[arg(long)]
+    pub auto_kind: bool,
+}
+
+/// The maximal isobaric set lengths that can be used, every length needs its own compiled
+/// version of the alignment algorithm, see `alignment::with_max_step`. Unbounded is [`u16::MAX`].
+pub const SUPPORTED_ISOBARIC_LENGTHS: [u16; 21] = [
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 20, 24, 32, 48, 64,
+];
+
+fn max_isobaric_length_parser(value: &str) -> Result<u16, String> {
+    let value = value.trim().to_ascii_lowercase();
+    if value == "unbounded" || value == "huge" {
+        return Ok(u16::MAX);
+    }
+    value
+        .parse::<u16>()
+        .ok()
+        .filter(|length| SUPPORTED_ISOBARIC_LENGTHS.contains(length))
+        .ok_or_else(|| {
+            format!(
+                "Invalid maximal isobaric length: {value}, use any of 1-16, 20, 24, 32, 48, 64, or 'unbounded'"
+            )
+        })
+}
+
+/// The maximal number of cells times the squared isobaric step that `--auto-kind` allows
+const AUTO_KIND_BUDGET: usize = 50_000_000;
+
+/// The maximal tolerance width (in Dalton) at the mass of an isobaric step that `--auto-kind`
+/// allows, this is narrower than the difference between K and Q (0.036 Da)
+const AUTO_KIND_TOLERANCE: f64 = 0.02;
+
+impl AlignmentKind {
+    /// An alignment kind with the given maximal isobaric step
+    pub const fn with_max_step(max_step: u16) -> Self {
+        Self {
+            max_isobaric_length: Some(max_step),
+            normal: false,
+            mass_based_long: false,
+            mass_based_huge: false,
+            auto_kind: false,
+        }
+    }
+
+    /// Resolve `--auto-kind` into a concrete alignment kind for the given sequence lengths and
+    /// tolerance, any other kind is returned as is.
+    pub fn resolve(self, len_a: usize, len_b: usize, tolerance: Tolerance<OrderedMass>) -> Self {
+        if !self.auto_kind {
+            return self;
+        }
+        let tolerance = match tolerance {
+            Tolerance::Relative(relative) => Tolerance::Relative(relative),
+            Tolerance::Absolute(absolute) => Tolerance::Absolute(absolute.into_inner()),
+        };
+        [u16::MAX, 8, 4]
+            .into_iter()
+            .find(|max_step| {
+                let step = usize::from(*max_step).min(len_a.max(len_b)).max(1);
+                let (low, high) = tolerance.bounds(Mass::new::<rustyms::system::dalton>(
+                    step as f64 * AVERAGE_RESIDUE_MASS,
+                ));
+                len_a * len_b * step * step <= AUTO_KIND_BUDGET
+                    && (high - low).value <= AUTO_KIND_TOLERANCE
+            })
+            .map_or(Self::with_max_step(1), Self::with_max_step)
+    }
+
+    /// The maximal isobaric step for this kind of alignment, for `--auto-kind` this is the default
+    /// of 4 as the actual step depends on the sequences, use [`Self::resolve`] first to get that step
+    pub const fn max_step(&self) -> u16 {
+        if let Some(length) = self.max_isobaric_length {
+            length
+        } else if self.normal {
+            1
+        } else if self.mass_based_huge {
+            u16::MAX
+        } else if self.mass_based_long {
+            8
+        } else {
+            4
+        }
+    }
+}
+
+/// The conventions for counting identity and gaps, different fields define percent identity differently
+#[derive(Args, Debug, Clone, Copy, Default)]
+pub struct StatsConventions {
+    /// Do not count gaps at the start and end of the alignment in the gaps and the length of the alignment,
+    /// as for example BLAST does for percent identity.
+    #[arg(long)]
+    pub ignore_terminal_gaps: bool,
+
+    /// Count isobaric and rotated steps as identical positions, so the identity reflects mass identity.
+    #[arg(long)]
+    pub isobaric_identity: bool,
+
+    /// Do not count identical amino acids with a different mass (for example because only one of them is
+    /// modified) as identical positions.
+    #[arg(long)]
+    pub strict_identity: bool,
+
+    /// Also show the identity and mass similarity for every region (FR1, CDR1, etc) separately in
+    /// the header of alignments against a germline, as a divergent CDR is hidden in the overall identity.
+    #[arg(long)]
+    pub region_identity: bool,
 }
 
 #[derive(Args, Debug)]
@@ -306,6 +1464,24 @@ pub struct ScoringMatrix {
 }
 
 impl ScoringMatrix {
+    /// Get a scoring matrix by name, eg `blosum62` or `pam30`
+    pub fn by_name(
+        name: &str,
+    ) -> Result<&'static [[i8; AminoAcid::TOTAL_NUMBER]; AminoAcid::TOTAL_NUMBER], String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "blosum45" => Ok(align::matrix::BLOSUM45),
+            "blosum50" => Ok(align::matrix::BLOSUM50),
+            "blosum62" => Ok(align::matrix::BLOSUM62),
+            "blosum80" => Ok(align::matrix::BLOSUM80),
+            "blosum90" => Ok(align::matrix::BLOSUM90),
+            "identity" => Ok(align::matrix::IDENTITY),
+            "pam30" => Ok(align::matrix::PAM30),
+            "pam70" => Ok(align::matrix::PAM70),
+            "pam250" => Ok(align::matrix::PAM250),
+            _ => Err(format!("Not a valid scoring matrix: '{name}'")),
+        }
+    }
+
     pub fn matrix(&self) -> &'static [[i8; AminoAcid::TOTAL_NUMBER]; AminoAcid::TOTAL_NUMBER] {
         if self.blosum45 {
             align::matrix::BLOSUM45
@@ -360,6 +1536,18 @@ pub struct AlignmentType {
 }
 
 impl AlignmentType {
+    /// Get an alignment type by the name of one of the flags (`global`, `semi-global`, `semi-global-a`, or `local`),
+    /// or the full specification as in `--type`
+    pub fn by_name(name: &str) -> Result<AlignType, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "global" => Ok(AlignType::GLOBAL),
+            "semi-global" => Ok(AlignType::GLOBAL_B),
+            "semi-global-a" => Ok(AlignType::GLOBAL_A),
+            "local" => Ok(AlignType::LOCAL),
+            _ => type_parser(name.trim()),
+        }
+    }
+
     pub fn ty(&self) -> rustyms::align::AlignType {
         if let Some(ty) = self.r#type {
             ty
@@ -387,7 +1575,10 @@ pub struct SecondSelection {
     pub file: Option<String>,
 
     /// A csv file of pairs of sequences to score it returns a csv file with statistics added as last columns.
-    /// The requirement is that the pair columns have to be called "a" and "b".
+    /// The requirement is that the pair columns have to be called "a" and "b". The optional columns "tolerance" (eg
+    /// `10ppm`), "type" (`global`, `local`, `semi-global`, `semi-global-a`, or as in `--type`), and "matrix" (eg
+    /// `blosum45` or `identity`) override the global settings for that row, leave a cell empty to use the global setting.
+    /// A row with an invalid override is reported and written with empty statistics.
     #[arg(long)]
     pub csv: Option<String>,
 
@@ -395,7 +1586,9 @@ pub struct SecondSelection {
     #[arg(long)]
     pub imgt: bool,
 
-    /// Align against one specific IMGT gene, using species is required if this is used.
+    /// Align against one specific IMGT gene, using species is required if this is used. Without an allele (eg
+    /// `IGHV3-23` instead of `IGHV3-23*01`) the first allele is used, or if no sequence is given all alleles are
+    /// shown stacked with the differences to the first allele highlighted.
     #[arg(long, value_parser=parse_specific_gene)]
     pub specific_gene: Option<(Gene, Option<usize>)>,
 
@@ -405,12 +1598,474 @@ pub struct SecondSelection {
 }
 
 fn parse_specific_gene(value: &str) -> Result<(Gene, Option<usize>), String> {
-    Gene::from_imgt_name_with_allele(value)
-        .map(|(g, a)| (g, Some(a)))
-        .or_else(|_| Gene::from_imgt_name(value).map(|g| (g, None)))
+    if value.contains('*') {
+        Gene::from_imgt_name_with_allele(value).map(|(g, a)| (g, Some(a)))
+    } else {
+        Gene::from_imgt_name(value).map(|g| (g, None))
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Build a persistent index (record offsets and k-mer seeds) for a fasta database. The index is saved
+    /// next to the database as `<FILE>.idx` and is automatically used by `--file` searches on that database
+    /// as long as the database is not changed.
+    Index {
+        /// The fasta database to index
+        fasta: String,
+    },
+    /// Compare the interpretation of a query by two references, the query is aligned to both references (or the
+    /// given saved paths are used) and both alignments are shown stacked on the query with a track that shows where
+    /// the two alignments disagree.
+    Compare {
+        /// The query sequence
+        query: String,
+        /// The first reference
+        first: String,
+        /// The second reference
+        second: String,
+        /// Use this saved path (with the first reference as A and the query as B, both starting at 0) instead of
+        /// aligning the query to the first reference
+        #[arg(long)]
+        first_path: Option<String>,
+        /// Use this saved path (with the second reference as A and the query as B, both starting at 0) instead of
+        /// aligning the query to the second reference
+        #[arg(long)]
+        second_path: Option<String>,
+    },
+    /// Align two IMGT germlines against each other, eg `align imgt-compare IGHV3-23*01 IGHV3-30*01 --species human`,
+    /// to see how related two germline genes are. The regions of the first germline are annotated. If no allele is
+    /// given the first allele of the gene is used.
+    ImgtCompare {
+        /// The first germline
+        #[arg(value_parser=parse_specific_gene)]
+        first: (Gene, Option<usize>),
+        /// The second germline
+        #[arg(value_parser=parse_specific_gene)]
+        second: (Gene, Option<usize>),
+        /// The species of both germlines, this can also be given before `imgt-compare`
+        #[arg(long)]
+        species: Option<Species>,
+    },
+    /// Explain the mass difference between two peptidoforms, eg `align delta PEPTIDE PEPTIDE[Oxidation]`. The exact
+    /// mass difference (B - A) is searched in the ontologies (see `--positions` to limit the modifications) and as
+    /// single amino acid substitutions of residues in A, using the tolerance at the mass of B.
+    Delta {
+        /// The first peptidoform
+        a: String,
+        /// The second peptidoform
+        b: String,
+    },
+    /// Compare the chemistry of two peptidoforms, eg `align elements PEPK PEPK[Label:13C(6)15N(2)]`. Shows both
+    /// molecular formulas, the element wise difference (B - A), and the mass difference in all mass modes.
+    Elements {
+        /// The first peptidoform
+        a: String,
+        /// The second peptidoform
+        b: String,
+    },
+    /// Find cross-linked peptide pairs that explain a precursor mass, eg `align crosslink 2009.02 PEPKIDE AKTNLSR`. All
+    /// cross-linkers from XL-MOD (and `--mod-db`) that can be placed on both peptides are tried, using `--tolerance` and
+    /// `--mass-mode`. Leave out the second peptide to search the peptides of the `--file` database (digested with
+    /// `--digest`, trypsin with 2 missed cleavages by default) instead. These options have to be given before `crosslink`.
+    Crosslink {
+        /// The neutral mass of the precursor in Dalton
+        mass: f64,
+        /// The first peptidoform
+        a: String,
+        /// The second peptidoform
+        b: Option<String>,
+    },
+    /// Digest a protein and list the resulting peptides with their positions and masses, eg `align digest <SEQUENCE>` or
+    /// `align digest <FILE.fasta> --enzyme lys-c`. The `--fixed` modifications (given before `digest`) are applied to the
+    /// peptides, note that the ontology placement rules are often broad so give the rule as in `-F Carbamidomethyl@C`.
+    Digest {
+        /// A protein sequence or a fasta file
+        input: String,
+        /// The enzyme: 'trypsin', 'lys-c', 'arg-c', 'asp-n', 'glu-c', or 'chymotrypsin'
+        #[arg(long, default_value_t = Enzyme::Trypsin, value_parser=enzyme_parser)]
+        enzyme: Enzyme,
+        /// The maximal number of missed cleavages
+        #[arg(long, default_value_t = 2)]
+        missed: usize,
+        /// Output the peptides as a fasta file instead of a table
+        #[arg(long)]
+        fasta: bool,
+    },
+    /// Strictly parse ProForma sequences and show all errors and warnings, eg `align validate <SEQUENCE>` or
+    /// `align validate <FILE>` with one sequence per line (empty lines and lines starting with `#` are skipped). Exits
+    /// with a non zero code if any sequence is invalid.
+    Validate {
+        /// A ProForma sequence or a file with sequences
+        input: String,
+    },
+    /// List all substitutions of one or two residues by one or two other residues that explain a mass difference, eg
+    /// `align substitutions 14.016 --tolerance 0.02da`. Leave out the difference to list all near-isobaric substitutions.
+    /// The tolerance is taken at the mass of the original residues.
+    Substitutions {
+        /// The mass difference (new - original) in Dalton
+        #[arg(default_value_t = 0.0, allow_hyphen_values = true)]
+        delta: f64,
+    },
+    /// Reconstruct and show a previously computed alignment from its path (as shown in the alignment header
+    /// and stored in the csv output), without aligning again. The scoring, alignment type, and alignment kind
+    /// are taken from the normal options, which have to be given before `replay`.
+    Replay {
+        /// The first sequence
+        #[arg(long)]
+        a: String,
+        /// The second sequence
+        #[arg(long)]
+        b: String,
+        /// The path of the alignment, eg `4=1X5=`
+        #[arg(long)]
+        path: String,
+        /// The start position of the alignment on the first sequence (0 based)
+        #[arg(long, default_value_t = 0)]
+        start_a: usize,
+        /// The start position of the alignment on the second sequence (0 based)
+        #[arg(long, default_value_t = 0)]
+        start_b: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum TransferDirection {
+    AToB,
+    BToA,
+}
+impl Display for TransferDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AToB => write!(f, "A → B"),
+            Self::BToA => write!(f, "B → A"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmbiguousHandling {
+    /// Try all resolutions and take the best
+    Expand,
+    /// Try all resolutions, take the best and report the average score
+    Average,
+    /// Score every ambiguous residue as a mismatch
+    Mismatch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberingScheme {
+    Imgt,
+    Kabat,
+    Chothia,
+    /// Enhanced Chothia, which places the framework insertions at structurally correct positions
+    Martin,
+}
+impl Display for NumberingScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Imgt => "IMGT",
+                Self::Kabat => "Kabat",
+                Self::Chothia => "Chothia",
+                Self::Martin => "Martin",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Score,
+    Identity,
+    MassSimilarity,
+    Gaps,
+    Species,
+}
+impl Display for SortKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Score => "score",
+                Self::Identity => "identity",
+                Self::MassSimilarity => "mass-similarity",
+                Self::Gaps => "gaps",
+                Self::Species => "species",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Digest {
+    pub enzyme: Enzyme,
+    pub missed_cleavages: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Enzyme {
+    Trypsin,
+    LysC,
+    ArgC,
+    AspN,
+    GluC,
+    Chymotrypsin,
+}
+
+impl Enzyme {
+    /// The cleavage rules for this enzyme
+    pub fn protease(self) -> Protease {
+        let not_proline = || {
+            Some(
+                AminoAcid::CANONICAL_AMINO_ACIDS
+                    .iter()
+                    .copied()
+                    .filter(|aa| *aa != AminoAcid::Proline)
+                    .collect(),
+            )
+        };
+        match self {
+            Self::Trypsin => Protease {
+                n_term: vec![Some(vec![AminoAcid::Lysine, AminoAcid::Arginine])],
+                c_term: vec![not_proline()],
+            },
+            Self::LysC => Protease::c_terminal_of(&[AminoAcid::Lysine]),
+            Self::ArgC => Protease::c_terminal_of(&[AminoAcid::Arginine]),
+            Self::AspN => Protease::n_terminal_of(&[AminoAcid::AsparticAcid]),
+            Self::GluC => Protease::c_terminal_of(&[AminoAcid::GlutamicAcid]),
+            Self::Chymotrypsin => Protease {
+                n_term: vec![Some(vec![
+                    AminoAcid::Phenylalanine,
+                    AminoAcid::Tryptophan,
+                    AminoAcid::Tyrosine,
+                ])],
+                c_term: vec![not_proline()],
+            },
+        }
+    }
+}
+
+impl Display for Enzyme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Trypsin => "trypsin",
+                Self::LysC => "lys-c",
+                Self::ArgC => "arg-c",
+                Self::AspN => "asp-n",
+                Self::GluC => "glu-c",
+                Self::Chymotrypsin => "chymotrypsin",
+            }
+        )
+    }
+}
+
+/// The additional columns in the `--csv` output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CsvExtraColumns {
+    pub aligned: bool,
+    pub coordinates: bool,
+    pub components: bool,
+}
+
+impl CsvExtraColumns {
+    /// The header of the extra columns
+    pub fn header(self) -> Vec<&'static str> {
+        let mut output = Vec::new();
+        if self.aligned {
+            output.extend(["aligned a", "aligned b"]);
+        }
+        if self.coordinates {
+            output.extend(["start a", "end a", "start b", "end b"]);
+        }
+        if self.components {
+            output.extend(["identity", "mass similarity", "similarity", "gaps fraction"]);
+        }
+        output
+    }
+}
+
+/// A statistic written for every row in `--csv` mode, see `--stats`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvStat {
+    Path,
+    Score,
+    AbsoluteScore,
+    MaximalScore,
+    Identical,
+    MassSimilar,
+    Gaps,
+    Length,
+    Identity,
+    MassSimilarity,
+    Similarity,
+    MassDifference,
+    Ppm,
+}
+
+impl CsvStat {
+    pub const ALL: &'static [Self] = &[
+        Self::Path,
+        Self::Score,
+        Self::AbsoluteScore,
+        Self::MaximalScore,
+        Self::Identical,
+        Self::MassSimilar,
+        Self::Gaps,
+        Self::Length,
+        Self::Identity,
+        Self::MassSimilarity,
+        Self::Similarity,
+        Self::MassDifference,
+        Self::Ppm,
+    ];
+
+    /// The statistics written if `--stats` is not given
+    pub const DEFAULT: &'static [Self] = &[
+        Self::Path,
+        Self::Score,
+        Self::AbsoluteScore,
+        Self::MaximalScore,
+        Self::Identical,
+        Self::MassSimilar,
+        Self::Gaps,
+        Self::Length,
+    ];
+
+    /// The column name in the output
+    pub const fn header(self) -> &'static str {
+        match self {
+            Self::Path => "path",
+            Self::Score => "score",
+            Self::AbsoluteScore => "absolute score",
+            Self::MaximalScore => "maximal score",
+            Self::Identical => "identical",
+            Self::MassSimilar => "mass similar",
+            Self::Gaps => "gaps",
+            Self::Length => "length",
+            Self::Identity => "identity",
+            Self::MassSimilarity => "mass similarity",
+            Self::Similarity => "similarity",
+            Self::MassDifference => "mass difference",
+            Self::Ppm => "ppm",
+        }
+    }
+}
+
+fn csv_stats_parser(value: &str) -> Result<CsvStats, String> {
+    value
+        .split(',')
+        .map(|name| {
+            let name = name.trim().to_ascii_lowercase().replace(['_', '-'], " ");
+            CsvStat::ALL
+                .iter()
+                .find(|stat| stat.header() == name)
+                .copied()
+                .ok_or_else(|| {
+                    format!(
+                        "Not a valid statistic: {name}, use any of {}",
+                        CsvStat::ALL
+                            .iter()
+                            .map(|stat| stat.header().replace(' ', "_"))
+                            .join(", ")
+                    )
+                })
+        })
+        .collect()
+}
+type CsvStats = Vec<CsvStat>;
+
+fn csv_extra_columns_parser(value: &str) -> Result<CsvExtraColumns, String> {
+    let mut columns = CsvExtraColumns::default();
+    for column in value.split(',').map(|c| c.trim().to_ascii_lowercase()) {
+        match column.as_str() {
+            "aligned" => columns.aligned = true,
+            "coordinates" => columns.coordinates = true,
+            "components" => columns.components = true,
+            "all" => {
+                columns = CsvExtraColumns {
+                    aligned: true,
+                    coordinates: true,
+                    components: true,
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "Not a valid column: {column}, use 'aligned', 'coordinates', 'components', or 'all'"
+                ))
+            }
+        }
+    }
+    Ok(columns)
+}
+
+fn delimiter_parser(value: &str) -> Result<char, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "tab" | "\\t" => Ok('\t'),
+        "comma" => Ok(','),
+        "semicolon" => Ok(';'),
+        _ => match value.chars().collect_vec().as_slice() {
+            [c] if c.is_ascii() && *c != '"' => Ok(*c),
+            _ => Err(format!(
+                "Not a valid delimiter: {value}, use 'tab', 'comma', 'semicolon', or a single character"
+            )),
+        },
+    }
+}
+
+/// Detect the delimiter of a csv file from its first line (see [`detect_delimiter`]), gzipped files
+/// cannot be peeked at so these default to a comma
+pub fn file_delimiter(path: &str) -> char {
+    if path.ends_with(".gz") {
+        ','
+    } else {
+        std::io::BufRead::lines(std::io::BufReader::new(
+            std::fs::File::open(path).expect("Failed to open CSV file"),
+        ))
+        .next()
+        .and_then(Result::ok)
+        .map_or(',', |line| detect_delimiter(&line))
+    }
+}
+
+/// Detect the delimiter from the first line of a csv file, either an explicit `sep=<C>` line or the
+/// most common of tab, semicolon, and comma in the header (defaults to comma)
+pub fn detect_delimiter(first_line: &str) -> char {
+    if let Some(c) = first_line
+        .strip_prefix("sep=")
+        .and_then(|sep| sep.chars().next())
+    {
+        return c;
+    }
+    ['\t', ';', ',']
+        .into_iter()
+        .map(|c| (c, first_line.matches(c).count()))
+        .filter(|(_, count)| *count > 0)
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+        .map_or(',', |(c, _)| c)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum DecoyKind {
+    Reverse,
+    Shuffle,
+}
+impl Display for DecoyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Reverse => write!(f, "reversing"),
+            Self::Shuffle => write!(f, "shuffling"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
 pub enum IsobaricNumber {
     All,
     Limited(usize),
@@ -423,8 +2078,69 @@ impl Display for IsobaricNumber {
         }
     }
 }
-fn mass_tolerance_parse(input: &str) -> Result<Tolerance<Mass>, &'static str> {
-    input.parse().map_err(|()| "Invalid tolerance parameter")
+/// The average mass of an amino acid residue, used to estimate the mass of isobaric steps
+const AVERAGE_RESIDUE_MASS: f64 = 111.1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MassTolerance {
+    Single(Tolerance<Mass>),
+    /// A relative and absolute tolerance, whichever gives the larger bound is used
+    Hybrid(Tolerance<Mass>, Tolerance<Mass>),
+}
+
+impl MassTolerance {
+    /// Get the tolerance that applies at the given mass
+    pub fn at(&self, mass: Mass) -> Tolerance<Mass> {
+        match self {
+            Self::Single(tolerance) => *tolerance,
+            Self::Hybrid(relative, absolute) => {
+                let width = |tolerance: &Tolerance<Mass>| {
+                    let (low, high) = tolerance.bounds(mass);
+                    (high - low).value
+                };
+                if width(relative) >= width(absolute) {
+                    *relative
+                } else {
+                    *absolute
+                }
+            }
+        }
+    }
+
+    /// Get the tolerance used in alignments. The alignment only supports a single tolerance for all steps, so a hybrid
+    /// tolerance is reduced to the one of its two tolerances that is larger at the mass of the smallest step (a single
+    /// residue of average mass). This one is then used for every step, so the absolute part always covers the short
+    /// isobaric steps, even though longer steps could have been accepted by the relative part.
+    pub fn for_alignment(&self) -> Tolerance<Mass> {
+        self.at(Mass::new::<rustyms::system::dalton>(AVERAGE_RESIDUE_MASS))
+    }
+}
+
+impl Display for MassTolerance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Single(tolerance) => write!(f, "{tolerance}"),
+            Self::Hybrid(relative, absolute) => write!(f, "{relative} + {absolute}"),
+        }
+    }
+}
+
+pub fn mass_tolerance_parse(input: &str) -> Result<MassTolerance, &'static str> {
+    if let Ok(tolerance) = input.parse() {
+        return Ok(MassTolerance::Single(tolerance));
+    }
+    input
+        .match_indices('+')
+        .find_map(
+            |(index, _)| match (input[..index].parse(), input[index + 1..].parse()) {
+                (Ok(a @ Tolerance::Relative(_)), Ok(b @ Tolerance::Absolute(_)))
+                | (Ok(b @ Tolerance::Absolute(_)), Ok(a @ Tolerance::Relative(_))) => {
+                    Some(MassTolerance::Hybrid(a, b))
+                }
+                _ => None,
+            },
+        )
+        .ok_or("Invalid tolerance parameter")
 }
 fn options_parse(input: &str) -> Result<IsobaricNumber, &'static str> {
     if input.to_lowercase() == "all" {
@@ -437,7 +2153,7 @@ fn options_parse(input: &str) -> Result<IsobaricNumber, &'static str> {
     }
 }
 fn peptide_parser(input: &str) -> Result<Peptidoform<SimpleLinear>, String> {
-    Peptidoform::pro_forma(input, None)
+    Peptidoform::pro_forma(input, custom::database())
         .map_err(|e| e.to_string())?
         .into_simple_linear()
         .ok_or("Not a simple peptide".to_string())
@@ -484,33 +2200,48 @@ impl Display for Modifications {
         }
     }
 }
-fn modifications_parse(input: &str) -> Result<Modifications, String> {
-    fn parse_position(pos: &str) -> Result<Position, String> {
-        match pos {
-            "*" => Ok(Position::Anywhere),
-            "C" => Ok(Position::ProteinCTerm),
-            "c" => Ok(Position::AnyCTerm),
-            "N" => Ok(Position::ProteinNTerm),
-            "n" => Ok(Position::AnyNTerm),
-            _ => Err(format!(
-                "'{pos}' is not a valid modification placement position use any of: */N/n/C/c"
-            )),
-        }
+fn parse_position(pos: &str) -> Result<Position, String> {
+    match pos {
+        "*" => Ok(Position::Anywhere),
+        "C" => Ok(Position::ProteinCTerm),
+        "c" => Ok(Position::AnyCTerm),
+        "N" => Ok(Position::ProteinNTerm),
+        "n" => Ok(Position::AnyNTerm),
+        _ => Err(format!(
+            "'{pos}' is not a valid modification placement position use any of: */N/n/C/c"
+        )),
+    }
+}
+fn parse_aa(aa: &str) -> Result<Option<Vec<AminoAcid>>, String> {
+    if aa == "*" {
+        Ok(None)
+    } else {
+        Ok(Some(
+            aa.chars()
+                .map(|c| {
+                    AminoAcid::try_from(c).map_err(|_| format!("'{c}' is not a valid amino acid"))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        ))
     }
-    fn parse_aa(aa: &str) -> Result<Option<Vec<AminoAcid>>, String> {
-        if aa == "*" {
-            Ok(None)
+}
+
+/// Parse a placement rule, as used after the `@` in `--fixed` and `--variable`, eg `STY`, `K-n`, or `*-C`
+pub(crate) fn placement_rule_parse(rule: &str) -> Result<PlacementRule, String> {
+    if let Some((aa, position)) = rule.split_once('-') {
+        if let Some(aa) = parse_aa(aa)? {
+            Ok(PlacementRule::AminoAcid(aa, parse_position(position)?))
         } else {
-            Ok(Some(
-                aa.chars()
-                    .map(|c| {
-                        AminoAcid::try_from(c)
-                            .map_err(|_| format!("'{c}' is not a valid amino acid"))
-                    })
-                    .collect::<Result<Vec<_>, _>>()?,
-            ))
+            Ok(PlacementRule::Terminal(parse_position(position)?))
         }
+    } else if let Some(aa) = parse_aa(rule)? {
+        Ok(PlacementRule::AminoAcid(aa, Position::Anywhere))
+    } else {
+        Err("Cannot have a modification rule that allows a modification on all position on all amino acids".to_string())
     }
+}
+
+fn modifications_parse(input: &str) -> Result<Modifications, String> {
     fn split(input: &str) -> Vec<&str> {
         let input = input.trim_end_matches(',');
         let mut index = None;
@@ -560,25 +2291,14 @@ fn modifications_parse(input: &str) -> Result<Modifications, String> {
             .map(|m| {
                 if let Some((head, tail)) = m.split_once('@') {
                     let modification =
-                    SimpleModificationInner::try_from(head, 0..head.len(), &mut Vec::new(), &mut Vec::new(), None).map_err(|e| e.to_string()).and_then(|m| if let Some(d) = m.0.defined() {
+                    SimpleModificationInner::try_from(head, 0..head.len(), &mut Vec::new(), &mut Vec::new(), custom::database()).map_err(|e| e.to_string()).and_then(|m| if let Some(d) = m.0.defined() {
                         Ok(d) } else {
                             Err("Can not define ambiguous modifications for the modifications parameter".to_string())
                         }
                     )?;
-                    let rule = if let Some((aa, position)) = tail.split_once('-') {
-                        if let Some(aa) = parse_aa(aa)? {
-                            PlacementRule::AminoAcid(aa, parse_position(position)?)
-                        } else {
-                            PlacementRule::Terminal(parse_position(position)?)
-                        }
-                    } else if let Some(aa) = parse_aa(tail)? {
-                            PlacementRule::AminoAcid(aa, Position::Anywhere)
-                        } else {
-                            return Err("Cannot have a modification rule that allows a modification on all position on all amino acids".to_string())
-                        };
-                    Ok((modification, Some(rule)))
+                    Ok((modification, Some(placement_rule_parse(tail)?)))
                 } else {
-                    SimpleModificationInner::try_from(m, 0..m.len(), &mut Vec::new(), &mut Vec::new(), None).map_err(|e| e.to_string()).and_then(|m| if let Some(d) = m.0.defined() {
+                    SimpleModificationInner::try_from(m, 0..m.len(), &mut Vec::new(), &mut Vec::new(), custom::database()).map_err(|e| e.to_string()).and_then(|m| if let Some(d) = m.0.defined() {
                         Ok((d, None)) } else {
                             Err("Can not define ambiguous modifications for the modifications parameter".to_string())
                         }
@@ -590,16 +2310,162 @@ fn modifications_parse(input: &str) -> Result<Modifications, String> {
     }
 }
 
-fn modification_parse(input: &str) -> Result<SimpleModification, String> {
+type IsobaricPattern = Vec<PatternElement>;
+
+fn isobaric_pattern_parser(input: &str) -> Result<IsobaricPattern, String> {
+    let pattern = input
+        .split_whitespace()
+        .flat_map(|token| match token {
+            "X" | "x" => vec![Ok(PatternElement::Any)],
+            "*" => vec![Ok(PatternElement::Gap)],
+            _ if token.starts_with('[') && token.ends_with(']') && token.len() > 2 => {
+                vec![token[1..token.len() - 1]
+                    .chars()
+                    .map(|c| {
+                        AminoAcid::try_from(c)
+                            .map_err(|()| format!("Not a valid amino acid: '{c}' in '{token}'"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(PatternElement::Residues)]
+            }
+            _ => token
+                .chars()
+                .map(|c| {
+                    AminoAcid::try_from(c)
+                        .map(|aa| PatternElement::Residues(vec![aa]))
+                        .map_err(|()| format!("Not a valid amino acid: '{c}' in '{token}'"))
+                })
+                .collect(),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if pattern.is_empty() {
+        Err("Empty pattern".to_string())
+    } else if pattern
+        .iter()
+        .filter(|p| **p == PatternElement::Gap)
+        .count()
+        > 1
+    {
+        Err("Only one gap `*` is allowed in a pattern".to_string())
+    } else {
+        Ok(pattern)
+    }
+}
+
+fn motif_parser(value: &str) -> Result<Motif, String> {
+    let residue = |c: char| {
+        AminoAcid::try_from(c.to_ascii_uppercase())
+            .map_err(|()| format!("Not a valid amino acid: '{c}' in '{value}'"))
+    };
+    let mut motif = Vec::new();
+    let mut chars = value.chars().filter(|c| !c.is_whitespace());
+    while let Some(c) = chars.next() {
+        motif.push(match c {
+            'X' | 'x' | '.' => MotifElement::Any,
+            '[' => {
+                let set: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                let (except, set) = set
+                    .strip_prefix('^')
+                    .map_or((false, set.as_str()), |set| (true, set));
+                if set.is_empty() {
+                    return Err(format!("Empty residue set in '{value}'"));
+                }
+                let set = set.chars().map(residue).collect::<Result<Vec<_>, _>>()?;
+                if except {
+                    MotifElement::Except(set)
+                } else {
+                    MotifElement::Residues(set)
+                }
+            }
+            c => MotifElement::Residues(vec![residue(c)?]),
+        });
+    }
+    if motif.is_empty() {
+        Err("Empty motif".to_string())
+    } else {
+        Ok(motif)
+    }
+}
+
+fn taxonomy_parser(input: &str) -> Result<Taxonomy, String> {
+    let input = input.trim();
+    if let Ok(id) = input.parse() {
+        Ok(Taxonomy::Id(id))
+    } else {
+        Ok(Taxonomy::Name(Species::from_str(input).map_or_else(
+            |_| input.to_string(),
+            |species| species.scientific_name().to_string(),
+        )))
+    }
+}
+
+type Glycan = Vec<(MonoSaccharide, isize)>;
+
+fn glycan_parser(input: &str) -> Result<Glycan, String> {
+    MonoSaccharide::from_composition(input).map_err(|err| err.to_string())
+}
+
+fn label_parser(input: &str) -> Result<Label, String> {
+    match input.trim().to_ascii_lowercase().as_str() {
+        "silac-k8r10" | "silac" => Ok(Label::SilacK8R10),
+        "tmt16" | "tmtpro" => Ok(Label::Tmt16),
+        "dimethyl" => Ok(Label::Dimethyl),
+        _ => Err("Invalid label, use 'silac-k8r10', 'tmt16', or 'dimethyl'".to_string()),
+    }
+}
+
+fn mass_lookup_parser(input: &str) -> Result<MassLookup, String> {
+    match input.trim().to_ascii_lowercase().as_str() {
+        "formula" | "formulas" => Ok(MassLookup::Formula),
+        "isobaric" => Ok(MassLookup::Isobaric),
+        _ => Err("Invalid mass lookup, use 'formula' or 'isobaric'".to_string()),
+    }
+}
+
+fn terminus_parser(input: &str) -> Result<Terminus, String> {
+    if input.trim().eq_ignore_ascii_case("free") {
+        Ok(Terminus::Free)
+    } else {
+        modification_parse(input).map(Terminus::Modified)
+    }
+}
+
+/// Find a modification by its accession in an ontology, eg `U:35`, `UNIMOD:35`, `MOD:00046`, or
+/// `RESID:AA0001`. Returns None if the input is not an accession.
+fn accession_parse(input: &str) -> Option<Result<SimpleModification, String>> {
+    let (prefix, accession) = input.trim().split_once(':')?;
+    let ontology = match prefix.to_ascii_uppercase().as_str() {
+        "U" | "UNIMOD" => Ontology::Unimod,
+        "M" | "MOD" | "PSI-MOD" => Ontology::Psimod,
+        "X" | "XLMOD" | "XL-MOD" => Ontology::Xlmod,
+        "R" | "RESID" => Ontology::Resid,
+        "C" | "CUSTOM" => Ontology::Custom,
+        _ => return None,
+    };
+    let number = accession
+        .strip_prefix("AA")
+        .filter(|_| ontology == Ontology::Resid)
+        .unwrap_or(accession);
+    let id: usize = number.parse().ok()?;
+    Some(
+        ontology
+            .find_id(id, custom::database())
+            .ok_or_else(|| format!("No {} modification with accession {id}", ontology.name())),
+    )
+}
+
+pub(crate) fn modification_parse(input: &str) -> Result<SimpleModification, String> {
     if input.is_empty() {
         Err("Empty".to_string())
+    } else if let Some(modification) = accession_parse(input) {
+        modification
     } else {
         SimpleModificationInner::try_from(
             input,
             0..input.len(),
             &mut Vec::new(),
             &mut Vec::new(),
-            None,
+            custom::database(),
         )
         .map(|(m, _)| match m {
             ReturnModification::Defined(d) => d,
@@ -610,3 +2476,217 @@ fn modification_parse(input: &str) -> Result<SimpleModification, String> {
         .map_err(|err| err.to_string())
     }
 }
+
+#[test]
+fn hybrid_tolerance() {
+    let tolerance = mass_tolerance_parse("10ppm+0.002da").unwrap();
+    assert_eq!(
+        tolerance.at(Mass::new::<rustyms::system::dalton>(100.0)),
+        Tolerance::new_absolute(Mass::new::<rustyms::system::dalton>(0.002))
+    );
+    assert_eq!(
+        tolerance.at(Mass::new::<rustyms::system::dalton>(1000.0)),
+        Tolerance::new_ppm(10.0)
+    );
+    assert!(mass_tolerance_parse("10ppm+5ppm").is_err());
+}
+
+#[test]
+fn hybrid_tolerance_short_step() {
+    // N and GG differ by 0.008 Da, outside of 50 ppm but inside of 0.01 Da
+    let tolerance = mass_tolerance_parse("50ppm+0.01da").unwrap();
+    let scoring = AlignScoring {
+        tolerance: tolerance.for_alignment().convert(),
+        ..crate::test_helpers::scoring()
+    };
+    let a = crate::test_helpers::parse("N");
+    let b = crate::test_helpers::parse("G[+0.008]G");
+    let alignment = rustyms::align::align::<4, _, _>(&a, &b, scoring, AlignType::GLOBAL);
+    assert_eq!(alignment.path().len(), 1);
+    assert_eq!(
+        alignment.path()[0].match_type,
+        rustyms::align::MatchType::Isobaric
+    );
+}
+
+#[test]
+fn auto_kind() {
+    let kind = AlignmentKind {
+        auto_kind: true,
+        ..AlignmentKind::default()
+    };
+    let ppm = Tolerance::new_ppm(10.0);
+    assert_eq!(kind.resolve(5, 5, ppm).max_step(), u16::MAX);
+    assert_eq!(kind.resolve(100, 100, ppm).max_step(), 8);
+    assert_eq!(kind.resolve(5000, 5000, ppm).max_step(), 1);
+    assert_eq!(
+        kind.resolve(
+            20,
+            20,
+            Tolerance::new_absolute(Mass::new::<rustyms::system::dalton>(0.1))
+        )
+        .max_step(),
+        1
+    );
+}
+
+#[test]
+fn gene_patterns() {
+    assert!(glob("IGHV3*", "IGHV3-23*01"));
+    assert!(glob("IGHV1-?9", "IGHV1-69"));
+    assert!(!glob("IGHV1-?9", "IGHV1-691"));
+    assert!(glob("*", ""));
+    assert!(!glob("IGKV*", "IGHV3-23"));
+}
+
+#[test]
+fn segments() {
+    let default = segments_parser("VJC").unwrap();
+    assert_eq!(default, default_segments());
+    assert_eq!(segments_parser("V:11-,J:10-,C:11-").unwrap(), default);
+    let fusion = segments_parser("V,J,G:1001").unwrap();
+    assert_eq!(fusion[2].0, GeneType::C(Some(rustyms::imgt::Constant::G)));
+    assert_eq!(fusion[2].1, "1001".parse().unwrap());
+    assert!(segments_parser("X").is_err());
+}
+
+#[test]
+fn region_weights() {
+    assert_eq!(
+        region_weights_parser("cdr3:0.2, FR1:2").unwrap(),
+        vec![
+            (Region::ComplementarityDeterminingRegion(3), 0.2),
+            (Region::Framework(1), 2.0)
+        ]
+    );
+    assert!(region_weights_parser("CDR3").is_err());
+    assert!(region_weights_parser("X:1").is_err());
+    assert!(region_weights_parser("CDR1:-1").is_err());
+    assert_eq!(
+        regions_parser("REGIONS=FR1:25;CDR1:8;").unwrap(),
+        vec![
+            (Region::Framework(1), 25),
+            (Region::ComplementarityDeterminingRegion(1), 8)
+        ]
+    );
+    assert!(regions_parser("FR1").is_err());
+    assert!(regions_parser("FR1:25;CRD1:8").is_err());
+}
+
+#[test]
+fn formula_options() {
+    let target = formula_target_parser("146.058").unwrap();
+    assert_eq!(target.mass, 146.058);
+    assert!((target.tolerance - 0.0005).abs() < 1e-12);
+    assert_eq!(formula_target_parser("146").unwrap().tolerance, 0.5);
+    assert!(formula_target_parser("-1").is_err());
+    assert_eq!(
+        adduct_parser("NH4").unwrap().elements,
+        vec![(Element::N, 1), (Element::H, 4)]
+    );
+    assert_eq!(
+        adduct_parser("H-1").unwrap().elements,
+        vec![(Element::H, -1)]
+    );
+    assert!(adduct_parser("Xx").is_err());
+    assert!(adduct_parser("na").is_err());
+    let bound = element_bound_parser("Fe[0-2]").unwrap();
+    assert_eq!(
+        (bound.element, bound.min, bound.max),
+        (Element::Fe, 0, Some(2))
+    );
+    assert_eq!(bound.to_string(), "Fe[0-2]");
+    assert_eq!(element_bound_parser("Se").unwrap().max, None);
+    assert_eq!(element_bound_parser("P[1-]").unwrap().min, 1);
+    assert_eq!(element_bound_parser("S[2]").unwrap().max, Some(2));
+    assert!(element_bound_parser("Fe[2-1]").is_err());
+    assert!(element_bound_parser("Fe[1").is_err());
+    let window = mass_defect_parser("CH2:-0.1..0.05").unwrap();
+    assert_eq!((window.min, window.max), (-0.1, 0.05));
+    assert!(window.base.is_some());
+    assert!(mass_defect_parser("0..0.3").unwrap().base.is_none());
+    assert!(mass_defect_parser("0.3..0").is_err());
+    assert!(mass_defect_parser("0.3").is_err());
+}
+
+#[test]
+fn csv_stats() {
+    assert_eq!(
+        csv_stats_parser("score, identity,mass_similar,ppm,path").unwrap(),
+        [
+            CsvStat::Score,
+            CsvStat::Identity,
+            CsvStat::MassSimilar,
+            CsvStat::Ppm,
+            CsvStat::Path
+        ]
+    );
+    assert!(csv_stats_parser("score,rmsd").is_err());
+}
+
+#[test]
+fn csv_extra_columns() {
+    let columns = csv_extra_columns_parser("aligned, Coordinates").unwrap();
+    assert!(columns.aligned && columns.coordinates && !columns.components);
+    assert_eq!(csv_extra_columns_parser("all").unwrap().header().len(), 10);
+    assert!(csv_extra_columns_parser("gapped").is_err());
+}
+
+#[test]
+fn delimiters() {
+    assert_eq!(detect_delimiter("a\tb\tscore"), '\t');
+    assert_eq!(detect_delimiter("a;b;note, with comma"), ';');
+    assert_eq!(detect_delimiter("a,b"), ',');
+    assert_eq!(detect_delimiter("sep=|"), '|');
+    assert_eq!(detect_delimiter("sequence"), ',');
+    assert_eq!(delimiter_parser("tab").unwrap(), '\t');
+    assert!(delimiter_parser("tabs").is_err());
+}
+
+#[test]
+fn csv_overrides() {
+    assert_eq!(
+        AlignmentType::by_name("Semi-Global").unwrap(),
+        AlignType::GLOBAL_B
+    );
+    assert_eq!(
+        AlignmentType::by_name("1001").unwrap(),
+        "1001".parse().unwrap()
+    );
+    assert!(AlignmentType::by_name("glolal").is_err());
+    assert_eq!(
+        ScoringMatrix::by_name("PAM30").unwrap(),
+        align::matrix::PAM30
+    );
+    assert!(ScoringMatrix::by_name("blosum100").is_err());
+}
+
+#[test]
+fn isobaric_pattern() {
+    let pattern = isobaric_pattern_parser("X x N [ST] *").unwrap();
+    assert_eq!(
+        pattern,
+        vec![
+            PatternElement::Any,
+            PatternElement::Any,
+            PatternElement::Residues(vec![AminoAcid::Asparagine]),
+            PatternElement::Residues(vec![AminoAcid::Serine, AminoAcid::Threonine]),
+            PatternElement::Gap,
+        ]
+    );
+    assert!(isobaric_pattern_parser("* K *").is_err());
+    assert!(isobaric_pattern_parser("[ST").is_err());
+}
+
+#[test]
+fn accessions() {
+    let oxidation = Ontology::Unimod.find_name("Oxidation", None).unwrap();
+    assert_eq!(modification_parse("U:35").unwrap(), oxidation);
+    assert_eq!(modification_parse("UNIMOD:35").unwrap(), oxidation);
+    assert_eq!(
+        modification_parse("MOD:00046").unwrap(),
+        modification_parse("M:46").unwrap()
+    );
+    assert!(modification_parse("U:999999").is_err());
+    assert!(accession_parse("U:Oxidation").is_none());
+}
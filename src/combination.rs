@@ -0,0 +1,217 @@
+use colored::{Color, Colorize, Styles};
+use itertools::Itertools;
+use rustyms::{
+    modification::{Ontology, SimpleModification},
+    placement_rule::Position,
+    system::{dalton, Mass},
+    AminoAcid, Chemical, MassMode, Tolerance,
+};
+
+use crate::{
+    render::{display_mass, table},
+    styling::Styling,
+};
+
+/// The maximal number of combinations shown
+const MAX_SHOWN: usize = 50;
+
+/// A combination of modifications that together explain a mass shift
+#[derive(Clone, Debug)]
+pub struct Combination {
+    /// The modifications, with the number of other modifications with the same formula
+    pub modifications: Vec<(SimpleModification, usize)>,
+    /// The total mass of the modifications
+    pub mass: Mass,
+    /// The total mass minus the mass shift to explain
+    pub error: Mass,
+}
+
+/// Find all combinations of two up to `max` Unimod modifications (the same modification can be used
+/// multiple times) that together explain the mass shift. Modifications with the same formula are
+/// grouped and represented by the one with the lowest Unimod id, modifications without a mass are
+/// not used. The combinations are sorted by the number of modifications, as a combination of more
+/// modifications is less likely, and then by absolute error.
+pub fn search(
+    mass: Mass,
+    tolerance: Tolerance<Mass>,
+    max: usize,
+    positions: Option<&[(Vec<AminoAcid>, Position)]>,
+    mass_mode: MassMode,
+) -> Vec<Combination> {
+    let candidates = Ontology::Unimod
+        .lookup(None)
+        .iter()
+        .filter(|(_, _, m)| {
+            positions.is_none_or(|positions| {
+                positions.iter().any(|(aas, p)| {
+                    aas.iter()
+                        .any(|aa| m.is_possible_aa(*aa, *p).any_possible())
+                })
+            })
+        })
+        .sorted_by_key(|(id, _, _)| *id)
+        .into_group_map_by(|(_, _, m)| m.formula())
+        .into_values()
+        .map(|group| {
+            let modification = group[0].2.clone();
+            (
+                modification.formula().mass(mass_mode).value,
+                modification,
+                group.len() - 1,
+            )
+        })
+        .filter(|(m, _, _)| m.abs() > 1e-6)
+        .sorted_by(|a, b| a.0.total_cmp(&b.0))
+        .collect_vec();
+    let (low, high) = tolerance.bounds(mass);
+    let mut output = Vec::new();
+    let mut chosen = Vec::with_capacity(max);
+    for count in 2..=max {
+        combine(
+            &candidates,
+            (low.value, high.value),
+            count,
+            0,
+            0.0,
+            &mut chosen,
+            &mut |chosen: &[usize], total: f64| {
+                output.push(Combination {
+                    modifications: chosen
+                        .iter()
+                        .map(|i| (candidates[*i].1.clone(), candidates[*i].2))
+                        .collect(),
+                    mass: Mass::new::<dalton>(total),
+                    error: Mass::new::<dalton>(total) - mass,
+                });
+            },
+        );
+    }
+    output.sort_by(|a, b| {
+        a.modifications
+            .len()
+            .cmp(&b.modifications.len())
+            .then(a.error.value.abs().total_cmp(&b.error.value.abs()))
+    });
+    output
+}
+
+/// Choose `left` more candidates from `start` onwards, the last one is found with a binary search
+/// for the range of masses that fits the bounds
+fn combine(
+    candidates: &[(f64, SimpleModification, usize)],
+    bounds: (f64, f64),
+    left: usize,
+    start: usize,
+    total: f64,
+    chosen: &mut Vec<usize>,
+    found: &mut impl FnMut(&[usize], f64),
+) {
+    if left == 1 {
+        let first = start + candidates[start..].partition_point(|(m, _, _)| total + m < bounds.0);
+        for (index, (m, _, _)) in candidates.iter().enumerate().skip(first) {
+            if total + m > bounds.1 {
+                break;
+            }
+            chosen.push(index);
+            found(chosen, total + m);
+            chosen.pop();
+        }
+        return;
+    }
+    for index in start..candidates.len() {
+        chosen.push(index);
+        combine(
+            candidates,
+            bounds,
+            left - 1,
+            index,
+            total + candidates[index].0,
+            chosen,
+            found,
+        );
+        chosen.pop();
+    }
+}
+
+/// Search and show all combinations of modifications that explain the mass shift
+pub fn show(
+    mass: Mass,
+    tolerance: Tolerance<Mass>,
+    max: usize,
+    positions: Option<&[(Vec<AminoAcid>, Position)]>,
+    mass_mode: MassMode,
+    precision: Option<usize>,
+) {
+    println!(
+        "\nCombinations of up to {max} Unimod modifications close to the given {mass_mode}: {}",
+        format!("tolerance: {tolerance}").dimmed()
+    );
+    let combinations = search(mass, tolerance, max, positions, mass_mode);
+    if combinations.is_empty() {
+        println!("{}", "No combinations found".red());
+        return;
+    }
+    let mut data = vec![[
+        "Modifications".to_string(),
+        mass_mode.to_string(),
+        "Error".to_string(),
+        "Same formula".to_string(),
+    ]];
+    for combination in combinations.iter().take(MAX_SHOWN) {
+        data.push([
+            combination
+                .modifications
+                .iter()
+                .map(|(m, _)| m.to_string())
+                .join(" + "),
+            display_mass(combination.mass, false, precision),
+            format!("{:+.5} Da", combination.error.value),
+            combination
+                .modifications
+                .iter()
+                .map(|(_, n)| n)
+                .sum::<usize>()
+                .to_string(),
+        ]);
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_fg(Some(Color::Magenta)),
+            Styling::with_fg(Some(Color::Yellow)),
+            Styling::none(),
+            Styling::with_style(Styles::Dimmed),
+        ],
+    );
+    if combinations.len() > MAX_SHOWN {
+        println!(
+            "{}",
+            format!("Showing {MAX_SHOWN} of {} combinations", combinations.len())
+                .dimmed()
+                .italic()
+        );
+    }
+}
+
+#[test]
+fn oxidation_deamidation() {
+    // Oxidation (15.995) + Deamidated (0.984)
+    let found = search(
+        Mass::new::<dalton>(16.97892),
+        Tolerance::new_absolute(Mass::new::<dalton>(0.0001)),
+        2,
+        None,
+        MassMode::Monoisotopic,
+    );
+    assert!(found.iter().any(|c| {
+        let names = c
+            .modifications
+            .iter()
+            .map(|(m, _)| m.to_string())
+            .sorted()
+            .collect_vec();
+        names == ["U:Deamidated", "U:Oxidation"]
+    }));
+    assert!(found.iter().all(|c| c.error.value.abs() <= 0.0001));
+}
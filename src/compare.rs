@@ -0,0 +1,120 @@
+use colored::{Color, Colorize};
+use rustyms::align::{Alignment, MatchType};
+
+use crate::render::CombinedLines;
+
+/// The interpretation of a single query position in an alignment
+#[derive(Clone, PartialEq, Eq)]
+pub struct Interpretation {
+    /// The reference residue(s) aligned to this query position, empty for a gap or unaligned
+    /// position, for steps covering multiple query positions the full set is placed on the first
+    /// and the others are left empty but marked as part of a set
+    pub residues: String,
+    /// The type of the step
+    pub match_type: Option<MatchType>,
+    /// If this position is part of a step that covers multiple query positions
+    pub in_set: bool,
+}
+
+/// Project the alignment onto the query (sequence B), giving the interpretation of every query
+/// position. Reference residues aligned to gaps in the query are not included.
+pub fn project<A, B>(alignment: &Alignment<'_, A, B>) -> Vec<Interpretation> {
+    let mut output = vec![
+        Interpretation {
+            residues: String::new(),
+            match_type: None,
+            in_set: false,
+        };
+        alignment.seq_b().len()
+    ];
+    let (mut a, mut b) = alignment.start();
+    for piece in alignment.path() {
+        if piece.step_b > 0 {
+            let residues: String = alignment.seq_a().sequence()[a..a + piece.step_a as usize]
+                .iter()
+                .map(|s| s.aminoacid.char())
+                .collect();
+            let set = piece.step_b > 1 || piece.step_a > 1;
+            for offset in 0..piece.step_b as usize {
+                output[b + offset] = Interpretation {
+                    residues: if offset == 0 {
+                        residues.clone()
+                    } else {
+                        String::new()
+                    },
+                    match_type: Some(piece.match_type),
+                    in_set: set,
+                };
+            }
+        }
+        a += piece.step_a as usize;
+        b += piece.step_b as usize;
+    }
+    output
+}
+
+/// Show the query with both interpretations stacked. Every column shows the query residue with
+/// above it the first and below it the second reference, and an agreement track which is empty
+/// if both agree, `≠` if the references have different residues, and `~` if the residues agree
+/// but the type of step (identity, isobaric set, etc) differs.
+pub fn show_comparison(
+    query: &[char],
+    first: &[Interpretation],
+    second: &[Interpretation],
+    names: (&str, &str),
+    line_width: usize,
+) {
+    let width = |i: &Interpretation| i.residues.chars().count().max(1);
+    let cell = |i: &Interpretation, w: usize| {
+        if i.residues.is_empty() {
+            if i.in_set { "·" } else { "-" }.repeat(w)
+        } else {
+            format!("{:·<w$}", i.residues)
+        }
+    };
+    let agreement = query
+        .iter()
+        .zip(first.iter().zip(second))
+        .filter(|(_, (f, s))| f == s)
+        .count();
+    println!(
+        "{} {}",
+        "Agreement".underline().italic(),
+        format!(
+            "({agreement}/{} query positions, {:.1}%)",
+            query.len(),
+            agreement as f64 / query.len().max(1) as f64 * 100.0
+        )
+        .dimmed()
+    );
+    let mut writer = CombinedLines::stacked(line_width.max(1), "Query", names.1);
+    for (q, (f, s)) in query.iter().zip(first.iter().zip(second)) {
+        let w = width(f).max(width(s));
+        let (track, colour) = if f.residues != s.residues || f.in_set != s.in_set {
+            ('≠', Some(Color::Red))
+        } else if f.match_type != s.match_type {
+            ('~', Some(Color::Yellow))
+        } else {
+            (' ', None)
+        };
+        let (f, q, s) = (cell(f, w), format!("{q:·<w$}"), cell(s, w));
+        for ((f, q), s) in f.chars().zip(q.chars()).zip(s.chars()) {
+            writer.add_stacked_column(names.0, [f, q, s], track, colour);
+        }
+    }
+    print!("{}", writer.finish());
+}
+
+#[test]
+fn projection() {
+    use crate::test_helpers::{global, parse};
+    use itertools::Itertools;
+    let (a, b) = (parse("AKTNLSHLGY"), parse("AKTNLHLGY"));
+    let alignment = global::<1>(&a, &b);
+    let projected = project(&alignment);
+    assert_eq!(projected.len(), 9);
+    assert_eq!(
+        projected.iter().map(|i| i.residues.as_str()).join(""),
+        "AKTNLHLGY"
+    );
+}
@@ -0,0 +1,120 @@
+use rustyms::{
+    align::{AlignScoring, Alignment},
+    AtMax, SimpleLinear,
+};
+
+use crate::ambiguity::{path_steps, short, Step};
+
+/// The temperature used to turn score drops into weights, a drop of this many score points makes an
+/// alternative e (2.718) times less likely than the original
+const TEMPERATURE: f64 = 2.0;
+
+/// The steps below this confidence are seen as unreliable
+pub const LOW_CONFIDENCE: f64 = 0.5;
+
+/// The steps at or above this confidence are seen as reliable
+pub const HIGH_CONFIDENCE: f64 = 0.9;
+
+/// Calculate a posterior like confidence for every step in the path. For every step a set of forced
+/// alternatives is scored: pairs are broken up into a deletion and insertion, isobaric and rotated
+/// sets are broken up into single residue steps, and gaps are shifted one position or merged with
+/// an opposite gap. The confidence is the weight of the original alignment over the summed weight
+/// of the original and all alternatives for that step, where the weight is `exp(-drop/T)` for a
+/// score drop `drop`. A step without any better scoring or equal scoring alternative has a
+/// confidence close to one, a step with an equal scoring alternative has at most 0.5.
+pub fn confidence<A: AtMax<SimpleLinear>, B: AtMax<SimpleLinear>>(
+    alignment: &Alignment<'_, A, B>,
+    scoring: AlignScoring,
+) -> Vec<f64> {
+    let steps = path_steps(alignment);
+    let score = alignment.score().absolute;
+    (0..steps.len())
+        .map(|index| {
+            let weights: f64 = alternatives(&steps, index)
+                .iter()
+                .filter_map(|variant| {
+                    Alignment::create_from_path(
+                        alignment.seq_a(),
+                        alignment.seq_b(),
+                        alignment.start_a(),
+                        alignment.start_b(),
+                        &short(variant),
+                        scoring,
+                        alignment.align_type(),
+                        alignment.max_step(),
+                    )
+                })
+                .map(|other| {
+                    let drop = (score - other.score().absolute).max(0) as f64;
+                    (-drop / TEMPERATURE).exp()
+                })
+                .sum();
+            1.0 / (1.0 + weights)
+        })
+        .collect()
+}
+
+/// All forced alternatives for the step at the given index
+fn alternatives(steps: &[Step], index: usize) -> Vec<Vec<Step>> {
+    let replace = |start: usize, end: usize, with: &[Step]| {
+        let mut variant = steps[..start].to_vec();
+        variant.extend_from_slice(with);
+        variant.extend_from_slice(&steps[end..]);
+        variant
+    };
+    let mut output = Vec::new();
+    match &steps[index] {
+        Step::Pair => output.push(replace(
+            index,
+            index + 1,
+            &[Step::Deletion, Step::Insertion],
+        )),
+        Step::Special(_, a, b) => {
+            let mut with = vec![Step::Pair; (*a).min(*b) as usize];
+            with.extend(std::iter::repeat_n(
+                if a > b {
+                    Step::Deletion
+                } else {
+                    Step::Insertion
+                },
+                a.abs_diff(*b) as usize,
+            ));
+            output.push(replace(index, index + 1, &with));
+        }
+        gap => {
+            let opposite = if *gap == Step::Deletion {
+                Step::Insertion
+            } else {
+                Step::Deletion
+            };
+            if index > 0 {
+                if steps[index - 1] == Step::Pair {
+                    output.push(replace(index - 1, index + 1, &[gap.clone(), Step::Pair]));
+                } else if steps[index - 1] == opposite {
+                    output.push(replace(index - 1, index + 1, &[Step::Pair]));
+                }
+            }
+            if index + 1 < steps.len() {
+                if steps[index + 1] == Step::Pair {
+                    output.push(replace(index, index + 2, &[Step::Pair, gap.clone()]));
+                } else if steps[index + 1] == opposite {
+                    output.push(replace(index, index + 2, &[Step::Pair]));
+                }
+            }
+        }
+    }
+    output
+}
+
+#[test]
+fn shifted_gap_confidence() {
+    use crate::test_helpers::{global, parse, scoring};
+    let (a, b) = (parse("WAAAAKQRQ"), parse("WAAAKQRQ"));
+    let alignment = global::<1>(&a, &b);
+    let confidence = confidence(&alignment, scoring());
+    assert_eq!(confidence.len(), alignment.path().len());
+    // The gap can be placed anywhere in the poly A stretch
+    assert!(confidence[4] <= LOW_CONFIDENCE);
+    // The tryptophan is certain
+    assert!(confidence[0] > HIGH_CONFIDENCE);
+}
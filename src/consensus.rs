@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use colored::Colorize;
+use itertools::Itertools;
+use rustyms::align::Alignment;
+
+/// A single position in the consensus sequence
+pub struct ConsensusPosition {
+    /// The most common residue(s) at this position, `-` for a gap
+    pub residue: String,
+    /// The fraction of hits that agree with the consensus residue
+    pub agreement: f64,
+}
+
+/// Build a consensus sequence from the given hits, all hits have to be aligned to the same query
+/// as sequence B. Every position of the query gets the residue that was most often aligned to it,
+/// insertions in the hits relative to the query are not taken into account. If multiple residues
+/// are aligned to multiple query positions in a single unbalanced step (isobaric sets of unequal
+/// length) the full set is placed on the first query position.
+pub fn consensus<'a, A: 'a, B: 'a>(
+    alignments: impl IntoIterator<Item = &'a Alignment<'a, A, B>>,
+    query_length: usize,
+) -> Vec<ConsensusPosition> {
+    let mut counts: Vec<HashMap<String, usize>> = vec![HashMap::new(); query_length];
+    let mut total = 0;
+    for alignment in alignments {
+        total += 1;
+        let mut covered = vec![false; query_length];
+        let (mut a, mut b) = alignment.start();
+        for piece in alignment.path() {
+            let residues = &alignment.seq_a().sequence()[a..a + piece.step_a as usize];
+            if piece.step_a == piece.step_b {
+                for (offset, residue) in residues.iter().enumerate() {
+                    *counts[b + offset]
+                        .entry(residue.aminoacid.char().to_string())
+                        .or_default() += 1;
+                    covered[b + offset] = true;
+                }
+            } else if piece.step_b > 0 && piece.step_a > 0 {
+                *counts[b]
+                    .entry(residues.iter().map(|r| r.aminoacid.char()).collect())
+                    .or_default() += 1;
+                covered[b] = true;
+            }
+            a += piece.step_a as usize;
+            b += piece.step_b as usize;
+        }
+        for (position, covered) in covered.into_iter().enumerate() {
+            if !covered {
+                *counts[position].entry("-".to_string()).or_default() += 1;
+            }
+        }
+    }
+    counts
+        .into_iter()
+        .map(|counts| {
+            let (residue, count) = counts
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+                .unwrap_or(("-".to_string(), 0));
+            ConsensusPosition {
+                residue,
+                agreement: count as f64 / total.max(1) as f64,
+            }
+        })
+        .collect()
+}
+
+/// Show the consensus sequence, with below every position the agreement as a digit (`9` means
+/// 90% or more) or `*` for full agreement.
+pub fn show_consensus(consensus: &[ConsensusPosition], hits: usize, line_width: usize) {
+    println!(
+        "{} {}",
+        "Consensus of the top hits".underline().italic(),
+        format!(
+            "({hits} hits, mean agreement {:.1}%)",
+            consensus.iter().map(|p| p.agreement).sum::<f64>() / consensus.len().max(1) as f64
+                * 100.0
+        )
+        .dimmed()
+    );
+    for chunk in &consensus.iter().chunks(line_width.max(1)) {
+        let (residues, agreement): (String, String) = chunk
+            .map(|position| {
+                let width = position.residue.chars().count();
+                let digit = if position.agreement >= 1.0 {
+                    "*".green().to_string()
+                } else {
+                    let digit = ((position.agreement * 10.0).floor() as usize).to_string();
+                    if position.agreement >= 0.5 {
+                        digit.yellow().to_string()
+                    } else {
+                        digit.red().to_string()
+                    }
+                };
+                (
+                    position.residue.clone(),
+                    format!("{digit}{}", " ".repeat(width - 1)),
+                )
+            })
+            .unzip();
+        println!("{residues}");
+        println!("{agreement}");
+    }
+}
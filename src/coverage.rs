@@ -0,0 +1,106 @@
+use colored::Colorize;
+use itertools::Itertools;
+use rustyms::{
+    align::{Alignment, MatchType},
+    identification::FastaData,
+};
+
+/// How well a single residue of a protein is covered
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    /// Not covered by any peptide
+    None,
+    /// Covered, but only by mismatches or gaps
+    Mismatch,
+    /// Covered by an identical or mass equivalent (isobaric/rotated) step
+    Identical,
+}
+
+/// The coverage of the peptides mapped onto a single database protein
+pub struct Coverage {
+    levels: Vec<Level>,
+    peptides: usize,
+}
+
+impl Coverage {
+    pub fn new(length: usize) -> Self {
+        Self {
+            levels: vec![Level::None; length],
+            peptides: 0,
+        }
+    }
+
+    /// Add the alignment of a peptide onto this protein, the protein has to be sequence A
+    pub fn add<A, B>(&mut self, alignment: &Alignment<'_, A, B>) {
+        let mut index = alignment.start_a();
+        for piece in alignment.path() {
+            let level = match piece.match_type {
+                MatchType::FullIdentity | MatchType::Isobaric | MatchType::Rotation => {
+                    Level::Identical
+                }
+                MatchType::IdentityMassMismatch | MatchType::Mismatch | MatchType::Gap => {
+                    Level::Mismatch
+                }
+            };
+            for position in index..index + piece.step_a as usize {
+                self.levels[position] = self.levels[position].max(level);
+            }
+            index += piece.step_a as usize;
+        }
+        self.peptides += 1;
+    }
+
+    /// Show the coverage report with the coverage track under the protein sequence
+    pub fn show(&self, fasta: &FastaData, line_width: usize) {
+        let covered = self.levels.iter().filter(|l| **l != Level::None).count();
+        let identical = self
+            .levels
+            .iter()
+            .filter(|l| **l == Level::Identical)
+            .count();
+        println!(
+            "{} ({}), {} peptide{}",
+            "Coverage".underline().italic(),
+            fasta.identifier().to_string().dimmed(),
+            self.peptides,
+            if self.peptides == 1 { "" } else { "s" }
+        );
+        println!(
+            "Covered: {} ({}/{}), Identity of covered residues: {} ({}/{})",
+            format!("{:.1}%", covered as f64 / self.levels.len() as f64 * 100.0).blue(),
+            covered,
+            self.levels.len(),
+            format!("{:.1}%", identical as f64 / covered.max(1) as f64 * 100.0).blue(),
+            identical,
+            covered,
+        );
+        let width = self.levels.len().to_string().len();
+        for (index, chunk) in fasta
+            .peptide()
+            .sequence()
+            .iter()
+            .zip(&self.levels)
+            .chunks(line_width.max(1))
+            .into_iter()
+            .enumerate()
+        {
+            let (residues, track): (String, String) = chunk
+                .map(|(residue, level)| {
+                    (
+                        residue.aminoacid.char().to_string(),
+                        match level {
+                            Level::None => " ".to_string(),
+                            Level::Mismatch => "░".yellow().to_string(),
+                            Level::Identical => "█".green().to_string(),
+                        },
+                    )
+                })
+                .unzip();
+            println!(
+                "{:>width$} {residues}",
+                (index * line_width + 1).to_string().dimmed()
+            );
+            println!("{:width$} {track}", "");
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use colored::{Color, Colorize, Styles};
+use itertools::Itertools;
+use rustyms::{
+    modification::{LinkerSpecificity, Ontology, SimpleModification, SimpleModificationInner},
+    placement_rule::{PlacementRule, Position},
+    system::{dalton, Mass},
+    MassMode, Peptidoform, SimpleLinear, Tolerance,
+};
+
+use crate::{
+    custom,
+    render::{display_mass, table},
+    styling::Styling,
+};
+
+/// The maximal number of cross-links shown
+const MAX_SHOWN: usize = 100;
+
+/// A candidate for the second peptide of the cross-link
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    pub peptide: Peptidoform<SimpleLinear>,
+    /// The protein this peptide is from, if it was digested from a database
+    pub protein: Option<String>,
+}
+
+/// A cross-linked peptide pair that explains the precursor mass
+#[derive(Clone, Debug)]
+pub struct Crosslink {
+    /// The index of the second peptide in the candidates
+    pub candidate: usize,
+    pub linker: SimpleModification,
+    /// The 0 based positions in the first peptide the linker can be placed on
+    pub sites_a: Vec<usize>,
+    /// The 0 based positions in the second peptide the linker can be placed on
+    pub sites_b: Vec<usize>,
+    /// The total mass of both peptides and the linker
+    pub mass: Mass,
+    /// The total mass minus the precursor mass
+    pub error: Mass,
+}
+
+/// The mass of a peptide, the first formula is used if there are multiple
+fn mass(peptide: &Peptidoform<SimpleLinear>, mass_mode: MassMode) -> Mass {
+    peptide
+        .formulas()
+        .iter()
+        .next()
+        .map(|f| f.mass(mass_mode))
+        .unwrap_or_default()
+}
+
+/// All 0 based positions in the peptide where any of these rules allow the linker, the first and
+/// last residue count as the peptide N and C terminus
+fn sites(peptide: &Peptidoform<SimpleLinear>, rules: &[PlacementRule]) -> Vec<usize> {
+    let last = peptide.len().saturating_sub(1);
+    peptide
+        .sequence()
+        .iter()
+        .enumerate()
+        .filter(|(index, element)| {
+            let position = if *index == 0 {
+                Position::AnyNTerm
+            } else if *index == last {
+                Position::AnyCTerm
+            } else {
+                Position::Anywhere
+            };
+            PlacementRule::any_possible_aa(rules, element.aminoacid.aminoacid(), position)
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// The positions on both peptides where this linker can be placed, or None if the linker cannot
+/// connect these peptides. For asymmetric linkers both orientations are tried.
+fn placement(
+    a: &Peptidoform<SimpleLinear>,
+    b: &Peptidoform<SimpleLinear>,
+    specificities: &[LinkerSpecificity],
+) -> Option<(Vec<usize>, Vec<usize>)> {
+    let mut sites_a = Vec::new();
+    let mut sites_b = Vec::new();
+    let mut add = |left: Vec<usize>, right: Vec<usize>| {
+        if !left.is_empty() && !right.is_empty() {
+            sites_a.extend(left);
+            sites_b.extend(right);
+        }
+    };
+    for specificity in specificities {
+        match specificity {
+            LinkerSpecificity::Symmetric(rules, _, _) => add(sites(a, rules), sites(b, rules)),
+            LinkerSpecificity::Asymmetric((left, right), _, _) => {
+                add(sites(a, left), sites(b, right));
+                add(sites(a, right), sites(b, left));
+            }
+        }
+    }
+    (!sites_a.is_empty()).then(|| {
+        (
+            sites_a.into_iter().sorted().dedup().collect(),
+            sites_b.into_iter().sorted().dedup().collect(),
+        )
+    })
+}
+
+/// Find all combinations of a cross-linker (from XL-MOD and `--mod-db`) and a second peptide from
+/// the candidates that together with the first peptide match the precursor mass. The linker has to
+/// be placeable on both peptides according to its specificities. The cross-links are sorted by
+/// absolute error.
+pub fn search(
+    precursor: Mass,
+    tolerance: Tolerance<Mass>,
+    a: &Peptidoform<SimpleLinear>,
+    candidates: &[Candidate],
+    mass_mode: MassMode,
+) -> Vec<Crosslink> {
+    let mass_a = mass(a, mass_mode);
+    let masses = candidates
+        .iter()
+        .enumerate()
+        .map(|(index, c)| (mass(&c.peptide, mass_mode).value, index))
+        .sorted_by(|a, b| a.0.total_cmp(&b.0))
+        .collect_vec();
+    let (low, high) = tolerance.bounds(precursor);
+    let mut output = Vec::new();
+    for ontology in [Ontology::Xlmod, Ontology::Custom] {
+        for (_, _, linker) in ontology.lookup(custom::database()) {
+            let SimpleModificationInner::Linker {
+                specificities,
+                formula,
+                ..
+            } = &**linker
+            else {
+                continue;
+            };
+            let rest = mass_a + formula.mass(mass_mode);
+            let first = masses.partition_point(|(m, _)| *m < (low - rest).value);
+            for (mass_b, index) in masses[first..]
+                .iter()
+                .take_while(|(m, _)| *m <= (high - rest).value)
+            {
+                if let Some((sites_a, sites_b)) =
+                    placement(a, &candidates[*index].peptide, specificities)
+                {
+                    let total = rest + Mass::new::<dalton>(*mass_b);
+                    output.push(Crosslink {
+                        candidate: *index,
+                        linker: linker.clone(),
+                        sites_a,
+                        sites_b,
+                        mass: total,
+                        error: total - precursor,
+                    });
+                }
+            }
+        }
+    }
+    output.sort_by(|a, b| a.error.value.abs().total_cmp(&b.error.value.abs()));
+    output
+}
+
+/// Search and show all cross-linked peptide pairs that match the precursor mass
+pub fn show(
+    precursor: Mass,
+    tolerance: Tolerance<Mass>,
+    a: &Peptidoform<SimpleLinear>,
+    candidates: &[Candidate],
+    mass_mode: MassMode,
+    precision: Option<usize>,
+) {
+    println!(
+        "Cross-links of {} with {} for {} {}",
+        a.to_string().blue(),
+        if candidates.len() == 1 {
+            candidates[0].peptide.to_string().blue().to_string()
+        } else {
+            format!("{} database peptides", candidates.len())
+        },
+        display_mass(precursor, true, precision),
+        format!("({mass_mode}, tolerance: {tolerance})").dimmed()
+    );
+    let crosslinks = search(precursor, tolerance, a, candidates, mass_mode);
+    if crosslinks.is_empty() {
+        println!("{}", "No cross-links found".red());
+        return;
+    }
+    // Only show the protein column when searching a database
+    let database = candidates.iter().any(|c| c.protein.is_some());
+    let mut data = vec![[
+        "Linker",
+        "Peptide B",
+        "Protein",
+        "Sites A",
+        "Sites B",
+        &mass_mode.to_string(),
+        "Error",
+    ]
+    .map(String::from)];
+    for crosslink in crosslinks.iter().take(MAX_SHOWN) {
+        let candidate = &candidates[crosslink.candidate];
+        data.push([
+            crosslink.linker.to_string(),
+            candidate.peptide.to_string(),
+            candidate.protein.clone().unwrap_or_default(),
+            crosslink.sites_a.iter().map(|p| p + 1).join(","),
+            crosslink.sites_b.iter().map(|p| p + 1).join(","),
+            display_mass(crosslink.mass, false, precision),
+            format!("{:+.5} Da", crosslink.error.value),
+        ]);
+    }
+    let mut styling = vec![
+        Styling::with_fg(Some(Color::Magenta)),
+        Styling::with_fg(Some(Color::Blue)),
+        Styling::with_style(Styles::Dimmed),
+        Styling::none(),
+        Styling::none(),
+        Styling::with_fg(Some(Color::Yellow)),
+        Styling::none(),
+    ];
+    let data = data
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .enumerate()
+                .filter(|(i, _)| database || *i != 2)
+                .map(|(_, cell)| cell)
+                .collect_vec()
+        })
+        .collect_vec();
+    if !database {
+        styling.remove(2);
+    }
+    table(&data, true, &styling);
+    if crosslinks.len() > MAX_SHOWN {
+        println!(
+            "{}",
+            format!("Showing {MAX_SHOWN} of {} cross-links", crosslinks.len())
+                .dimmed()
+                .italic()
+        );
+    }
+}
+
+#[test]
+fn bs3() {
+    use crate::test_helpers::parse;
+    let a = parse("PEPKIDE");
+    let candidates = [
+        Candidate {
+            peptide: parse("AKTNLSR"),
+            protein: None,
+        },
+        Candidate {
+            peptide: parse("AGTNLSR"),
+            protein: None,
+        },
+    ];
+    // BS3/DSS bridge is C8H10O2 (138.068 Da)
+    let precursor = mass(&a, MassMode::Monoisotopic)
+        + mass(&candidates[0].peptide, MassMode::Monoisotopic)
+        + Mass::new::<dalton>(138.06808);
+    let found = search(
+        precursor,
+        Tolerance::new_absolute(Mass::new::<dalton>(0.001)),
+        &a,
+        &candidates,
+        MassMode::Monoisotopic,
+    );
+    assert!(!found.is_empty());
+    assert!(found.iter().all(|c| c.candidate == 0));
+    assert!(found
+        .iter()
+        .any(|c| c.sites_a.contains(&3) && c.sites_b.contains(&1)));
+}
@@ -0,0 +1,251 @@
+use std::{path::Path, sync::OnceLock};
+
+use clap::CommandFactory;
+
+use rustyms::{
+    modification::{ModificationId, Ontology, SimpleModification, SimpleModificationInner},
+    ontologies::CustomDatabase,
+    placement_rule::PlacementRule,
+    MolecularFormula,
+};
+
+use crate::cli::placement_rule_parse;
+
+static DATABASE: OnceLock<CustomDatabase> = OnceLock::new();
+
+/// The custom modification database given with `--mod-db`, if any
+pub fn database() -> Option<&'static CustomDatabase> {
+    DATABASE.get()
+}
+
+/// Load the custom modification database given with `--mod-db` in these command line arguments.
+/// This has to be done before the command line is parsed, as the parsers for modifications and
+/// peptides need the database to resolve custom modifications.
+pub fn load_from_args(args: impl IntoIterator<Item = String>) -> Result<(), String> {
+    if let Some(path) = mod_db_argument(args) {
+        let database = load(Path::new(&path))?;
+        let _ = DATABASE.set(database);
+    }
+    Ok(())
+}
+
+/// Get the `--mod-db` argument. The command line is parsed without validating the values (which
+/// could need the database) and while ignoring errors, these are reported in the full parse.
+fn mod_db_argument(args: impl IntoIterator<Item = String>) -> Option<String> {
+    crate::cli::Cli::command()
+        .mut_args(|arg| {
+            if arg.get_action().takes_values() {
+                arg.value_parser(clap::builder::ValueParser::string())
+            } else {
+                arg
+            }
+        })
+        .ignore_errors(true)
+        .try_get_matches_from(args)
+        .ok()?
+        .get_one::<String>("mod_db")
+        .cloned()
+}
+
+/// Load a custom modification database, the format is based on the extension: `.json` for the
+/// serialised rustyms custom database, `.obo` for an OBO file, and otherwise a TSV file.
+///
+/// The TSV file has a modification per line with the columns name, formula or mass, placement
+/// rules, and an optional description. The formula is given in ProForma style (eg `C2H2O`), or a
+/// mass shift as a number (eg `+42.011`). The placement rules use the same syntax as `--fixed`
+/// (eg `K,*-n`), and can be left empty to allow the modification anywhere. Empty lines, lines
+/// starting with `#`, and a header line starting with `name` are ignored.
+///
+/// The OBO file has a `[Term]` per modification with `name`, `def`, and `synonym` as usual and
+/// `property_value: formula "C2H2O"` or `property_value: mass "42.011"` and any number of
+/// `property_value: site "K"` lines for the placement rules.
+pub fn load(path: &Path) -> Result<CustomDatabase, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("Could not read '{}': {err}", path.display()))?;
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("json") => serde_json::from_str(&text)
+            .map_err(|err| format!("Invalid custom modification JSON: {err}")),
+        Some("obo") => parse_obo(&text),
+        _ => parse_tsv(&text),
+    }
+}
+
+/// A modification as read from a file, before it is turned into a database entry
+#[derive(Default)]
+struct Definition {
+    name: String,
+    description: String,
+    synonyms: Vec<String>,
+    formula: Option<MolecularFormula>,
+    rules: Vec<PlacementRule>,
+}
+
+impl Definition {
+    fn build(self, index: usize) -> Result<(Option<usize>, String, SimpleModification), String> {
+        if self.name.is_empty() {
+            return Err(format!("Custom modification {index} has no name"));
+        }
+        let formula = self
+            .formula
+            .ok_or_else(|| format!("Custom modification '{}' has no formula or mass", self.name))?;
+        Ok((
+            Some(index),
+            self.name.to_ascii_lowercase(),
+            SimpleModification::new(SimpleModificationInner::Database {
+                specificities: if self.rules.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![(self.rules, Vec::new(), Vec::new())]
+                },
+                formula,
+                id: ModificationId {
+                    ontology: Ontology::Custom,
+                    name: self.name,
+                    id: Some(index),
+                    description: self.description,
+                    synonyms: self.synonyms.into_iter().collect(),
+                    cross_ids: Default::default(),
+                },
+            }),
+        ))
+    }
+}
+
+/// Parse a formula in ProForma style or a mass shift
+fn formula_or_mass(value: &str) -> Result<MolecularFormula, String> {
+    let value = value.trim();
+    if let Ok(mass) = value.parse::<f64>() {
+        Ok(MolecularFormula::with_additional_mass(mass))
+    } else {
+        MolecularFormula::from_pro_forma(value, .., false, false, true)
+            .map_err(|err| format!("Invalid formula '{value}': {err}"))
+    }
+}
+
+/// Parse comma separated placement rules
+fn rules(value: &str) -> Result<Vec<PlacementRule>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .map(placement_rule_parse)
+        .collect()
+}
+
+fn parse_tsv(text: &str) -> Result<CustomDatabase, String> {
+    let mut output = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty()
+            || line.starts_with('#')
+            || line.to_ascii_lowercase().starts_with("name\t")
+        {
+            continue;
+        }
+        let columns: Vec<&str> = line.split('\t').collect();
+        let definition = Definition {
+            name: columns[0].trim().to_string(),
+            formula: Some(formula_or_mass(
+                columns.get(1).copied().unwrap_or_default(),
+            )?),
+            rules: rules(columns.get(2).copied().unwrap_or_default())?,
+            description: columns
+                .get(3)
+                .map_or(String::new(), |d| d.trim().to_string()),
+            ..Definition::default()
+        };
+        output.push(definition.build(output.len() + 1)?);
+    }
+    Ok(output)
+}
+
+fn parse_obo(text: &str) -> Result<CustomDatabase, String> {
+    // The text between the first pair of double quotes
+    let quoted = |value: &str| {
+        value
+            .split_once('"')
+            .and_then(|(_, rest)| rest.split_once('"'))
+            .map(|(inner, _)| inner.to_string())
+    };
+    let mut output = Vec::new();
+    let mut current: Option<Definition> = None;
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            if let Some(definition) = current.take() {
+                output.push(definition.build(output.len() + 1)?);
+            }
+            if line == "[Term]" {
+                current = Some(Definition::default());
+            }
+            continue;
+        }
+        let (Some(definition), Some((key, value))) = (current.as_mut(), line.split_once(':'))
+        else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "name" => definition.name = value.to_string(),
+            "def" => definition.description = quoted(value).unwrap_or_default(),
+            "synonym" => definition.synonyms.extend(quoted(value)),
+            "property_value" => {
+                let (property, rest) = value.split_once(' ').unwrap_or((value, ""));
+                let content = quoted(rest).unwrap_or_else(|| rest.trim().to_string());
+                match property {
+                    "formula" | "mass" => definition.formula = Some(formula_or_mass(&content)?),
+                    "site" => definition.rules.extend(rules(&content)?),
+                    _ => (),
+                }
+            }
+            _ => (),
+        }
+    }
+    if let Some(definition) = current.take() {
+        output.push(definition.build(output.len() + 1)?);
+    }
+    Ok(output)
+}
+
+#[test]
+fn custom_database() {
+    use rustyms::Chemical;
+
+    let tsv = parse_tsv(
+        "name\tformula\trules\n# comment\nMyAcetyl\tC2H2O\tK,*-n\tAn acetylation\nShift\t+12.5\t\n",
+    )
+    .unwrap();
+    assert_eq!(tsv.len(), 2);
+    assert_eq!(tsv[0].1, "myacetyl");
+    assert_eq!(
+        Ontology::Custom.find_name("MyAcetyl", Some(&tsv)),
+        Some(tsv[0].2.clone())
+    );
+    let obo = parse_obo(
+        "format-version: 1.2\n\n[Term]\nid: CUSTOM:1\nname: MyAcetyl\ndef: \"An acetylation\" []\nsynonym: \"acetyl\" EXACT []\nproperty_value: formula \"C2H2O\"\nproperty_value: site \"K\"\n\n[Typedef]\nid: part_of\n",
+    )
+    .unwrap();
+    assert_eq!(obo.len(), 1);
+    assert_eq!(obo[0].2.formula(), tsv[0].2.formula());
+    assert!(parse_tsv("Missing\tnot a formula\n").is_err());
+}
+
+#[test]
+fn mod_db_pre_pass() {
+    let parse = |args: &[&str]| mod_db_argument(args.iter().map(|a| a.to_string()));
+    assert_eq!(
+        parse(&["align", "AKG", "-t", "10ppm", "--mod-db", "a.tsv", "--fast"]),
+        Some("a.tsv".to_string())
+    );
+    assert_eq!(
+        parse(&["align", "--mod-db=b.obo", "AKG"]),
+        Some("b.obo".to_string())
+    );
+    assert_eq!(parse(&["align", "index", "x.fasta"]), None);
+    assert_eq!(
+        parse(&["align", "AKG", "--fixed", "C:Label@K", "--mod-db", "c.tsv"]),
+        Some("c.tsv".to_string())
+    );
+}
@@ -0,0 +1,104 @@
+use std::fmt::Write;
+
+use itertools::Itertools;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use rustyms::identification::FastaData;
+
+use crate::cli::DecoyKind;
+
+/// The prefix added to the identifier of all generated decoy sequences
+pub const DECOY_PREFIX: &str = "DECOY_";
+
+/// Generate decoys for database sequences. The decoys are named after the original sequence with
+/// [`DECOY_PREFIX`] prepended. Shuffling uses a fixed seed so that repeated runs give identical
+/// results, as long as the sequences are given in the same order.
+pub struct DecoyGenerator {
+    kind: DecoyKind,
+    rng: StdRng,
+}
+
+impl DecoyGenerator {
+    pub fn new(kind: DecoyKind) -> Self {
+        Self {
+            kind,
+            rng: StdRng::seed_from_u64(42),
+        }
+    }
+
+    /// Generate a decoy for every given sequence
+    pub fn generate(&mut self, sequences: &[FastaData]) -> Vec<FastaData> {
+        let mut database = String::new();
+        for fasta in sequences {
+            let mut residues = fasta
+                .peptide()
+                .sequence()
+                .iter()
+                .map(|s| s.aminoacid.char())
+                .collect_vec();
+            match self.kind {
+                DecoyKind::Reverse => residues.reverse(),
+                DecoyKind::Shuffle => residues.shuffle(&mut self.rng),
+            }
+            writeln!(
+                &mut database,
+                ">{DECOY_PREFIX}{} {}\n{}",
+                fasta.identifier(),
+                fasta.description(),
+                residues.into_iter().collect::<String>()
+            )
+            .unwrap();
+        }
+        FastaData::parse_reader(database.as_bytes(), None)
+            .expect("The generated decoy database could not be parsed")
+    }
+}
+
+/// Check if this database entry is a decoy
+pub fn is_decoy(fasta: &FastaData) -> bool {
+    fasta.identifier().to_string().starts_with(DECOY_PREFIX)
+}
+
+/// Calculate the q-value for every hit using target-decoy competition. The hits are given as their
+/// score and decoy status and have to be sorted from the best to the worst score. The q-value is the
+/// lowest FDR (decoys / targets) at which this hit would still be accepted. Hits with the same score
+/// can only be accepted together, so these get the same q-value.
+pub fn q_values(hits: &[(f64, bool)]) -> Vec<f64> {
+    let mut targets = 0;
+    let mut decoys = 0;
+    let mut values = vec![0.0; hits.len()];
+    let mut start = 0;
+    while start < hits.len() {
+        let end = start + hits[start..].partition_point(|(score, _)| *score == hits[start].0);
+        for (_, decoy) in &hits[start..end] {
+            if *decoy {
+                decoys += 1;
+            } else {
+                targets += 1;
+            }
+        }
+        values[start..end].fill((decoys as f64 / targets.max(1) as f64).min(1.0));
+        start = end;
+    }
+    let mut minimum = 1.0_f64;
+    for value in values.iter_mut().rev() {
+        minimum = minimum.min(*value);
+        *value = minimum;
+    }
+    values
+}
+
+#[test]
+fn q_value_calculation() {
+    let hits = |decoys: &[bool]| {
+        decoys
+            .iter()
+            .enumerate()
+            .map(|(i, d)| (-(i as f64), *d))
+            .collect_vec()
+    };
+    let values = q_values(&hits(&[false, false, true, false, true, true]));
+    assert_eq!(values, vec![0.0, 0.0, 1.0 / 3.0, 1.0 / 3.0, 2.0 / 3.0, 1.0]);
+    // A target tied with a decoy gets the same q-value, whatever the order of the two
+    let tied = [(0.9, false), (0.8, true), (0.8, false), (0.7, false)];
+    assert_eq!(q_values(&tied), vec![0.0, 1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0]);
+}
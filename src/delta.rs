@@ -0,0 +1,249 @@
+use colored::{Color, Colorize, Styles};
+use itertools::Itertools;
+use rustyms::{
+    modification_search_mass,
+    placement_rule::Position,
+    system::{dalton, Mass},
+    AminoAcid, Chemical, MassMode, MolecularFormula, MultiChemical, Peptidoform, SimpleLinear,
+    Tolerance, WithinTolerance,
+};
+
+use crate::{
+    cli::MassTolerance,
+    combination, custom,
+    render::{display_mass, table},
+    styling::Styling,
+};
+
+/// An amino acid substitution that explains a mass difference
+#[derive(Clone, Debug, PartialEq)]
+pub struct Substitution {
+    pub from: AminoAcid,
+    pub to: AminoAcid,
+    /// The 0 based positions of the original amino acid in the first peptide
+    pub positions: Vec<usize>,
+    /// The mass difference of the substitution minus the mass difference to explain
+    pub error: Mass,
+}
+
+/// Find all single canonical amino acid substitutions of residues in the peptide that change the
+/// mass by the given difference, sorted by error
+pub fn substitutions(
+    peptide: &Peptidoform<SimpleLinear>,
+    difference: Mass,
+    tolerance: Tolerance<Mass>,
+    mass_mode: MassMode,
+) -> Vec<Substitution> {
+    let mass = |aa: AminoAcid| {
+        aa.formulas()
+            .iter()
+            .next()
+            .map(|f| f.mass(mass_mode))
+            .unwrap_or_default()
+    };
+    let mut output = Vec::new();
+    for from in AminoAcid::CANONICAL_AMINO_ACIDS {
+        let positions = peptide
+            .sequence()
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.aminoacid.aminoacid() == *from)
+            .map(|(i, _)| i)
+            .collect_vec();
+        if positions.is_empty() {
+            continue;
+        }
+        for to in AminoAcid::CANONICAL_AMINO_ACIDS {
+            let shift = mass(*to) - mass(*from);
+            if from != to && tolerance.within(&difference, &shift) {
+                output.push(Substitution {
+                    from: *from,
+                    to: *to,
+                    positions: positions.clone(),
+                    error: shift - difference,
+                });
+            }
+        }
+    }
+    output.sort_by(|a, b| a.error.value.abs().total_cmp(&b.error.value.abs()));
+    output
+}
+
+/// Show the mass difference between two peptides, and all ontology modifications and amino acid
+/// substitutions that explain it. The tolerance is taken at the mass of the second peptide, as
+/// that is the error on the measured mass of which the difference is derived.
+pub fn show(
+    a: &Peptidoform<SimpleLinear>,
+    b: &Peptidoform<SimpleLinear>,
+    tolerance: MassTolerance,
+    mass_mode: MassMode,
+    positions: Option<&[(Vec<AminoAcid>, Position)]>,
+    combinations: usize,
+    precision: Option<usize>,
+) {
+    let formula = |p: &Peptidoform<SimpleLinear>, name: &str| {
+        let formulas = p.formulas().unique();
+        if formulas.len() > 1 {
+            println!(
+                "{}",
+                format!("{name} has multiple formulas, the first is used").dimmed()
+            );
+        }
+        formulas.iter().next().cloned().unwrap_or_default()
+    };
+    let (formula_a, formula_b) = (formula(a, "A"), formula(b, "B"));
+    for (name, formula) in [("A", &formula_a), ("B", &formula_b)] {
+        println!(
+            "{name}: {} {}",
+            formula.hill_notation_fancy().green(),
+            display_mass(formula.mass(mass_mode), true, precision)
+        );
+    }
+    let difference = formula_b.mass(mass_mode) - formula_a.mass(mass_mode);
+    let difference_formula: MolecularFormula = &formula_b - &formula_a;
+    let (low, high) = tolerance
+        .at(formula_b.mass(mass_mode))
+        .bounds(formula_b.mass(mass_mode));
+    let absolute = Tolerance::new_absolute((high - low) / 2.0);
+    println!(
+        "Difference (B - A): {}{} {} {}",
+        if difference.value < 0.0 { "-" } else { "+" },
+        display_mass(difference.abs(), true, precision),
+        if difference_formula.is_empty() {
+            String::new()
+        } else {
+            difference_formula.hill_notation_fancy().green().to_string()
+        },
+        format!(
+            "({mass_mode}, tolerance: {})",
+            display_mass((high - low) / 2.0, false, precision)
+        )
+        .dimmed()
+    );
+    if absolute.within(&difference, &Mass::new::<dalton>(0.0)) {
+        println!("{}", "The peptides are isobaric".green());
+        return;
+    }
+
+    println!(
+        "\nModifications explaining the difference {}:",
+        if difference.value < 0.0 {
+            "(as a modification on B)"
+        } else {
+            "(as a modification on A)"
+        }
+        .dimmed()
+    );
+    let mut modifications = modification_search_mass(
+        difference.abs(),
+        absolute,
+        positions,
+        mass_mode,
+        custom::database(),
+    )
+    .map(|(ontology, id, _name, modification)| {
+        let error = modification.formula().mass(mass_mode) - difference.abs();
+        (ontology, id, modification, error)
+    })
+    .collect_vec();
+    modifications.sort_by(|a, b| a.3.value.abs().total_cmp(&b.3.value.abs()));
+    let modification_formula = if difference.value < 0.0 {
+        -difference_formula.clone()
+    } else {
+        difference_formula.clone()
+    };
+    let mut data = vec![[
+        "Name".to_string(),
+        "Id".to_string(),
+        "Formula".to_string(),
+        "Error".to_string(),
+    ]];
+    for (ontology, id, modification, error) in &modifications {
+        let same = modification.formula() == modification_formula;
+        data.push([
+            modification.to_string(),
+            format!(
+                "{}{}",
+                ontology.name(),
+                id.map_or(String::new(), |id| format!(":{id}")),
+            ),
+            format!(
+                "{}{}",
+                modification.formula().hill_notation_fancy(),
+                if same { " (exact)" } else { "" }
+            ),
+            format!("{:+.5} Da", error.value),
+        ]);
+    }
+    if data.len() > 1 {
+        table(
+            &data,
+            true,
+            &[
+                Styling::with_fg(Some(Color::Magenta)),
+                Styling::with_style(Styles::Dimmed),
+                Styling::with_fg(Some(Color::Green)),
+                Styling::none(),
+            ],
+        );
+    } else {
+        println!("{}", "No modifications found".red())
+    }
+    if combinations > 1 {
+        combination::show(
+            difference.abs(),
+            absolute,
+            combinations,
+            positions,
+            mass_mode,
+            precision,
+        );
+    }
+
+    println!("\nAmino acid substitutions explaining the difference:");
+    let substitutions = substitutions(a, difference, absolute, mass_mode);
+    if substitutions.is_empty() {
+        println!("{}", "No substitutions found".red());
+        return;
+    }
+    let mut data = vec![[
+        "Substitution".to_string(),
+        "Positions in A".to_string(),
+        "Error".to_string(),
+    ]];
+    for substitution in &substitutions {
+        data.push([
+            format!("{}>{}", substitution.from.char(), substitution.to.char()),
+            substitution.positions.iter().map(|p| p + 1).join(","),
+            format!("{:+.5} Da", substitution.error.value),
+        ]);
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_fg(Some(Color::Yellow)),
+            Styling::with_style(Styles::Dimmed),
+            Styling::none(),
+        ],
+    );
+}
+
+#[test]
+fn substitution() {
+    let peptide = Peptidoform::pro_forma("PEPTIDE", None)
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    // D > E is +14.016 Da
+    let found = substitutions(
+        &peptide,
+        Mass::new::<dalton>(14.01565),
+        Tolerance::new_absolute(Mass::new::<dalton>(0.001)),
+        MassMode::Monoisotopic,
+    );
+    assert!(found.iter().any(|s| s.from == AminoAcid::AsparticAcid
+        && s.to == AminoAcid::GlutamicAcid
+        && s.positions == [5]));
+    assert!(found.iter().all(|s| s.error.value.abs() < 0.001));
+}
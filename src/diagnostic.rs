@@ -0,0 +1,137 @@
+use colored::{Color, Colorize, Styles};
+use itertools::Itertools;
+use rustyms::{
+    fragment::{DiagnosticPosition, FragmentType},
+    model::ChargeRange,
+    system::{e, usize::Charge},
+    MassMode, Model, MolecularFormula, Peptidoform, SequencePosition, SimpleLinear,
+};
+
+use crate::{render::table, styling::Styling};
+
+/// An expected low mass ion of a peptide, an immonium ion or a modification specific diagnostic ion
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ion {
+    /// The label of the ion eg `iY` for the immonium ion of tyrosine
+    pub label: String,
+    /// The residues (1 based positions) this ion can originate from
+    pub origin: Vec<String>,
+    /// The neutral losses applied, if any
+    pub loss: String,
+    /// The formula of the singly charged ion, including the proton
+    pub formula: MolecularFormula,
+    pub mz: f64,
+}
+
+/// All singly charged immonium ions (including their modifications) and modification specific
+/// diagnostic ions (from the ontology definitions) of the peptide, sorted on m/z. Ions with the
+/// same composition from different residues are combined.
+pub fn ions(peptide: &Peptidoform<SimpleLinear>, mass_mode: MassMode) -> Vec<Ion> {
+    let model = Model::none()
+        .immonium((true, ChargeRange::ONE))
+        .modification_specific_diagnostic_ions((true, ChargeRange::ONE));
+    let residue = |position: SequencePosition| match position {
+        SequencePosition::NTerm => "N-term".to_string(),
+        SequencePosition::Index(index) => format!(
+            "{}{}",
+            peptide.sequence()[index].aminoacid.char(),
+            index + 1
+        ),
+        SequencePosition::CTerm => "C-term".to_string(),
+    };
+    let mut output: Vec<Ion> = Vec::new();
+    for fragment in peptide.generate_theoretical_fragments(Charge::new::<e>(1), &model) {
+        let (Some(formula), Some(mz)) = (fragment.formula.clone(), fragment.mz(mass_mode)) else {
+            continue;
+        };
+        let origin = match &fragment.ion {
+            FragmentType::Immonium(position, _)
+            | FragmentType::Diagnostic(DiagnosticPosition::Peptide(position, _)) => {
+                residue(position.sequence_index)
+            }
+            FragmentType::Diagnostic(DiagnosticPosition::Labile(modification)) => {
+                format!("labile {modification}")
+            }
+            _ => continue,
+        };
+        let label = fragment.ion.label().to_string();
+        let loss = fragment.neutral_loss.iter().join(",");
+        if let Some(ion) = output
+            .iter_mut()
+            .find(|ion| ion.label == label && ion.loss == loss && ion.formula == formula)
+        {
+            if !ion.origin.contains(&origin) {
+                ion.origin.push(origin);
+            }
+        } else {
+            output.push(Ion {
+                label,
+                origin: vec![origin],
+                loss,
+                formula,
+                mz: mz.value,
+            });
+        }
+    }
+    output.sort_by(|a, b| a.mz.total_cmp(&b.mz));
+    output
+}
+
+/// Show the immonium and diagnostic ions of the peptide as a table
+pub fn show(peptide: &Peptidoform<SimpleLinear>, mass_mode: MassMode, precision: Option<usize>) {
+    println!(
+        "Immonium and diagnostic ions {}",
+        format!("(singly charged, {mass_mode})").dimmed()
+    );
+    let ions = ions(peptide, mass_mode);
+    if ions.is_empty() {
+        println!("{}", "No immonium or diagnostic ions".red());
+        return;
+    }
+    let mut data = vec![[
+        "Ion".to_string(),
+        "From".to_string(),
+        "Loss".to_string(),
+        "Formula".to_string(),
+        "m/z".to_string(),
+    ]];
+    for ion in &ions {
+        data.push([
+            ion.label.clone(),
+            ion.origin.join(", "),
+            ion.loss.clone(),
+            ion.formula.hill_notation_fancy(),
+            precision.map_or(ion.mz.to_string(), |p| format!("{:.p$}", ion.mz)),
+        ]);
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_fg(Some(Color::Blue)),
+            Styling::with_style(Styles::Dimmed),
+            Styling::none(),
+            Styling::with_fg(Some(Color::Green)),
+            Styling::with_fg(Some(Color::Yellow)),
+        ],
+    );
+}
+
+#[test]
+fn phosphotyrosine() {
+    let peptide = Peptidoform::pro_forma("PEY[Phospho]EK", None)
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let found = ions(&peptide, MassMode::Monoisotopic);
+    // The immonium ion of phosphotyrosine is at 216.042
+    assert!(found
+        .iter()
+        .any(|ion| ion.loss.is_empty() && (ion.mz - 216.0420).abs() < 0.001));
+    // The immonium ion of glutamic acid is at 102.055, for both residues
+    let glutamic = found
+        .iter()
+        .find(|ion| ion.loss.is_empty() && (ion.mz - 102.0550).abs() < 0.001)
+        .unwrap();
+    assert_eq!(glutamic.origin, ["E2", "E4"]);
+}
@@ -0,0 +1,259 @@
+use std::ops::Range;
+
+use colored::{Color, Styles};
+use rustyms::{
+    modification::{SimpleModification, SimpleModificationInner},
+    placement_rule::{PlacementRule, Position},
+    MassMode, Peptidoform, Protease, SimpleLinear,
+};
+
+use crate::{
+    render::{display_dalton, table},
+    styling::Styling,
+};
+
+/// A peptide from a digestion
+#[derive(Clone, Debug)]
+pub struct DigestedPeptide {
+    /// The name of the protein
+    pub protein: String,
+    /// The location in the protein
+    pub window: Range<usize>,
+    /// The number of missed cleavages
+    pub missed: usize,
+    /// The peptide with the fixed modifications applied
+    pub peptide: Peptidoform<SimpleLinear>,
+}
+
+/// Get the location of all peptides in this protein after digestion with the given protease,
+/// allowing up to the given number of missed cleavages.
+pub fn digest_windows<T>(
+    sequence: &Peptidoform<T>,
+    protease: &Protease,
+    missed_cleavages: usize,
+) -> Vec<Range<usize>> {
+    let sites = &cleavage_sites(sequence, protease);
+    (0..sites.len())
+        .flat_map(|start| {
+            sites[start + 1..]
+                .iter()
+                .take(missed_cleavages + 1)
+                .map(move |end| sites[start]..*end)
+        })
+        .collect()
+}
+
+/// All locations where the protease cleaves this protein, including the start and end
+fn cleavage_sites<T>(sequence: &Peptidoform<T>, protease: &Protease) -> Vec<usize> {
+    let mut sites = vec![0];
+    if sequence.len() > protease.n_term.len() + protease.c_term.len() {
+        sites.extend(protease.match_locations(sequence.sequence()));
+    }
+    sites.push(sequence.len());
+    sites.dedup();
+    sites
+}
+
+/// Show a peptide with its flanking residues in the protein, e.g. `K.PEPTIDER.G`, with a hyphen
+/// for the protein termini.
+pub fn window_notation<T>(sequence: &Peptidoform<T>, window: &Range<usize>) -> String {
+    let residue = |index: usize| sequence.sequence()[index].aminoacid.char();
+    format!(
+        "{}.{}.{}",
+        window.start.checked_sub(1).map_or('-', residue),
+        sequence.sequence()[window.clone()]
+            .iter()
+            .map(|s| s.aminoacid.char())
+            .collect::<String>(),
+        if window.end < sequence.len() {
+            residue(window.end)
+        } else {
+            '-'
+        }
+    )
+}
+
+/// Digest all proteins and apply the fixed modifications to the peptides, see [`apply_fixed`]
+pub fn digest(
+    proteins: &[(String, Peptidoform<SimpleLinear>)],
+    protease: &Protease,
+    missed_cleavages: usize,
+    fixed: &[(SimpleModification, Option<PlacementRule>)],
+) -> Vec<DigestedPeptide> {
+    let mut output = Vec::new();
+    for (name, protein) in proteins {
+        let sites = cleavage_sites(protein, protease);
+        for window in digest_windows(protein, protease, missed_cleavages) {
+            let mut peptide = protein.sub_peptide(window.clone());
+            apply_fixed(
+                &mut peptide,
+                fixed,
+                window.start == 0,
+                window.end == protein.len(),
+            );
+            output.push(DigestedPeptide {
+                protein: name.clone(),
+                missed: sites
+                    .iter()
+                    .filter(|s| window.start < **s && **s < window.end)
+                    .count(),
+                window,
+                peptide,
+            });
+        }
+    }
+    output
+}
+
+/// Place the fixed modifications on every location where they are allowed, by the given placement
+/// rule or otherwise by the placement rules of the modification itself. Terminal rules are applied
+/// on the peptide terminus, and only for protein terminal rules if the peptide is at the protein
+/// terminus.
+pub fn apply_fixed(
+    peptide: &mut Peptidoform<SimpleLinear>,
+    fixed: &[(SimpleModification, Option<PlacementRule>)],
+    protein_n_term: bool,
+    protein_c_term: bool,
+) {
+    for (modification, rule) in fixed {
+        let rules = match (rule, &**modification) {
+            (Some(rule), _) => vec![rule.clone()],
+            (None, SimpleModificationInner::Database { specificities, .. }) => specificities
+                .iter()
+                .flat_map(|(rules, _, _)| rules.iter().cloned())
+                .collect(),
+            _ => Vec::new(),
+        };
+        let terminal = |position: Position, n_term: bool| match position {
+            Position::AnyNTerm => n_term,
+            Position::ProteinNTerm => n_term && protein_n_term,
+            Position::AnyCTerm => !n_term,
+            Position::ProteinCTerm => !n_term && protein_c_term,
+            Position::Anywhere => false,
+        };
+        let (mut n_term, mut c_term) = (false, false);
+        let mut side_chains = vec![false; peptide.len()];
+        for rule in &rules {
+            match rule {
+                PlacementRule::AminoAcid(aas, Position::Anywhere) => {
+                    for (index, element) in peptide.sequence().iter().enumerate() {
+                        side_chains[index] |= aas.contains(&element.aminoacid.aminoacid());
+                    }
+                }
+                PlacementRule::AminoAcid(aas, position) => {
+                    let first = peptide.sequence().first().map(|s| s.aminoacid.aminoacid());
+                    let last = peptide.sequence().last().map(|s| s.aminoacid.aminoacid());
+                    n_term |=
+                        terminal(*position, true) && first.is_some_and(|aa| aas.contains(&aa));
+                    c_term |=
+                        terminal(*position, false) && last.is_some_and(|aa| aas.contains(&aa));
+                }
+                PlacementRule::Terminal(position) => {
+                    n_term |= terminal(*position, true);
+                    c_term |= terminal(*position, false);
+                }
+                PlacementRule::Anywhere | PlacementRule::PsiModification(_, _) => (),
+            }
+        }
+        if n_term {
+            peptide.add_simple_n_term(modification.clone());
+        }
+        if c_term {
+            peptide.add_simple_c_term(modification.clone());
+        }
+        for (index, placed) in side_chains.into_iter().enumerate() {
+            if placed {
+                peptide.add_simple_modification(
+                    rustyms::SequencePosition::Index(index),
+                    modification.clone(),
+                );
+            }
+        }
+    }
+}
+
+/// Show the digested peptides as a table or in fasta format
+pub fn show(peptides: &[DigestedPeptide], fasta: bool, precision: Option<usize>) {
+    let mass = |peptide: &Peptidoform<SimpleLinear>, mass_mode: MassMode| {
+        peptide
+            .formulas()
+            .iter()
+            .next()
+            .map(|f| f.mass(mass_mode))
+            .unwrap_or_default()
+    };
+    if fasta {
+        for peptide in peptides {
+            println!(
+                ">{} {}-{} missed={}\n{}",
+                peptide.protein,
+                peptide.window.start + 1,
+                peptide.window.end,
+                peptide.missed,
+                peptide.peptide
+            );
+        }
+        return;
+    }
+    let mut data = vec![[
+        "Protein".to_string(),
+        "Position".to_string(),
+        "Missed".to_string(),
+        "Peptide".to_string(),
+        "Monoisotopic mass".to_string(),
+        "Average weight".to_string(),
+    ]];
+    for peptide in peptides {
+        data.push([
+            peptide.protein.clone(),
+            format!("{}-{}", peptide.window.start + 1, peptide.window.end),
+            peptide.missed.to_string(),
+            peptide.peptide.to_string(),
+            display_dalton(mass(&peptide.peptide, MassMode::Monoisotopic), precision),
+            display_dalton(mass(&peptide.peptide, MassMode::Average), precision),
+        ]);
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_style(Styles::Dimmed),
+            Styling::none(),
+            Styling::none(),
+            Styling::with_fg(Some(Color::Blue)),
+            Styling::with_fg(Some(Color::Yellow)),
+            Styling::with_fg(Some(Color::Yellow)),
+        ],
+    );
+}
+
+#[test]
+fn tryptic_peptides() {
+    let protein = Peptidoform::pro_forma("MKCPEPRPTIDEKAC", None)
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let carbamidomethyl = rustyms::modification::Ontology::Unimod
+        .find_name("Carbamidomethyl", None)
+        .unwrap();
+    let peptides = digest(
+        &[("P1".to_string(), protein)],
+        &crate::cli::Enzyme::Trypsin.protease(),
+        1,
+        &[(
+            carbamidomethyl,
+            Some(PlacementRule::AminoAcid(
+                vec![rustyms::AminoAcid::Cysteine],
+                Position::Anywhere,
+            )),
+        )],
+    );
+    let found = peptides
+        .iter()
+        .map(|p| (p.window.clone(), p.missed, p.peptide.to_string()))
+        .collect::<Vec<_>>();
+    assert!(found.contains(&(0..2, 0, "MK".to_string())));
+    assert!(found.contains(&(2..13, 0, "C[U:Carbamidomethyl]PEPRPTIDEK".to_string())));
+    assert!(found.contains(&(0..13, 1, "MKC[U:Carbamidomethyl]PEPRPTIDEK".to_string())));
+    assert!(!found.iter().any(|p| p.1 > 1));
+}
@@ -0,0 +1,207 @@
+use std::num::NonZeroU16;
+
+use colored::{Color, Colorize, Styles};
+use itertools::Itertools;
+use rustyms::{system::Mass, Element, MassMode, MolecularFormula, Peptidoform, SimpleLinear};
+
+use crate::{
+    render::{display_dalton, table},
+    styling::Styling,
+};
+
+/// The count of one element (or isotope) in both formulas
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ElementCount {
+    pub element: Element,
+    pub isotope: Option<NonZeroU16>,
+    pub a: i32,
+    pub b: i32,
+}
+
+impl ElementCount {
+    /// The count in B minus the count in A
+    pub const fn difference(&self) -> i32 {
+        self.b - self.a
+    }
+}
+
+/// The element wise comparison of two formulas, with all elements (and isotopes) found in either
+/// formula in Hill order (as in the formula itself)
+pub fn compare(a: &MolecularFormula, b: &MolecularFormula) -> Vec<ElementCount> {
+    let count = |formula: &MolecularFormula, element: Element, isotope: Option<NonZeroU16>| {
+        formula
+            .elements()
+            .iter()
+            .filter(|(e, i, _)| *e == element && *i == isotope)
+            .map(|(_, _, n)| *n)
+            .sum::<i32>()
+    };
+    a.elements()
+        .iter()
+        .chain(b.elements())
+        .map(|(element, isotope, _)| (*element, *isotope))
+        .unique()
+        .sorted_by_key(|(element, isotope)| {
+            // Hill order: carbon, hydrogen, then alphabetical
+            (
+                match element {
+                    Element::C => 0,
+                    Element::H => 1,
+                    _ => 2,
+                },
+                element.to_string(),
+                *isotope,
+            )
+        })
+        .map(|(element, isotope)| ElementCount {
+            element,
+            isotope,
+            a: count(a, element, isotope),
+            b: count(b, element, isotope),
+        })
+        .collect()
+}
+
+/// Show the molecular formulas of both peptides, the element wise difference, and the mass
+/// difference in all mass modes
+pub fn show(
+    a: &Peptidoform<SimpleLinear>,
+    b: &Peptidoform<SimpleLinear>,
+    precision: Option<usize>,
+) {
+    let formula = |p: &Peptidoform<SimpleLinear>, name: &str| {
+        let formulas = p.formulas().unique();
+        if formulas.len() > 1 {
+            println!(
+                "{}",
+                format!("{name} has multiple formulas, the first is used").dimmed()
+            );
+        }
+        formulas.iter().next().cloned().unwrap_or_default()
+    };
+    let (formula_a, formula_b) = (formula(a, "A"), formula(b, "B"));
+    for (name, formula) in [("A", &formula_a), ("B", &formula_b)] {
+        println!("{name}: {}", formula.hill_notation_fancy().green());
+    }
+    let difference: MolecularFormula = &formula_b - &formula_a;
+    println!(
+        "Difference (B - A): {}",
+        if difference.is_empty() {
+            "none, the formulas are identical".green().to_string()
+        } else {
+            difference.hill_notation_fancy().green().to_string()
+        }
+    );
+
+    let counts = compare(&formula_a, &formula_b);
+    if !counts.is_empty() {
+        let mut data = vec![[
+            "Element".to_string(),
+            "A".to_string(),
+            "B".to_string(),
+            "B - A".to_string(),
+        ]];
+        for count in &counts {
+            data.push([
+                count.isotope.map_or_else(
+                    || count.element.to_string(),
+                    |isotope| format!("[{isotope}{}]", count.element),
+                ),
+                count.a.to_string(),
+                count.b.to_string(),
+                match count.difference() {
+                    0 => "0".dimmed().to_string(),
+                    n if n > 0 => format!("+{n}").green().to_string(),
+                    n => n.to_string().red().to_string(),
+                },
+            ]);
+        }
+        println!();
+        table(
+            &data,
+            true,
+            &[
+                Styling::with_fg(Some(Color::Blue)),
+                Styling::none(),
+                Styling::none(),
+                Styling::none(),
+            ],
+        );
+    }
+    let additional = formula_b.additional_mass() - formula_a.additional_mass();
+    if additional != 0.0 {
+        println!(
+            "{}",
+            format!("The difference includes a mass shift of {additional:+} Da without formula")
+                .dimmed()
+                .italic()
+        );
+    }
+
+    println!();
+    let mut data = vec![[
+        "Mass mode".to_string(),
+        "A".to_string(),
+        "B".to_string(),
+        "B - A".to_string(),
+    ]];
+    for mode in [
+        MassMode::Monoisotopic,
+        MassMode::Average,
+        MassMode::MostAbundant,
+    ] {
+        let (mass_a, mass_b) = (formula_a.mass(mode), formula_b.mass(mode));
+        let shift: Mass = mass_b - mass_a;
+        data.push([
+            mode.to_string(),
+            display_dalton(mass_a, precision),
+            display_dalton(mass_b, precision),
+            format!(
+                "{}{}",
+                if shift.value < 0.0 { "-" } else { "+" },
+                display_dalton(shift.abs(), precision)
+            ),
+        ]);
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_style(Styles::Dimmed),
+            Styling::with_fg(Some(Color::Yellow)),
+            Styling::with_fg(Some(Color::Yellow)),
+            Styling::none(),
+        ],
+    );
+}
+
+#[test]
+fn oxidation() {
+    let parse = |s: &str| {
+        Peptidoform::pro_forma(s, None)
+            .unwrap()
+            .into_simple_linear()
+            .unwrap()
+            .formulas()[0]
+            .clone()
+    };
+    let counts = compare(&parse("PEPTMDE"), &parse("PEPTM[Oxidation]DE"));
+    assert_eq!(
+        counts
+            .iter()
+            .map(|c| (c.element, c.difference()))
+            .collect_vec(),
+        [
+            (Element::C, 0),
+            (Element::H, 0),
+            (Element::N, 0),
+            (Element::O, 1),
+            (Element::S, 0)
+        ]
+    );
+    // Heavy labelled lysine only differs in its isotopes
+    let counts = compare(&parse("PEPK"), &parse("PEPK[Label:13C(6)15N(2)]"));
+    assert!(counts
+        .iter()
+        .any(|c| c.element == Element::C && c.isotope.is_some() && c.difference() == 6));
+}
@@ -0,0 +1,101 @@
+use rustyms::{
+    align::{AlignScoring, Alignment, MatchType},
+    SequenceElement,
+};
+
+/// Explain the score of every step in the alignment. Every row contains the step number, the
+/// residues on A and B, the kind of step, the scoring components that contributed, the local score,
+/// and the cumulative score.
+pub fn explain_score<A, B>(
+    alignment: &Alignment<'_, A, B>,
+    scoring: AlignScoring<'_>,
+) -> Vec<[String; 7]> {
+    let (mut a, mut b) = alignment.start();
+    let mut last_gap = None;
+    let mut rows = Vec::with_capacity(alignment.path().len());
+    for (index, piece) in alignment.path().iter().enumerate() {
+        let seq_a = residues(alignment.seq_a().sequence(), a, piece.step_a);
+        let seq_b = residues(alignment.seq_b().sequence(), b, piece.step_b);
+        let (kind, components) = match piece.match_type {
+            MatchType::FullIdentity => (
+                "identity",
+                format!(
+                    "matrix {}/{}: {}",
+                    seq_a,
+                    seq_b,
+                    scoring.matrix[alignment.seq_a().sequence()[a].aminoacid.aminoacid() as usize]
+                        [alignment.seq_b().sequence()[b].aminoacid.aminoacid() as usize]
+                ),
+            ),
+            MatchType::IdentityMassMismatch => (
+                "mass mismatch",
+                format!("mass mismatch: {}", scoring.mass_mismatch),
+            ),
+            MatchType::Mismatch => ("mismatch", format!("mismatch: {}", scoring.mismatch)),
+            MatchType::Rotation => (
+                "rotation",
+                format!(
+                    "mass base {} + rotated {} × {}",
+                    scoring.mass_base, scoring.rotated, piece.step_a
+                ),
+            ),
+            MatchType::Isobaric => (
+                "isobaric",
+                if piece.step_a == 1 && piece.step_b == 1 {
+                    format!(
+                        "mass base {} + isobaric {}",
+                        scoring.mass_base, scoring.isobaric
+                    )
+                } else {
+                    format!(
+                        "mass base {} + isobaric {} × ({} + {}) / 2",
+                        scoring.mass_base, scoring.isobaric, piece.step_a, piece.step_b
+                    )
+                },
+            ),
+            MatchType::Gap => {
+                let orientation = (piece.step_a, piece.step_b);
+                (
+                    if piece.step_a == 0 {
+                        "insertion"
+                    } else {
+                        "deletion"
+                    },
+                    if last_gap == Some(orientation) {
+                        format!("gap extend {}", scoring.gap_extend)
+                    } else {
+                        format!(
+                            "gap start {} + gap extend {}",
+                            scoring.gap_start, scoring.gap_extend
+                        )
+                    },
+                )
+            }
+        };
+        last_gap = (piece.match_type == MatchType::Gap).then_some((piece.step_a, piece.step_b));
+        rows.push([
+            (index + 1).to_string(),
+            seq_a,
+            seq_b,
+            kind.to_string(),
+            components,
+            piece.local_score.to_string(),
+            piece.score.to_string(),
+        ]);
+        a += piece.step_a as usize;
+        b += piece.step_b as usize;
+    }
+    rows
+}
+
+/// Show the residues of a single step, or `-` for the gapped side of a gap
+fn residues<T>(sequence: &[SequenceElement<T>], start: usize, len: u16) -> String {
+    if len == 0 {
+        "-".to_string()
+    } else {
+        sequence[start..start + len as usize]
+            .iter()
+            .map(|s| s.aminoacid.char())
+            .collect()
+    }
+}
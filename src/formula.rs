@@ -0,0 +1,745 @@
+use colored::{Color, Colorize, Styles};
+use itertools::Itertools;
+use rustyms::{
+    system::{dalton, Mass},
+    Element, MassMode, MolecularFormula,
+};
+
+use crate::{
+    cli::MassTolerance,
+    render::{mass_modes, table},
+    styling::Styling,
+};
+
+/// The elements used in a formula search if none are given
+pub const DEFAULT_ELEMENTS: [ElementBound; 5] = [
+    ElementBound::any(Element::C),
+    ElementBound::any(Element::H),
+    ElementBound::any(Element::N),
+    ElementBound::any(Element::O),
+    ElementBound::any(Element::S),
+];
+
+/// The maximal number of formulas shown
+const MAX_SHOWN: usize = 100;
+
+/// A target mass for a formula search
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FormulaTarget {
+    /// The mass in dalton
+    pub mass: f64,
+    /// The absolute tolerance in dalton
+    pub tolerance: f64,
+}
+
+impl FormulaTarget {
+    /// Use the given tolerance, taken at the target mass, instead of the one derived from the digits
+    pub fn with_tolerance(self, tolerance: MassTolerance) -> Self {
+        let mass = Mass::new::<dalton>(self.mass);
+        let (low, high) = tolerance.at(mass).bounds(mass);
+        Self {
+            tolerance: (high - low).value / 2.0,
+            ..self
+        }
+    }
+}
+
+/// How strict formulas are filtered on chemical plausibility
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Plausibility {
+    /// Only leave out formulas with a negative ring and double bond equivalent
+    Lenient,
+    /// Leave out formulas outside of the element ratios seen in 99.7% of known compounds
+    #[default]
+    Normal,
+    /// Only keep formulas with the common element ratios and an integer ring and double bond equivalent
+    Strict,
+}
+
+impl Plausibility {
+    /// Check if a formula is plausible. Without carbon the ratio limits are only applied when
+    /// strict, which then rejects the formula.
+    pub fn check(self, formula: &MolecularFormula) -> bool {
+        let rdbe = rdbe(formula);
+        if rdbe < 0.0 {
+            return false;
+        }
+        let Some(limits) = self.limits() else {
+            return true;
+        };
+        if self == Self::Strict && rdbe.fract() != 0.0 {
+            return false;
+        }
+        let count = |element: Element| {
+            formula
+                .elements()
+                .iter()
+                .filter(|(e, _, _)| *e == element)
+                .map(|(_, _, n)| f64::from(*n))
+                .sum::<f64>()
+        };
+        let carbon = count(Element::C);
+        if carbon == 0.0 {
+            return self != Self::Strict;
+        }
+        limits.iter().all(|(element, min, max)| {
+            let ratio = count(*element) / carbon;
+            *min <= ratio && ratio <= *max
+        })
+    }
+
+    /// The minimal and maximal ratio to carbon for the limited elements, None if the ratios are not checked
+    const fn limits(self) -> Option<&'static [(Element, f64, f64)]> {
+        Some(match self {
+            Self::Lenient => return None,
+            Self::Normal => &[
+                (Element::H, 0.1, 6.0),
+                (Element::N, 0.0, 4.0),
+                (Element::O, 0.0, 3.0),
+                (Element::P, 0.0, 2.0),
+                (Element::S, 0.0, 3.0),
+            ],
+            Self::Strict => &[
+                (Element::H, 0.2, 3.1),
+                (Element::N, 0.0, 1.3),
+                (Element::O, 0.0, 1.2),
+                (Element::P, 0.0, 0.3),
+                (Element::S, 0.0, 0.8),
+            ],
+        })
+    }
+
+    /// Check if the partially filled in formula can still be completed to a plausible formula. The
+    /// counts are the elements that are already filled in, the elements are the ones that are still
+    /// open (with the maximal count they could get) and the remaining mass has to be filled by these
+    /// open elements. This never rejects a formula that would pass [`Self::check`], but it can accept
+    /// branches that do not lead to any plausible formula.
+    fn possible(
+        self,
+        remaining: f64,
+        tolerance: f64,
+        counts: &[(Element, Option<std::num::NonZeroU16>, i32)],
+        elements: &[(ElementBound, f64)],
+    ) -> bool {
+        // The bounds on the count of all open elements
+        let mut open = elements
+            .iter()
+            .map(|(bound, mass)| {
+                let mut max = ((remaining + tolerance) / mass).floor().max(0.0);
+                if let Some(bound) = bound.max {
+                    max = max.min(bound as f64);
+                }
+                (bound.element, *mass, bound.min as f64, max)
+            })
+            .collect_vec();
+        let fixed = |element: Element| {
+            counts
+                .iter()
+                .find(|(e, _, _)| *e == element)
+                .map(|(_, _, n)| f64::from(*n))
+        };
+        let range = |open: &[(Element, f64, f64, f64)], element: Element| {
+            fixed(element)
+                .map(|n| (n, n))
+                .or_else(|| {
+                    open.iter()
+                        .find(|(e, _, _, _)| *e == element)
+                        .map(|(_, _, min, max)| (*min, *max))
+                })
+                .unwrap_or((0.0, 0.0))
+        };
+        if let Some(limits) = self.limits() {
+            let (mut carbon_min, mut carbon_max) = range(&open, Element::C);
+            if self == Self::Strict {
+                carbon_min = carbon_min.max(1.0);
+            }
+            // Without carbon the ratios are not checked, so only when carbon is certain the ratios help
+            if carbon_min >= 1.0 {
+                for (element, min, max) in limits {
+                    let (low, high) = range(&open, *element);
+                    carbon_min = carbon_min.max((low / max).ceil());
+                    if *min > 0.0 {
+                        carbon_max = carbon_max.min((high / min).floor());
+                    }
+                }
+                if carbon_min > carbon_max {
+                    return false;
+                }
+                for (element, _, low, high) in &mut open {
+                    if *element == Element::C {
+                        (*low, *high) = (carbon_min, carbon_max);
+                    } else if let Some((_, min, max)) = limits.iter().find(|(e, _, _)| e == element)
+                    {
+                        *low = low.max((min * carbon_min).ceil());
+                        *high = high.min((max * carbon_max).floor());
+                    }
+                }
+                if open.iter().any(|(_, _, low, high)| low > high) {
+                    return false;
+                }
+            }
+        }
+        // The open elements have to be able to fill the remaining mass
+        let left = remaining - open.iter().map(|(_, mass, low, _)| low * mass).sum::<f64>();
+        let space = open
+            .iter()
+            .map(|(_, mass, low, high)| (high - low) * mass)
+            .sum::<f64>();
+        if left < -tolerance || space < left - tolerance {
+            return false;
+        }
+        // The highest RDBE possible, filling the mass with the elements that add the most RDBE per
+        // Dalton first, even if that takes a fractional element count
+        let mut best = 1.0
+            + counts
+                .iter()
+                .map(|(e, _, n)| f64::from(*n) * rdbe_contribution(*e))
+                .sum::<f64>()
+            + open
+                .iter()
+                .map(|(e, _, low, _)| low * rdbe_contribution(*e))
+                .sum::<f64>();
+        open.sort_by(|a, b| {
+            (rdbe_contribution(b.0) / b.1).total_cmp(&(rdbe_contribution(a.0) / a.1))
+        });
+        let mut filled = 0.0;
+        for (element, mass, low, high) in open {
+            let per_dalton = rdbe_contribution(element) / mass;
+            let target = if per_dalton > 0.0 {
+                left + tolerance
+            } else {
+                left - tolerance
+            };
+            let take = ((high - low) * mass).min(target - filled).max(0.0);
+            filled += take;
+            best += take * per_dalton;
+        }
+        best >= -1e-9
+    }
+}
+
+/// A window on the mass defect of the formulas, see `--mass-defect`
+#[derive(Clone, Debug, PartialEq)]
+pub struct MassDefect {
+    /// The repeating unit for a Kendrick mass defect, eg CH2, if not given the plain mass defect is used
+    pub base: Option<MolecularFormula>,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl MassDefect {
+    /// The mass defect of this mass, the mass minus the nominal mass. For a Kendrick mass defect the
+    /// mass is first rescaled so that the base has an integer mass, which gives all formulas that
+    /// only differ in the number of repeating units the same defect.
+    pub fn defect(&self, mass: f64) -> f64 {
+        let mass = self.base.as_ref().map_or(mass, |base| {
+            let base = base.monoisotopic_mass().value;
+            mass * base.round() / base
+        });
+        mass - mass.round()
+    }
+
+    /// Check if the (neutral) formula falls in the window
+    pub fn check(&self, formula: &MolecularFormula) -> bool {
+        let defect = self.defect(formula.monoisotopic_mass().value);
+        self.min <= defect && defect <= self.max
+    }
+
+    /// Check if any formula with a mass within the tolerance of this mass could fall in the window
+    fn possible(&self, mass: f64, tolerance: f64) -> bool {
+        let (low, high) = (self.defect(mass - tolerance), self.defect(mass + tolerance));
+        // If the nominal mass changes within the tolerance the defect wraps around
+        low > high || (low <= self.max && self.min <= high)
+    }
+
+    /// The name of the mass defect, eg `KMD (CH2)`
+    fn name(&self) -> String {
+        self.base
+            .as_ref()
+            .map_or("Mass defect".to_string(), |base| {
+                format!("KMD ({})", base.hill_notation_fancy())
+            })
+    }
+}
+
+/// The ring and double bond equivalent of a formula, based on the common valence of every element.
+/// Elements without a common valence are assumed to be divalent, so they do not change the result.
+pub fn rdbe(formula: &MolecularFormula) -> f64 {
+    1.0 + formula
+        .elements()
+        .iter()
+        .map(|(element, _, n)| f64::from(*n) * rdbe_contribution(*element))
+        .sum::<f64>()
+}
+
+/// The change in ring and double bond equivalent for a single atom of this element
+fn rdbe_contribution(element: Element) -> f64 {
+    let valence = match element {
+        Element::C | Element::Si => 4,
+        Element::N | Element::P | Element::B => 3,
+        Element::H
+        | Element::F
+        | Element::Cl
+        | Element::Br
+        | Element::I
+        | Element::Li
+        | Element::Na
+        | Element::K => 1,
+        _ => 2,
+    };
+    f64::from(valence - 2) / 2.0
+}
+
+/// An element for a formula search with bounds on its count
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ElementBound {
+    pub element: Element,
+    pub min: usize,
+    /// The maximal count, if not given it is only bounded by the mass
+    pub max: Option<usize>,
+}
+
+impl ElementBound {
+    /// An element with any count
+    pub const fn any(element: Element) -> Self {
+        Self {
+            element,
+            min: 0,
+            max: None,
+        }
+    }
+}
+
+impl std::fmt::Display for ElementBound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.min, self.max) {
+            (0, None) => write!(f, "{}", self.element),
+            (min, None) => write!(f, "{}[{min}-]", self.element),
+            (min, Some(max)) if min == max => write!(f, "{}[{min}]", self.element),
+            (min, Some(max)) => write!(f, "{}[{min}-{max}]", self.element),
+        }
+    }
+}
+
+/// An adduct, the mass of a formula search is then that of the (singly charged) ion
+#[derive(Clone, Debug, PartialEq)]
+pub struct Adduct {
+    /// The elements added to the neutral molecule, negative counts are losses, in the given order
+    pub elements: Vec<(Element, i32)>,
+}
+
+impl Adduct {
+    /// The elements as a formula
+    pub fn formula(&self) -> MolecularFormula {
+        MolecularFormula::new(
+            &self
+                .elements
+                .iter()
+                .map(|(e, n)| (*e, None, *n))
+                .collect_vec(),
+            &[],
+        )
+        .unwrap()
+    }
+
+    /// The charge of the ion, positive if the adduct adds mass and negative for losses like `H-1`
+    pub fn charge(&self) -> i32 {
+        if self.formula().monoisotopic_mass().value >= 0.0 {
+            1
+        } else {
+            -1
+        }
+    }
+
+    /// The mass difference between the ion and the neutral molecule, including the lost or gained electron
+    pub fn shift(&self) -> f64 {
+        self.formula().monoisotopic_mass().value
+            - f64::from(self.charge()) * Element::Electron.mass(None).unwrap().value
+    }
+
+    /// The conventional notation for the ion, eg `[M+Na]+` or `[M-H]-`
+    pub fn label(&self) -> String {
+        let mut output = "[M".to_string();
+        let mut sign = 0;
+        for (element, count) in &self.elements {
+            if count.signum() != sign {
+                sign = count.signum();
+                output.push(if sign < 0 { '-' } else { '+' });
+            }
+            output.push_str(&element.to_string());
+            if count.abs() > 1 {
+                output.push_str(&count.abs().to_string());
+            }
+        }
+        output.push(']');
+        output.push(if self.charge() < 0 { '-' } else { '+' });
+        output
+    }
+}
+
+/// Find all formulas of the given elements (within their bounds) with a monoisotopic mass within
+/// the tolerance of the target mass, sorted by mass. Only plausible formulas (see [`Plausibility`])
+/// within the mass defect window (if given) are returned, branches of the search that cannot lead to
+/// such a formula are not explored.
+pub fn search(
+    mass: f64,
+    tolerance: f64,
+    elements: &[ElementBound],
+    plausibility: Plausibility,
+    mass_defect: Option<&MassDefect>,
+) -> Vec<MolecularFormula> {
+    if mass_defect.is_some_and(|mass_defect| !mass_defect.possible(mass, tolerance)) {
+        return Vec::new();
+    }
+    let mut elements = elements
+        .iter()
+        .filter_map(|e| e.element.mass(None).map(|m| (*e, m.value)))
+        .collect_vec();
+    // Placing the heaviest elements first keeps the number of options to consider smaller
+    elements.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut output = Vec::new();
+    let mut counts = Vec::with_capacity(elements.len());
+    let accept = |formula: &MolecularFormula| {
+        plausibility.check(formula) && mass_defect.is_none_or(|m| m.check(formula))
+    };
+    decompose(
+        mass,
+        tolerance,
+        &elements,
+        &mut counts,
+        plausibility,
+        &accept,
+        &mut output,
+    );
+    output.sort_by(|a, b| {
+        a.monoisotopic_mass()
+            .value
+            .total_cmp(&b.monoisotopic_mass().value)
+    });
+    output
+}
+
+/// Recursively fill in the counts for all elements, the last element gets all counts that fit the remaining mass
+fn decompose(
+    remaining: f64,
+    tolerance: f64,
+    elements: &[(ElementBound, f64)],
+    counts: &mut Vec<(Element, Option<std::num::NonZeroU16>, i32)>,
+    plausibility: Plausibility,
+    accept: &impl Fn(&MolecularFormula) -> bool,
+    output: &mut Vec<MolecularFormula>,
+) {
+    let Some(((bound, mass), rest)) = elements.split_first() else {
+        return;
+    };
+    if !plausibility.possible(remaining, tolerance, counts, elements) {
+        return;
+    }
+    let mut max = ((remaining + tolerance) / mass).floor();
+    if let Some(bound) = bound.max {
+        max = max.min(bound as f64);
+    }
+    let mut min = bound.min as f64;
+    if rest.is_empty() {
+        min = min.max(((remaining - tolerance) / mass).ceil());
+    }
+    if max < min {
+        return;
+    }
+    for count in min as i32..=max as i32 {
+        counts.push((bound.element, None, count));
+        let left = remaining - f64::from(count) * mass;
+        if rest.is_empty() {
+            if left.abs() <= tolerance && counts.iter().any(|(_, _, n)| *n > 0) {
+                let formula = MolecularFormula::new(counts, &[]).unwrap();
+                if accept(&formula) {
+                    output.push(formula);
+                }
+            }
+        } else {
+            decompose(left, tolerance, rest, counts, plausibility, accept, output);
+        }
+        counts.pop();
+    }
+}
+
+/// A formula found for a target mass, with the adduct if any and the mass error in Dalton
+pub type Found<'a> = (Option<&'a Adduct>, MolecularFormula, f64);
+
+/// Search all formulas for the target mass, interpreted as the ion of every given adduct or if no
+/// adducts are given as the neutral mass. The formulas are ranked by the absolute mass error (in
+/// Dalton), and implausible formulas are left out (see [`Plausibility`]) as well as formulas outside
+/// of the mass defect window if given.
+pub fn find<'a>(
+    target: FormulaTarget,
+    adducts: &'a [Adduct],
+    elements: &[ElementBound],
+    plausibility: Plausibility,
+    mass_defect: Option<&MassDefect>,
+) -> Vec<Found<'a>> {
+    let elements = if elements.is_empty() {
+        &DEFAULT_ELEMENTS
+    } else {
+        elements
+    };
+    let adducts = if adducts.is_empty() {
+        vec![None]
+    } else {
+        adducts.iter().map(Some).collect()
+    };
+    let mut results = adducts
+        .iter()
+        .flat_map(|adduct| {
+            let shift = adduct.map_or(0.0, Adduct::shift);
+            search(
+                target.mass - shift,
+                target.tolerance,
+                elements,
+                plausibility,
+                mass_defect,
+            )
+            .into_iter()
+            .map(move |formula| {
+                let error = formula.monoisotopic_mass().value + shift - target.mass;
+                (*adduct, formula, error)
+            })
+        })
+        .collect_vec();
+    results.sort_by(|a, b| a.2.abs().total_cmp(&b.2.abs()));
+    results
+}
+
+/// Search and show all formulas for the target mass, see [`find`]. The search always uses the
+/// monoisotopic mass, with `all_mass_modes` the average and most abundant mass of the formulas are
+/// shown as well.
+pub fn show(
+    target: FormulaTarget,
+    adducts: &[Adduct],
+    elements: &[ElementBound],
+    plausibility: Plausibility,
+    mass_defect: Option<&MassDefect>,
+    all_mass_modes: bool,
+    precision: Option<usize>,
+) {
+    let results = find(target, adducts, elements, plausibility, mass_defect);
+    let elements = if elements.is_empty() {
+        &DEFAULT_ELEMENTS
+    } else {
+        elements
+    };
+    let mass = |n: f64| {
+        precision.map_or_else(
+            || format!("{n} Da"),
+            |precision| format!("{n:.precision$} Da"),
+        )
+    };
+    println!(
+        "Formulas of {} with a monoisotopic mass of {} {}",
+        elements.iter().join(", "),
+        mass(target.mass).yellow(),
+        format!(
+            "(tolerance: {:.5} Da, {:.2} ppm)",
+            target.tolerance,
+            target.tolerance / target.mass * 1e6
+        )
+        .dimmed()
+    );
+    let note = || {
+        println!(
+            "{}",
+            format!(
+                "Left out chemically implausible formulas{}",
+                if plausibility == Plausibility::Lenient {
+                    ""
+                } else {
+                    ", use --lenient to show more"
+                }
+            )
+            .dimmed()
+            .italic()
+        );
+        if let Some(mass_defect) = mass_defect {
+            println!(
+                "{}",
+                format!(
+                    "Left out formulas with a {} outside of {}..{}",
+                    mass_defect.name().to_lowercase(),
+                    mass_defect.min,
+                    mass_defect.max
+                )
+                .dimmed()
+                .italic()
+            );
+        }
+    };
+    if results.is_empty() {
+        println!("{}", "No formulas found".red());
+        note();
+        return;
+    }
+    let with_adducts = !adducts.is_empty();
+    let mut header = vec!["Formula".to_string()];
+    if with_adducts {
+        header.push("Ion".to_string());
+    }
+    let modes = mass_modes(all_mass_modes, MassMode::Monoisotopic);
+    if all_mass_modes {
+        header.extend(modes.iter().map(|mode| format!("Neutral {mode}")));
+    } else {
+        header.push("Neutral mass".to_string());
+    }
+    header.push("RDBE".to_string());
+    if let Some(mass_defect) = mass_defect {
+        header.push(mass_defect.name());
+    }
+    header.extend(["Error".to_string(), "ppm".to_string()]);
+    let mut data = vec![header];
+    for (adduct, formula, error) in results.iter().take(MAX_SHOWN) {
+        let mut row = vec![formula.hill_notation_fancy()];
+        if with_adducts {
+            row.push(adduct.map_or(String::new(), Adduct::label));
+        }
+        row.extend(modes.iter().map(|mode| mass(formula.mass(*mode).value)));
+        row.push(rdbe(formula).to_string());
+        if let Some(mass_defect) = mass_defect {
+            row.push(format!(
+                "{:+.4}",
+                mass_defect.defect(formula.monoisotopic_mass().value)
+            ));
+        }
+        row.extend([
+            format!("{error:+.5} Da"),
+            format!("{:+.2}", error / target.mass * 1e6),
+        ]);
+        data.push(row);
+    }
+    let mut styling = vec![Styling::with_fg(Some(Color::Green))];
+    if with_adducts {
+        styling.push(Styling::with_fg(Some(Color::Magenta)));
+    }
+    styling.extend(modes.iter().map(|_| Styling::with_fg(Some(Color::Yellow))));
+    styling.push(Styling::none());
+    if mass_defect.is_some() {
+        styling.push(Styling::with_fg(Some(Color::Cyan)));
+    }
+    styling.extend([Styling::none(), Styling::with_style(Styles::Dimmed)]);
+    table(&data, true, &styling);
+    if results.len() > MAX_SHOWN {
+        println!(
+            "{}",
+            format!("Showing {MAX_SHOWN} of {} formulas", results.len())
+                .dimmed()
+                .italic()
+        );
+    }
+    note();
+}
+
+#[test]
+fn formula_search() {
+    // Glycine residue C2H3NO
+    let found = search(
+        57.02146,
+        0.00001,
+        &DEFAULT_ELEMENTS,
+        Plausibility::Lenient,
+        None,
+    );
+    assert_eq!(
+        found.iter().map(|f| f.hill_notation()).collect_vec(),
+        ["C2H3N1O1"]
+    );
+    // Phosphoserine residue C3H6NO5P, which needs P and is excluded if P is bounded to zero
+    let mut elements = DEFAULT_ELEMENTS.to_vec();
+    elements.push(ElementBound {
+        element: Element::P,
+        min: 1,
+        max: Some(1),
+    });
+    let found = search(166.99836, 0.00001, &elements, Plausibility::Lenient, None);
+    assert!(found.iter().any(|f| f.hill_notation() == "C3H6N1O5P1"));
+    elements.last_mut().unwrap().min = 0;
+    elements.last_mut().unwrap().max = Some(0);
+    assert!(
+        search(166.99836, 0.00001, &elements, Plausibility::Lenient, None)
+            .iter()
+            .all(|f| f.hill_notation() != "C3H6N1O5P1")
+    );
+    // Glucose C6H12O6 is plausible, H28O18S2 is not
+    let glucose = rustyms::molecular_formula!(C 6 H 12 O 6);
+    assert_eq!(rdbe(&glucose), 1.0);
+    assert!(Plausibility::Strict.check(&glucose));
+    let odd = rustyms::molecular_formula!(H 28 O 18 S 2);
+    assert!(!Plausibility::Lenient.check(&odd));
+    let radical = rustyms::molecular_formula!(C 6 H 11 O 6);
+    assert!(Plausibility::Normal.check(&radical));
+    assert!(!Plausibility::Strict.check(&radical));
+    let target = FormulaTarget {
+        mass: 1000.0,
+        tolerance: 0.5,
+    }
+    .with_tolerance(MassTolerance::Single(rustyms::Tolerance::new_ppm(3.0)));
+    assert!((target.tolerance - 0.003).abs() < 1e-9);
+    let sodium = Adduct {
+        elements: vec![(Element::Na, 1)],
+    };
+    let deprotonated = Adduct {
+        elements: vec![(Element::H, -1)],
+    };
+    assert_eq!(sodium.label(), "[M+Na]+");
+    assert_eq!(deprotonated.label(), "[M-H]-");
+    assert!((sodium.shift() - 22.98922).abs() < 0.0001);
+    assert!((deprotonated.shift() + 1.00728).abs() < 0.0001);
+}
+
+#[test]
+fn kendrick_mass_defect() {
+    let kendrick = MassDefect {
+        base: Some(rustyms::molecular_formula!(C 1 H 2)),
+        min: -0.5,
+        max: 0.5,
+    };
+    // Palmitic and stearic acid only differ in two CH2 units so have the same Kendrick mass defect
+    let palmitic = rustyms::molecular_formula!(C 16 H 32 O 2).monoisotopic_mass();
+    let stearic = rustyms::molecular_formula!(C 18 H 36 O 2).monoisotopic_mass();
+    assert!((kendrick.defect(palmitic.value) - kendrick.defect(stearic.value)).abs() < 1e-9);
+    let plain = MassDefect {
+        base: None,
+        min: 0.0,
+        max: 0.1,
+    };
+    assert!((plain.defect(palmitic.value) - 0.2402).abs() < 0.0001);
+    assert!(!plain.check(&rustyms::molecular_formula!(C 16 H 32 O 2)));
+}
+
+#[test]
+fn pruned_search() {
+    // The pruned search finds exactly the formulas that pass the filters afterwards
+    for mass in [180.0634, 301.1412, 612.2301] {
+        let all = search(mass, 0.005, &DEFAULT_ELEMENTS, Plausibility::Lenient, None);
+        for plausibility in [Plausibility::Normal, Plausibility::Strict] {
+            let filtered = all
+                .iter()
+                .filter(|f| plausibility.check(f))
+                .cloned()
+                .collect_vec();
+            assert_eq!(
+                search(mass, 0.005, &DEFAULT_ELEMENTS, plausibility, None),
+                filtered
+            );
+        }
+    }
+    let kendrick = MassDefect {
+        base: Some(rustyms::molecular_formula!(C 1 H 2)),
+        min: -0.1,
+        max: 0.1,
+    };
+    assert!(search(
+        612.2301,
+        0.005,
+        &DEFAULT_ELEMENTS,
+        Plausibility::Normal,
+        Some(&kendrick)
+    )
+    .is_empty());
+}
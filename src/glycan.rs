@@ -0,0 +1,334 @@
+use colored::{Color, Colorize};
+use itertools::Itertools;
+use rustyms::{
+    glycan::{GlycanStructure, MonoSaccharide},
+    modification::{GnoComposition, SimpleModificationInner},
+    modification_search_glycan, Chemical, MolecularFormula,
+};
+
+use crate::{
+    render::{display_mass, table},
+    styling::Styling,
+};
+
+/// Show a monosaccharide composition in the shorthand notation eg `HexNAc4Hex5`
+pub fn composition_text(composition: &[(MonoSaccharide, isize)]) -> String {
+    composition
+        .iter()
+        .map(|(sug, amount)| format!("{sug}{amount}"))
+        .join("")
+}
+
+/// The molecular formula of the monosaccharide composition (as a modification, so without water)
+pub fn formula(composition: &[(MonoSaccharide, isize)]) -> MolecularFormula {
+    SimpleModificationInner::Glycan(composition.to_vec()).formula()
+}
+
+/// A species to filter GNOme structures on, see `--taxonomy`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Taxonomy {
+    /// The NCBI taxonomy ID
+    Id(usize),
+    /// The scientific name
+    Name(String),
+}
+
+impl Taxonomy {
+    /// If this is the species with the given scientific name and taxonomy ID
+    fn matches(&self, name: &str, id: usize) -> bool {
+        match self {
+            Self::Id(own) => *own == id,
+            Self::Name(own) => own.eq_ignore_ascii_case(name),
+        }
+    }
+}
+
+/// Restrict GNOme structures to those found in a species and/or tissue (from GlycomeAtlas)
+#[derive(Clone, Debug, Default)]
+pub struct GnomeFilter {
+    pub taxonomy: Option<Taxonomy>,
+    /// (Part of) the tissue name or its UBERON ID
+    pub tissue: Option<String>,
+}
+
+impl GnomeFilter {
+    /// If the GNOme modification is found in this species and tissue, if both are given the tissue
+    /// has to be reported for that species. Other modifications are never filtered out.
+    pub fn matches(&self, modification: &SimpleModificationInner) -> bool {
+        let SimpleModificationInner::Gno {
+            taxonomy,
+            glycomeatlas,
+            ..
+        } = modification
+        else {
+            return true;
+        };
+        let species = |name: &str| {
+            self.taxonomy.as_ref().is_none_or(|filter| {
+                taxonomy
+                    .iter()
+                    .any(|(n, id)| n.eq_ignore_ascii_case(name) && filter.matches(n, *id))
+                    || matches!(filter, Taxonomy::Name(n) if n.eq_ignore_ascii_case(name))
+            })
+        };
+        match &self.tissue {
+            None => self
+                .taxonomy
+                .as_ref()
+                .is_none_or(|filter| taxonomy.iter().any(|(name, id)| filter.matches(name, *id))),
+            Some(tissue) => {
+                let tissue = tissue.to_ascii_lowercase();
+                glycomeatlas.iter().any(|(name, places)| {
+                    species(name)
+                        && places.iter().any(|(place, id)| {
+                            place.to_ascii_lowercase().contains(&tissue)
+                                || id.eq_ignore_ascii_case(&tissue)
+                        })
+                })
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.taxonomy.is_none() && self.tissue.is_none()
+    }
+}
+
+/// Show the formula and masses of a monosaccharide composition, and if asked the GNOme structures
+/// with this composition
+pub fn show(
+    composition: &[(MonoSaccharide, isize)],
+    gnome: Option<&GnomeFilter>,
+    precision: Option<usize>,
+) {
+    let formula = formula(composition);
+    println!("Glycan: {}", composition_text(composition).green());
+    println!(
+        "Full mass: {} {} {} {}",
+        display_mass(formula.monoisotopic_mass(), true, precision),
+        display_mass(formula.average_weight(), true, precision),
+        display_mass(formula.most_abundant_mass(), true, precision),
+        "(monoisotopic | average | most abundant, as a modification so without water)".dimmed(),
+    );
+    println!("Composition: {}", formula.hill_notation_fancy().green());
+    if let Some(filter) = gnome {
+        println!();
+        structures(composition, filter);
+    }
+}
+
+/// Show all GNOme modifications with the same monosaccharide composition that pass the filter
+pub fn structures(composition: &[(MonoSaccharide, isize)], filter: &GnomeFilter) {
+    println!(
+        "All GNOme modifications with the same monosaccharide composition{}:",
+        if filter.is_empty() {
+            String::new()
+        } else {
+            format!(
+                " {}",
+                format!(
+                    "(found in{}{})",
+                    filter.taxonomy.as_ref().map_or(String::new(), |t| match t {
+                        Taxonomy::Id(id) => format!(" taxonomy {id}"),
+                        Taxonomy::Name(name) => format!(" {name}"),
+                    }),
+                    filter
+                        .tissue
+                        .as_ref()
+                        .map_or(String::new(), |t| format!(" tissue {t}"))
+                )
+                .dimmed()
+            )
+        }
+    );
+    let mut data = vec![["Name".to_string(), "Definition".to_string()]];
+    for (_ontology, _id, _name, modification) in
+        modification_search_glycan(composition, true).filter(|(_, _, _, m)| filter.matches(m))
+    {
+        if let SimpleModificationInner::Gno {
+            composition: GnoComposition::Topology(structure),
+            ..
+        } = &*modification
+        {
+            data.push([modification.to_string(), structure.to_string()])
+        } else if let SimpleModificationInner::Gno {
+            composition: GnoComposition::Composition(composition),
+            ..
+        } = &*modification
+        {
+            data.push([modification.to_string(), composition_text(composition)])
+        }
+    }
+    if data.len() > 1 {
+        table(
+            &data,
+            true,
+            &[Styling::with_fg(Some(Color::Magenta)), Styling::none()],
+        );
+    } else {
+        println!("{}", "No modifications found".red())
+    }
+}
+
+/// A monosaccharide in a glycan structure with all monosaccharides attached to it
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Node {
+    name: String,
+    branches: Vec<Node>,
+}
+
+/// Read the tree back from the textual representation of a structure eg `Hex(Hex,HexNAc(Hex))`.
+/// Commas between digits are part of the name, as some substituents are named like `3,4Hb`.
+fn parse_tree(text: &[char], index: &mut usize) -> Node {
+    let mut name = String::new();
+    while *index < text.len() {
+        let c = text[*index];
+        let in_name = c == ','
+            && *index > 0
+            && text[*index - 1].is_ascii_digit()
+            && text.get(*index + 1).is_some_and(char::is_ascii_digit);
+        if matches!(c, '(' | ')' | ',') && !in_name {
+            break;
+        }
+        name.push(c);
+        *index += 1;
+    }
+    let mut branches = Vec::new();
+    if text.get(*index) == Some(&'(') {
+        loop {
+            *index += 1;
+            branches.push(parse_tree(text, index));
+            if text.get(*index) != Some(&',') {
+                break;
+            }
+        }
+        *index += 1; // Closing bracket
+    }
+    Node { name, branches }
+}
+
+/// The SNFG (Symbol Nomenclature For Glycans) symbol and colour for a monosaccharide, generic
+/// monosaccharides (eg Hex) have white symbols in SNFG and are shown as open symbols here
+fn snfg(name: &str) -> (&'static str, Option<Color>) {
+    let orange = Color::TrueColor {
+        r: 244,
+        g: 121,
+        b: 32,
+    };
+    match name {
+        "Hex" => ("○", None),
+        "Glc" => ("●", Some(Color::Blue)),
+        "Man" => ("●", Some(Color::Green)),
+        "Gal" => ("●", Some(Color::Yellow)),
+        "HexNAc" => ("□", None),
+        "GlcNAc" => ("■", Some(Color::Blue)),
+        "ManNAc" => ("■", Some(Color::Green)),
+        "GalNAc" => ("■", Some(Color::Yellow)),
+        "dHex" => ("△", None),
+        "Fuc" => ("▲", Some(Color::Red)),
+        "Pen" => ("☆", None),
+        "Xyl" => ("★", Some(orange)),
+        "HexA" => ("◇", None),
+        "GlcA" => ("◆", Some(Color::Blue)),
+        "NeuAc" | "Neu5Ac" | "NonNAAc" => ("◆", Some(Color::Magenta)),
+        "NeuGc" | "Neu5Gc" | "NonNAGc" => ("◆", Some(Color::Cyan)),
+        "Kdn" => ("◆", Some(Color::Green)),
+        _ => ("?", None),
+    }
+}
+
+/// Render a glycan structure as a tree, one monosaccharide per line starting at the reducing
+/// end, optionally prefixed with its SNFG symbol
+pub fn tree(structure: &GlycanStructure, symbols: bool) -> Vec<String> {
+    fn render(
+        node: &Node,
+        prefix: &str,
+        last: Option<bool>,
+        symbols: bool,
+        lines: &mut Vec<String>,
+    ) {
+        let (connector, continuation) = match last {
+            None => ("", ""),
+            Some(false) => ("├─", "│ "),
+            Some(true) => ("└─", "  "),
+        };
+        let symbol = if symbols {
+            let (symbol, colour) = snfg(&node.name);
+            format!("{} ", colour.map_or(symbol.normal(), |c| symbol.color(c)))
+        } else {
+            String::new()
+        };
+        lines.push(format!(
+            "{}{symbol}{}",
+            format!("{prefix}{connector}").dimmed(),
+            node.name
+        ));
+        let prefix = format!("{prefix}{continuation}");
+        for (index, branch) in node.branches.iter().enumerate() {
+            render(
+                branch,
+                &prefix,
+                Some(index == node.branches.len() - 1),
+                symbols,
+                lines,
+            );
+        }
+    }
+    let text = structure.to_string().chars().collect_vec();
+    let mut lines = Vec::new();
+    render(&parse_tree(&text, &mut 0), "", None, symbols, &mut lines);
+    lines
+}
+
+#[test]
+fn sialylated_biantennary() {
+    let composition = MonoSaccharide::from_composition("HexNAc4Hex5NeuAc2").unwrap();
+    let formula = formula(&composition);
+    // The A2G2S2 glycan as a modification (C84H136N6O61) is 2204.772 Da
+    assert!((formula.monoisotopic_mass().value - 2204.7724).abs() < 0.01);
+    assert_eq!(composition_text(&composition), "Hex5HexNAc4Neu5Ac2");
+}
+
+#[test]
+fn structure_tree() {
+    let structure: GlycanStructure = "hexnac(fuc,hexnac(hex(hex,hex)))".parse().unwrap();
+    colored::control::set_override(false);
+    assert_eq!(
+        tree(&structure, false),
+        [
+            "HexNAc",
+            "├─Fuc",
+            "└─HexNAc",
+            "  └─Hex",
+            "    ├─Hex",
+            "    └─Hex"
+        ]
+    );
+    assert_eq!(tree(&structure, true)[1], "├─▲ Fuc");
+}
+
+#[test]
+fn gnome_filter() {
+    use rustyms::modification::Ontology;
+    let human = GnomeFilter {
+        taxonomy: Some(Taxonomy::Name("Homo sapiens".to_string())),
+        tissue: None,
+    };
+    let by_id = GnomeFilter {
+        taxonomy: Some(Taxonomy::Id(9606)),
+        tissue: None,
+    };
+    let found = Ontology::Gnome.find_name("g00875vp", None).unwrap();
+    assert!(human.matches(&found));
+    assert!(by_id.matches(&found));
+    let other = Ontology::Gnome.find_name("g00524no", None).unwrap();
+    assert!(!human.matches(&other));
+    assert!(GnomeFilter::default().matches(&other));
+    let brain = GnomeFilter {
+        taxonomy: Some(Taxonomy::Id(10090)),
+        tissue: Some("Brain".to_string()),
+    };
+    let mouse = Ontology::Gnome.find_name("g00709cf", None).unwrap();
+    assert!(brain.matches(&mouse));
+    assert!(!brain.matches(&found));
+}
@@ -0,0 +1,97 @@
+use std::collections::HashSet;
+
+use rustyms::{AminoAcid, Peptidoform};
+
+/// One position in a motif for `--highlight`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MotifElement {
+    /// Any single residue, `X` or `.`
+    Any,
+    /// One of these residues, a single letter or `[ST]`
+    Residues(Vec<AminoAcid>),
+    /// Any residue except these, `[^P]`
+    Except(Vec<AminoAcid>),
+}
+
+impl MotifElement {
+    fn matches(&self, aa: AminoAcid) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Residues(options) => options.contains(&aa),
+            Self::Except(options) => !options.contains(&aa),
+        }
+    }
+}
+
+pub type Motif = Vec<MotifElement>;
+
+/// The residues to highlight in the rendering of an alignment, see `--highlight` and `--highlight-positions`
+#[derive(Clone, Debug, Default)]
+pub struct Highlight {
+    pub motifs: Vec<Motif>,
+    /// 1 based positions
+    pub positions: Vec<usize>,
+}
+
+impl Highlight {
+    /// All (0 based) indices in the peptide that are part of any motif match, or that are in the positions list. The
+    /// positions are only used if an offset is given; it is the index of the first residue of this peptide in the
+    /// numbering of the positions, for example when the peptide is the remainder of a query in a chained alignment.
+    pub fn residues<Complexity>(
+        &self,
+        peptide: &Peptidoform<Complexity>,
+        offset: Option<usize>,
+    ) -> HashSet<usize> {
+        let sequence = peptide
+            .sequence()
+            .iter()
+            .map(|s| s.aminoacid.aminoacid())
+            .collect::<Vec<_>>();
+        let mut output = HashSet::new();
+        for motif in &self.motifs {
+            for (start, window) in sequence.windows(motif.len()).enumerate() {
+                if motif
+                    .iter()
+                    .zip(window)
+                    .all(|(element, aa)| element.matches(*aa))
+                {
+                    output.extend(start..start + motif.len());
+                }
+            }
+        }
+        if let Some(offset) = offset {
+            output.extend(
+                self.positions
+                    .iter()
+                    .filter_map(|p| p.checked_sub(1 + offset))
+                    .filter(|i| *i < sequence.len()),
+            );
+        }
+        output
+    }
+}
+
+#[test]
+fn sequons() {
+    let motif = vec![
+        MotifElement::Residues(vec![AminoAcid::Asparagine]),
+        MotifElement::Except(vec![AminoAcid::Proline]),
+        MotifElement::Residues(vec![AminoAcid::Serine, AminoAcid::Threonine]),
+    ];
+    let highlight = Highlight {
+        motifs: vec![motif],
+        positions: vec![1, 9],
+    };
+    let peptide = Peptidoform::pro_forma("ANGTNPSNAS", None)
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let mut found = highlight
+        .residues(&peptide, None)
+        .into_iter()
+        .collect::<Vec<_>>();
+    found.sort_unstable();
+    assert_eq!(found, [1, 2, 3, 7, 8, 9]);
+    assert!(highlight.residues(&peptide, Some(0)).contains(&0));
+    assert!(highlight.residues(&peptide, Some(5)).contains(&3));
+}
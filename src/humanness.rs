@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+
+use colored::{Color, Colorize, Styles};
+use rayon::prelude::*;
+use rustyms::{
+    align::{AlignScoring, AlignType, Alignment, MatchType, Side},
+    imgt::{Allele, AlleleSelection, ChainType, GeneType, Selection, Species},
+    peptidoform::{Region, UnAmbiguous},
+    Peptidoform, SimpleLinear,
+};
+
+use crate::{alignment::align, cli::AlignmentKind, render::table, styling::Styling};
+
+/// The identity of one framework of the nearest human germline to the query
+pub struct FrameworkIdentity {
+    pub region: Region,
+    /// The number of germline residues in this framework that are identical in the query
+    pub identical: usize,
+    /// The number of germline residues in this framework
+    pub length: usize,
+}
+
+/// The humanness of a query, based on the nearest human V germline
+pub struct Humanness<'a> {
+    pub germline: Allele<'static>,
+    pub alignment: Alignment<'a, UnAmbiguous, SimpleLinear>,
+    /// The fraction of the germline residues that are identical in the query
+    pub identity: f64,
+    /// The identity of the frameworks (FR1 to FR3) of the germline
+    pub frameworks: Vec<FrameworkIdentity>,
+}
+
+impl Humanness<'_> {
+    /// The germinality index: the fraction of the human framework residues that are identical in
+    /// the query
+    pub fn germinality(&self) -> f64 {
+        let (identical, length) = self
+            .frameworks
+            .iter()
+            .fold((0, 0), |acc, f| (acc.0 + f.identical, acc.1 + f.length));
+        identical as f64 / length.max(1) as f64
+    }
+
+    /// Show the humanness report
+    pub fn show(&self) {
+        println!(
+            "{} {}",
+            "Humanness".underline().italic(),
+            format!(
+                "(nearest human germline {} / {})",
+                self.germline.name(),
+                self.germline.fancy_name()
+            )
+            .dimmed()
+        );
+        println!(
+            "V identity: {}, Germinality index: {}",
+            format!("{:.2}%", self.identity * 100.0).green(),
+            format!("{:.2}%", self.germinality() * 100.0).green(),
+        );
+        let mut data = vec![[
+            "Region".to_string(),
+            "Identical".to_string(),
+            "Length".to_string(),
+            "Identity".to_string(),
+        ]];
+        for framework in &self.frameworks {
+            data.push([
+                framework.region.to_string(),
+                framework.identical.to_string(),
+                framework.length.to_string(),
+                format!(
+                    "{:.2}%",
+                    framework.identical as f64 / framework.length.max(1) as f64 * 100.0
+                ),
+            ]);
+        }
+        table(
+            &data,
+            true,
+            &[
+                Styling::with_style(Styles::Bold),
+                Styling::none(),
+                Styling::none(),
+                Styling::with_fg(Some(Color::Green)),
+            ],
+        );
+    }
+}
+
+/// Align the query to all human V germlines (of the given chains) and determine the humanness
+/// based on the best scoring germline. Returns None if there are no human germlines to align to.
+pub fn humanness<'a>(
+    query: &'a Peptidoform<SimpleLinear>,
+    chains: Option<HashSet<ChainType>>,
+    scoring: AlignScoring<'a>,
+    kind: AlignmentKind,
+) -> Option<Humanness<'a>> {
+    let (germline, alignment) = Selection {
+        species: Some(HashSet::from([Species::HomoSapiens])),
+        chains,
+        genes: Some(HashSet::from([GeneType::V])),
+        allele: AlleleSelection::All,
+    }
+    .par_germlines()
+    .map(|germline| {
+        let alignment = align(
+            germline.sequence,
+            query,
+            scoring,
+            AlignType {
+                left: Side::Specified { a: true, b: true },
+                right: Side::EitherGlobal,
+            },
+            kind,
+        );
+        (germline, alignment)
+    })
+    .filter(|(_, alignment)| !alignment.normalised_score().is_nan())
+    .max_by(|a, b| {
+        a.1.normalised_score()
+            .total_cmp(&b.1.normalised_score())
+            .then(b.0.name().cmp(&a.0.name()))
+    })?;
+    let mut identical = vec![false; germline.sequence.len()];
+    let (mut a, _) = alignment.start();
+    for piece in alignment.path() {
+        if piece.match_type == MatchType::FullIdentity {
+            identical[a] = true;
+        }
+        a += piece.step_a as usize;
+    }
+    let mut frameworks = Vec::new();
+    let mut start = 0;
+    for (region, length) in germline.regions {
+        if matches!(region, Region::Framework(_)) {
+            frameworks.push(FrameworkIdentity {
+                region: region.clone(),
+                identical: identical[start..start + length]
+                    .iter()
+                    .filter(|i| **i)
+                    .count(),
+                length: *length,
+            });
+        }
+        start += length;
+    }
+    Some(Humanness {
+        identity: identical.iter().filter(|i| **i).count() as f64
+            / germline.sequence.len().max(1) as f64,
+        germline,
+        alignment,
+        frameworks,
+    })
+}
@@ -0,0 +1,204 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use itertools::Itertools;
+use rustyms::identification::FastaData;
+
+use crate::{
+    prefilter::KmerIndex,
+    stream::{parse_record, FastaRecords},
+};
+
+/// The magic bytes at the start of every index file, the last byte is the format version
+const MAGIC: &[u8; 8] = b"ALIGNIX\x02";
+
+/// A persistent index for a fasta database. It stores the byte offset and length of every record
+/// in the fasta file together with the mass of every sequence and the mass-equivalent k-mer seeds of
+/// all sequences. This allows a search to only read and parse the records that pass the prefilter.
+pub struct DatabaseIndex {
+    /// The size of the fasta file at the time of indexing
+    fasta_size: u64,
+    /// The last modification time (seconds since the unix epoch) of the fasta file at the time of indexing
+    fasta_modified: u64,
+    /// The byte offset and byte length of every record in the fasta file
+    records: Vec<(u64, u64)>,
+    /// The lowest monoisotopic mass (in Dalton) of the unmodified sequence of every record
+    masses: Vec<f64>,
+    /// The k-mer seeds for all records
+    pub kmers: KmerIndex,
+}
+
+impl DatabaseIndex {
+    /// The location of the index for the given fasta file, this is the fasta path with `.idx` appended
+    pub fn index_path(fasta: &Path) -> PathBuf {
+        let mut path = fasta.as_os_str().to_owned();
+        path.push(".idx");
+        PathBuf::from(path)
+    }
+
+    /// Build the index for the given fasta file
+    pub fn build(fasta: &Path, k: usize) -> Result<Self, String> {
+        let (fasta_size, fasta_modified) = file_stamp(fasta)?;
+        let file = File::open(fasta).map_err(|e| format!("Could not open fasta file: {e}"))?;
+        let mut records = Vec::new();
+        let mut masses = Vec::new();
+        let mut kmers = KmerIndex::new(k);
+        for record in FastaRecords::new(BufReader::new(file)) {
+            let (offset, record) = record?;
+            let peptide = parse_record(&record)?.peptide().clone();
+            kmers.add(&peptide);
+            masses.push(
+                peptide
+                    .bare_formulas()
+                    .mass_bounds()
+                    .into_option()
+                    .map_or(0.0, |(low, _)| low.monoisotopic_mass().value),
+            );
+            records.push((offset, record.len() as u64));
+        }
+        Ok(Self {
+            fasta_size,
+            fasta_modified,
+            records,
+            masses,
+            kmers,
+        })
+    }
+
+    /// The number of records in the database
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// The lowest and highest mass (in Dalton) of all sequences in the database
+    pub fn mass_range(&self) -> Option<(f64, f64)> {
+        self.masses
+            .iter()
+            .copied()
+            .minmax_by(f64::total_cmp)
+            .into_option()
+    }
+
+    /// Write the index to the given location
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut writer = BufWriter::new(
+            File::create(path).map_err(|e| format!("Could not create index file: {e}"))?,
+        );
+        (|| {
+            writer.write_all(MAGIC)?;
+            write_u64(&mut writer, self.fasta_size)?;
+            write_u64(&mut writer, self.fasta_modified)?;
+            write_u64(&mut writer, self.records.len() as u64)?;
+            for ((offset, length), mass) in self.records.iter().zip(&self.masses) {
+                write_u64(&mut writer, *offset)?;
+                write_u64(&mut writer, *length)?;
+                write_u64(&mut writer, mass.to_bits())?;
+            }
+            self.kmers.write(&mut writer)?;
+            writer.flush()
+        })()
+        .map_err(|e| format!("Could not write index file: {e}"))
+    }
+
+    /// Load the index for the given fasta file, returns None if there is no index. Returns an
+    /// error if the index is unreadable or if the fasta file changed since it was indexed.
+    pub fn load(fasta: &Path) -> Option<Result<Self, String>> {
+        let file = File::open(Self::index_path(fasta)).ok()?;
+        Some(Self::read(BufReader::new(file)).and_then(|index| {
+            if file_stamp(fasta)? == (index.fasta_size, index.fasta_modified) {
+                Ok(index)
+            } else {
+                Err("The fasta file changed since it was indexed, rerun `align index`".to_string())
+            }
+        }))
+    }
+
+    fn read(mut reader: impl Read) -> Result<Self, String> {
+        let mut magic = [0; 8];
+        (|| {
+            reader.read_exact(&mut magic)?;
+            if &magic != MAGIC {
+                return Ok(None);
+            }
+            let fasta_size = read_u64(&mut reader)?;
+            let fasta_modified = read_u64(&mut reader)?;
+            // Every record takes at least one byte of the fasta file, so this bounds the number of records
+            let number = read_u64(&mut reader)?;
+            if number > fasta_size {
+                return Ok(None);
+            }
+            let mut records = Vec::new();
+            let mut masses = Vec::new();
+            for _ in 0..number {
+                let (offset, length) = (read_u64(&mut reader)?, read_u64(&mut reader)?);
+                if offset
+                    .checked_add(length)
+                    .is_none_or(|end| end > fasta_size)
+                {
+                    return Ok(None);
+                }
+                records.push((offset, length));
+                masses.push(f64::from_bits(read_u64(&mut reader)?));
+            }
+            let kmers = KmerIndex::read(&mut reader, records.len())?;
+            Ok(Some(Self {
+                fasta_size,
+                fasta_modified,
+                records,
+                masses,
+                kmers,
+            }))
+        })()
+        .map_err(|e: std::io::Error| format!("Could not read index file: {e}"))?
+        .ok_or_else(|| "Not a valid index file, rerun `align index`".to_string())
+    }
+
+    /// Read and parse only the given records from the fasta file
+    pub fn read_records(
+        &self,
+        fasta: &Path,
+        indices: impl IntoIterator<Item = usize>,
+    ) -> Result<Vec<FastaData>, String> {
+        let mut file = File::open(fasta).map_err(|e| format!("Could not open fasta file: {e}"))?;
+        let mut buffer = Vec::new();
+        let mut sequences = Vec::new();
+        for index in indices {
+            let (offset, length) = *self
+                .records
+                .get(index)
+                .ok_or_else(|| format!("Record {index} is not in the index"))?;
+            buffer.resize(length as usize, 0);
+            file.seek(SeekFrom::Start(offset))
+                .and_then(|_| file.read_exact(&mut buffer))
+                .map_err(|e| format!("Could not read fasta file: {e}"))?;
+            sequences.push(parse_record(&buffer)?);
+        }
+        Ok(sequences)
+    }
+}
+
+/// Get the size and modification time of a file to detect changes after indexing
+pub fn file_stamp(path: &Path) -> Result<(u64, u64), String> {
+    let metadata = std::fs::metadata(path)
+        .map_err(|e| format!("Could not read {}: {e}", path.to_string_lossy()))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+    Ok((metadata.len(), modified))
+}
+
+pub fn write_u64(writer: &mut impl Write, value: u64) -> std::io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub fn read_u64(reader: &mut impl Read) -> std::io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
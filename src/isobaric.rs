@@ -0,0 +1,384 @@
+use std::io::{BufWriter, Write};
+
+use colored::Colorize;
+use itertools::Itertools;
+use rustyms::{
+    find_isobaric_sets,
+    modification::{Modification, SimpleModification},
+    molecular_formula,
+    system::Mass,
+    AminoAcid, Chemical, Peptidoform, SemiAmbiguous, SequenceElement, SimpleLinear, Tolerance,
+    WithinTolerance,
+};
+
+use crate::{
+    cli::{Cli, IsobaricNumber},
+    render::display_mass,
+};
+
+/// The number of isobaric sets shown in the terminal when the sets are written to a file
+const PREVIEW: usize = 10;
+
+/// A terminus of the generated isobaric sets, set with `--isobaric-n-term` and `--isobaric-c-term`
+#[derive(Clone, Debug)]
+pub enum Terminus {
+    /// No modification, the free amine or acid
+    Free,
+    /// Always this modification
+    Modified(SimpleModification),
+}
+
+/// The terminal modifications of a sequence, these are kept on the generated isobaric sets unless
+/// overruled by `--isobaric-n-term` or `--isobaric-c-term`
+pub fn termini(
+    sequence: &Peptidoform<SimpleLinear>,
+) -> (Vec<SimpleModification>, Vec<SimpleModification>) {
+    let simple = |modifications: &[Modification]| {
+        modifications
+            .iter()
+            .filter_map(|m| match m {
+                Modification::Simple(s) => Some(s.clone()),
+                _ => None,
+            })
+            .collect()
+    };
+    (simple(sequence.get_n_term()), simple(sequence.get_c_term()))
+}
+
+/// A position in an isobaric pattern, see `--isobaric-pattern`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PatternElement {
+    /// Any single residue
+    Any,
+    /// Any number of residues
+    Gap,
+    /// One of these residues
+    Residues(Vec<AminoAcid>),
+}
+
+/// The amino acids used for the generation
+fn amino_acids(args: &Cli) -> &[AminoAcid] {
+    args.amino_acids
+        .as_deref()
+        .unwrap_or(AminoAcid::UNIQUE_MASS_AMINO_ACIDS)
+}
+
+/// Generate the isobaric sets that fit the pattern. For every choice of the fixed residues in the
+/// pattern the mass of these residues (with `--fixed` modifications) is taken off before
+/// generating, and only compositions that fill the open positions exactly are used. The residues
+/// of a composition are placed in the open positions in order.
+fn pattern_sets<'a>(
+    bare: Mass,
+    pattern: &'a [PatternElement],
+    args: &'a Cli,
+) -> impl Iterator<Item = Peptidoform<SimpleLinear>> + 'a {
+    let open = pattern
+        .iter()
+        .filter(|p| **p == PatternElement::Any)
+        .count();
+    let gap = pattern.contains(&PatternElement::Gap);
+    let (low, high) = args.tolerance.at(bare).bounds(bare);
+    let tolerance = Tolerance::new_absolute((high - low) / 2.0);
+    pattern
+        .iter()
+        .filter_map(|p| match p {
+            PatternElement::Residues(options) => Some(options.clone()),
+            _ => None,
+        })
+        .multi_cartesian_product()
+        .flat_map(move |choice| {
+            let mut fixed: Peptidoform<SimpleLinear> = choice
+                .into_iter()
+                .map(|aa| SequenceElement::<SemiAmbiguous>::new(aa.into(), None))
+                .collect::<Peptidoform<SemiAmbiguous>>()
+                .into();
+            crate::digest::apply_fixed(&mut fixed, args.fixed.mods(), false, false);
+            let left = bare
+                - fixed
+                    .bare_formulas()
+                    .iter()
+                    .next()
+                    .map(|f| f.mass(args.mass_mode))
+                    .unwrap_or_default();
+            let compositions: Box<dyn Iterator<Item = Peptidoform<SimpleLinear>>> =
+                if open == 0 && !gap {
+                    Box::new(
+                        tolerance
+                            .within(&left, &Mass::default())
+                            .then(Peptidoform::default)
+                            .into_iter(),
+                    )
+                } else if left.value > 0.0 {
+                    Box::new(find_isobaric_sets(
+                        left,
+                        tolerance,
+                        amino_acids(args),
+                        args.fixed.mods(),
+                        args.variable.mods(),
+                        args.include.as_ref(),
+                    ))
+                } else {
+                    Box::new(std::iter::empty())
+                };
+            compositions
+                .filter(move |set| set.len() == open || gap && set.len() > open)
+                .map(move |set| {
+                    let mut free = set.sequence().iter().cloned();
+                    let mut fixed = fixed.sequence().iter().cloned();
+                    let extra = set.len() - open;
+                    let mut output = set.clone();
+                    *output.sequence_mut() = pattern
+                        .iter()
+                        .flat_map(|p| match p {
+                            PatternElement::Any => free.next().into_iter().collect_vec(),
+                            PatternElement::Gap => free.by_ref().take(extra).collect_vec(),
+                            PatternElement::Residues(_) => fixed.next().into_iter().collect_vec(),
+                        })
+                        .collect();
+                    output
+                })
+        })
+}
+
+/// The residues of the set regardless of their order, used to deduplicate with `--compositions-only`
+fn composition(set: &Peptidoform<SimpleLinear>) -> Vec<String> {
+    set.sequence()
+        .iter()
+        .map(|s| format!("{}{}", s.aminoacid.char(), s.modifications.iter().join(",")))
+        .sorted()
+        .collect()
+}
+
+/// The full mass of the generated set, these do not have multiple formulas
+fn mass(set: &Peptidoform<SimpleLinear>, args: &Cli) -> Mass {
+    set.formulas()
+        .iter()
+        .next()
+        .map(|f| f.mass(args.mass_mode))
+        .unwrap_or_default()
+}
+
+/// The terminal modifications for the isobaric sets, the given modifications unless these are
+/// overruled on the command line
+fn terminal_modifications(
+    termini: (Vec<SimpleModification>, Vec<SimpleModification>),
+    args: &Cli,
+) -> (Vec<SimpleModification>, Vec<SimpleModification>) {
+    let choose = |terminus: &Option<Terminus>, default: Vec<SimpleModification>| match terminus {
+        None => default,
+        Some(Terminus::Free) => Vec::new(),
+        Some(Terminus::Modified(m)) => vec![m.clone()],
+    };
+    (
+        choose(&args.isobaric_n_term, termini.0),
+        choose(&args.isobaric_c_term, termini.1),
+    )
+}
+
+/// The summed mass of the modifications
+fn sum(modifications: &[SimpleModification], args: &Cli) -> Mass {
+    modifications
+        .iter()
+        .map(|m| m.formula().mass(args.mass_mode))
+        .sum::<Mass>()
+}
+
+/// Generate the isobaric sets for this bare mass (without the terminal modifications) with the
+/// settings from the command line. The sets get the given terminal modifications, unless these are
+/// overruled on the command line in which case the mass difference is taken into account. All sets
+/// are generated, the total number is returned together with the sets closest in mass (up to the
+/// limit) with their mass and mass error, sorted on absolute mass error and sequence.
+pub fn sets(
+    bare: Mass,
+    termini: (Vec<SimpleModification>, Vec<SimpleModification>),
+    args: &Cli,
+    limit: IsobaricNumber,
+) -> (usize, Vec<(String, Mass, Mass)>) {
+    let target = bare + sum(&termini.0, args) + sum(&termini.1, args);
+    let (n_term, c_term) = terminal_modifications(termini, args);
+    let bare = target - sum(&n_term, args) - sum(&c_term, args);
+    let sets: Box<dyn Iterator<Item = Peptidoform<SimpleLinear>>> =
+        if let Some(pattern) = &args.isobaric_pattern {
+            Box::new(pattern_sets(bare, pattern, args))
+        } else {
+            Box::new(find_isobaric_sets(
+                bare,
+                args.tolerance.at(bare),
+                amino_acids(args),
+                args.fixed.mods(),
+                args.variable.mods(),
+                args.include.as_ref(),
+            ))
+        };
+    let sets = sets.map(|mut set| {
+        for m in &n_term {
+            set.add_simple_n_term(m.clone());
+        }
+        for m in &c_term {
+            set.add_simple_c_term(m.clone());
+        }
+        set
+    });
+    let sets: Box<dyn Iterator<Item = Peptidoform<SimpleLinear>>> = if args.compositions_only {
+        Box::new(sets.unique_by(composition))
+    } else {
+        Box::new(sets)
+    };
+    // Sort on the absolute error and then on the sequence so the output is the same every run
+    let order = |a: &(String, Mass, Mass), b: &(String, Mass, Mass)| {
+        a.2.value
+            .abs()
+            .total_cmp(&b.2.value.abs())
+            .then_with(|| a.0.cmp(&b.0))
+    };
+    let water = molecular_formula!(H 2 O 1).mass(args.mass_mode);
+    let mut count = 0;
+    let mut closest = Vec::new();
+    for set in sets {
+        let mass = mass(&set, args) - water;
+        closest.push((set.to_string(), mass, mass - target));
+        count += 1;
+        // Only keep the closest sets, but do not sort after every new set
+        if let IsobaricNumber::Limited(limit) = limit {
+            if closest.len() >= 2 * limit.max(512) {
+                closest.sort_by(order);
+                closest.truncate(limit);
+            }
+        }
+    }
+    closest.sort_by(order);
+    if let IsobaricNumber::Limited(limit) = limit {
+        closest.truncate(limit);
+    }
+    (count, closest)
+}
+
+/// Generate the isobaric sets for this bare mass (without the terminal modifications), see
+/// [`sets`], and show these in the terminal, or if `--isobaric-output` is given write these to that
+/// file with only the first few shown in the terminal.
+pub fn show(bare: Mass, termini: (Vec<SimpleModification>, Vec<SimpleModification>), args: &Cli) {
+    let target = bare + sum(&termini.0, args) + sum(&termini.1, args);
+    let (n_term, c_term) = terminal_modifications(termini.clone(), args);
+    if !n_term.is_empty() || !c_term.is_empty() {
+        let list = |modifications: &[SimpleModification]| {
+            if modifications.is_empty() {
+                "free".to_string()
+            } else {
+                modifications.iter().join(", ")
+            }
+        };
+        println!(
+            "{}",
+            format!(
+                "Isobaric sets with N terminal: {}, C terminal: {}",
+                list(&n_term),
+                list(&c_term)
+            )
+            .dimmed()
+        );
+    }
+    println!(
+        "Isobaric options {}: ",
+        match args.isobaric {
+            IsobaricNumber::All => format!("(all, tolerance {})", args.tolerance),
+            IsobaricNumber::Limited(limit) =>
+                format!("(limited to {limit}, tolerance {})", args.tolerance),
+        }
+        .dimmed()
+    );
+    let _ = std::io::stdout().flush();
+    let (count, sets) = sets(bare, termini, args, args.isobaric);
+    let Some(path) = &args.isobaric_output else {
+        for (set, _, _) in &sets {
+            print!("{}, ", set.blue());
+        }
+        println!(
+            "\n{}",
+            if sets.len() < count {
+                format!(
+                    "Showing the {} closest of {count} isobaric sets",
+                    sets.len()
+                )
+            } else {
+                format!("Found {count} isobaric sets")
+            }
+            .dimmed()
+        );
+        return;
+    };
+    let csv = path.to_ascii_lowercase().ends_with(".csv");
+    let mut writer = BufWriter::new(std::fs::File::create(path).unwrap());
+    if csv {
+        writeln!(writer, "sequence,mass,error,ppm").unwrap();
+    }
+    for (set, mass, error) in &sets {
+        if csv {
+            writeln!(
+                writer,
+                "{set},{},{},{}",
+                mass.value,
+                error.value,
+                error.value / target.value * 1e6
+            )
+            .unwrap();
+        } else {
+            writeln!(writer, "{set}").unwrap();
+        }
+    }
+    writer.flush().unwrap();
+    for (set, _, _) in sets.iter().take(PREVIEW) {
+        print!("{}, ", set.blue());
+    }
+    if sets.len() > PREVIEW {
+        print!("{}", "…".dimmed());
+    }
+    println!(
+        "\n{}",
+        format!("Written {} of {count} isobaric sets to {path}", sets.len()).dimmed()
+    );
+}
+
+/// Generate the isobaric sets for the neutral mass of a full peptide, so including the water of
+/// the termini, see [`show`]
+pub fn show_for_mass(mass: Mass, args: &Cli) {
+    let bare = mass - molecular_formula!(H 2 O 1).mass(args.mass_mode);
+    let precision = (!args.full_number).then_some(crate::NUMBER_PRECISION);
+    println!(
+        "Peptide: {} Bare: {} {}\n",
+        display_mass(mass, true, precision),
+        display_mass(bare, true, precision),
+        format!("({}, no N/C terminal taken into account)", args.mass_mode).dimmed()
+    );
+    show(bare, (Vec::new(), Vec::new()), args);
+}
+
+#[test]
+fn tryptic_pattern() {
+    use clap::Parser;
+    let args = Cli::parse_from(["align", "PEPTIDEK", "--isobaric-pattern", "* [KR]"]);
+    let bare = Mass::new::<rustyms::system::dalton>(909.444);
+    let sets = pattern_sets(bare, args.isobaric_pattern.as_ref().unwrap(), &args)
+        .take(20)
+        .collect_vec();
+    assert_eq!(sets.len(), 20);
+    for set in sets {
+        let last = set.sequence().last().unwrap().aminoacid.aminoacid();
+        assert!(matches!(last, AminoAcid::Lysine | AminoAcid::Arginine));
+        assert!(
+            (mass(&set, &args) - molecular_formula!(H 2 O 1).mass(args.mass_mode) - bare)
+                .value
+                .abs()
+                < 0.01
+        );
+    }
+}
+
+#[test]
+fn same_composition() {
+    use crate::test_helpers::parse;
+    assert_eq!(composition(&parse("GAK")), composition(&parse("KGA")));
+    assert_ne!(
+        composition(&parse("GAK")),
+        composition(&parse("GAK[Acetyl]"))
+    );
+}
@@ -0,0 +1,111 @@
+use colored::{Color, Colorize, Styles};
+use rustyms::MolecularFormula;
+
+use crate::{render::table, styling::Styling};
+
+/// The mass difference between the isotope peaks, based on the 13C - 12C difference
+pub const ISOTOPE_SPACING: f64 = 1.003_354_835;
+
+/// Peaks with a relative abundance below this are not shown
+const CUTOFF: f64 = 0.001;
+
+/// The width of the bar for the highest peak in the bar chart
+const BAR_WIDTH: usize = 40;
+
+/// The theoretical isotope distribution of a formula as the number of additional neutrons and
+/// the abundance relative to the highest peak. Peaks after the last peak above 0.1% are left out.
+pub fn distribution(formula: &MolecularFormula) -> Vec<(usize, f64)> {
+    let distribution = formula.isotopic_distribution(CUTOFF / 100.0);
+    let max = distribution.iter().copied().fold(0.0, f64::max);
+    if max == 0.0 {
+        return Vec::new();
+    }
+    let mut peaks: Vec<(usize, f64)> = distribution.iter().map(|a| a / max).enumerate().collect();
+    while peaks.last().is_some_and(|(_, a)| *a < CUTOFF) {
+        peaks.pop();
+    }
+    peaks
+}
+
+/// Show the isotope distribution of a formula as a table and a bar chart, the masses of the
+/// isotope peaks are approximated from the monoisotopic mass using [`ISOTOPE_SPACING`]
+pub fn show(formula: &MolecularFormula, precision: Option<usize>) {
+    let peaks = distribution(formula);
+    if peaks.is_empty() {
+        println!("{}", "No isotope distribution for an empty formula".red());
+        return;
+    }
+    let monoisotopic = formula.monoisotopic_mass().value;
+    let mass = |n: f64| {
+        precision.map_or_else(
+            || format!("{n} Da"),
+            |precision| format!("{n:.precision$} Da"),
+        )
+    };
+    let mut data = vec![[
+        "Peak".to_string(),
+        "Mass".to_string(),
+        "Relative".to_string(),
+    ]];
+    for (offset, abundance) in &peaks {
+        data.push([
+            label(*offset),
+            mass(monoisotopic + *offset as f64 * ISOTOPE_SPACING),
+            format!("{:.2}%", abundance * 100.0),
+        ]);
+    }
+    println!("{}", "Isotope distribution".underline().italic());
+    table(
+        &data,
+        true,
+        &[
+            Styling::none(),
+            Styling::with_fg(Some(Color::Yellow)),
+            Styling::with_style(Styles::Dimmed),
+        ],
+    );
+    let width = peaks
+        .iter()
+        .map(|(o, _)| label(*o).len())
+        .max()
+        .unwrap_or(0);
+    for (offset, abundance) in &peaks {
+        println!(
+            "{:>width$} {}",
+            label(*offset),
+            bar(*abundance, BAR_WIDTH).blue()
+        );
+    }
+}
+
+/// The label of an isotope peak, eg `M` or `M+2`
+fn label(offset: usize) -> String {
+    if offset == 0 {
+        "M".to_string()
+    } else {
+        format!("M+{offset}")
+    }
+}
+
+/// A horizontal bar for a fraction of the given width using eighth blocks for the last character
+fn bar(fraction: f64, width: usize) -> String {
+    const PARTS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    let eighths = (fraction.clamp(0.0, 1.0) * (width * 8) as f64).round() as usize;
+    let mut output = "█".repeat(eighths / 8);
+    let part = eighths % 8;
+    if part != 0 {
+        output.push(PARTS[part]);
+    }
+    output
+}
+
+#[test]
+fn isotopes() {
+    assert_eq!(bar(1.0, 4), "████");
+    assert_eq!(bar(0.5625, 2), "█▏");
+    assert_eq!(bar(0.0, 4), "");
+    let glycine = rustyms::molecular_formula!(C 2 H 5 N 1 O 2);
+    let peaks = distribution(&glycine);
+    assert_eq!(peaks[0], (0, 1.0));
+    assert!(peaks[1].1 > 0.01 && peaks[1].1 < 0.05);
+}
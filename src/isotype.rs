@@ -0,0 +1,84 @@
+use std::collections::HashSet;
+
+use itertools::Itertools;
+use rayon::prelude::*;
+use rustyms::{
+    align::{AlignScoring, AlignType, Alignment, Side},
+    imgt::{Allele, AlleleSelection, ChainType, Gene, GeneType, Selection, Species},
+    peptidoform::UnAmbiguous,
+    Peptidoform, SimpleLinear,
+};
+
+use crate::{alignment::align, cli::AlignmentKind};
+
+/// The isotype (and subclass) or light chain type for a constant gene, eg `IgG1` for IGHG1,
+/// `Kappa` for IGKC, and `Lambda2` for IGLC2
+pub fn isotype(gene: &Gene) -> String {
+    let subclass = gene
+        .family
+        .iter()
+        .map(|(n, s)| format!("{}{s}", n.map(|n| n.to_string()).unwrap_or_default()))
+        .join("");
+    match (gene.chain, gene.kind) {
+        (ChainType::Heavy, GeneType::C(Some(_))) => format!("Ig{}{subclass}", gene.kind),
+        (ChainType::LightKappa, GeneType::C(_)) => format!("Kappa{subclass}"),
+        (ChainType::LightLambda, GeneType::C(_)) => format!("Lambda{subclass}"),
+        _ => gene.to_string(),
+    }
+}
+
+/// Align the query against all selected constant genes and give the best alignment for every
+/// isotype, the best isotype first
+pub fn per_isotype<'a>(
+    query: &'a Peptidoform<SimpleLinear>,
+    species: Option<HashSet<Species>>,
+    chains: Option<HashSet<ChainType>>,
+    allele: AlleleSelection,
+    scoring: AlignScoring<'a>,
+    kind: AlignmentKind,
+) -> Vec<(
+    String,
+    Allele<'static>,
+    Alignment<'a, UnAmbiguous, SimpleLinear>,
+)> {
+    let mut alignments: Vec<_> = Selection {
+        species,
+        chains,
+        genes: Some(HashSet::from([GeneType::C(None)])),
+        allele,
+    }
+    .par_germlines()
+    .map(|germline| {
+        // The constant region is found anywhere in the query but should cover the germline
+        let alignment = align(
+            germline.sequence,
+            query,
+            scoring,
+            AlignType {
+                left: Side::Specified { a: true, b: false },
+                right: Side::EitherGlobal,
+            },
+            kind,
+        );
+        (isotype(&germline.gene), germline, alignment)
+    })
+    .filter(|(_, _, alignment)| !alignment.normalised_score().is_nan())
+    .collect();
+    alignments.sort_unstable_by(|a, b| {
+        b.2.normalised_score()
+            .total_cmp(&a.2.normalised_score())
+            .then(a.1.name().cmp(&b.1.name()))
+    });
+    let mut seen = HashSet::new();
+    alignments.retain(|(isotype, _, _)| seen.insert(isotype.clone()));
+    alignments
+}
+
+#[test]
+fn isotype_names() {
+    let name = |n| isotype(&Gene::from_imgt_name(n).unwrap());
+    assert_eq!(name("IGHG1"), "IgG1");
+    assert_eq!(name("IGHM"), "IgM");
+    assert_eq!(name("IGKC"), "Kappa");
+    assert_eq!(name("IGLC2"), "Lambda2");
+}
@@ -0,0 +1,189 @@
+use std::ops::Range;
+
+use colored::Colorize;
+use itertools::Itertools;
+use rustyms::{
+    align::{Alignment, MatchType},
+    imgt::{Allele, GeneType},
+    peptidoform::Region,
+    Peptidoform, SimpleLinear,
+};
+use serde::Serialize;
+
+/// The origin of a residue in the junction
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    /// Identical to the aligned residue of the V gene
+    V,
+    /// Identical to the aligned residue of the J gene
+    J,
+    /// Not templated by the V or J gene (N or P additions, the D gene, or mutations)
+    NonTemplated,
+}
+
+impl Origin {
+    const fn char(self) -> char {
+        match self {
+            Self::V => 'V',
+            Self::J => 'J',
+            Self::NonTemplated => 'N',
+        }
+    }
+}
+
+/// The analysis of the junction of a domain alignment
+pub struct Junction {
+    /// The location of the CDR3 in the query (0 based, exclusive end)
+    pub cdr3: Range<usize>,
+    /// The CDR3 sequence
+    pub peptide: Peptidoform<SimpleLinear>,
+    /// The origin of every CDR3 residue
+    pub origins: Vec<Origin>,
+    /// If the residue before the CDR3 is the conserved cysteine (C104)
+    pub cys104: bool,
+    /// If the residue after the CDR3 is the conserved tryptophan or phenylalanine (W/F118)
+    pub trp118: bool,
+}
+
+/// The JSON representation of a [`Junction`], with 1 based inclusive positions
+#[derive(Serialize)]
+struct JunctionJson {
+    cdr3: String,
+    start: usize,
+    end: usize,
+    length: usize,
+    origin: String,
+    v_templated: usize,
+    j_templated: usize,
+    non_templated: usize,
+    c104: bool,
+    w118: bool,
+}
+
+/// Analyse the junction of the query based on a chained (V-J-C) domain alignment and the regions
+/// generated from it. Returns None if there is no CDR3 in the query.
+pub fn analyse<A>(
+    query: &Peptidoform<SimpleLinear>,
+    alignments: &[(Allele, Alignment<'_, A, SimpleLinear>)],
+    regions: &[(Region, usize)],
+) -> Option<Junction> {
+    let mut start = 0;
+    let mut cdr3 = None;
+    for (region, length) in regions {
+        if *region == Region::ComplementarityDeterminingRegion(3) {
+            cdr3 = Some(start..(start + length).min(query.len()));
+        }
+        start += length;
+    }
+    let cdr3 = cdr3.filter(|r| !r.is_empty())?;
+
+    let mut templated = vec![None; query.len()];
+    let mut offset = 0;
+    for (allele, alignment) in alignments {
+        let origin = match allele.gene.kind {
+            GeneType::V => Some(Origin::V),
+            GeneType::J => Some(Origin::J),
+            GeneType::C(_) => None,
+        };
+        let mut b = alignment.start_b();
+        for piece in alignment.path() {
+            if matches!(
+                piece.match_type,
+                MatchType::FullIdentity | MatchType::IdentityMassMismatch
+            ) {
+                for index in offset + b..offset + b + piece.step_b as usize {
+                    if let Some(t) = templated.get_mut(index) {
+                        *t = t.or(origin);
+                    }
+                }
+            }
+            b += piece.step_b as usize;
+        }
+        offset += alignment.len_b() + alignment.start_b();
+    }
+
+    let residue = |index: Option<usize>| {
+        index
+            .and_then(|i| query.sequence().get(i))
+            .map(|s| s.aminoacid.char())
+    };
+    Some(Junction {
+        origins: templated[cdr3.clone()]
+            .iter()
+            .map(|o| o.unwrap_or(Origin::NonTemplated))
+            .collect(),
+        peptide: query.sub_peptide(cdr3.clone()),
+        cys104: residue(cdr3.start.checked_sub(1)) == Some('C'),
+        trp118: matches!(residue(Some(cdr3.end)), Some('W' | 'F')),
+        cdr3,
+    })
+}
+
+impl Junction {
+    /// The number of residues with the given origin
+    pub fn count(&self, origin: Origin) -> usize {
+        self.origins.iter().filter(|o| **o == origin).count()
+    }
+
+    /// Show the junction report
+    pub fn show(&self) {
+        println!(
+            "{} {}",
+            "Junction".underline().italic(),
+            format!(
+                "(CDR3 {}-{}, {} residues)",
+                self.cdr3.start + 1,
+                self.cdr3.end,
+                self.cdr3.len()
+            )
+            .dimmed()
+        );
+        println!("CDR3:   {}", self.peptide);
+        println!(
+            "Origin: {}",
+            self.origins
+                .iter()
+                .map(|o| match o {
+                    Origin::V => "V".green().to_string(),
+                    Origin::J => "J".blue().to_string(),
+                    Origin::NonTemplated => "N".yellow().to_string(),
+                })
+                .join("")
+        );
+        println!(
+            "Templated by V: {}, by J: {}, non templated: {}",
+            self.count(Origin::V),
+            self.count(Origin::J),
+            self.count(Origin::NonTemplated)
+        );
+        let anchor = |present: bool| {
+            if present {
+                "present".green()
+            } else {
+                "absent".red()
+            }
+        };
+        println!(
+            "Anchors: C104 {}, W/F118 {}",
+            anchor(self.cys104),
+            anchor(self.trp118)
+        );
+    }
+
+    /// The junction as a JSON object
+    pub fn json(&self) -> String {
+        serde_json::to_string(&JunctionJson {
+            cdr3: self.peptide.to_string(),
+            start: self.cdr3.start + 1,
+            end: self.cdr3.end,
+            length: self.cdr3.len(),
+            origin: self.origins.iter().map(|o| o.char()).collect(),
+            v_templated: self.count(Origin::V),
+            j_templated: self.count(Origin::J),
+            non_templated: self.count(Origin::NonTemplated),
+            c104: self.cys104,
+            w118: self.trp118,
+        })
+        .unwrap()
+    }
+}
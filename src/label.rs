@@ -0,0 +1,146 @@
+use colored::{Color, Colorize, Styles};
+use rustyms::{
+    modification::{Ontology, SimpleModification},
+    placement_rule::{PlacementRule, Position},
+    AminoAcid, MassMode, MolecularFormula, Peptidoform, SimpleLinear,
+};
+
+use crate::{
+    render::{display_dalton, table},
+    styling::Styling,
+};
+
+/// A standard labelling chemistry, see `--label`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Label {
+    /// Heavy SILAC lysine (13C6 15N2, +8) and arginine (13C6 15N4, +10)
+    SilacK8R10,
+    /// TMTpro (16plex) on lysine and the N terminus
+    Tmt16,
+    /// Light reductive dimethylation on lysine and the N terminus
+    Dimethyl,
+}
+
+impl Label {
+    /// The modifications placed on the side chains, these are also used as fixed modifications in
+    /// the isobaric generation
+    pub fn side_chains(self) -> Vec<(SimpleModification, Option<PlacementRule>)> {
+        let unimod = |id: usize| Ontology::Unimod.find_id(id, None).unwrap();
+        let on = |aa: AminoAcid| Some(PlacementRule::AminoAcid(vec![aa], Position::Anywhere));
+        match self {
+            Self::SilacK8R10 => vec![
+                (unimod(259), on(AminoAcid::Lysine)),
+                (unimod(267), on(AminoAcid::Arginine)),
+            ],
+            Self::Tmt16 => vec![(unimod(2016), on(AminoAcid::Lysine))],
+            Self::Dimethyl => vec![(unimod(36), on(AminoAcid::Lysine))],
+        }
+    }
+
+    /// The modification placed on the peptide N terminus, if any
+    pub fn n_term(self) -> Option<SimpleModification> {
+        match self {
+            Self::SilacK8R10 => None,
+            Self::Tmt16 => Ontology::Unimod.find_id(2016, None),
+            Self::Dimethyl => Ontology::Unimod.find_id(36, None),
+        }
+    }
+
+    /// The peptide with this label applied on all side chains and on the N terminus
+    pub fn apply(self, peptide: &Peptidoform<SimpleLinear>) -> Peptidoform<SimpleLinear> {
+        let mut labelled = peptide.clone();
+        crate::digest::apply_fixed(&mut labelled, &self.side_chains(), false, false);
+        if let Some(modification) = self.n_term() {
+            labelled.add_simple_n_term(modification);
+        }
+        labelled
+    }
+}
+
+impl std::fmt::Display for Label {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::SilacK8R10 => "silac-k8r10",
+                Self::Tmt16 => "tmt16",
+                Self::Dimethyl => "dimethyl",
+            }
+        )
+    }
+}
+
+/// Show the unlabelled and labelled peptide with their masses and the mass difference
+pub fn show(
+    peptide: &Peptidoform<SimpleLinear>,
+    label: Label,
+    mass_mode: MassMode,
+    precision: Option<usize>,
+) {
+    let labelled = label.apply(peptide);
+    let formula = |p: &Peptidoform<SimpleLinear>| -> MolecularFormula {
+        p.formulas().iter().next().cloned().unwrap_or_default()
+    };
+    let (light, heavy) = (formula(peptide), formula(&labelled));
+    println!(
+        "Label {} {}",
+        label.to_string().green(),
+        format!("({mass_mode})").dimmed()
+    );
+    table(
+        &[
+            [
+                String::new(),
+                "Sequence".to_string(),
+                "Formula".to_string(),
+                "Mass".to_string(),
+            ],
+            [
+                "Unlabelled".to_string(),
+                peptide.to_string(),
+                light.hill_notation_fancy(),
+                display_dalton(light.mass(mass_mode), precision),
+            ],
+            [
+                "Labelled".to_string(),
+                labelled.to_string(),
+                heavy.hill_notation_fancy(),
+                display_dalton(heavy.mass(mass_mode), precision),
+            ],
+        ],
+        true,
+        &[
+            Styling::with_style(Styles::Dimmed),
+            Styling::with_fg(Some(Color::Blue)),
+            Styling::with_fg(Some(Color::Green)),
+            Styling::with_fg(Some(Color::Yellow)),
+        ],
+    );
+    let difference: MolecularFormula = &heavy - &light;
+    println!(
+        "Difference: +{} {}",
+        display_dalton(heavy.mass(mass_mode) - light.mass(mass_mode), precision).yellow(),
+        if difference.is_empty() {
+            "(no labelling sites)".dimmed().to_string()
+        } else {
+            difference.hill_notation_fancy().green().to_string()
+        }
+    );
+}
+
+#[test]
+fn labelled_masses() {
+    let peptide = Peptidoform::pro_forma("PEPKTIDER", None)
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let mass = |p: &Peptidoform<SimpleLinear>| p.formulas()[0].monoisotopic_mass().value;
+    let silac = Label::SilacK8R10.apply(&peptide);
+    assert!((mass(&silac) - mass(&peptide) - 18.0222).abs() < 0.001);
+    // TMTpro on the lysine and the N terminus
+    let tmt = Label::Tmt16.apply(&peptide);
+    assert!((mass(&tmt) - mass(&peptide) - 2.0 * 304.2071).abs() < 0.001);
+    let dimethyl = Label::Dimethyl.apply(&peptide);
+    assert!((mass(&dimethyl) - mass(&peptide) - 2.0 * 28.0313).abs() < 0.001);
+}
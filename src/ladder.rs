@@ -0,0 +1,88 @@
+use colored::{Color, Colorize, Styles};
+use rustyms::{molecular_formula, system::Mass, MassMode, Peptidoform, SimpleLinear};
+
+use crate::{
+    render::{display_dalton, table},
+    styling::Styling,
+};
+
+/// The first mass of this (sub) peptide
+fn mass(peptide: &Peptidoform<SimpleLinear>, mass_mode: MassMode) -> Mass {
+    peptide
+        .formulas()
+        .iter()
+        .next()
+        .map(|f| f.mass(mass_mode))
+        .unwrap_or_default()
+}
+
+/// The neutral N terminal and C terminal ladder of a peptide, for every position the cumulative
+/// mass of the residues up to and including this position (the b ion without charge) and of the
+/// residues from this position to the end plus water (the y ion without charge). Modifications,
+/// including terminal modifications, are included. If a residue has multiple formulas the first
+/// one is used.
+pub fn ladder(peptide: &Peptidoform<SimpleLinear>, mass_mode: MassMode) -> Vec<(Mass, Mass)> {
+    let water = molecular_formula!(H 2 O 1).mass(mass_mode);
+    (0..peptide.len())
+        .map(|index| {
+            (
+                mass(&peptide.sub_peptide(..=index), mass_mode) - water,
+                mass(&peptide.sub_peptide(index..), mass_mode),
+            )
+        })
+        .collect()
+}
+
+/// Show the N and C terminal ladder of the peptide as a table
+pub fn show(peptide: &Peptidoform<SimpleLinear>, mass_mode: MassMode, precision: Option<usize>) {
+    println!(
+        "Ladder {}",
+        format!(
+            "(cumulative residue {mass_mode} without charge, the C terminal side includes water)"
+        )
+        .dimmed()
+    );
+    let length = peptide.len();
+    let mut data = vec![[
+        "#".to_string(),
+        "N terminal (b)".to_string(),
+        "Residue".to_string(),
+        "C terminal (y)".to_string(),
+        "#".to_string(),
+    ]];
+    for (index, (n_term, c_term)) in ladder(peptide, mass_mode).into_iter().enumerate() {
+        data.push([
+            (index + 1).to_string(),
+            display_dalton(n_term, precision),
+            peptide.sub_peptide(index..=index).to_string(),
+            display_dalton(c_term, precision),
+            (length - index).to_string(),
+        ]);
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_style(Styles::Dimmed),
+            Styling::with_fg(Some(Color::Yellow)),
+            Styling::with_fg(Some(Color::Blue)),
+            Styling::with_fg(Some(Color::Yellow)),
+            Styling::with_style(Styles::Dimmed),
+        ],
+    );
+}
+
+#[test]
+fn ladder_masses() {
+    let peptide = Peptidoform::pro_forma("GA", None)
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let found = ladder(&peptide, MassMode::Monoisotopic);
+    assert_eq!(found.len(), 2);
+    // G residue 57.02146, A residue 71.03711, water 18.01056
+    assert!((found[0].0.value - 57.02146).abs() < 0.0001);
+    assert!((found[1].0.value - 128.05858).abs() < 0.0001);
+    assert!((found[0].1.value - 146.06914).abs() < 0.0001);
+    assert!((found[1].1.value - 89.04768).abs() < 0.0001);
+}
@@ -0,0 +1,223 @@
+use colored::{Color, Colorize, Styles};
+use itertools::Itertools;
+use rustyms::peptidoform::Region;
+
+use crate::{render::table, styling::Styling};
+
+/// A kind of sequence liability
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// NG or NS, asparagine deamidation
+    Deamidation,
+    /// DG, aspartate isomerization
+    Isomerization,
+    /// DP, acid labile peptide bond
+    Cleavage,
+    /// A cysteine in the variable domain outside the conserved C23 and C104
+    UnpairedCysteine,
+    /// N-X-S/T with X not P, N-linked glycosylation sequon
+    NGlycosylation,
+    /// M, methionine oxidation
+    Oxidation,
+}
+
+impl Kind {
+    const fn description(self) -> &'static str {
+        match self {
+            Self::Deamidation => "Deamidation",
+            Self::Isomerization => "Isomerization",
+            Self::Cleavage => "Cleavage",
+            Self::UnpairedCysteine => "Unpaired cysteine",
+            Self::NGlycosylation => "N-glycosylation",
+            Self::Oxidation => "Oxidation",
+        }
+    }
+}
+
+/// A liability found in a sequence
+#[derive(Clone, Debug, PartialEq)]
+pub struct Liability {
+    pub kind: Kind,
+    /// The 0 based index of the first residue of the motif
+    pub position: usize,
+    pub motif: String,
+    /// The region of the first residue of the motif
+    pub region: Option<Region>,
+    /// The IMGT number of the first residue of the motif
+    pub imgt: Option<String>,
+}
+
+/// Scan a sequence for developability liabilities. The regions (see
+/// [`crate::render::generate_regions`]) and IMGT numbering (see [`crate::numbering::number`]) of
+/// the sequence give the context. When regions are known N-glycosylation sequons and methionines
+/// are only reported in the CDRs, and cysteines are reported as unpaired if they are numbered but
+/// not at the conserved positions 23 and 104. Without regions sequons and methionines are reported
+/// everywhere and cysteines only if the sequence has an odd number of them.
+pub fn scan(
+    sequence: &[char],
+    regions: &[(Region, usize)],
+    numbering: &[Option<String>],
+) -> Vec<Liability> {
+    let region_of = |index: usize| {
+        let mut end = 0;
+        regions
+            .iter()
+            .find(|(_, length)| {
+                end += length;
+                index < end
+            })
+            .map(|(r, _)| r.clone())
+    };
+    let in_cdr = |index: usize| {
+        regions.is_empty()
+            || matches!(
+                region_of(index),
+                Some(Region::ComplementarityDeterminingRegion(_))
+            )
+    };
+    let odd_cysteines = sequence.iter().filter(|c| **c == 'C').count() % 2 == 1;
+    let mut output = Vec::new();
+    for (index, residue) in sequence.iter().enumerate() {
+        let next = sequence.get(index + 1).copied();
+        let found: &[(Kind, usize)] = match (*residue, next, sequence.get(index + 2)) {
+            ('N', Some('G' | 'S'), Some('S' | 'T')) if in_cdr(index) => {
+                &[(Kind::Deamidation, 2), (Kind::NGlycosylation, 3)]
+            }
+            ('N', Some('G' | 'S'), _) => &[(Kind::Deamidation, 2)],
+            ('N', Some(x), Some('S' | 'T')) if x != 'P' && in_cdr(index) => {
+                &[(Kind::NGlycosylation, 3)]
+            }
+            ('D', Some('G'), _) => &[(Kind::Isomerization, 2)],
+            ('D', Some('P'), _) => &[(Kind::Cleavage, 2)],
+            ('M', _, _) if in_cdr(index) => &[(Kind::Oxidation, 1)],
+            ('C', _, _) => {
+                let unpaired = if regions.is_empty() {
+                    odd_cysteines
+                } else {
+                    numbering
+                        .get(index)
+                        .and_then(|n| n.as_deref())
+                        .is_some_and(|n| n != "23" && n != "104")
+                };
+                if unpaired {
+                    &[(Kind::UnpairedCysteine, 1)]
+                } else {
+                    &[]
+                }
+            }
+            _ => &[],
+        };
+        for (kind, length) in found {
+            output.push(Liability {
+                kind: *kind,
+                position: index,
+                motif: sequence[index..(index + length).min(sequence.len())]
+                    .iter()
+                    .collect(),
+                region: region_of(index),
+                imgt: numbering.get(index).cloned().flatten(),
+            });
+        }
+    }
+    output
+}
+
+/// Show the liabilities as a table
+pub fn show(liabilities: &[Liability]) {
+    println!("{}", "Liabilities".underline().italic());
+    if liabilities.is_empty() {
+        println!("{}", "No liabilities found".green());
+        return;
+    }
+    let mut data = vec![[
+        "Position".to_string(),
+        "IMGT".to_string(),
+        "Region".to_string(),
+        "Motif".to_string(),
+        "Liability".to_string(),
+    ]];
+    for liability in liabilities {
+        data.push([
+            (liability.position + 1).to_string(),
+            liability.imgt.clone().unwrap_or_default(),
+            liability
+                .region
+                .as_ref()
+                .map_or(String::new(), ToString::to_string),
+            liability.motif.clone(),
+            liability.kind.description().to_string(),
+        ]);
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::none(),
+            Styling::with_style(Styles::Dimmed),
+            Styling::none(),
+            Styling::with_fg(Some(Color::Yellow)),
+            Styling::none(),
+        ],
+    );
+    println!(
+        "{}",
+        liabilities
+            .iter()
+            .counts_by(|l| l.kind.description())
+            .into_iter()
+            .sorted()
+            .map(|(kind, count)| format!("{kind}: {count}"))
+            .join(", ")
+            .dimmed()
+    );
+}
+
+/// The liabilities in the same format as the regions, eg `NG@55;DP@102`
+pub fn annotation(liabilities: &[Liability]) -> String {
+    liabilities
+        .iter()
+        .map(|l| format!("{}@{}", l.motif, l.position + 1))
+        .join(";")
+}
+
+#[test]
+fn liabilities() {
+    let sequence: Vec<char> = "CANGSMDPCNVT".chars().collect();
+    let regions = [
+        (Region::Framework(1), 2),
+        (Region::ComplementarityDeterminingRegion(1), 4),
+        (Region::Framework(2), 6),
+    ];
+    let numbering: Vec<Option<String>> = ["23", "24", "27", "28", "29", "30"]
+        .iter()
+        .map(|n| Some(n.to_string()))
+        .chain(["39", "40", "41"].iter().map(|n| Some(n.to_string())))
+        .chain([None, None, None])
+        .collect();
+    let found = scan(&sequence, &regions, &numbering)
+        .into_iter()
+        .map(|l| (l.kind, l.position))
+        .collect_vec();
+    assert_eq!(
+        found,
+        [
+            (Kind::Deamidation, 2),
+            (Kind::NGlycosylation, 2),
+            (Kind::Oxidation, 5),
+            (Kind::Cleavage, 6),
+            (Kind::UnpairedCysteine, 8),
+        ]
+    );
+    // Without regions the sequon in FR2 is reported, and with an even number of cysteines none are unpaired
+    let found = scan(&sequence, &[], &[]);
+    assert_eq!(
+        found
+            .iter()
+            .filter(|l| l.kind == Kind::UnpairedCysteine)
+            .count(),
+        0
+    );
+    assert!(found
+        .iter()
+        .any(|l| l.kind == Kind::NGlycosylation && l.position == 9));
+}
@@ -0,0 +1,68 @@
+//! The logic behind the `align` command line tool, exposed so that other tools can reuse the
+//! alignment orchestration, rendering, and annotation generation without calling the binary.
+
+pub mod alignment;
+mod alleles;
+mod ambiguity;
+mod ambiguous;
+mod app;
+mod batch;
+mod bound;
+mod cdr;
+mod checkpoint;
+mod chimera;
+mod cli;
+mod combination;
+mod compare;
+mod confidence;
+mod consensus;
+mod coverage;
+mod crosslink;
+mod custom;
+mod decoy;
+mod delta;
+mod diagnostic;
+mod digest;
+mod elemental;
+mod explain;
+mod formula;
+mod glycan;
+pub mod highlight;
+mod humanness;
+mod index;
+mod isobaric;
+mod isotopes;
+mod isotype;
+mod junction;
+mod label;
+mod ladder;
+mod legend;
+mod liabilities;
+mod lookup;
+mod mutations;
+mod numbering;
+mod paired;
+mod placement;
+mod prefilter;
+pub mod render;
+mod repertoire;
+mod retention;
+mod stats;
+mod stream;
+pub mod styling;
+mod substitution;
+#[cfg(test)]
+mod test_helpers;
+mod transfer;
+mod tree;
+mod triple;
+mod validate;
+
+pub use app::run;
+pub use cli::{
+    AlignmentKind, Layout, MassDeltaUnit, MassTolerance, StatsConventions,
+    SUPPORTED_ISOBARIC_LENGTHS,
+};
+
+/// Define the default precision (in number of digits shown) for number output
+pub const NUMBER_PRECISION: usize = 3;
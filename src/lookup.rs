@@ -0,0 +1,174 @@
+use colored::{Color, Colorize, Styles};
+use itertools::Itertools;
+use rustyms::{
+    modification::{ModificationId, Ontology, SimpleModification, SimpleModificationInner},
+    Chemical,
+};
+
+use crate::{
+    custom,
+    render::{display_mass, table},
+    styling::Styling,
+};
+
+/// The ontologies that are searched by name
+const ONTOLOGIES: [Ontology; 6] = [
+    Ontology::Unimod,
+    Ontology::Psimod,
+    Ontology::Gnome,
+    Ontology::Xlmod,
+    Ontology::Resid,
+    Ontology::Custom,
+];
+
+/// The maximal number of matches shown
+const MAX_SHOWN: usize = 100;
+
+/// How well a modification matches the search, from best to worst
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    Name,
+    NamePrefix,
+    NameSubstring,
+    Synonym,
+    SynonymSubstring,
+}
+
+/// A modification found by name
+#[derive(Clone, Debug)]
+pub struct Match {
+    pub ontology: Ontology,
+    pub id: Option<usize>,
+    pub modification: SimpleModification,
+    pub kind: MatchKind,
+    /// The synonym that matched, if matched on a synonym
+    pub synonym: Option<String>,
+}
+
+/// The id of a modification, if it is defined in an ontology
+pub fn modification_id(modification: &SimpleModificationInner) -> Option<&ModificationId> {
+    match modification {
+        SimpleModificationInner::Database { id, .. }
+        | SimpleModificationInner::Linker { id, .. }
+        | SimpleModificationInner::Gno { id, .. } => Some(id),
+        _ => None,
+    }
+}
+
+/// Search all ontologies (including the custom modifications from `--mod-db`) for modifications with the query (case insensitive) in their name or in
+/// one of their synonyms. The matches are sorted by how well they match (see [`MatchKind`]) and
+/// then by the length of the name, so the closest names come first.
+pub fn search(query: &str) -> Vec<Match> {
+    let query = query.trim().to_lowercase();
+    let mut output = Vec::new();
+    for ontology in ONTOLOGIES {
+        for (id, name, modification) in ontology.lookup(custom::database()) {
+            let name = name.to_lowercase();
+            let found = if name == query {
+                Some((MatchKind::Name, None))
+            } else if name.starts_with(&query) {
+                Some((MatchKind::NamePrefix, None))
+            } else if name.contains(&query) {
+                Some((MatchKind::NameSubstring, None))
+            } else {
+                modification_id(modification).and_then(|mid| {
+                    mid.synonyms
+                        .iter()
+                        .filter_map(|s| {
+                            let lower = s.to_lowercase();
+                            if lower == query {
+                                Some((MatchKind::Synonym, Some(s.clone())))
+                            } else if lower.contains(&query) {
+                                Some((MatchKind::SynonymSubstring, Some(s.clone())))
+                            } else {
+                                None
+                            }
+                        })
+                        .min_by_key(|(kind, _)| *kind)
+                })
+            };
+            if let Some((kind, synonym)) = found {
+                output.push(Match {
+                    ontology,
+                    id: *id,
+                    modification: modification.clone(),
+                    kind,
+                    synonym,
+                });
+            }
+        }
+    }
+    output.sort_by_key(|m| (m.kind, m.modification.to_string().len()));
+    output
+}
+
+/// Search and show all modifications matching the query
+pub fn show(query: &str, precision: Option<usize>) {
+    let matches = search(query);
+    println!(
+        "Modifications matching '{}' {}",
+        query.trim().green(),
+        format!(
+            "(names and synonyms in {})",
+            ONTOLOGIES.iter().map(|o| o.name()).join(", ")
+        )
+        .dimmed()
+    );
+    if matches.is_empty() {
+        println!("{}", "No modifications found".red());
+        return;
+    }
+    let mut data = vec![[
+        "Name".to_string(),
+        "Id".to_string(),
+        "Monoisotopic mass".to_string(),
+        "Formula".to_string(),
+        "Synonym".to_string(),
+    ]];
+    for found in matches.iter().take(MAX_SHOWN) {
+        data.push([
+            found.modification.to_string(),
+            format!(
+                "{}{}",
+                found.ontology.name(),
+                found.id.map_or(String::new(), |id| format!(":{id}")),
+            ),
+            display_mass(
+                found.modification.formula().monoisotopic_mass(),
+                false,
+                precision,
+            ),
+            found.modification.formula().hill_notation_fancy(),
+            found.synonym.clone().unwrap_or_default(),
+        ]);
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_fg(Some(Color::Magenta)),
+            Styling::with_style(Styles::Dimmed),
+            Styling::with_fg(Some(Color::Yellow)),
+            Styling::with_fg(Some(Color::Green)),
+            Styling::with_style(Styles::Dimmed),
+        ],
+    );
+    if matches.len() > MAX_SHOWN {
+        println!(
+            "{}",
+            format!("Showing {MAX_SHOWN} of {} modifications", matches.len())
+                .dimmed()
+                .italic()
+        );
+    }
+}
+
+#[test]
+fn phospho() {
+    let found = search("PHOSPHO");
+    assert_eq!(found[0].modification.to_string(), "U:Phospho");
+    assert_eq!(found[0].kind, MatchKind::Name);
+    assert!(found
+        .iter()
+        .any(|m| m.ontology == Ontology::Psimod && m.kind != MatchKind::Name));
+}
@@ -0,0 +1,133 @@
+use colored::{Color, Colorize, Styles};
+use rustyms::{
+    align::{Alignment, MatchType},
+    imgt::Allele,
+    peptidoform::AnnotatedPeptide,
+    AtMax, SequenceElement, SimpleLinear,
+};
+
+use crate::{numbering::germline_numbering, render::table, styling::Styling};
+
+/// A single difference between the germline and the query
+pub struct Mutation {
+    /// The IMGT position of the (first) germline residue, if known
+    pub imgt: Option<String>,
+    /// The position in the germline (0 based)
+    pub germline_position: usize,
+    /// The position in the query (0 based)
+    pub query_position: usize,
+    /// The germline residue(s), empty for an insertion in the query
+    pub germline: String,
+    /// The query residue(s), empty for a deletion in the query
+    pub query: String,
+    /// The region of the germline
+    pub region: Option<String>,
+    /// The type of the difference
+    pub kind: &'static str,
+    /// If the germline and query residues have the same mass
+    pub mass_identical: bool,
+}
+
+/// Find all positions where the query differs from the germline, the germline has to be sequence A
+/// and the query sequence B of the alignment.
+pub fn mutations<A: AtMax<SimpleLinear>, B: AtMax<SimpleLinear>>(
+    allele: &Allele,
+    alignment: &Alignment<'_, A, B>,
+) -> Vec<Mutation> {
+    let numbering = germline_numbering(allele);
+    let (mut a, mut b) = alignment.start();
+    let mut output = Vec::new();
+    for piece in alignment.path() {
+        let kind = match (piece.match_type, piece.step_a, piece.step_b) {
+            (MatchType::FullIdentity, _, _) => None,
+            (MatchType::IdentityMassMismatch, _, _) => Some("Modification"),
+            (MatchType::Mismatch, _, _) => Some("Substitution"),
+            (MatchType::Isobaric, _, _) => Some("Isobaric"),
+            (MatchType::Rotation, _, _) => Some("Rotation"),
+            (MatchType::Gap, 0, _) => Some("Insertion"),
+            (MatchType::Gap, _, _) => Some("Deletion"),
+        };
+        if let Some(kind) = kind {
+            output.push(Mutation {
+                imgt: numbering.get(a).cloned().flatten(),
+                germline_position: a,
+                query_position: b,
+                germline: residues(&alignment.seq_a()[a..a + piece.step_a as usize]),
+                query: residues(&alignment.seq_b()[b..b + piece.step_b as usize]),
+                // The regions are indexed from 1
+                region: allele
+                    .get_region((a + 1).min(allele.sequence.len()))
+                    .map(|(r, _)| r.to_string()),
+                kind,
+                mass_identical: matches!(
+                    piece.match_type,
+                    MatchType::Isobaric | MatchType::Rotation
+                ),
+            });
+        }
+        a += piece.step_a as usize;
+        b += piece.step_b as usize;
+    }
+    output
+}
+
+/// The one letter codes for the given residues
+fn residues<T>(sequence: &[SequenceElement<T>]) -> String {
+    sequence.iter().map(|s| s.aminoacid.char()).collect()
+}
+
+/// Show a table with all mutations, the query positions are shifted by the given offset
+pub fn show_mutations(allele: &Allele, mutations: &[Mutation], query_offset: usize) {
+    println!(
+        "{} {}",
+        "Mutations".underline().italic(),
+        format!(
+            "({} versus {}, {} difference{})",
+            "Query",
+            allele.name(),
+            mutations.len(),
+            if mutations.len() == 1 { "" } else { "s" }
+        )
+        .dimmed()
+    );
+    if mutations.is_empty() {
+        return;
+    }
+    let mut data = vec![[
+        "IMGT".to_string(),
+        "Germline".to_string(),
+        "Query".to_string(),
+        "Region".to_string(),
+        "Type".to_string(),
+        "Mass identical".to_string(),
+    ]];
+    for mutation in mutations {
+        let show = |residues: &str, position: usize| {
+            if residues.is_empty() {
+                "-".to_string()
+            } else {
+                format!("{residues}{}", position + 1)
+            }
+        };
+        data.push([
+            mutation.imgt.clone().unwrap_or_else(|| "-".to_string()),
+            show(&mutation.germline, mutation.germline_position),
+            show(&mutation.query, mutation.query_position + query_offset),
+            mutation.region.clone().unwrap_or_default(),
+            mutation.kind.to_string(),
+            if mutation.mass_identical { "yes" } else { "no" }.to_string(),
+        ]);
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_style(Styles::Dimmed),
+            Styling::none(),
+            Styling::none(),
+            Styling::none(),
+            Styling::none(),
+            Styling::with_fg(Some(Color::Yellow)),
+        ],
+    );
+}
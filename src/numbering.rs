@@ -0,0 +1,351 @@
+use rustyms::{
+    imgt::{Allele, ChainType, GeneType},
+    peptidoform::Region,
+};
+
+use crate::cli::NumberingScheme;
+
+/// A range of scheme positions that is distributed over one or more consecutive IMGT regions
+struct Span {
+    /// The number of IMGT regions (in the order FR1, CDR1, FR2, CDR2, FR3, CDR3, FR4) covered
+    regions: usize,
+    first: u16,
+    last: u16,
+    /// The positions that are left out first if there are fewer residues than positions
+    deletions: &'static [u16],
+    /// If any further positions are left out from the start (a truncated N terminus) instead of the end
+    truncate_start: bool,
+    /// The position after which additional residues get an insertion code
+    insertion: u16,
+}
+
+const fn span(
+    regions: usize,
+    first: u16,
+    last: u16,
+    deletions: &'static [u16],
+    truncate_start: bool,
+    insertion: u16,
+) -> Span {
+    Span {
+        regions,
+        first,
+        last,
+        deletions,
+        truncate_start,
+        insertion,
+    }
+}
+
+/// The spans for the given scheme and chain
+fn spans(scheme: NumberingScheme, heavy: bool) -> [Span; 7] {
+    let empty = || span(0, 0, 0, &[], false, 0);
+    match (scheme, heavy) {
+        (NumberingScheme::Imgt, _) => [
+            span(1, 1, 26, &[10], true, 26),
+            span(
+                1,
+                27,
+                38,
+                &[32, 33, 31, 34, 30, 35, 29, 36, 28, 37, 27, 38],
+                false,
+                32,
+            ),
+            span(1, 39, 55, &[], false, 55),
+            span(
+                1,
+                56,
+                65,
+                &[60, 61, 59, 62, 58, 63, 57, 64, 56, 65],
+                false,
+                60,
+            ),
+            span(1, 66, 104, &[73, 81, 82], false, 104),
+            span(
+                1,
+                105,
+                117,
+                &[
+                    111, 112, 110, 113, 109, 114, 108, 115, 107, 116, 106, 117, 105,
+                ],
+                false,
+                111,
+            ),
+            span(1, 118, 128, &[], false, 128),
+        ],
+        (scheme, true) => [
+            span(1, 1, 25, &[], true, 25),
+            if scheme == NumberingScheme::Kabat {
+                span(2, 26, 50, &[35, 34, 33, 32, 31], false, 35)
+            } else {
+                span(2, 26, 50, &[31, 32, 30, 33, 29], false, 31)
+            },
+            span(1, 51, 57, &[52, 53, 54, 55, 56], false, 52),
+            span(
+                1,
+                58,
+                92,
+                &[],
+                false,
+                if scheme == NumberingScheme::Martin {
+                    72
+                } else {
+                    82
+                },
+            ),
+            span(
+                1,
+                93,
+                102,
+                &[100, 99, 98, 97, 96, 95, 94, 101, 93, 102],
+                false,
+                100,
+            ),
+            span(1, 103, 113, &[], false, 113),
+            empty(),
+        ],
+        (scheme, false) => [
+            if scheme == NumberingScheme::Kabat {
+                span(2, 1, 32, &[10, 28, 29, 30, 31], true, 27)
+            } else {
+                span(2, 1, 32, &[10, 30, 31, 29, 28], true, 30)
+            },
+            span(1, 33, 49, &[], false, 49),
+            span(
+                2,
+                50,
+                88,
+                &[],
+                false,
+                if scheme == NumberingScheme::Martin {
+                    68
+                } else {
+                    66
+                },
+            ),
+            span(1, 89, 97, &[95, 96, 94, 93, 92, 91, 90, 97, 89], false, 95),
+            span(1, 98, 107, &[], false, 107),
+            empty(),
+            empty(),
+        ],
+    }
+}
+
+/// The index of the region in the order FR1, CDR1, FR2, CDR2, FR3, CDR3, FR4
+const fn region_index(region: &Region) -> Option<usize> {
+    match region {
+        Region::Framework(n @ 1..=4) => Some((*n - 1) * 2),
+        Region::ComplementarityDeterminingRegion(n @ 1..=3) => Some((*n - 1) * 2 + 1),
+        _ => None,
+    }
+}
+
+/// Number all residues of the query based on the regions generated from a domain alignment (see
+/// [`crate::render::generate_regions`]). The numbers follow the region boundaries found by the IMGT
+/// alignment, within every region (or set of regions) the residues are distributed over the
+/// positions of the scheme with the conventional positions for deletions and insertions, so for
+/// unusual sequences this is an approximation of the scheme. Residues outside of the variable
+/// domain get no number. Insertions are shown as `111.1` for IMGT and `100A` for the other
+/// schemes, the IMGT CDR3 insertions are placed symmetrically on 111 and 112.
+pub fn number(
+    regions: &[(Region, usize)],
+    chain: ChainType,
+    scheme: NumberingScheme,
+) -> Vec<Option<String>> {
+    let mut output = vec![None; regions.iter().map(|(_, l)| l).sum()];
+    let mut locations: [Vec<usize>; 7] = Default::default();
+    let mut start = 0;
+    for (region, length) in regions {
+        if let Some(index) = region_index(region) {
+            locations[index].extend(start..start + length);
+        }
+        start += length;
+    }
+    let mut region = 0;
+    for span in spans(scheme, chain == ChainType::Heavy) {
+        if span.regions == 0 {
+            continue;
+        }
+        let residues = locations[region..region + span.regions].concat();
+        region += span.regions;
+        let labels = labels(&span, scheme, residues.len());
+        for (residue, label) in residues.into_iter().zip(labels) {
+            output[residue] = Some(label);
+        }
+    }
+    output
+}
+
+/// The IMGT numbering of all residues of a germline. V genes are numbered based on their regions
+/// (with the partial CDR3 from 105), J genes are numbered from the conserved W/F118 (the CDR3
+/// part backwards from 117), and C genes are not numbered.
+pub fn germline_numbering(allele: &Allele) -> Vec<Option<String>> {
+    match allele.gene.kind {
+        GeneType::V => {
+            let mut output = number(allele.regions, allele.gene.chain, NumberingScheme::Imgt);
+            // The germline only has the start of the CDR3, so number it from 105 onwards
+            let cdr3: usize = allele
+                .regions
+                .iter()
+                .take_while(|(r, _)| *r != Region::ComplementarityDeterminingRegion(3))
+                .map(|(_, l)| l)
+                .sum();
+            for (i, label) in output.iter_mut().skip(cdr3).enumerate() {
+                *label = label.as_ref().map(|_| (105 + i).to_string());
+            }
+            output
+        }
+        GeneType::J => {
+            let mut output = Vec::with_capacity(allele.sequence.len());
+            for (region, length) in allele.regions {
+                if *region == Region::ComplementarityDeterminingRegion(3) {
+                    output.extend((0..*length).rev().map(|i| Some((117 - i).to_string())));
+                } else if *region == Region::Framework(4) {
+                    output.extend((0..*length).map(|i| Some((118 + i).to_string())));
+                } else {
+                    output.extend(std::iter::repeat_n(None, *length));
+                }
+            }
+            output
+        }
+        GeneType::C(_) => vec![None; allele.sequence.len()],
+    }
+}
+
+/// Pad sequences to the IMGT unique numbering. Every row is given as its residues with their IMGT
+/// labels (see [`number`] and [`germline_numbering`]), residues without a label are left out. The
+/// output has a column for every position from 1 to 128 and for every insertion present in any of
+/// the rows, and gives the labels of the columns and the padded rows, with unoccupied positions
+/// shown as `.`.
+pub fn imgt_gapped(rows: &[Vec<(char, Option<String>)>]) -> (Vec<String>, Vec<String>) {
+    // The order of a label, the CDR3 insertions on 112 are numbered down towards 112
+    let key = |label: &str| {
+        let (base, insertion) = label.split_once('.').unwrap_or((label, "0"));
+        let base: usize = base.parse().unwrap_or(0);
+        let insertion: isize = insertion.parse().unwrap_or(0);
+        (base, if base == 112 { -insertion } else { insertion })
+    };
+    let mut columns: Vec<String> = (1..=128).map(|p| p.to_string()).collect();
+    for label in rows.iter().flatten().filter_map(|(_, l)| l.as_ref()) {
+        if !columns.contains(label) {
+            columns.push(label.clone());
+        }
+    }
+    columns.sort_by_key(|label| key(label));
+    let padded = rows
+        .iter()
+        .map(|row| {
+            columns
+                .iter()
+                .map(|column| {
+                    row.iter()
+                        .find(|(_, l)| l.as_ref() == Some(column))
+                        .map_or('.', |(c, _)| *c)
+                })
+                .collect()
+        })
+        .collect();
+    (columns, padded)
+}
+
+/// Get the labels for a span filled with the given number of residues
+fn labels(span: &Span, scheme: NumberingScheme, residues: usize) -> Vec<String> {
+    let mut positions: Vec<u16> = (span.first..=span.last).collect();
+    let mut deletions = span.deletions.iter();
+    while positions.len() > residues {
+        if let Some(deletion) = deletions.next() {
+            positions.retain(|p| p != deletion);
+        } else if span.truncate_start {
+            positions.remove(0);
+        } else {
+            positions.pop();
+        }
+    }
+    let extra = residues - positions.len();
+    let mut output = Vec::with_capacity(residues);
+    for position in positions {
+        output.push(position.to_string());
+        if position != span.insertion || extra == 0 {
+            continue;
+        }
+        if scheme == NumberingScheme::Imgt && span.insertion < span.last {
+            // IMGT places the insertions in a CDR symmetrically around the centre
+            let after = extra.div_ceil(2);
+            output.extend((1..=after).map(|i| format!("{position}.{i}")));
+            output.extend(
+                (1..=extra - after)
+                    .rev()
+                    .map(|i| format!("{}.{i}", position + 1)),
+            );
+        } else if scheme == NumberingScheme::Imgt {
+            output.extend((1..=extra).map(|i| format!("{position}.{i}")));
+        } else {
+            output.extend((0..extra).map(|i| format!("{position}{}", insertion_code(i))));
+        }
+    }
+    output
+}
+
+/// The letter code for the insertion with this index, A to Z followed by AA, AB, etc
+fn insertion_code(index: usize) -> String {
+    let letter = |i: usize| char::from(b'A' + (i % 26) as u8);
+    if index < 26 {
+        letter(index).to_string()
+    } else {
+        format!("{}{}", letter(index / 26 - 1), letter(index))
+    }
+}
+
+#[test]
+fn gapped() {
+    let row = |residues: &str, labels: &[&str]| {
+        residues
+            .chars()
+            .zip(labels)
+            .map(|(c, l)| (c, Some(l.to_string())))
+            .collect::<Vec<_>>()
+    };
+    let (columns, padded) = imgt_gapped(&[
+        row("ABC", &["1", "3", "112"]),
+        row("DEF", &["111.1", "112.2", "112.1"]),
+    ]);
+    assert_eq!(columns.len(), 131);
+    assert_eq!(
+        &columns[110..115],
+        ["111", "111.1", "112.2", "112.1", "112"]
+    );
+    assert_eq!(&padded[0][..3], "A.B");
+    assert_eq!(&padded[0][110..115], "....C");
+    assert_eq!(&padded[1][110..115], ".DEF.");
+}
+
+#[test]
+fn trastuzumab_heavy() {
+    let regions = [
+        (Region::Framework(1), 25),
+        (Region::ComplementarityDeterminingRegion(1), 8),
+        (Region::Framework(2), 17),
+        (Region::ComplementarityDeterminingRegion(2), 8),
+        (Region::Framework(3), 38),
+        (Region::ComplementarityDeterminingRegion(3), 13),
+        (Region::Framework(4), 11),
+        (Region::ConstantHeavy(1), 3),
+    ];
+    let kabat = number(&regions, ChainType::Heavy, NumberingScheme::Kabat);
+    let label = |i: usize| kabat[i].as_deref();
+    // EVQLVESGGG...SRWGGDGFYAMDYWGQ
+    assert_eq!(label(0), Some("1"));
+    assert_eq!(label(25), Some("26"));
+    assert_eq!(label(52), Some("52A"));
+    assert_eq!(label(83), Some("82A"));
+    assert_eq!(label(95), Some("92"));
+    assert_eq!(label(104), Some("100A"));
+    assert_eq!(label(108), Some("102"));
+    assert_eq!(label(119), Some("113"));
+    assert_eq!(label(120), None);
+    let imgt = number(&regions, ChainType::Heavy, NumberingScheme::Imgt);
+    assert_eq!(imgt[9].as_deref(), Some("11"));
+    assert_eq!(imgt[29].as_deref(), Some("35"));
+    assert_eq!(imgt[96].as_deref(), Some("105"));
+    assert_eq!(imgt[108].as_deref(), Some("117"));
+}
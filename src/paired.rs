@@ -0,0 +1,162 @@
+use colored::{Color, Colorize};
+use itertools::Itertools;
+use rustyms::{
+    align::Alignment,
+    imgt::{Allele, GeneType, Species},
+    peptidoform::{Region, UnAmbiguous},
+    Peptidoform, SimpleLinear,
+};
+use serde::Serialize;
+
+use crate::{cdr::extract_regions, render::generate_regions, render::table, styling::Styling};
+
+/// The domain alignment of one chain of a paired antibody
+pub struct PairedChain<'a> {
+    /// The name of the chain, eg `Heavy`
+    pub name: &'static str,
+    /// The best alignment for every gene, in the order V, J, C
+    pub genes: Vec<(Allele<'static>, Alignment<'a, UnAmbiguous, SimpleLinear>)>,
+    /// The CDR1 to CDR3 subsequences of the query
+    pub cdrs: [Option<String>; 3],
+}
+
+impl<'a> PairedChain<'a> {
+    pub fn new(
+        name: &'static str,
+        query: &Peptidoform<SimpleLinear>,
+        genes: Vec<(Allele<'static>, Alignment<'a, UnAmbiguous, SimpleLinear>)>,
+    ) -> Self {
+        let regions = extract_regions(query, &generate_regions(&genes));
+        let cdrs = [1, 2, 3].map(|n| {
+            regions
+                .iter()
+                .find(|r| r.region == Region::ComplementarityDeterminingRegion(n))
+                .map(|r| r.peptide.to_string())
+        });
+        Self { name, genes, cdrs }
+    }
+
+    /// The name of the best allele for the given gene type
+    fn call(&self, kind: GeneType) -> Option<String> {
+        self.genes
+            .iter()
+            .find(|(allele, _)| match (allele.gene.kind, kind) {
+                (GeneType::C(_), GeneType::C(_)) => true,
+                (a, b) => a == b,
+            })
+            .map(|(allele, _)| allele.name())
+    }
+
+    fn json(&self) -> ChainJson {
+        ChainJson {
+            v: self.call(GeneType::V),
+            j: self.call(GeneType::J),
+            c: self.call(GeneType::C(None)),
+            species: self
+                .species()
+                .iter()
+                .map(|s| s.scientific_name().to_string())
+                .collect(),
+            cdr1: self.cdrs[0].clone(),
+            cdr2: self.cdrs[1].clone(),
+            cdr3: self.cdrs[2].clone(),
+        }
+    }
+
+    /// All species of the genes of this chain, in order of appearance
+    fn species(&self) -> Vec<Species> {
+        self.genes.iter().map(|(a, _)| a.species).unique().collect()
+    }
+}
+
+/// If all genes of both chains are from the same species
+pub fn species_consistent(heavy: &PairedChain, light: &PairedChain) -> bool {
+    heavy
+        .species()
+        .iter()
+        .chain(&light.species())
+        .unique()
+        .count()
+        <= 1
+}
+
+/// Show a combined summary of both chains
+pub fn show_summary(heavy: &PairedChain, light: &PairedChain) {
+    println!("{}", "Paired antibody".underline().italic());
+    let mut data = vec![[
+        "Chain".to_string(),
+        "V".to_string(),
+        "J".to_string(),
+        "C".to_string(),
+        "CDR1".to_string(),
+        "CDR2".to_string(),
+        "CDR3".to_string(),
+        "Species".to_string(),
+    ]];
+    for chain in [heavy, light] {
+        let show = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        data.push([
+            chain.name.to_string(),
+            show(chain.call(GeneType::V)),
+            show(chain.call(GeneType::J)),
+            show(chain.call(GeneType::C(None))),
+            show(chain.cdrs[0].clone()),
+            show(chain.cdrs[1].clone()),
+            show(chain.cdrs[2].clone()),
+            chain.species().iter().map(|s| s.common_name()).join(", "),
+        ]);
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::none(),
+            Styling::with_fg(Some(Color::Magenta)),
+            Styling::with_fg(Some(Color::Magenta)),
+            Styling::with_fg(Some(Color::Magenta)),
+            Styling::none(),
+            Styling::none(),
+            Styling::none(),
+            Styling::none(),
+        ],
+    );
+    if species_consistent(heavy, light) {
+        println!("{}", "All genes are from the same species".green());
+    } else {
+        println!(
+            "{}",
+            "The genes are from different species, this could be a chimeric or humanised antibody"
+                .yellow()
+        );
+    }
+}
+
+/// The JSON representation of one chain of a paired antibody
+#[derive(Serialize)]
+struct ChainJson {
+    v: Option<String>,
+    j: Option<String>,
+    c: Option<String>,
+    species: Vec<String>,
+    cdr1: Option<String>,
+    cdr2: Option<String>,
+    cdr3: Option<String>,
+}
+
+/// The JSON representation of a paired antibody
+#[derive(Serialize)]
+struct PairedJson {
+    heavy: ChainJson,
+    light: ChainJson,
+    species_consistent: bool,
+}
+
+/// A single JSON object describing the paired antibody
+pub fn json(heavy: &PairedChain, light: &PairedChain) -> String {
+    serde_json::to_string(&PairedJson {
+        heavy: heavy.json(),
+        light: light.json(),
+        species_consistent: species_consistent(heavy, light),
+    })
+    .unwrap()
+}
@@ -0,0 +1,172 @@
+use colored::{Color, Colorize, Styles};
+use itertools::Itertools;
+use rustyms::{
+    modification::{LinkerSpecificity, Ontology, SimpleModification, SimpleModificationInner},
+    placement_rule::{PlacementRule, Position},
+    Peptidoform, SequencePosition, SimpleLinear,
+};
+
+use crate::{render::table, styling::Styling};
+
+/// A location on a peptide where a modification is allowed
+#[derive(Clone, Debug)]
+pub struct Site {
+    pub position: SequencePosition,
+    /// The placement rules that allow the modification here
+    pub rules: Vec<String>,
+}
+
+/// Show a placement rule in the same notation as the modification details
+fn rule_text(rule: &PlacementRule) -> String {
+    match rule {
+        PlacementRule::AminoAcid(aa, pos) => {
+            format!("{}@{pos}", aa.iter().map(|a| a.char()).collect::<String>())
+        }
+        PlacementRule::PsiModification(index, pos) => format!(
+            "{}@{pos}",
+            Ontology::Psimod
+                .find_id(*index, None)
+                .map_or(format!("MOD:{index}"), |m| m.to_string())
+        ),
+        PlacementRule::Terminal(pos) => pos.to_string(),
+        PlacementRule::Anywhere => "Anywhere".to_string(),
+    }
+}
+
+/// All placement rules of the modification, with a prefix for the side of asymmetric linkers
+fn rules(modification: &SimpleModificationInner) -> Vec<(&'static str, &PlacementRule)> {
+    match modification {
+        SimpleModificationInner::Database { specificities, .. } => specificities
+            .iter()
+            .flat_map(|(rules, _, _)| rules.iter().map(|r| ("", r)))
+            .collect(),
+        SimpleModificationInner::Linker { specificities, .. } => specificities
+            .iter()
+            .flat_map(|specificity| match specificity {
+                LinkerSpecificity::Symmetric(rules, _, _) => {
+                    rules.iter().map(|r| ("", r)).collect()
+                }
+                LinkerSpecificity::Asymmetric((left, right), _, _) => left
+                    .iter()
+                    .map(|r| ("left ", r))
+                    .chain(right.iter().map(|r| ("right ", r)))
+                    .collect_vec(),
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Find all locations on the peptide (termini and residues) where the placement rules of the
+/// modification allow it, with the rules that allow it. Returns None if the modification has no
+/// placement rules, in which case it can be placed anywhere.
+pub fn sites(
+    peptide: &Peptidoform<SimpleLinear>,
+    modification: &SimpleModification,
+) -> Option<Vec<Site>> {
+    let rules = rules(modification);
+    if rules.is_empty() || peptide.is_empty() {
+        return None;
+    }
+    let sequence = peptide.sequence();
+    let positions = std::iter::once(SequencePosition::NTerm)
+        .chain((0..sequence.len()).map(SequencePosition::Index))
+        .chain(std::iter::once(SequencePosition::CTerm));
+    Some(
+        positions
+            .filter_map(|position| {
+                let (element, terminal) = match position {
+                    SequencePosition::NTerm => (&sequence[0], true),
+                    SequencePosition::Index(index) => (&sequence[index], false),
+                    SequencePosition::CTerm => (&sequence[sequence.len() - 1], true),
+                };
+                let allowed = rules
+                    .iter()
+                    .filter(|(_, rule)| {
+                        // Side chain rules also allow any position, but are not placed on the termini
+                        let side_chain = matches!(
+                            rule,
+                            PlacementRule::AminoAcid(_, Position::Anywhere)
+                                | PlacementRule::PsiModification(_, Position::Anywhere)
+                                | PlacementRule::Anywhere
+                        );
+                        !(terminal && side_chain) && rule.is_possible(element, position)
+                    })
+                    .map(|(prefix, rule)| format!("{prefix}{}", rule_text(rule)))
+                    .unique()
+                    .collect_vec();
+                (!allowed.is_empty()).then_some(Site {
+                    position,
+                    rules: allowed,
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Show all locations on the peptide where the modification is allowed
+pub fn show(peptide: &Peptidoform<SimpleLinear>, modification: &SimpleModification) {
+    println!(
+        "Placement of {} on {}",
+        modification.to_string().purple(),
+        peptide.to_string().blue()
+    );
+    let Some(sites) = sites(peptide, modification) else {
+        println!(
+            "{}",
+            "This modification has no placement rules, so it is allowed everywhere".dimmed()
+        );
+        return;
+    };
+    if sites.is_empty() {
+        println!(
+            "{}",
+            "The modification is not allowed anywhere on this sequence".red()
+        );
+        return;
+    }
+    let sequence = peptide.sequence();
+    let mut data = vec![[
+        "Position".to_string(),
+        "Residue".to_string(),
+        "Allowed by".to_string(),
+    ]];
+    for site in &sites {
+        let (position, residue) = match site.position {
+            SequencePosition::NTerm => ("N-term".to_string(), sequence[0].aminoacid.char()),
+            SequencePosition::Index(index) => {
+                ((index + 1).to_string(), sequence[index].aminoacid.char())
+            }
+            SequencePosition::CTerm => (
+                "C-term".to_string(),
+                sequence[sequence.len() - 1].aminoacid.char(),
+            ),
+        };
+        data.push([position, residue.to_string(), site.rules.join(", ")]);
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_style(Styles::Dimmed),
+            Styling::with_fg(Some(Color::Yellow)),
+            Styling::with_fg(Some(Color::Green)),
+        ],
+    );
+}
+
+#[test]
+fn phospho_sites() {
+    let peptide = Peptidoform::pro_forma("SPEKTY", None)
+        .unwrap()
+        .into_simple_linear()
+        .unwrap();
+    let phospho = Ontology::Unimod.find_name("Phospho", None).unwrap();
+    let found = sites(&peptide, &phospho).unwrap();
+    let positions = found.iter().map(|s| s.position).collect_vec();
+    for index in [0, 4, 5] {
+        assert!(positions.contains(&SequencePosition::Index(index)));
+    }
+    assert!(!positions.contains(&SequencePosition::Index(1)));
+    assert!(!positions.contains(&SequencePosition::NTerm));
+}
@@ -0,0 +1,171 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Read, Write},
+};
+
+use itertools::Itertools;
+use rustyms::{
+    system::{dalton, Mass},
+    MultiChemical, Peptidoform, Tolerance,
+};
+
+use crate::index::{read_u64, write_u64};
+
+/// The width of the mass bins (in Dalton) that define when two k-mers are mass-equivalent, this is
+/// the width used in the persistent index and the minimal width for a search
+pub const BIN_WIDTH: f64 = 0.05;
+
+/// The mass of the heaviest residue (W), used to get the highest possible mass of a k-mer
+const MAX_RESIDUE_MASS: f64 = 186.079_313;
+
+/// The width of the mass bins for a search with this tolerance. Two k-mers in neighbouring bins are
+/// still mass-equivalent, so the width has to be at least the tolerance for the heaviest possible
+/// k-mer to not lose any candidates.
+pub fn bin_width(tolerance: Tolerance<Mass>, k: usize) -> f64 {
+    let mass = Mass::new::<dalton>(k as f64 * MAX_RESIDUE_MASS);
+    let (low, high) = tolerance.bounds(mass);
+    BIN_WIDTH.max((high - low).value / 2.0)
+}
+
+/// An index of all mass-equivalent k-mers in a database. Two k-mers are mass-equivalent if the
+/// summed residue masses fall within the same (or neighbouring) mass bin, this means that I/L
+/// swaps and any rotation within the k-mer still share the seed. Modifications are ignored.
+pub struct KmerIndex {
+    /// The length of the k-mers
+    pub k: usize,
+    /// For every mass bin all indices of database sequences that contain a k-mer with that mass
+    seeds: HashMap<i64, Vec<usize>>,
+    /// The number of sequences in the index
+    sequences: usize,
+}
+
+impl KmerIndex {
+    /// Create an empty index
+    pub fn new(k: usize) -> Self {
+        Self {
+            k,
+            seeds: HashMap::new(),
+            sequences: 0,
+        }
+    }
+
+    /// Add the next database sequence to the index, the sequences are numbered in the order they are added
+    pub fn add<T>(&mut self, sequence: &Peptidoform<T>) {
+        for bin in kmer_bins(sequence, self.k, BIN_WIDTH).into_iter().unique() {
+            self.seeds.entry(bin).or_default().push(self.sequences);
+        }
+        self.sequences += 1;
+    }
+
+    /// Get the indices of all database sequences that share at least one k-mer with the query. This
+    /// uses the bins of [`BIN_WIDTH`], so it should only be used if the [`bin_width`] for the search
+    /// is not larger.
+    pub fn candidates<T>(&self, query: &Peptidoform<T>) -> HashSet<usize> {
+        kmer_bins(query, self.k, BIN_WIDTH)
+            .into_iter()
+            .flat_map(|bin| [bin - 1, bin, bin + 1])
+            .filter_map(|bin| self.seeds.get(&bin))
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    /// Write this index in the binary format used by the persistent database index
+    pub fn write(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        write_u64(writer, self.k as u64)?;
+        write_u64(writer, self.sequences as u64)?;
+        write_u64(writer, self.seeds.len() as u64)?;
+        for (bin, indices) in &self.seeds {
+            write_u64(writer, *bin as u64)?;
+            write_u64(writer, indices.len() as u64)?;
+            for index in indices {
+                write_u64(writer, *index as u64)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read an index that was written with [`Self::write`], it should contain the given number of sequences
+    pub fn read(reader: &mut impl Read, sequences: usize) -> std::io::Result<Self> {
+        let invalid =
+            || std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid k-mer index");
+        let k = read_u64(reader)? as usize;
+        if read_u64(reader)? != sequences as u64 {
+            return Err(invalid());
+        }
+        let bins = read_u64(reader)?;
+        let mut seeds = HashMap::new();
+        for _ in 0..bins {
+            let bin = read_u64(reader)? as i64;
+            // Every sequence is at most once in every bin
+            let length = read_u64(reader)?;
+            if length > sequences as u64 {
+                return Err(invalid());
+            }
+            let indices = (0..length)
+                .map(|_| match read_u64(reader)? {
+                    index if index < sequences as u64 => Ok(index as usize),
+                    _ => Err(invalid()),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            seeds.insert(bin, indices);
+        }
+        Ok(Self {
+            k,
+            seeds,
+            sequences,
+        })
+    }
+}
+
+/// The mass-equivalent k-mers of a query, used to check database sequences one by one
+pub struct QuerySeeds {
+    k: usize,
+    width: f64,
+    bins: HashSet<i64>,
+}
+
+impl QuerySeeds {
+    /// The seeds for this query, with bins of the given width, see [`bin_width`]
+    pub fn new<T>(query: &Peptidoform<T>, k: usize, width: f64) -> Self {
+        Self {
+            k,
+            width,
+            bins: kmer_bins(query, k, width)
+                .into_iter()
+                .flat_map(|bin| [bin - 1, bin, bin + 1])
+                .collect(),
+        }
+    }
+
+    /// Check if this database sequence shares at least one k-mer with the query
+    pub fn matches<T>(&self, sequence: &Peptidoform<T>) -> bool {
+        kmer_bins(sequence, self.k, self.width)
+            .into_iter()
+            .any(|bin| self.bins.contains(&bin))
+    }
+}
+
+/// Get the mass bins for all k-mers in this sequence, any k-mer that contains an amino acid
+/// without a single defined mass (B/Z/X) is skipped.
+fn kmer_bins<T>(sequence: &Peptidoform<T>, k: usize, width: f64) -> Vec<i64> {
+    if k == 0 {
+        return Vec::new();
+    }
+    let masses = sequence
+        .sequence()
+        .iter()
+        .map(|element| {
+            element
+                .aminoacid
+                .aminoacid()
+                .single_formula()
+                .map(|f| f.monoisotopic_mass().value)
+        })
+        .collect_vec();
+    masses
+        .windows(k)
+        .filter_map(|window| window.iter().copied().sum::<Option<f64>>())
+        .map(|mass| (mass / width).round() as i64)
+        .collect()
+}
@@ -1,17 +1,24 @@
 use colored::{Color, Colorize, Styles};
 use itertools::Itertools;
-use rustyms::align::{Alignment, Piece};
+use rustyms::align::MatchType;
+use rustyms::align::{Alignment, Piece, Stats};
 use rustyms::imgt::Allele;
 use rustyms::peptidoform::{AnnotatedPeptide, Annotation, Region};
 use rustyms::system::Mass;
-use rustyms::{align::MatchType, Tolerance};
-use rustyms::{AminoAcid, AtMax, Linear, Peptidoform};
+use rustyms::{AminoAcid, AtMax, Linear, MassMode, Peptidoform, SequenceElement};
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::fmt::Write;
+use std::ops::Range;
 
-use crate::{legend::*, Cli};
+use crate::{
+    cli::{Layout, MassDeltaUnit, MassTolerance, StatsConventions},
+    confidence::{HIGH_CONFIDENCE, LOW_CONFIDENCE},
+    highlight::Highlight,
+    legend::*,
+    stats::{region_stats, stats},
+};
 use crate::{styling::*, NUMBER_PRECISION};
 
 #[derive(PartialEq, Eq)]
@@ -24,6 +31,97 @@ enum StepType {
     MassMismatch,
 }
 
+impl StepType {
+    fn new(step: &Piece) -> Self {
+        match (step.match_type, step.step_a, step.step_b) {
+            (MatchType::Isobaric, _, _) => Self::Special, // Catch any 1/1 isobaric sets before they are counted as Match/Mismatch
+            (MatchType::FullIdentity, _, _) => Self::Match,
+            (MatchType::IdentityMassMismatch, _, _) => Self::MassMismatch,
+            (MatchType::Mismatch, _, _) => Self::Mismatch,
+            (_, 0, 1) => Self::Insertion,
+            (_, 1, 0) => Self::Deletion,
+            _ => Self::Special,
+        }
+    }
+
+    /// The colour and the marker of this step
+    const fn marker(&self) -> (Option<Color>, &'static str) {
+        match self {
+            Self::Insertion => (Some(Color::Yellow), "+"),
+            Self::Deletion => (Some(Color::Yellow), "+"),
+            Self::Match => (None, " "),
+            Self::MassMismatch => (Some(Color::Red), "m"),
+            Self::Mismatch => (Some(Color::Red), "⨯"),
+            Self::Special => (Some(Color::Yellow), "-"), // ⇤⇥ ⤚---⤙ ├─┤ ║ ⤚⤙ l╴r╶
+        }
+    }
+}
+
+/// Extra information per step of the path that is shown in the alignment
+#[derive(Clone, Copy)]
+pub enum StepMarks<'a> {
+    /// The indices of the steps that differ between co-optimal alignments, marked with '?'
+    Ambiguous(&'a HashSet<usize>),
+    /// The confidence (0..=1) of every step, used to colour the alignment
+    Confidence(&'a [f64]),
+    /// The steps on positions that distinguish the germline allele from the other alleles of the
+    /// gene, marked with '*' in green if the query has the residue of this allele and red otherwise
+    Alleles(&'a HashMap<usize, bool>),
+}
+
+/// A sequence annotated with regions given by the user (see `--regions`), to show an alignment
+/// coloured by region without an IMGT germline
+pub struct RegionAnnotated<'a, C> {
+    peptide: &'a Peptidoform<C>,
+    regions: &'a [(Region, usize)],
+}
+
+impl<'a, C> RegionAnnotated<'a, C> {
+    pub const fn new(peptide: &'a Peptidoform<C>, regions: &'a [(Region, usize)]) -> Self {
+        Self { peptide, regions }
+    }
+}
+
+impl<C> AnnotatedPeptide for RegionAnnotated<'_, C> {
+    type Complexity = C;
+    fn peptide(&self) -> &Peptidoform<C> {
+        self.peptide
+    }
+    fn regions(&self) -> &[(Region, usize)] {
+        self.regions
+    }
+    fn annotations(&self) -> &[(Annotation, usize)] {
+        &[]
+    }
+}
+
+/// The settings for rendering alignments
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    /// The tolerance that is shown in the header
+    pub tolerance: MassTolerance,
+    /// If the alignment kind was picked automatically, this is noted in the header
+    pub auto_kind: bool,
+    /// Show all digits of the numbers instead of rounding them
+    pub full_number: bool,
+    pub stats: StatsConventions,
+    /// The maximal number of columns on a line
+    pub line_width: usize,
+    /// Show the unaligned residues before and after the alignment
+    pub context: bool,
+    /// Show a ruler with the positions on B
+    pub b_ruler: bool,
+    /// Colour the columns on their local score, not used for chained alignments
+    pub heatmap: bool,
+    /// Show the mass differences of the steps, not used for chained alignments
+    pub mass_deltas: Option<(MassDeltaUnit, MassMode)>,
+    pub highlight: Option<Highlight>,
+    /// The layout of a pairwise alignment, not used for chained alignments
+    pub layout: Layout,
+    /// Only show the differences, not used for chained alignments
+    pub diff: bool,
+}
+
 pub fn show_annotated_mass_alignment<
     A: AtMax<Linear>,
     B: AtMax<Linear>,
@@ -31,163 +129,305 @@ pub fn show_annotated_mass_alignment<
 >(
     alignment: &Alignment<'_, A, B>,
     imgt: Option<&Annotated>,
+    marks: Option<StepMarks>,
     only_display_a: bool,
     omit_headers: bool,
     line_names: (
         impl Into<String> + Display + Clone,
         impl Into<String> + Display + Clone,
     ),
-    args: &Cli,
+    options: &RenderOptions,
 ) {
+    print!(
+        "{}",
+        format_annotated_mass_alignment(
+            alignment,
+            imgt,
+            marks,
+            only_display_a,
+            omit_headers,
+            line_names,
+            options
+        )
+    );
+}
+
+/// Render the alignment (with header if `only_display_a` is false) to a string
+pub fn format_annotated_mass_alignment<
+    A: AtMax<Linear>,
+    B: AtMax<Linear>,
+    Annotated: AnnotatedPeptide,
+>(
+    alignment: &Alignment<'_, A, B>,
+    imgt: Option<&Annotated>,
+    marks: Option<StepMarks>,
+    only_display_a: bool,
+    omit_headers: bool,
+    line_names: (
+        impl Into<String> + Display + Clone,
+        impl Into<String> + Display + Clone,
+    ),
+    options: &RenderOptions,
+) -> String {
+    let mut output = String::new();
     if !only_display_a {
-        show_alignment_header(
+        output += &format_alignment_header(
             alignment,
-            args.tolerance,
             line_names.clone(),
             None,
-            args.full_number,
+            imgt.map(|i| i.regions()),
+            options,
         );
     }
-    let mut writer =
-        CombinedLines::new(args.line_width, only_display_a, omit_headers, line_names.1);
+    if options.diff && !only_display_a {
+        output += &format_diff(alignment);
+        return output;
+    }
+    if options.layout == Layout::SideBySide && !only_display_a {
+        output += &format_side_by_side(alignment, line_names, options.heatmap);
+        return output;
+    }
+    let mut writer = CombinedLines::new(
+        options.line_width,
+        only_display_a,
+        omit_headers,
+        options.b_ruler,
+        line_names.1,
+    );
     show_alignment_inner(
         &mut writer,
         alignment,
         imgt,
-        args.context,
-        None,
-        false,
+        marks,
         String::new(),
-        line_names.0.into(),
+        InnerOptions {
+            numbering: None,
+            context: options.context,
+            start_context_override: None,
+            room_on_end: false,
+            a_name: line_names.0.into(),
+            deltas: options.mass_deltas,
+            highlight: options
+                .highlight
+                .as_ref()
+                .map_or_else(Default::default, |highlight| {
+                    (
+                        highlight.residues(alignment.seq_a(), Some(0)),
+                        highlight.residues(alignment.seq_b(), Some(0)),
+                    )
+                }),
+            b_offset: 0,
+            heatmap: options.heatmap,
+        },
     );
-    writer.flush();
+    output += &writer.finish();
+    output
 }
 
 pub fn show_chained_annotated_mass_alignment<A: AtMax<Linear>, B: AtMax<Linear>>(
     alignments: &[(Allele, Alignment<'_, A, B>)],
-    tolerance: Tolerance<Mass>,
-    line_width: usize,
-    context: bool,
-    full_number: bool,
+    numbering: Option<&[Option<String>]>,
     generate_annotation: bool,
+    options: &RenderOptions,
 ) {
+    print!(
+        "{}",
+        format_chained_annotated_mass_alignment(alignments, numbering, options)
+    );
+    if generate_annotation {
+        println!(
+            "REGIONS={}",
+            generate_regions(alignments)
+                .iter()
+                .map(|(r, l)| format!("{r}:{l}"))
+                .join(";")
+        );
+        if let Some(numbering) = numbering {
+            println!(
+                "NUMBERING={}",
+                numbering
+                    .iter()
+                    .map(|n| n.as_deref().unwrap_or("-"))
+                    .join(";")
+            );
+        }
+    }
+}
+
+/// Render a chained alignment (for example V-J-C) to a string
+pub fn format_chained_annotated_mass_alignment<A: AtMax<Linear>, B: AtMax<Linear>>(
+    alignments: &[(Allele, Alignment<'_, A, B>)],
+    numbering: Option<&[Option<String>]>,
+    options: &RenderOptions,
+) -> String {
+    let mut output = String::new();
     let mut start = 0;
     for alignment in alignments {
-        println!(
+        writeln!(
+            &mut output,
             "{}",
             format!("{} / {}", alignment.0.name(), alignment.0.fancy_name(),).purple(),
-        );
-        show_alignment_header(
+        )
+        .unwrap();
+        output += &format_alignment_header(
             &alignment.1,
-            tolerance,
             (alignment.0.name(), "Query"),
             Some(start),
-            full_number,
+            Some(alignment.0.regions),
+            options,
         );
         start += alignment.1.len_b() + alignment.1.start_b();
     }
 
-    let mut writer = CombinedLines::new(line_width, false, false, "Query");
+    let mut writer = CombinedLines::new(options.line_width, false, false, options.b_ruler, "Query");
     let mut number_tail = String::new();
     let mut last_context = None;
+    let mut offset = 0;
     for (index, alignment) in alignments.iter().enumerate() {
         (number_tail, last_context) = show_alignment_inner(
             &mut writer,
             &alignment.1,
             Some(&alignment.0),
-            index == alignments.len() - 1 && context,
-            last_context, // Original overwrite J with CDR3
-            index != alignments.len() - 1,
+            None,
             number_tail,
-            alignment.0.name(),
+            InnerOptions {
+                numbering: numbering.map(|n| &n[offset.min(n.len())..]),
+                context: index == alignments.len() - 1 && options.context,
+                start_context_override: last_context, // Original overwrite J with CDR3
+                room_on_end: index != alignments.len() - 1,
+                a_name: alignment.0.name(),
+                deltas: None,
+                highlight: options
+                    .highlight
+                    .as_ref()
+                    .map_or_else(Default::default, |highlight| {
+                        (
+                            highlight.residues(alignment.1.seq_a(), None),
+                            highlight.residues(alignment.1.seq_b(), Some(offset)),
+                        )
+                    }),
+                b_offset: offset,
+                heatmap: false,
+            },
         );
+        offset += alignment.1.len_b() + alignment.1.start_b();
     }
-    writer.flush();
+    output += &writer.finish();
 
-    if generate_annotation {
-        // Show annotation and regions for fasta
-        // let mut annotations = Vec::new();
-        let mut regions = Vec::new();
-        let mut a_regions = alignments
-            .iter()
-            .map(|(a, al)| (a, (al.start_b() != 0).then_some((None, al.start_a()))))
-            .flat_map(|(a, start)| {
-                start
-                    .into_iter()
-                    .chain(a.regions.iter().map(|(r, l)| (Some(r.clone()), *l)))
-            })
-            .collect_vec(); // TODO: this misses unmatched regions between alignments
-        a_regions.reverse();
+    output
+}
 
-        let mut len_a = 0;
-        let mut len_b = 0;
-        let mut last_region = None;
-        for path in alignments
-            .iter()
-            .map(|(_, al)| {
-                (
-                    al,
-                    (al.start_b() != 0).then_some(Piece {
-                        score: 0,
-                        local_score: 0,
-                        match_type: MatchType::FullIdentity,
-                        step_a: al.start_a() as u16,
-                        step_b: al.start_b() as u16,
-                    }),
-                )
-            })
-            .flat_map(|(al, a)| a.into_iter().chain(al.path().iter().cloned()))
-        {
-            len_a += path.step_a as usize;
-            len_b += path.step_b as usize;
-            if let Some((r, l)) = a_regions.last().cloned() {
-                if l <= len_a {
-                    let region = r
-                        .clone()
-                        .or(last_region)
-                        .unwrap_or(Region::Other("Unknown".to_string()));
-                    if regions.last().is_some_and(|(r, _)| *r == region) {
-                        regions.last_mut().unwrap().1 += len_b;
-                    } else {
-                        regions.push((region, len_b));
-                    }
-                    last_region = r.clone();
-                    a_regions.pop();
-                    len_a -= l;
-                    len_b = 0;
+/// Generate the regions annotation for the query based on a chained alignment, every region is
+/// given with the number of query residues in that region.
+pub fn generate_regions<A, B>(
+    alignments: &[(Allele, Alignment<'_, A, B>)],
+) -> Vec<(Region, usize)> {
+    let mut regions = Vec::new();
+    let mut a_regions = alignments
+        .iter()
+        .map(|(a, al)| (a, (al.start_b() != 0).then_some((None, al.start_a()))))
+        .flat_map(|(a, start)| {
+            start
+                .into_iter()
+                .chain(a.regions.iter().map(|(r, l)| (Some(r.clone()), *l)))
+        })
+        .collect_vec(); // TODO: this misses unmatched regions between alignments
+    a_regions.reverse();
+
+    let mut len_a = 0;
+    let mut len_b = 0;
+    let mut last_region = None;
+    for path in alignments
+        .iter()
+        .map(|(_, al)| {
+            (
+                al,
+                (al.start_b() != 0).then_some(Piece {
+                    score: 0,
+                    local_score: 0,
+                    match_type: MatchType::FullIdentity,
+                    step_a: al.start_a() as u16,
+                    step_b: al.start_b() as u16,
+                }),
+            )
+        })
+        .flat_map(|(al, a)| a.into_iter().chain(al.path().iter().cloned()))
+    {
+        len_a += path.step_a as usize;
+        len_b += path.step_b as usize;
+        if let Some((r, l)) = a_regions.last().cloned() {
+            if l <= len_a {
+                let region = r
+                    .clone()
+                    .or(last_region)
+                    .unwrap_or(Region::Other("Unknown".to_string()));
+                if regions.last().is_some_and(|(r, _)| *r == region) {
+                    regions.last_mut().unwrap().1 += len_b;
+                } else {
+                    regions.push((region, len_b));
                 }
+                last_region = r.clone();
+                a_regions.pop();
+                len_a -= l;
+                len_b = 0;
             }
         }
-        // Map the remaining piece to the last element
-        if let Some((r, _)) = a_regions.last().cloned() {
-            let region = r
-                .clone()
-                .or(last_region)
-                .unwrap_or(Region::Other("Unknown".to_string()));
-            if regions.last().is_some_and(|(r, _)| *r == region) {
-                regions.last_mut().unwrap().1 += len_b;
-            } else {
-                regions.push((region, len_b));
-            }
+    }
+    // Map the remaining piece to the last element
+    if let Some((r, _)) = a_regions.last().cloned() {
+        let region = r
+            .clone()
+            .or(last_region)
+            .unwrap_or(Region::Other("Unknown".to_string()));
+        if regions.last().is_some_and(|(r, _)| *r == region) {
+            regions.last_mut().unwrap().1 += len_b;
+        } else {
+            regions.push((region, len_b));
         }
-
-        println!(
-            "REGIONS={}",
-            regions.iter().map(|(r, l)| format!("{r}:{l}")).join(";")
-        );
     }
+    regions
 }
 
-fn show_alignment_inner<A, B, Annotated: AnnotatedPeptide>(
-    writer: &mut CombinedLines,
-    alignment: &Alignment<'_, A, B>,
-    imgt: Option<&Annotated>,
+/// The settings for writing one alignment with [`show_alignment_inner`], for a chained alignment
+/// these differ for every alignment in the chain
+struct InnerOptions<'a> {
+    /// The numbering of the query, starting at this alignment
+    numbering: Option<&'a [Option<String>]>,
     context: bool,
+    /// The region of the previous alignment in the chain, used as region of the start context
     start_context_override: Option<Region>,
+    /// If another alignment follows, so region names do not have to be compressed at the end
     room_on_end: bool,
-    number_tail: String,
     a_name: String,
+    deltas: Option<(MassDeltaUnit, MassMode)>,
+    /// The highlighted residues on A and B
+    highlight: (HashSet<usize>, HashSet<usize>),
+    /// The position of the start of B in the full query
+    b_offset: usize,
+    heatmap: bool,
+}
+
+/// Write the columns of an alignment, the number tail and region of the end of the alignment are
+/// returned so that a chained alignment can continue from there
+fn show_alignment_inner<A: AtMax<Linear>, B: AtMax<Linear>, Annotated: AnnotatedPeptide>(
+    writer: &mut CombinedLines,
+    alignment: &Alignment<'_, A, B>,
+    imgt: Option<&Annotated>,
+    marks: Option<StepMarks>,
+    number_tail: String,
+    options: InnerOptions,
 ) -> (String, Option<Region>) {
+    let InnerOptions {
+        numbering,
+        context,
+        start_context_override,
+        room_on_end,
+        a_name,
+        deltas,
+        highlight,
+        b_offset,
+        heatmap,
+    } = options;
     let (mut a, mut b) = alignment.start();
     let a_glycan = find_possible_n_glycan_locations(alignment.seq_a());
     let b_glycan = find_possible_n_glycan_locations(alignment.seq_b());
@@ -196,6 +436,7 @@ fn show_alignment_inner<A, B, Annotated: AnnotatedPeptide>(
     let mut number_tail = number_tail;
     let mut is_number = false;
     let mut last_region = start_context_override.as_ref();
+    let heat = heatmap.then(|| score_range(alignment));
 
     let mut header = |a: usize,
                       len: usize,
@@ -231,7 +472,10 @@ fn show_alignment_inner<A, B, Annotated: AnnotatedPeptide>(
                 is_number = false;
             }
         }
-        if (a + number_shift_back) % NUMBER_GAP == 0 && number_tail.is_empty() {
+        if numbering.is_none()
+            && (a + number_shift_back).is_multiple_of(NUMBER_GAP)
+            && number_tail.is_empty()
+        {
             number_tail = (a + number_shift_back).to_string();
             number_tail = format!(
                 "{}{number_tail}",
@@ -269,6 +513,7 @@ fn show_alignment_inner<A, B, Annotated: AnnotatedPeptide>(
                 .as_ref()
                 .map(|_| Styling::none())
                 .unwrap_or(Styling::with_style(Styles::Dimmed));
+            ruler_label(numbering, b_index, &mut number_tail);
 
             writer.add_column(
                 start_context_override
@@ -281,45 +526,39 @@ fn show_alignment_inner<A, B, Annotated: AnnotatedPeptide>(
                 (number_tail.pop().unwrap_or(' '), base_style.clone()),
                 (
                     a_index.map_or(' ', |a| alignment.seq_a().sequence()[a].aminoacid.char()),
-                    base_style.clone().maybe_style(a_index.and_then(|a| {
-                        alignment.seq_a().sequence()[a..a + 1]
-                            .iter()
-                            .any(|a| !a.modifications.is_empty())
-                            .then_some(Styles::Underline)
-                    })),
+                    highlighted(
+                        base_style.clone().maybe_style(a_index.and_then(|a| {
+                            alignment.seq_a().sequence()[a..a + 1]
+                                .iter()
+                                .any(|a| !a.modifications.is_empty())
+                                .then_some(Styles::Underline)
+                        })),
+                        &highlight.0,
+                        a_index.map_or(0..0, |a| a..a + 1),
+                    ),
                 ),
                 (
                     b_index.map_or(' ', |b| alignment.seq_b().sequence()[b].aminoacid.char()),
-                    base_style.clone().maybe_style(b_index.and_then(|b| {
-                        alignment.seq_b().sequence()[b..b + 1]
-                            .iter()
-                            .any(|a| !a.modifications.is_empty())
-                            .then_some(Styles::Underline)
-                    })),
+                    highlighted(
+                        base_style.clone().maybe_style(b_index.and_then(|b| {
+                            alignment.seq_b().sequence()[b..b + 1]
+                                .iter()
+                                .any(|a| !a.modifications.is_empty())
+                                .then_some(Styles::Underline)
+                        })),
+                        &highlight.1,
+                        b_index.map_or(0..0, |b| b..b + 1),
+                    ),
                 ),
                 ' ',
+                b_index.map(|b| b + b_offset),
             );
         }
     }
     // Actual alignment / middle
     for (index, step) in alignment.path().iter().enumerate() {
-        let ty = match (step.match_type, step.step_a, step.step_b) {
-            (MatchType::Isobaric, _, _) => StepType::Special, // Catch any 1/1 isobaric sets before they are counted as Match/Mismatch
-            (MatchType::FullIdentity, _, _) => StepType::Match,
-            (MatchType::IdentityMassMismatch, _, _) => StepType::MassMismatch,
-            (MatchType::Mismatch, _, _) => StepType::Mismatch,
-            (_, 0, 1) => StepType::Insertion,
-            (_, 1, 0) => StepType::Deletion,
-            _ => StepType::Special,
-        };
-        let (colour, ch) = match ty {
-            StepType::Insertion => (Some(Color::Yellow), "+"),
-            StepType::Deletion => (Some(Color::Yellow), "+"),
-            StepType::Match => (None, " "),
-            StepType::MassMismatch => (Some(Color::Red), "m"),
-            StepType::Mismatch => (Some(Color::Red), "⨯"),
-            StepType::Special => (Some(Color::Yellow), "-"), // ⇤⇥ ⤚---⤙ ├─┤ ║ ⤚⤙ l╴r╶
-        };
+        let ty = StepType::new(step);
+        let (mut colour, ch) = ty.marker();
 
         let region = imgt.and_then(|imgt| imgt.get_region(a + step.step_a as usize));
         let len = step.step_a.max(step.step_b) as usize;
@@ -377,7 +616,40 @@ fn show_alignment_inner<A, B, Annotated: AnnotatedPeptide>(
         }
         .chars()
         .collect::<Vec<_>>();
-        let bottom = if ty == StepType::Special {
+        // Colour every step by its confidence if requested, unreliable steps are marked with an exclamation mark
+        let confidence = match marks {
+            Some(StepMarks::Confidence(confidence)) => confidence.get(index).copied(),
+            _ => None,
+        };
+        let allele = match marks {
+            Some(StepMarks::Alleles(alleles)) => alleles.get(&index).copied(),
+            _ => None,
+        };
+        if let Some(supported) = allele {
+            colour = Some(if supported { Color::Green } else { Color::Red });
+        }
+        if let Some(confidence) = confidence {
+            colour = Some(if confidence >= HIGH_CONFIDENCE {
+                Color::Green
+            } else if confidence >= LOW_CONFIDENCE {
+                Color::Yellow
+            } else {
+                Color::Red
+            });
+        }
+        if let Some(range) = heat {
+            colour = Some(heat_colour(column_score(step), range));
+        }
+        // Steps that differ between co-optimal alignments are marked with a question mark
+        let bottom = if matches!(marks, Some(StepMarks::Ambiguous(a)) if a.contains(&index))
+            && ty != StepType::Special
+        {
+            "?".repeat(len)
+        } else if confidence.is_some_and(|c| c < LOW_CONFIDENCE) && ty != StepType::Special {
+            "!".repeat(len)
+        } else if allele.is_some() {
+            "*".repeat(len)
+        } else if ty == StepType::Special {
             match len {
                 1 => "─".to_string(),
                 2 => "╶╴".to_string(),
@@ -389,8 +661,24 @@ fn show_alignment_inner<A, B, Annotated: AnnotatedPeptide>(
         .chars()
         .collect::<Vec<_>>();
 
+        if let Some((unit, mass_mode)) =
+            deltas.filter(|_| matches!(ty, StepType::Special | StepType::MassMismatch))
+        {
+            writer.add_delta(
+                &mass_delta(alignment, (a, b), step, unit, mass_mode),
+                colour,
+            );
+        }
+
         // Now write to the buffers one character at a time
         for s in 0..len {
+            if ruler_label(
+                numbering,
+                (s < step.step_b as usize).then_some(b + s),
+                &mut number_tail,
+            ) {
+                is_number = true;
+            }
             writer.add_column(
                 &a_name,
                 region.and_then(|r| r.0.fg_color()),
@@ -402,38 +690,47 @@ fn show_alignment_inner<A, B, Annotated: AnnotatedPeptide>(
                 ),
                 (
                     a_str[s],
-                    Styling::none()
-                        .fg(imgt.and_then(|imgt| {
-                            imgt.get_annotations(a).next().and_then(|a| a.fg_color())
-                        }))
-                        .or_fg(
-                            a_glycan
-                                .contains(&a)
-                                .then_some(Annotation::NGlycan)
-                                .and_then(|a| a.fg_color()),
-                        )
-                        .maybe_style(
-                            (alignment.seq_a()[a..a + step.step_a as usize]
-                                .iter()
-                                .any(|a| !a.modifications.is_empty()))
-                            .then_some(Styles::Underline),
-                        ),
+                    highlighted(
+                        Styling::none()
+                            .fg(imgt.and_then(|imgt| {
+                                imgt.get_annotations(a).next().and_then(|a| a.fg_color())
+                            }))
+                            .or_fg(
+                                a_glycan
+                                    .contains(&a)
+                                    .then_some(Annotation::NGlycan)
+                                    .and_then(|a| a.fg_color()),
+                            )
+                            .maybe_style(
+                                (alignment.seq_a()[a..a + step.step_a as usize]
+                                    .iter()
+                                    .any(|a| !a.modifications.is_empty()))
+                                .then_some(Styles::Underline),
+                            ),
+                        &highlight.0,
+                        a..a + step.step_a as usize,
+                    ),
                 ),
                 (
                     b_str[s],
-                    Styling::none()
-                        .fg(b_glycan
-                            .contains(&b)
-                            .then_some(Annotation::NGlycan)
-                            .and_then(|a| a.fg_color()))
-                        .maybe_style(
-                            (alignment.seq_b()[b..b + step.step_b as usize]
-                                .iter()
-                                .any(|a| !a.modifications.is_empty()))
-                            .then_some(Styles::Underline),
-                        ),
+                    highlighted(
+                        Styling::none()
+                            .fg(b_glycan
+                                .contains(&b)
+                                .then_some(Annotation::NGlycan)
+                                .and_then(|a| a.fg_color()))
+                            .maybe_style(
+                                (alignment.seq_b()[b..b + step.step_b as usize]
+                                    .iter()
+                                    .any(|a| !a.modifications.is_empty()))
+                                .then_some(Styles::Underline),
+                            ),
+                        &highlight.1,
+                        b..b + step.step_b as usize,
+                    ),
                 ),
                 bottom[s],
+                (s < step.step_b as usize).then_some(b + s + b_offset),
             )
         }
         a += step.step_a as usize;
@@ -457,6 +754,7 @@ fn show_alignment_inner<A, B, Annotated: AnnotatedPeptide>(
                 room_on_end,
                 false,
             );
+            ruler_label(numbering, b_index, &mut number_tail);
 
             writer.add_column(
                 &a_name,
@@ -469,45 +767,356 @@ fn show_alignment_inner<A, B, Annotated: AnnotatedPeptide>(
                 ),
                 (
                     a_index.map_or(' ', |a| alignment.seq_a()[a].aminoacid.char()),
-                    Styling::with_style(Styles::Dimmed).maybe_style(a_index.and_then(|a| {
-                        alignment.seq_a()[a..a + 1]
-                            .iter()
-                            .any(|a| !a.modifications.is_empty())
-                            .then_some(Styles::Underline)
-                    })),
+                    highlighted(
+                        Styling::with_style(Styles::Dimmed).maybe_style(a_index.and_then(|a| {
+                            alignment.seq_a()[a..a + 1]
+                                .iter()
+                                .any(|a| !a.modifications.is_empty())
+                                .then_some(Styles::Underline)
+                        })),
+                        &highlight.0,
+                        a_index.map_or(0..0, |a| a..a + 1),
+                    ),
                 ),
                 (
                     b_index.map_or(' ', |b| alignment.seq_b()[b].aminoacid.char()),
-                    Styling::with_style(Styles::Dimmed).maybe_style(b_index.and_then(|b| {
-                        alignment.seq_b()[b..b + 1]
-                            .iter()
-                            .any(|a| !a.modifications.is_empty())
-                            .then_some(Styles::Underline)
-                    })),
+                    highlighted(
+                        Styling::with_style(Styles::Dimmed).maybe_style(b_index.and_then(|b| {
+                            alignment.seq_b()[b..b + 1]
+                                .iter()
+                                .any(|a| !a.modifications.is_empty())
+                                .then_some(Styles::Underline)
+                        })),
+                        &highlight.1,
+                        b_index.map_or(0..0, |b| b..b + 1),
+                    ),
                 ),
                 ' ',
+                b_index.map(|b| b + b_offset),
             );
         }
     }
     (number_tail, last_region.cloned())
 }
 
+/// Render the alignment with A and B in two columns and the marker of the step in between, with one step per row
+/// and the position of the first residue of the step in A and B on both sides
+fn format_side_by_side<A, B>(
+    alignment: &Alignment<'_, A, B>,
+    names: (impl Display, impl Display),
+    heatmap: bool,
+) -> String {
+    fn residues<C>(sequence: &[SequenceElement<C>], start: usize, len: u16) -> (String, String) {
+        if len == 0 {
+            (String::new(), "-".to_string())
+        } else {
+            (
+                (start + 1).to_string(),
+                sequence[start..start + len as usize]
+                    .iter()
+                    .map(|s| s.aminoacid.char())
+                    .collect(),
+            )
+        }
+    }
+    let (mut a, mut b) = alignment.start();
+    let mut rows = Vec::with_capacity(alignment.path().len());
+    for step in alignment.path() {
+        let (position_a, residues_a) = residues(alignment.seq_a().sequence(), a, step.step_a);
+        let (position_b, residues_b) = residues(alignment.seq_b().sequence(), b, step.step_b);
+        rows.push((
+            position_a,
+            residues_a,
+            StepType::new(step),
+            residues_b,
+            position_b,
+            column_score(step),
+        ));
+        a += step.step_a as usize;
+        b += step.step_b as usize;
+    }
+    let names = (names.0.to_string(), names.1.to_string());
+    let heat = heatmap.then(|| score_range(alignment));
+    let position_width = rows.iter().map(|r| r.0.len()).max().unwrap_or_default();
+    let residues_width = rows
+        .iter()
+        .map(|r| r.1.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max(names.0.chars().count());
+    let mut output = format!(
+        "{:position_width$} {:residues_width$}   {}\n",
+        "",
+        names.0.dimmed(),
+        names.1.dimmed()
+    );
+    for (position_a, residues_a, ty, residues_b, position_b, score) in &rows {
+        let (colour, marker) = match ty.marker() {
+            (_, " ") => (None, "│"),
+            other => other,
+        };
+        let colour = heat.map_or(colour, |range| Some(heat_colour(*score, range)));
+        writeln!(
+            &mut output,
+            "{} {} {} {} {}",
+            format!("{position_a:>position_width$}").dimmed(),
+            format!("{residues_a:residues_width$}").color_e(colour),
+            marker.color_e(colour),
+            residues_b.color_e(colour),
+            position_b.dimmed()
+        )
+        .unwrap();
+    }
+    output.push('\n');
+    output
+}
+
+/// Render only the differences of the alignment like a unified diff, every run of consecutive differing steps is
+/// given as a hunk with its ranges in A and B (1 based start and length) and the residues of A and B
+fn format_diff<A, B>(alignment: &Alignment<'_, A, B>) -> String {
+    fn residues<C>(
+        sign: char,
+        sequence: &[SequenceElement<C>],
+        range: Range<usize>,
+        colour: Color,
+        output: &mut String,
+    ) {
+        if !range.is_empty() {
+            let residues = sequence[range].iter().map(|s| s.aminoacid.char());
+            writeln!(
+                output,
+                "{}",
+                format!("{sign}{}", residues.collect::<String>()).color(colour)
+            )
+            .unwrap();
+        }
+    }
+    let range = |start: usize, len: usize| match len {
+        0 => format!("{start},0"),
+        1 => (start + 1).to_string(),
+        len => format!("{},{len}", start + 1),
+    };
+    let hunks = alignment
+        .path()
+        .iter()
+        .scan(alignment.start(), |(a, b), step| {
+            let start = (*a, *b);
+            *a += step.step_a as usize;
+            *b += step.step_b as usize;
+            Some((start, step))
+        })
+        .chunk_by(|(_, step)| StepType::new(step) == StepType::Match);
+    let mut output = String::new();
+    for (_, hunk) in hunks.into_iter().filter(|(matching, _)| !matching) {
+        let hunk = hunk.collect_vec();
+        let (a, b) = hunk[0].0;
+        let len_a = hunk.iter().map(|(_, s)| s.step_a as usize).sum::<usize>();
+        let len_b = hunk.iter().map(|(_, s)| s.step_b as usize).sum::<usize>();
+        let kinds = hunk
+            .iter()
+            .map(|(_, step)| match (StepType::new(step), step.match_type) {
+                (StepType::Insertion, _) => "insertion",
+                (StepType::Deletion, _) => "deletion",
+                (StepType::Mismatch, _) => "mismatch",
+                (StepType::MassMismatch, _) => "mass mismatch",
+                (_, MatchType::Rotation) => "rotation",
+                _ => "isobaric",
+            })
+            .unique()
+            .join(", ");
+        writeln!(
+            &mut output,
+            "{} {}",
+            format!("@@ -{} +{} @@", range(a, len_a), range(b, len_b)).cyan(),
+            kinds.dimmed()
+        )
+        .unwrap();
+        residues(
+            '-',
+            alignment.seq_a().sequence(),
+            a..a + len_a,
+            Color::Red,
+            &mut output,
+        );
+        residues(
+            '+',
+            alignment.seq_b().sequence(),
+            b..b + len_b,
+            Color::Green,
+            &mut output,
+        );
+    }
+    if output.is_empty() {
+        output = format!("{}\n", "No differences".green());
+    }
+    output.push('\n');
+    output
+}
+
+/// The local score of a step divided over all columns of the step, see `--heatmap`
+fn column_score(step: &Piece) -> f64 {
+    step.local_score as f64 / f64::from(step.step_a.max(step.step_b).max(1))
+}
+
+/// The lowest and highest column score in the path of the alignment
+fn score_range<A, B>(alignment: &Alignment<'_, A, B>) -> (f64, f64) {
+    alignment
+        .path()
+        .iter()
+        .map(column_score)
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), score| {
+            (min.min(score), max.max(score))
+        })
+}
+
+/// The colour of a column score on a gradient from red (the lowest score of the alignment) via yellow to green (the
+/// highest score)
+fn heat_colour(score: f64, (min, max): (f64, f64)) -> Color {
+    let fraction = if max > min {
+        (score - min) / (max - min)
+    } else {
+        1.0
+    };
+    Color::TrueColor {
+        r: (510.0 * (1.0 - fraction)).min(255.0) as u8,
+        g: (510.0 * fraction).min(255.0) as u8,
+        b: 0,
+    }
+}
+
+/// Highlight the residue(s) if any of them is in the set, see `--highlight`
+fn highlighted(styling: Styling, highlight: &HashSet<usize>, residues: Range<usize>) -> Styling {
+    if residues.into_iter().any(|i| highlight.contains(&i)) {
+        styling.fg(Some(Color::Magenta)).style(Styles::Bold)
+    } else {
+        styling
+    }
+}
+
+/// The local mass difference (B - A) of a single step, in milli Dalton or in ppm relative to the
+/// mass of A. Terminal modifications are not included.
+fn mass_delta<A: AtMax<Linear>, B: AtMax<Linear>>(
+    alignment: &Alignment<'_, A, B>,
+    (a, b): (usize, usize),
+    step: &Piece,
+    unit: MassDeltaUnit,
+    mass_mode: MassMode,
+) -> String {
+    let mass_a = alignment
+        .seq_a()
+        .sub_peptide(a..a + step.step_a as usize)
+        .bare_formulas()
+        .iter()
+        .next()
+        .map(|f| f.mass(mass_mode))
+        .unwrap_or_default();
+    let mass_b = alignment
+        .seq_b()
+        .sub_peptide(b..b + step.step_b as usize)
+        .bare_formulas()
+        .iter()
+        .next()
+        .map(|f| f.mass(mass_mode))
+        .unwrap_or_default();
+    let (value, unit) = match unit {
+        MassDeltaUnit::MilliDalton => ((mass_b - mass_a).value * 1e3, "mDa"),
+        MassDeltaUnit::Ppm => ((mass_b - mass_a).value / mass_a.value * 1e6, "ppm"),
+    };
+    // Do not show rounding errors of isobaric steps as -0.0
+    let value = if (value * 10.0).round() == 0.0 {
+        0.0
+    } else {
+        value
+    };
+    format!("{value:+.1}{unit}")
+}
+
+/// If there is no number or region name being written, start writing the scheme number of the
+/// residue at b if it is a multiple of ten or the first insertion on a position. Returns true if
+/// a number was started.
+fn ruler_label(
+    numbering: Option<&[Option<String>]>,
+    b: Option<usize>,
+    number_tail: &mut String,
+) -> bool {
+    let Some(label) = numbering
+        .zip(b)
+        .and_then(|(numbering, b)| numbering.get(b))
+        .and_then(Option::as_ref)
+    else {
+        return false;
+    };
+    let split = label
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(label.len());
+    let (number, insertion) = label.split_at(split);
+    if !number_tail.is_empty()
+        || !(insertion.is_empty() && number.ends_with('0') || insertion == "A" || insertion == ".1")
+    {
+        return false;
+    }
+    *number_tail = format!("{label} ").chars().rev().collect();
+    true
+}
+
 pub fn show_alignment_header<A: AtMax<Linear>, B: AtMax<Linear>>(
     alignment: &Alignment<'_, A, B>,
-    tolerance: Tolerance<Mass>,
     names: (impl Display, impl Display),
     additional_b_start: Option<usize>,
-    full_number: bool,
+    regions: Option<&[(Region, usize)]>,
+    options: &RenderOptions,
 ) {
-    let precision = if full_number {
+    print!(
+        "{}",
+        format_alignment_header(alignment, names, additional_b_start, regions, options)
+    );
+}
+
+/// Render the header with all statistics of an alignment to a string
+pub fn format_alignment_header<A: AtMax<Linear>, B: AtMax<Linear>>(
+    alignment: &Alignment<'_, A, B>,
+    names: (impl Display, impl Display),
+    additional_b_start: Option<usize>,
+    regions: Option<&[(Region, usize)]>,
+    options: &RenderOptions,
+) -> String {
+    let (tolerance, auto_kind, conventions) =
+        (options.tolerance, options.auto_kind, &options.stats);
+    let precision = if options.full_number {
         None
     } else {
         Some(NUMBER_PRECISION)
     };
-    let stats = alignment.stats();
+    let stats = stats(alignment, conventions);
     let score = alignment.score();
-    println!(
-        "Identity: {} {}, Mass similarity: {} {}, Similarity: {} {}, Gaps: {} {}, Score: {} {}, {}\nStart: {} {} {} {}, Path: {}\n{}\n",
+    let regions = regions
+        .filter(|_| conventions.region_identity)
+        .map(|regions| {
+            let regions = region_stats(alignment, regions, conventions);
+            let line = |name: &str, value: fn(&Stats) -> (f64, usize)| {
+                format!(
+                    "{name}: {}\n",
+                    regions
+                        .iter()
+                        .map(|(region, stats)| {
+                            let (fraction, count) = value(stats);
+                            format!(
+                                "{region} {} {}",
+                                display_with_precision(fraction, precision).blue(),
+                                format!("({count}/{})", stats.length).dimmed()
+                            )
+                        })
+                        .join(", ")
+                )
+            };
+            line("Region identity", |s| (s.identity(), s.identical))
+                + &line("Region mass similarity", |s| {
+                    (s.mass_similarity(), s.mass_similar)
+                })
+        })
+        .unwrap_or_default();
+    format!(
+        "Identity: {} {}, Mass similarity: {} {}, Similarity: {} {}, Gaps: {} {}, Score: {} {}, {}\n{regions}Start: {} {} {} {}, Path: {}\n{}\n\n",
         display_with_precision(stats.identity(), precision).bright_blue(),
         format!("({}/{})", stats.identical, stats.length).dimmed(),
         display_with_precision(stats.mass_similarity(), precision).blue(),
@@ -534,22 +1143,38 @@ pub fn show_alignment_header<A: AtMax<Linear>, B: AtMax<Linear>>(
         (additional_b_start.unwrap_or_default() + alignment.start_b()).to_string().magenta(),
         alignment.short().dimmed(),
         {
+            let tolerance = match tolerance {
+                MassTolerance::Single(tolerance) => tolerance.to_string(),
+                MassTolerance::Hybrid(..) => format!("{} (from {tolerance})", tolerance.for_alignment()),
+            };
             format!("Tolerance: {tolerance}, Alignment: {} ({}), Maximal isobaric step: {}",
             alignment.align_type().description(),
             alignment.align_type().symbol(),
-            alignment.max_step()).dimmed()
+            alignment.max_step()).dimmed().to_string() + &if auto_kind { " (auto)".dimmed().to_string() } else { String::new() }
         },
-    );
+    )
 }
 
-struct CombinedLines {
+/// Writes the columns of an alignment as lines of at most `line_width` columns
+pub(crate) struct CombinedLines {
+    output: String,
     numbers: String,
+    numbers_line: bool,
     a: String,
     a_content: bool,
     b: String,
     b_content: bool,
+    c: String,
+    c_content: bool,
+    c_name: Option<String>,
     marker: String,
     marker_content: bool,
+    b_numbers: Option<String>,
+    b_number_tail: String,
+    deltas: String,
+    deltas_content: bool,
+    delta_tail: String,
+    delta_colour: Option<Color>,
     chars: usize,
     lines: usize,
     line_width: usize,
@@ -564,16 +1189,28 @@ impl CombinedLines {
         line_width: usize,
         only_display_a: bool,
         omit_headers: bool,
+        b_ruler: bool,
         b_name: impl Into<String>,
     ) -> Self {
         Self {
+            output: String::new(),
             numbers: String::with_capacity(line_width),
+            numbers_line: true,
             a: String::with_capacity(line_width),
             a_content: false,
             b: String::with_capacity(line_width),
             b_content: false,
+            c: String::new(),
+            c_content: false,
+            c_name: None,
             marker: String::with_capacity(line_width),
             marker_content: false,
+            b_numbers: b_ruler.then(|| String::with_capacity(line_width)),
+            b_number_tail: String::new(),
+            deltas: String::with_capacity(line_width),
+            deltas_content: false,
+            delta_tail: String::new(),
+            delta_colour: None,
             chars: 0,
             lines: 0,
             line_width,
@@ -584,6 +1221,52 @@ impl CombinedLines {
         }
     }
 
+    /// A writer for three stacked sequences (A, B, and C) and a marker line, without position
+    /// numbers, see [`Self::add_stacked_column`]
+    pub(crate) fn stacked(
+        line_width: usize,
+        b_name: impl Into<String>,
+        c_name: impl Into<String>,
+    ) -> Self {
+        Self {
+            numbers_line: false,
+            c: String::with_capacity(line_width),
+            c_name: Some(c_name.into()),
+            ..Self::new(line_width, false, false, false, b_name)
+        }
+    }
+
+    /// Add a column to a [`Self::stacked`] writer, with the residues of A, B, and C and a marker
+    pub(crate) fn add_stacked_column(
+        &mut self,
+        a_name: &str,
+        residues: [char; 3],
+        marker: char,
+        marker_colour: Option<Color>,
+    ) {
+        self.a_names.insert(a_name.to_string());
+        for ((line, content), residue) in [
+            (&mut self.a, &mut self.a_content),
+            (&mut self.b, &mut self.b_content),
+            (&mut self.c, &mut self.c_content),
+        ]
+        .into_iter()
+        .zip(residues)
+        {
+            line.push(residue);
+            *content |= !residue.is_whitespace();
+        }
+        write!(&mut self.marker, "{}", marker.color_e(marker_colour)).unwrap();
+        self.marker_content |= !marker.is_whitespace();
+        self.deltas.push(' ');
+
+        self.chars += 1;
+        if self.chars.is_multiple_of(self.line_width) {
+            self.flush()
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn add_column(
         &mut self,
         a_name: &str,
@@ -594,6 +1277,7 @@ impl CombinedLines {
         a: (char, Styling),
         b: (char, Styling),
         c: char,
+        b_index: Option<usize>,
     ) {
         // Determine the foreground colour for the a/b/marker lines
         let color_fg = region_colour.or(type_colour);
@@ -624,6 +1308,23 @@ impl CombinedLines {
         .unwrap();
         self.b_content |= !b.0.is_whitespace();
 
+        // The ruler for B starts a number on every tenth residue of B
+        if let Some(b_numbers) = &mut self.b_numbers {
+            if let Some(b) = b_index.filter(|b| self.b_number_tail.is_empty() && (b + 1) % 10 == 0)
+            {
+                self.b_number_tail = format!("{} ", b + 1).chars().rev().collect();
+            }
+            write!(
+                b_numbers,
+                "{}",
+                self.b_number_tail
+                    .pop()
+                    .unwrap_or(' ')
+                    .apply(&Styling::with_style(Styles::Dimmed))
+            )
+            .unwrap();
+        }
+
         write!(
             &mut self.marker,
             "{}",
@@ -632,17 +1333,30 @@ impl CombinedLines {
         .unwrap();
         self.marker_content |= !c.is_whitespace();
 
+        let d = self.delta_tail.pop().unwrap_or(' ');
+        write!(&mut self.deltas, "{}", d.color_e(self.delta_colour)).unwrap();
+        self.deltas_content |= !d.is_whitespace();
+
         // Flush if the maximal number of chars is reached
         self.chars += 1;
-        if self.chars % self.line_width == 0 {
+        if self.chars.is_multiple_of(self.line_width) {
             self.flush()
         }
     }
 
+    /// Start writing a mass delta label on the deltas line from the next column, the label is dropped
+    /// if the previous label is not yet fully written
+    fn add_delta(&mut self, label: &str, colour: Option<Color>) {
+        if self.delta_tail.is_empty() {
+            self.delta_tail = format!("{label} ").chars().rev().collect();
+            self.delta_colour = colour;
+        }
+    }
+
     fn flush(&mut self) {
         // Only print a line if is has content
-        if !self.omit_headers {
-            println!("{}", self.numbers);
+        if !self.omit_headers && self.numbers_line {
+            writeln!(&mut self.output, "{}", self.numbers).unwrap();
         }
         let padding = if self.lines > 0 {
             " ".repeat(self.line_width - self.chars)
@@ -650,70 +1364,168 @@ impl CombinedLines {
             String::new()
         };
         if self.a_content {
-            print!("{}", self.a,);
+            write!(&mut self.output, "{}", self.a,).unwrap();
             if self.omit_headers {
-                println!();
+                writeln!(&mut self.output).unwrap();
             } else {
-                println!("{} {}", padding, self.a_names.iter().join(" / ").dimmed(),);
+                writeln!(
+                    &mut self.output,
+                    "{} {}",
+                    padding,
+                    self.a_names.iter().join(" / ").dimmed(),
+                )
+                .unwrap();
             }
         }
         if !self.only_display_a && self.b_content {
-            print!("{}", self.b,);
+            write!(&mut self.output, "{}", self.b,).unwrap();
+            if self.omit_headers {
+                writeln!(&mut self.output).unwrap();
+            } else {
+                writeln!(&mut self.output, "{} {}", padding, self.b_name.dimmed(),).unwrap();
+            }
+        }
+        if let Some(c_name) = self.c_name.as_ref().filter(|_| self.c_content) {
+            write!(&mut self.output, "{}", self.c).unwrap();
             if self.omit_headers {
-                println!();
+                writeln!(&mut self.output).unwrap();
             } else {
-                println!("{} {}", padding, self.b_name.dimmed(),);
+                writeln!(&mut self.output, "{} {}", padding, c_name.dimmed()).unwrap();
+            }
+        }
+        if let Some(b_numbers) = self.b_numbers.as_mut().filter(|_| !self.only_display_a) {
+            if !self.omit_headers {
+                writeln!(&mut self.output, "{b_numbers}").unwrap();
             }
+            b_numbers.clear();
         }
         if !self.only_display_a && self.marker_content {
-            println!("{}", self.marker);
+            writeln!(&mut self.output, "{}", self.marker).unwrap();
+        }
+        if !self.only_display_a && self.deltas_content {
+            writeln!(&mut self.output, "{}", self.deltas).unwrap();
         }
         // Reset all internal state
         self.numbers.clear();
         self.a.clear();
         self.b.clear();
+        self.c.clear();
         self.marker.clear();
+        self.deltas.clear();
         self.a_content = false;
         self.b_content = false;
+        self.c_content = false;
         self.marker_content = false;
+        self.deltas_content = false;
         self.chars = 0;
         self.lines += 1;
         self.a_names.clear();
     }
+
+    /// Flush the last line and return the full rendered output
+    pub(crate) fn finish(mut self) -> String {
+        // Write the rest of the last mass delta label past the end of the line
+        let tail: String = self.delta_tail.drain(..).rev().collect();
+        self.deltas_content |= !tail.trim().is_empty();
+        write!(&mut self.deltas, "{}", tail.color_e(self.delta_colour)).unwrap();
+        self.flush();
+        self.output
+    }
+}
+
+pub fn table<R: AsRef<[String]>>(data: &[R], header: bool, styling: &[Styling]) {
+    print!("{}", format_table(data, header, styling));
 }
 
-pub fn table<const N: usize>(data: &[[String; N]], header: bool, styling: &[Styling; N]) {
-    let sizes = data.iter().fold([0; N], |mut sizes, row| {
-        for i in 0..N {
-            sizes[i] = sizes[i].max(row[i].chars().count());
+/// Render a table to a string, if `header` is set the first row is used as header
+pub fn format_table<R: AsRef<[String]>>(data: &[R], header: bool, styling: &[Styling]) -> String {
+    let n = styling.len();
+    let sizes = data.iter().fold(vec![0; n], |mut sizes, row| {
+        for (size, cell) in sizes.iter_mut().zip(row.as_ref()) {
+            *size = (*size).max(cell.chars().count());
         }
         sizes
     });
-    let line = |start, middle, end| {
-        print!("{start}");
-        for size in sizes.iter().take(N - 1).copied() {
-            print!("{}{middle}", "─".repeat(size));
+    let mut output = String::new();
+    let line = |output: &mut String, start, middle, end| {
+        write!(output, "{start}").unwrap();
+        for size in sizes.iter().take(n - 1).copied() {
+            write!(output, "{}{middle}", "─".repeat(size)).unwrap();
         }
-        println!("{}{end}", "─".repeat(sizes[N - 1]));
+        writeln!(output, "{}{end}", "─".repeat(sizes[n - 1])).unwrap();
     };
-    line("╭", "┬", "╮");
+    line(&mut output, "╭", "┬", "╮");
     if header {
-        print!("│");
-        #[allow(clippy::needless_range_loop)]
-        for i in 0..N {
-            print!("{:^w$}│", data[0][i].blue(), w = sizes[i]);
+        write!(output, "│").unwrap();
+        for (cell, size) in data[0].as_ref().iter().zip(&sizes) {
+            write!(output, "{:^w$}│", cell.blue(), w = size).unwrap();
         }
-        println!();
-        line("├", "┼", "┤");
+        writeln!(output).unwrap();
+        line(&mut output, "├", "┼", "┤");
     }
     for row in data.iter().skip(usize::from(header)) {
-        print!("│");
-        for i in 0..N {
-            print!("{:w$}│", row[i].apply(&styling[i]), w = sizes[i]);
+        write!(output, "│").unwrap();
+        for ((cell, size), style) in row.as_ref().iter().zip(&sizes).zip(styling) {
+            write!(output, "{:w$}│", cell.apply(style), w = size).unwrap();
         }
-        println!();
+        writeln!(output).unwrap();
+    }
+    line(&mut output, "╰", "┴", "╯");
+    output
+}
+
+/// The mass modes to show in tables, all three for `--all-mass-modes` and otherwise only the selected one
+pub fn mass_modes(all: bool, mass_mode: MassMode) -> Vec<MassMode> {
+    if all {
+        vec![
+            MassMode::Monoisotopic,
+            MassMode::Average,
+            MassMode::MostAbundant,
+        ]
+    } else {
+        vec![mass_mode]
+    }
+}
+
+/// The mass difference (A - B) of the aligned parts in the given mass mode, if there are multiple
+/// masses the smallest difference is given (as [`Alignment::mass_difference`] for monoisotopic)
+pub fn mass_difference<A: AtMax<Linear>, B: AtMax<Linear>>(
+    alignment: &Alignment<'_, A, B>,
+    mass_mode: MassMode,
+) -> Mass {
+    alignment
+        .mass_a()
+        .iter()
+        .cartesian_product(alignment.mass_b().iter())
+        .map(|(a, b)| a.mass(mass_mode) - b.mass(mass_mode))
+        .min_by(|a, b| a.abs().value.total_cmp(&b.abs().value))
+        .unwrap_or_default()
+}
+
+/// The aligned residues of A and B with gaps (`-`), the shorter side of an isobaric step is padded
+/// with gaps as well so both strings have the same length. Modifications are not shown.
+pub fn gapped<A, B>(alignment: &Alignment<'_, A, B>) -> (String, String) {
+    let (mut a, mut b) = (String::new(), String::new());
+    let (mut index_a, mut index_b) = (alignment.start_a(), alignment.start_b());
+    for piece in alignment.path() {
+        let (step_a, step_b) = (piece.step_a as usize, piece.step_b as usize);
+        let width = step_a.max(step_b);
+        a.extend(
+            alignment.seq_a().sequence()[index_a..index_a + step_a]
+                .iter()
+                .map(|s| s.aminoacid.char())
+                .chain(std::iter::repeat_n('-', width - step_a)),
+        );
+        b.extend(
+            alignment.seq_b().sequence()[index_b..index_b + step_b]
+                .iter()
+                .map(|s| s.aminoacid.char())
+                .chain(std::iter::repeat_n('-', width - step_b)),
+        );
+        index_a += step_a;
+        index_b += step_b;
     }
-    line("╰", "┴", "╯");
+    (a, b)
 }
 
 pub fn display_mass(value: Mass, colour: bool, precision: Option<usize>) -> String {
@@ -729,6 +1541,11 @@ pub fn display_mass(value: Mass, colour: bool, precision: Option<usize>) -> Stri
     )
 }
 
+/// Display a mass in Dalton without engineering notation, for lists of masses that are compared by hand
+pub fn display_dalton(value: Mass, precision: Option<usize>) -> String {
+    format!("{} Da", display_with_precision(value.value, precision))
+}
+
 fn display_with_precision(n: f64, precision: Option<usize>) -> String {
     if let Some(precision) = precision {
         format!("{n:.precision$}")
@@ -797,3 +1614,58 @@ fn find_possible_n_glycan_locations<A>(sequence: &Peptidoform<A>) -> Vec<usize>
     }
     result
 }
+
+#[test]
+fn gapped_alignment() {
+    use crate::test_helpers::{global, parse};
+    let (a, b) = (parse("ANGTE"), parse("AGGGTE"));
+    let alignment = global::<4>(&a, &b);
+    // N is isobaric with GG
+    assert_eq!(
+        gapped(&alignment),
+        ("AN-GTE".to_string(), "AGGGTE".to_string())
+    );
+}
+
+#[test]
+fn mass_deltas() {
+    use crate::test_helpers::{global, parse};
+    let (a, b) = (parse("PEPTMDE"), parse("PEPTM[Oxidation]DE"));
+    let alignment = global::<4>(&a, &b);
+    let step = &alignment.path()[4];
+    assert_eq!(step.match_type, MatchType::IdentityMassMismatch);
+    assert_eq!(
+        mass_delta(
+            &alignment,
+            (4, 4),
+            step,
+            MassDeltaUnit::MilliDalton,
+            MassMode::Monoisotopic
+        ),
+        "+15994.9mDa"
+    );
+    let (a, b) = (parse("ANGTE"), parse("AGGGTE"));
+    let alignment = global::<4>(&a, &b);
+    // N is isobaric with GG
+    assert_eq!(
+        mass_delta(
+            &alignment,
+            (1, 1),
+            &alignment.path()[1],
+            MassDeltaUnit::Ppm,
+            MassMode::Monoisotopic
+        ),
+        "+0.0ppm"
+    );
+}
+
+#[test]
+fn heat_gradient() {
+    let rgb = |score| match heat_colour(score, (-2.0, 6.0)) {
+        Color::TrueColor { r, g, b } => (r, g, b),
+        _ => unreachable!(),
+    };
+    assert_eq!(rgb(-2.0), (255, 0, 0));
+    assert_eq!(rgb(2.0), (255, 255, 0));
+    assert_eq!(rgb(6.0), (0, 255, 0));
+}
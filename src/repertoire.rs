@@ -0,0 +1,222 @@
+use std::io::{BufWriter, Write};
+
+use colored::Colorize;
+use itertools::Itertools;
+use rayon::prelude::*;
+use rustyms::{
+    align::Alignment,
+    identification::FastaData,
+    imgt::{Allele, GeneType},
+    Peptidoform, SimpleLinear, UnAmbiguous,
+};
+
+use crate::{
+    alignment::{consecutive_align, DomainSettings},
+    cli::{file_delimiter, Cli},
+    custom,
+    render::generate_regions,
+    stats,
+};
+
+/// If the file is a fasta file, based on the extension
+fn is_fasta(path: &str) -> bool {
+    let extension = path.rsplit('.').next().unwrap_or_default().to_lowercase();
+    ["fasta", "fas", "fa"].contains(&extension.as_str())
+}
+
+/// Read the sequences for a batch domain annotation, from a fasta file (using the identifiers) or
+/// from a csv file with the sequences in the column "a" (using the column "id" if present, or the
+/// line number otherwise)
+pub fn read_sequences(path: &str, delimiter: char) -> Vec<(String, Peptidoform<SimpleLinear>)> {
+    if is_fasta(path) {
+        FastaData::parse_file(path)
+            .unwrap()
+            .into_iter()
+            .map(|fasta| {
+                (
+                    fasta.identifier().to_string(),
+                    fasta.peptide().clone().cast(),
+                )
+            })
+            .collect()
+    } else {
+        rustyms::csv::parse_csv(path, delimiter as u8, None)
+            .expect("Failed to parse CSV file")
+            .map(|line| {
+                let line = line.expect("Failed to read CSV line");
+                let id = line.index_column("id").map_or_else(
+                    |_| (line.line_index() + 1).to_string(),
+                    |(id, _)| id.trim().to_string(),
+                );
+                let sequence =
+                    Peptidoform::pro_forma(line.index_column("a").unwrap().0, custom::database())
+                        .unwrap()
+                        .into_simple_linear()
+                        .unwrap();
+                (id, sequence)
+            })
+            .collect()
+    }
+}
+
+/// If the hit for a segment is of the gene type of that segment, a `C` segment matches all constant
+/// genes
+fn is_segment(segment: GeneType, gene: GeneType) -> bool {
+    match (segment, gene) {
+        (GeneType::C(None), GeneType::C(_)) => true,
+        (segment, gene) => segment == gene,
+    }
+}
+
+/// The header of the batch domain annotation, with per segment the call, normalised score, and
+/// identity
+pub fn header(segments: &[GeneType]) -> Vec<String> {
+    [
+        "id".to_string(),
+        "sequence".to_string(),
+        "species".to_string(),
+    ]
+    .into_iter()
+    .chain(segments.iter().flat_map(|gene| {
+        [
+            gene.to_string(),
+            format!("{gene} score"),
+            format!("{gene} identity"),
+        ]
+    }))
+    .chain(["regions".to_string()])
+    .collect()
+}
+
+/// The columns for one sequence, absent segments are left empty
+pub fn row(
+    args: &Cli,
+    id: &str,
+    sequence: &Peptidoform<SimpleLinear>,
+    segments: &[GeneType],
+    tops: &[(
+        Allele<'static>,
+        Alignment<'static, UnAmbiguous, SimpleLinear>,
+    )],
+) -> Vec<String> {
+    let mut hits = tops.iter().peekable();
+    let mut output = vec![
+        id.to_string(),
+        sequence.to_string(),
+        tops.first().map_or(String::new(), |(allele, _)| {
+            allele.species.scientific_name().to_string()
+        }),
+    ];
+    for segment in segments {
+        if let Some((allele, alignment)) =
+            hits.next_if(|(allele, _)| is_segment(*segment, allele.gene.kind))
+        {
+            output.extend([
+                allele.name(),
+                format!("{:.3}", alignment.normalised_score()),
+                format!("{:.3}", stats::stats(alignment, &args.stats).identity()),
+            ]);
+        } else {
+            output.extend([String::new(), String::new(), String::new()]);
+        }
+    }
+    output.push(
+        generate_regions(tops)
+            .iter()
+            .map(|(r, l)| format!("{r}:{l}"))
+            .join(";"),
+    );
+    output
+}
+
+/// Quote a csv field if it contains the delimiter, a quote, or a line break, the regions column for
+/// example uses `;` internally which is also a common delimiter
+fn quote(field: &str, delimiter: char) -> String {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Run a domain alignment for every sequence in the file and write the calls to a csv file, by
+/// default next to the input as `<FILE>_domains.csv`, without showing the alignments
+pub fn annotate(args: &Cli, path: &str) {
+    // The output of a fasta file is comma separated unless another delimiter is given
+    let delimiter = args.delimiter.unwrap_or_else(|| {
+        if is_fasta(path) {
+            ','
+        } else {
+            file_delimiter(path)
+        }
+    });
+    let sequences = read_sequences(path, delimiter);
+    let settings = DomainSettings {
+        return_number: 1,
+        ..args.domain_settings()
+    };
+    let genes = settings
+        .segments
+        .iter()
+        .map(|(gene, _)| *gene)
+        .collect_vec();
+    let rows: Vec<Vec<String>> = sequences
+        .par_iter()
+        .map(|(id, sequence)| {
+            let (scores, _) = consecutive_align(sequence, &settings);
+            let tops = scores
+                .alignments
+                .into_iter()
+                .filter_map(|options| options.into_iter().next())
+                .collect_vec();
+            row(args, id, sequence, &genes, &tops)
+        })
+        .collect();
+    let stdout = args.output.as_deref() == Some("-");
+    let output_path = args
+        .output
+        .clone()
+        .unwrap_or_else(|| format!("{path}_domains.csv"));
+    let mut writer: BufWriter<Box<dyn Write>> = BufWriter::new(if stdout {
+        Box::new(std::io::stdout())
+    } else {
+        Box::new(std::fs::File::create(&output_path).unwrap())
+    });
+    let separator = delimiter.to_string();
+    writeln!(writer, "{}", header(&genes).join(&separator)).unwrap();
+    for row in &rows {
+        writeln!(
+            writer,
+            "{}",
+            row.iter().map(|f| quote(f, delimiter)).join(&separator)
+        )
+        .unwrap();
+    }
+    writer.flush().unwrap();
+    let message = format!(
+        "Annotated {} sequences to {}",
+        rows.len(),
+        if stdout { "stdout" } else { &output_path }
+    );
+    if stdout {
+        eprintln!("{}", message.dimmed());
+    } else {
+        println!("{}", message.dimmed());
+    }
+}
+
+#[test]
+fn segment_columns() {
+    assert!(is_segment(
+        GeneType::C(None),
+        GeneType::C(Some(rustyms::imgt::Constant::G))
+    ));
+    assert!(!is_segment(GeneType::V, GeneType::J));
+    assert_eq!(
+        header(&[GeneType::V, GeneType::J]).join(","),
+        "id,sequence,species,V,V score,V identity,J,J score,J identity,regions"
+    );
+    assert_eq!(quote("FR1:25;CDR1:8", ','), "FR1:25;CDR1:8");
+    assert_eq!(quote("FR1:25;CDR1:8", ';'), "\"FR1:25;CDR1:8\"");
+    assert_eq!(quote("a \"b\"", ','), "\"a \"\"b\"\"\"");
+}
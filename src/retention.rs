@@ -0,0 +1,111 @@
+use colored::Colorize;
+use rustyms::{AminoAcid, Peptidoform, SimpleLinear};
+
+/// A linear calibration from the hydrophobicity index to retention time in minutes, fitted on the
+/// observed retention times of some known peptides to fit the gradient of the run
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Calibration {
+    pub slope: f64,
+    pub intercept: f64,
+}
+
+impl Calibration {
+    /// The retention time in minutes for this hydrophobicity index
+    pub fn retention_time(self, index: f64) -> f64 {
+        self.slope * index + self.intercept
+    }
+}
+
+/// The retention coefficients (in % acetonitrile) for reversed phase chromatography with TFA as
+/// ion pairing reagent, for the residue anywhere in the peptide and at the N terminus. The
+/// coefficients are from Guo et al. (1986) with the N terminal corrections from Krokhin et al. (2004).
+fn coefficients(aa: AminoAcid) -> (f64, f64) {
+    match aa {
+        AminoAcid::Tryptophan => (8.8, -4.0),
+        AminoAcid::Phenylalanine => (8.1, -7.0),
+        AminoAcid::Leucine => (8.1, -9.0),
+        AminoAcid::Isoleucine => (7.4, -8.0),
+        AminoAcid::AmbiguousLeucine => (7.75, -8.5),
+        AminoAcid::Methionine => (5.5, -5.5),
+        AminoAcid::Valine => (5.0, -5.7),
+        AminoAcid::Tyrosine => (4.5, -3.0),
+        AminoAcid::Cysteine | AminoAcid::Selenocysteine => (2.6, 4.0),
+        AminoAcid::Proline => (2.0, 0.0),
+        AminoAcid::Alanine => (2.0, -1.5),
+        AminoAcid::GlutamicAcid => (1.1, 7.0),
+        AminoAcid::Threonine => (0.6, 5.0),
+        AminoAcid::AsparticAcid => (0.2, 9.0),
+        AminoAcid::Glutamine => (0.0, 1.0),
+        AminoAcid::AmbiguousGlutamine => (0.55, 4.0),
+        AminoAcid::Serine => (-0.2, 5.0),
+        AminoAcid::Glycine => (-0.2, 5.0),
+        AminoAcid::Arginine => (-0.6, 8.0),
+        AminoAcid::Asparagine => (-0.6, 5.0),
+        AminoAcid::AmbiguousAsparagine => (-0.2, 7.0),
+        AminoAcid::Histidine => (-2.1, 4.0),
+        AminoAcid::Lysine | AminoAcid::Pyrrolysine => (-2.1, 4.6),
+        _ => (0.0, 0.0),
+    }
+}
+
+/// Predict the hydrophobicity index (in % acetonitrile) of a peptide with a simple additive model
+/// in the style of SSRCalc version 1: the sum of the retention coefficients, with a correction for
+/// the first three residues as these are close to the charged N terminus, a correction for short
+/// and long peptides, and a flattening of the most hydrophobic peptides. Modifications are ignored.
+pub fn hydrophobicity(peptide: &Peptidoform<SimpleLinear>) -> f64 {
+    let residues: Vec<AminoAcid> = peptide
+        .sequence()
+        .iter()
+        .map(|s| s.aminoacid.aminoacid())
+        .collect();
+    let length = residues.len() as f64;
+    let sum: f64 = residues.iter().map(|aa| coefficients(*aa).0).sum();
+    let n_term: f64 = residues
+        .iter()
+        .zip([0.42, 0.22, 0.05])
+        .map(|(aa, weight)| weight * coefficients(*aa).1)
+        .sum();
+    let length_correction = if length < 10.0 {
+        1.0 - 0.027 * (10.0 - length)
+    } else if length > 20.0 {
+        1.0 - 0.014 * (length - 20.0)
+    } else {
+        1.0
+    };
+    let index = length_correction * (sum + n_term);
+    if index >= 38.0 {
+        index - 0.3 * (index - 38.0)
+    } else {
+        index
+    }
+}
+
+/// Show the predicted hydrophobicity index, and the retention time if calibrated
+pub fn show(peptide: &Peptidoform<SimpleLinear>, calibration: Option<Calibration>) {
+    let index = hydrophobicity(peptide);
+    print!(
+        "Predicted hydrophobicity index: {}",
+        format!("{index:.2}").yellow()
+    );
+    if let Some(time) = calibration.map(|c| c.retention_time(index)) {
+        print!(", retention time: {}", format!("{time:.2} min").yellow());
+    }
+    println!(
+        " {}",
+        "(SSRCalc style model, modifications are ignored)".dimmed()
+    );
+}
+
+#[test]
+fn hydrophobic_order() {
+    use crate::test_helpers::parse;
+    let hydrophobic = hydrophobicity(&parse("LLWFLLWVR"));
+    let hydrophilic = hydrophobicity(&parse("DSKGSNTEK"));
+    assert!(hydrophobic > hydrophilic);
+    assert!(hydrophilic < 10.0);
+    let calibration = Calibration {
+        slope: 0.5,
+        intercept: 2.0,
+    };
+    assert_eq!(calibration.retention_time(10.0), 7.0);
+}
@@ -0,0 +1,259 @@
+use rustyms::{
+    align::{Alignment, MatchType, Piece, Stats},
+    peptidoform::Region,
+};
+
+use std::cmp::Ordering;
+
+use crate::cli::{SortKey, StatsConventions};
+
+/// Calculate the statistics for an alignment following the given conventions. With the default
+/// conventions this gives the same result as [`Alignment::stats`].
+pub fn stats<A, B>(alignment: &Alignment<'_, A, B>, conventions: &StatsConventions) -> Stats {
+    let mut stats = empty();
+    for piece in counted_path(alignment, conventions).0 {
+        count(&mut stats, piece, conventions);
+    }
+    stats
+}
+
+/// Calculate the statistics for an alignment separately for every region of the first sequence,
+/// with the regions given as in [`rustyms::peptidoform::AnnotatedPeptide::regions`]. Insertions
+/// in the second sequence are counted for the region of the preceding residue. Only regions that
+/// are covered by the alignment are returned, in the order of the sequence.
+pub fn region_stats<A, B>(
+    alignment: &Alignment<'_, A, B>,
+    regions: &[(Region, usize)],
+    conventions: &StatsConventions,
+) -> Vec<(Region, Stats)> {
+    let mut output: Vec<(Region, Stats)> = Vec::new();
+    let (path, position) = counted_path(alignment, conventions);
+    for (region, piece) in piece_regions(path, position, regions) {
+        let Some(region) = region else {
+            continue;
+        };
+        if output.last().is_none_or(|(r, _)| r != region) {
+            output.push((region.clone(), empty()));
+        }
+        count(&mut output.last_mut().unwrap().1, piece, conventions);
+    }
+    output
+}
+
+/// For every region find the hit with the highest identity in that region, with ties broken by the
+/// number of identical residues and then by the order of the hits. Every hit is given as the regions
+/// of its first sequence and the alignment. The regions are given in the order they appear in the
+/// first hit, followed by any regions not covered by the first hit. Returns the region, the index of
+/// the best hit, and its statistics for the region.
+pub fn best_per_region<'a, A: 'a, B: 'a>(
+    hits: impl IntoIterator<Item = (&'a [(Region, usize)], &'a Alignment<'a, A, B>)>,
+    conventions: &StatsConventions,
+) -> Vec<(Region, usize, Stats)> {
+    let mut output: Vec<(Region, usize, Stats)> = Vec::new();
+    for (index, (regions, alignment)) in hits.into_iter().enumerate() {
+        for (region, stats) in region_stats(alignment, regions, conventions) {
+            match output.iter_mut().find(|(r, _, _)| *r == region) {
+                Some(best) => {
+                    if stats
+                        .identity()
+                        .total_cmp(&best.2.identity())
+                        .then(stats.identical.cmp(&best.2.identical))
+                        .is_gt()
+                    {
+                        *best = (region, index, stats);
+                    }
+                }
+                None => output.push((region, index, stats)),
+            }
+        }
+    }
+    output
+}
+
+/// The normalised score of an alignment with the score of every step weighted by the region of
+/// the first sequence it falls in, regions without a weight have weight 1. The maximal score is
+/// scaled by the mean weight over the alignment, so without weights this is the normalised score.
+/// An empty path, or a path that only covers regions with weight 0, has score 0.
+pub fn weighted_score<A, B>(
+    alignment: &Alignment<'_, A, B>,
+    regions: &[(Region, usize)],
+    weights: &[(Region, f64)],
+) -> f64 {
+    let (mut score, mut weighted_length, mut length) = (0.0, 0.0, 0.0);
+    for (region, piece) in piece_regions(alignment.path(), alignment.start_a(), regions) {
+        let weight = region
+            .and_then(|r| weights.iter().find(|(w, _)| w == r))
+            .map_or(1.0, |(_, w)| *w);
+        let len = f64::from(piece.step_a.max(piece.step_b));
+        score += weight * piece.local_score as f64;
+        weighted_length += weight * len;
+        length += len;
+    }
+    if weighted_length == 0.0 || alignment.score().max == 0 {
+        return 0.0;
+    }
+    score / (alignment.score().max as f64 * weighted_length / length)
+}
+
+/// Get the region of the first sequence for every piece of the path, with the position on the
+/// first sequence where the path starts. Insertions in the second sequence get the region of the
+/// preceding residue.
+fn piece_regions<'a>(
+    path: &'a [Piece],
+    mut position: usize,
+    regions: &'a [(Region, usize)],
+) -> impl Iterator<Item = (Option<&'a Region>, &'a Piece)> {
+    path.iter().map(move |piece| {
+        let residue = if piece.step_a == 0 {
+            position.saturating_sub(1)
+        } else {
+            position
+        };
+        let mut end = 0;
+        let region = regions
+            .iter()
+            .find(|(_, length)| {
+                end += length;
+                residue < end
+            })
+            .or(regions.last())
+            .map(|(r, _)| r);
+        position += piece.step_a as usize;
+        (region, piece)
+    })
+}
+
+const fn empty() -> Stats {
+    Stats {
+        identical: 0,
+        mass_similar: 0,
+        similar: 0,
+        gaps: 0,
+        length: 0,
+    }
+}
+
+/// The part of the path that is counted, with the position on the first sequence where it starts
+fn counted_path<'a, A, B>(
+    alignment: &'a Alignment<'_, A, B>,
+    conventions: &StatsConventions,
+) -> (&'a [Piece], usize) {
+    let path = alignment.path();
+    let (mut start, mut end) = (0, path.len());
+    if conventions.ignore_terminal_gaps {
+        while start < end && path[start].match_type == MatchType::Gap {
+            start += 1;
+        }
+        while end > start && path[end - 1].match_type == MatchType::Gap {
+            end -= 1;
+        }
+    }
+    let position = alignment.start_a()
+        + path[..start]
+            .iter()
+            .map(|p| p.step_a as usize)
+            .sum::<usize>();
+    (&path[start..end], position)
+}
+
+/// Add a single piece of the path to the statistics
+fn count(stats: &mut Stats, piece: &Piece, conventions: &StatsConventions) {
+    let m = piece.match_type;
+    let len = piece.step_a.max(piece.step_b) as usize;
+    let identical = match m {
+        MatchType::FullIdentity => true,
+        MatchType::IdentityMassMismatch => !conventions.strict_identity,
+        MatchType::Isobaric | MatchType::Rotation => conventions.isobaric_identity,
+        MatchType::Mismatch | MatchType::Gap => false,
+    };
+    stats.identical += usize::from(identical) * len;
+    stats.mass_similar += usize::from(matches!(
+        m,
+        MatchType::FullIdentity | MatchType::Isobaric | MatchType::Rotation
+    )) * len;
+    stats.similar += usize::from(
+        matches!(
+            m,
+            MatchType::IdentityMassMismatch | MatchType::FullIdentity | MatchType::Mismatch
+        ) && piece.local_score >= 0,
+    ) * len;
+    stats.gaps += usize::from(m == MatchType::Gap);
+    stats.length += len;
+}
+
+/// Order two hits by the given key, the best hit is ordered first. Ties are broken by the
+/// normalised score. Sorting by species is not defined for bare alignments, these are ordered by
+/// normalised score and the species has to be compared beforehand.
+pub fn compare_hits<A, B>(
+    a: &Alignment<'_, A, B>,
+    b: &Alignment<'_, A, B>,
+    key: SortKey,
+    conventions: &StatsConventions,
+) -> Ordering {
+    let by_score = || b.normalised_score().total_cmp(&a.normalised_score());
+    let (stats_a, stats_b) = (stats(a, conventions), stats(b, conventions));
+    match key {
+        SortKey::Score | SortKey::Species => Ordering::Equal,
+        SortKey::Identity => stats_b.identity().total_cmp(&stats_a.identity()),
+        SortKey::MassSimilarity => stats_b
+            .mass_similarity()
+            .total_cmp(&stats_a.mass_similarity()),
+        SortKey::Gaps => stats_a.gaps_fraction().total_cmp(&stats_b.gaps_fraction()),
+    }
+    .then_with(by_score)
+}
+
+#[test]
+fn terminal_gaps() {
+    use crate::test_helpers::{global, parse};
+    let (a, b) = (parse("MAKTNLSHLGY"), parse("AKTNLSHLGYGG"));
+    let alignment = global::<4>(&a, &b);
+    let default = alignment.stats();
+    let own = stats(&alignment, &StatsConventions::default());
+    assert_eq!(
+        (own.identical, own.gaps, own.length),
+        (default.identical, default.gaps, default.length)
+    );
+    let ignored = stats(
+        &alignment,
+        &StatsConventions {
+            ignore_terminal_gaps: true,
+            ..Default::default()
+        },
+    );
+    assert_eq!(
+        (ignored.identical, ignored.gaps, ignored.length),
+        (10, 0, 10)
+    );
+}
+
+#[test]
+fn weighted() {
+    use crate::test_helpers::{global, parse};
+    let (a, b) = (parse("AKTNLSHLGYWW"), parse("AKTNLSHLGYGG"));
+    let alignment = global::<4>(&a, &b);
+    let regions = [
+        (Region::Framework(1), 10),
+        (Region::ComplementarityDeterminingRegion(1), 2),
+    ];
+    let plain = weighted_score(&alignment, &regions, &[]);
+    assert!((plain - alignment.normalised_score()).abs() < 1e-9);
+    let weighted = weighted_score(
+        &alignment,
+        &regions,
+        &[(Region::ComplementarityDeterminingRegion(1), 0.0)],
+    );
+    assert!(weighted > plain);
+    let zero = weighted_score(
+        &alignment,
+        &regions,
+        &[
+            (Region::Framework(1), 0.0),
+            (Region::ComplementarityDeterminingRegion(1), 0.0),
+        ],
+    );
+    assert_eq!(zero, 0.0);
+    let cdr = region_stats(&alignment, &regions, &StatsConventions::default());
+    assert_eq!(cdr[1].0, Region::ComplementarityDeterminingRegion(1));
+    assert_eq!((cdr[1].1.identical, cdr[1].1.length), (0, 2));
+}
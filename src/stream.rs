@@ -0,0 +1,172 @@
+use std::{io::BufRead, ops::Range};
+
+use rustyms::{align::Alignment, identification::FastaData, SemiAmbiguous, SimpleLinear};
+
+use crate::{
+    cli::{SortKey, StatsConventions},
+    decoy, stats,
+};
+
+/// The number of fasta records that are aligned in parallel before the results are merged
+pub const BATCH_SIZE: usize = 4096;
+
+/// Read the raw records from a fasta file one by one, without parsing or keeping the full file in memory.
+/// Every item is the byte offset of the record in the file together with the bytes of the full record.
+/// Any content before the first header is ignored.
+pub struct FastaRecords<R> {
+    reader: R,
+    offset: u64,
+    /// The header line of the next record, as it is read as the end of the previous record
+    next: Option<(u64, Vec<u8>)>,
+}
+
+impl<R: BufRead> FastaRecords<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            offset: 0,
+            next: None,
+        }
+    }
+
+    /// Read a single line, returns None at the end of the file
+    fn line(&mut self) -> Result<Option<(u64, Vec<u8>)>, String> {
+        let mut line = Vec::new();
+        let read = self
+            .reader
+            .read_until(b'\n', &mut line)
+            .map_err(|e| format!("Could not read fasta file: {e}"))?;
+        let start = self.offset;
+        self.offset += read as u64;
+        Ok((read > 0).then_some((start, line)))
+    }
+}
+
+impl<R: BufRead> Iterator for FastaRecords<R> {
+    type Item = Result<(u64, Vec<u8>), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (start, mut record) = match self.next.take() {
+            Some(header) => header,
+            None => loop {
+                match self.line() {
+                    Ok(Some((start, line))) if line.starts_with(b">") => break (start, line),
+                    Ok(Some(_)) => (),
+                    Ok(None) => return None,
+                    Err(err) => return Some(Err(err)),
+                }
+            },
+        };
+        loop {
+            match self.line() {
+                Ok(Some((start, line))) if line.starts_with(b">") => {
+                    self.next = Some((start, line));
+                    break;
+                }
+                Ok(Some((_, line))) => record.extend(line),
+                Ok(None) => break,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        Some(Ok((start, record)))
+    }
+}
+
+/// Parse a single fasta record
+pub fn parse_record(record: &[u8]) -> Result<FastaData, String> {
+    FastaData::parse_reader(record, None)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .next()
+        .ok_or_else(|| "Empty fasta record".to_string())
+}
+
+/// A database hit, with the location of the aligned peptide in the protein if the database was digested (None if
+/// no digested peptide could be aligned and the whole protein was aligned instead)
+pub type Hit = (
+    FastaData,
+    Alignment<'static, SemiAmbiguous, SimpleLinear>,
+    Option<Range<usize>>,
+);
+
+/// Keep track of the best hits in a database search, only the best `size` hits are kept in
+/// memory, ranked on the given sort key. For target-decoy competition only the score and decoy
+/// status of all other hits are kept.
+pub struct TopHits {
+    size: usize,
+    sort_by: SortKey,
+    conventions: StatsConventions,
+    hits: Vec<Hit>,
+    scores: Vec<(f64, bool)>,
+}
+
+impl TopHits {
+    pub fn new(size: usize, sort_by: SortKey, conventions: StatsConventions) -> Self {
+        Self {
+            size,
+            sort_by,
+            conventions,
+            hits: Vec::with_capacity(size * 2),
+            scores: Vec::new(),
+        }
+    }
+
+    /// Add a batch of hits, if the total is over the bounds the worst hits are dropped
+    pub fn extend(&mut self, hits: impl IntoIterator<Item = Hit>) {
+        for hit in hits {
+            self.scores
+                .push((hit.1.normalised_score(), decoy::is_decoy(&hit.0)));
+            self.hits.push(hit);
+            if self.hits.len() >= self.size * 2 {
+                self.truncate();
+            }
+        }
+    }
+
+    /// The normalised score of the worst hit that is still kept, or negative infinity if less than `size` hits were seen
+    /// or if the hits are not ranked on score
+    pub fn threshold(&mut self) -> f64 {
+        if self.size == 0 || self.hits.len() < self.size || self.sort_by != SortKey::Score {
+            f64::NEG_INFINITY
+        } else {
+            self.truncate();
+            self.hits[self.size - 1].1.normalised_score()
+        }
+    }
+
+    fn truncate(&mut self) {
+        if self.sort_by == SortKey::Score {
+            self.hits.sort_by(|a, b| b.1.cmp(&a.1));
+        } else {
+            self.hits
+                .sort_by(|a, b| stats::compare_hits(&a.1, &b.1, self.sort_by, &self.conventions));
+        }
+        self.hits.truncate(self.size);
+    }
+
+    /// Get the best hits, sorted from best to worst on the sort key, every hit with its q-value based on all hits seen
+    /// (see [`decoy::q_values`]), together with the number of target hits with a q-value of at most the given FDR.
+    pub fn finish(mut self, fdr: f64) -> (Vec<(Hit, f64)>, usize) {
+        self.truncate();
+        self.scores.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let q_values = decoy::q_values(&self.scores);
+        let accepted = self
+            .scores
+            .iter()
+            .zip(&q_values)
+            .filter(|((_, decoy), q)| **q <= fdr && !decoy)
+            .count();
+        let hits = self
+            .hits
+            .into_iter()
+            .map(|hit| {
+                // Look up the q-value on the score, hits with the same score share the q-value
+                let score = hit.1.normalised_score();
+                let index = self.scores.partition_point(|(s, _)| *s > score);
+                let q = q_values.get(index).copied().unwrap_or(1.0);
+                (hit, q)
+            })
+            .collect();
+        (hits, accepted)
+    }
+}
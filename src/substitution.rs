@@ -0,0 +1,153 @@
+use colored::{Color, Colorize, Styles};
+use itertools::Itertools;
+use rustyms::{system::Mass, AminoAcid, MassMode, MultiChemical, WithinTolerance};
+
+use crate::{
+    cli::MassTolerance,
+    render::{display_dalton, table},
+    styling::Styling,
+};
+
+/// A substitution of one or two residues by one or two other residues
+#[derive(Clone, Debug, PartialEq)]
+pub struct Swap {
+    pub from: Vec<AminoAcid>,
+    pub to: Vec<AminoAcid>,
+    /// The mass of the new residues minus the mass of the original residues
+    pub shift: Mass,
+    /// The shift minus the mass difference to explain
+    pub error: Mass,
+}
+
+/// Find all substitutions of one or two canonical residues by one or two other canonical residues
+/// that shift the mass by the given difference, sorted by the number of residues and then by error. Substitutions that keep a residue
+/// (like `AG>AS`) are left out as these are already covered by the single substitution. The
+/// tolerance is taken at the mass of the original residues. For a difference of zero each pair of
+/// isobaric residues is only given once.
+pub fn substitutions(difference: Mass, tolerance: MassTolerance, mass_mode: MassMode) -> Vec<Swap> {
+    let mass = |residues: &[AminoAcid]| {
+        residues
+            .iter()
+            .map(|aa| {
+                aa.formulas()
+                    .iter()
+                    .next()
+                    .map(|f| f.mass(mass_mode))
+                    .unwrap_or_default()
+            })
+            .sum::<Mass>()
+    };
+    let groups = AminoAcid::CANONICAL_AMINO_ACIDS
+        .iter()
+        .map(|aa| vec![*aa])
+        .chain(
+            AminoAcid::CANONICAL_AMINO_ACIDS
+                .iter()
+                .combinations_with_replacement(2)
+                .map(|pair| pair.into_iter().copied().collect_vec()),
+        )
+        .map(|residues| (mass(&residues), residues))
+        .collect_vec();
+    let mut output = Vec::new();
+    for (index, (from_mass, from)) in groups.iter().enumerate() {
+        for (other, (to_mass, to)) in groups.iter().enumerate() {
+            let shift = *to_mass - *from_mass;
+            if index == other
+                || from.iter().any(|aa| to.contains(aa))
+                || (difference.value == 0.0 && other < index)
+                || !tolerance
+                    .at(*from_mass)
+                    .within(&(*from_mass + difference), to_mass)
+            {
+                continue;
+            }
+            output.push(Swap {
+                from: from.clone(),
+                to: to.clone(),
+                shift,
+                error: shift - difference,
+            });
+        }
+    }
+    output.sort_by(|a, b| {
+        (a.from.len() + a.to.len())
+            .cmp(&(b.from.len() + b.to.len()))
+            .then_with(|| a.error.value.abs().total_cmp(&b.error.value.abs()))
+    });
+    output
+}
+
+/// Show all single and double residue substitutions that explain the mass difference
+pub fn show(
+    difference: Mass,
+    tolerance: MassTolerance,
+    mass_mode: MassMode,
+    precision: Option<usize>,
+) {
+    println!(
+        "Substitutions for {} {}",
+        display_dalton(difference, precision).yellow(),
+        format!("({mass_mode}, tolerance: {tolerance})").dimmed()
+    );
+    let substitutions = substitutions(difference, tolerance, mass_mode);
+    if substitutions.is_empty() {
+        println!("{}", "No substitutions found".red());
+        return;
+    }
+    let residues = |r: &[AminoAcid]| r.iter().map(|aa| aa.char()).collect::<String>();
+    let mut data = vec![[
+        "Substitution".to_string(),
+        "Shift".to_string(),
+        "Error".to_string(),
+    ]];
+    for substitution in &substitutions {
+        data.push([
+            format!(
+                "{}>{}",
+                residues(&substitution.from),
+                residues(&substitution.to)
+            ),
+            display_dalton(substitution.shift, precision),
+            format!("{:+.5} Da", substitution.error.value),
+        ]);
+    }
+    table(
+        &data,
+        true,
+        &[
+            Styling::with_fg(Some(Color::Yellow)),
+            Styling::with_style(Styles::Dimmed),
+            Styling::none(),
+        ],
+    );
+}
+
+#[test]
+fn near_isobaric() {
+    use rustyms::{system::dalton, Tolerance};
+    let found = substitutions(
+        Mass::new::<dalton>(0.0),
+        MassTolerance::Single(Tolerance::new_absolute(Mass::new::<dalton>(0.05))),
+        MassMode::Monoisotopic,
+    );
+    let has = |from: &[AminoAcid], to: &[AminoAcid]| {
+        found
+            .iter()
+            .any(|s| s.from == from && s.to == to || s.from == to && s.to == from)
+    };
+    assert!(has(
+        &[AminoAcid::Asparagine],
+        &[AminoAcid::Glycine, AminoAcid::Glycine]
+    ));
+    assert!(has(&[AminoAcid::Glutamine], &[AminoAcid::Lysine]));
+    assert!(!has(&[AminoAcid::Glycine], &[AminoAcid::Alanine]));
+    // Each pair is only given once for a difference of zero
+    assert_eq!(
+        found
+            .iter()
+            .filter(|s| s.from == [AminoAcid::Leucine] || s.to == [AminoAcid::Leucine])
+            .filter(|s| s.from == [AminoAcid::Isoleucine] || s.to == [AminoAcid::Isoleucine])
+            .count(),
+        1
+    );
+}
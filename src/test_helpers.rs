@@ -0,0 +1,30 @@
+//! The fixtures shared by the unit tests
+
+use rustyms::{
+    align::{align, matrix::BLOSUM62, AlignScoring, AlignType, Alignment},
+    Peptidoform, SimpleLinear,
+};
+
+/// Parse a ProForma sequence, without custom modifications
+pub(crate) fn parse(sequence: &str) -> Peptidoform<SimpleLinear> {
+    Peptidoform::pro_forma(sequence, None)
+        .unwrap()
+        .into_simple_linear()
+        .unwrap()
+}
+
+/// The default scoring with the BLOSUM62 matrix
+pub(crate) fn scoring() -> AlignScoring<'static> {
+    AlignScoring {
+        matrix: BLOSUM62,
+        ..Default::default()
+    }
+}
+
+/// Align two sequences globally with [`scoring`], with isobaric steps up to `STEPS` residues
+pub(crate) fn global<'a, const STEPS: u16>(
+    a: &'a Peptidoform<SimpleLinear>,
+    b: &'a Peptidoform<SimpleLinear>,
+) -> Alignment<'a, SimpleLinear, SimpleLinear> {
+    align::<STEPS, _, _>(a, b, scoring(), AlignType::GLOBAL)
+}
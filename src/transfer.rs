@@ -0,0 +1,103 @@
+use rustyms::{
+    align::{Alignment, MatchType},
+    Peptidoform, SimpleLinear,
+};
+
+use crate::cli::TransferDirection;
+
+/// The result of transferring modifications from one sequence to the other
+pub struct Transfer {
+    /// The target sequence with all transferred modifications added
+    pub peptide: Peptidoform<SimpleLinear>,
+    /// The number of transferred modifications
+    pub transferred: usize,
+    /// All modifications that could not be transferred, with the reason
+    pub lost: Vec<String>,
+}
+
+/// Transfer the modifications across the alignment. Modifications are only transferred to the
+/// aligned position if that position contains the same amino acid, any modification in an
+/// isobaric or rotated set, on a mismatch, or in a gap is reported as lost. Terminal modifications
+/// are only transferred if both sequences are aligned up to that terminus.
+pub fn transfer_modifications(
+    alignment: &Alignment<'_, SimpleLinear, SimpleLinear>,
+    direction: TransferDirection,
+) -> Transfer {
+    let (source, mut target) = match direction {
+        TransferDirection::AToB => (alignment.seq_a(), alignment.seq_b().clone()),
+        TransferDirection::BToA => (alignment.seq_b(), alignment.seq_a().clone()),
+    };
+    let oriented = |a: usize, b: usize| match direction {
+        TransferDirection::AToB => (a, b),
+        TransferDirection::BToA => (b, a),
+    };
+    let mut transferred = 0;
+    let mut lost = Vec::new();
+    let (start_source, start_target) = oriented(alignment.start_a(), alignment.start_b());
+    let (mut a, mut b) = alignment.start();
+    for piece in alignment.path() {
+        let (index_source, index_target) = oriented(a, b);
+        let (step_source, _) = oriented(piece.step_a as usize, piece.step_b as usize);
+        for offset in 0..step_source {
+            let position = index_source + offset;
+            let element = &source.sequence()[position];
+            let reason = match piece.match_type {
+                MatchType::FullIdentity | MatchType::IdentityMassMismatch => None,
+                MatchType::Mismatch => Some("aligned to a different amino acid"),
+                MatchType::Isobaric | MatchType::Rotation => {
+                    Some("part of an isobaric or rotated set")
+                }
+                MatchType::Gap => Some("aligned to a gap"),
+            };
+            for modification in &element.modifications {
+                if let Some(reason) = reason {
+                    lost.push(format!(
+                        "{modification} on {}{}: {reason}",
+                        element.aminoacid.char(),
+                        position + 1
+                    ));
+                } else {
+                    let modifications =
+                        &mut target.sequence_mut()[index_target + offset].modifications;
+                    if !modifications.contains(modification) {
+                        modifications.push(modification.clone());
+                        transferred += 1;
+                    }
+                }
+            }
+        }
+        a += piece.step_a as usize;
+        b += piece.step_b as usize;
+    }
+    let (end_source, end_target) = oriented(a, b);
+    let n_terminal = start_source == 0 && start_target == 0;
+    let c_terminal = end_source == source.len() && end_target == target.len();
+    for (n_term, aligned) in [(true, n_terminal), (false, c_terminal)] {
+        let (modifications, mut terminal) = if n_term {
+            (source.get_n_term(), target.get_n_term().to_vec())
+        } else {
+            (source.get_c_term(), target.get_c_term().to_vec())
+        };
+        for modification in modifications {
+            if !aligned {
+                lost.push(format!(
+                    "{modification} on the {} terminus: terminus is not aligned",
+                    if n_term { "N" } else { "C" }
+                ));
+            } else if !terminal.contains(modification) {
+                terminal.push(modification.clone());
+                transferred += 1;
+            }
+        }
+        if n_term {
+            target.set_n_term(terminal);
+        } else {
+            target.set_c_term(terminal);
+        }
+    }
+    Transfer {
+        peptide: target,
+        transferred,
+        lost,
+    }
+}
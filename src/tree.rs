@@ -0,0 +1,318 @@
+use std::{cmp::Ordering, fmt::Display};
+
+use rayon::prelude::*;
+use rustyms::{AtMax, Peptidoform, SimpleLinear};
+
+use crate::alignment::{align, AlignSettings};
+
+/// The method used to build a tree from a distance matrix
+#[derive(Debug, Clone, Copy)]
+pub enum TreeMethod {
+    /// Unweighted pair group method with arithmetic mean, gives a rooted ultrametric tree
+    Upgma,
+    /// Neighbour joining, gives an unrooted tree (shown rooted at the last join)
+    NeighbourJoining,
+}
+
+impl Display for TreeMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Upgma => write!(f, "UPGMA"),
+            Self::NeighbourJoining => write!(f, "neighbour joining"),
+        }
+    }
+}
+
+impl TreeMethod {
+    /// Build a tree in Newick format, the distance matrix has to be square and symmetric
+    pub fn build(self, names: &[String], distances: &[Vec<f64>]) -> String {
+        self.build_tree(names, distances)
+            .map_or(";".to_string(), |tree| format!("{tree};"))
+    }
+
+    /// Build a tree, the distance matrix has to be square and symmetric. Returns None if there are no names.
+    pub fn build_tree(self, names: &[String], distances: &[Vec<f64>]) -> Option<Tree> {
+        let leaves = names.iter().map(|n| Tree::Leaf(n.clone())).collect();
+        match self {
+            Self::Upgma => upgma(leaves, distances.to_vec()),
+            Self::NeighbourJoining => neighbour_joining(leaves, distances.to_vec()),
+        }
+    }
+}
+
+/// Align all pairs of sequences and give the distances (one minus the normalised score) between them as a square and
+/// symmetric matrix, pairs without a defined score get the maximal distance of one
+pub fn distance_matrix<A: AtMax<SimpleLinear> + Sync>(
+    sequences: &[&Peptidoform<A>],
+    settings: AlignSettings,
+) -> Vec<Vec<f64>> {
+    let pairs: Vec<_> = (0..sequences.len())
+        .flat_map(|i| (i + 1..sequences.len()).map(move |j| (i, j)))
+        .collect();
+    let scores: Vec<_> = pairs
+        .par_iter()
+        .map(|(i, j)| {
+            align(
+                sequences[*i],
+                sequences[*j],
+                settings.scoring,
+                settings.ty,
+                settings.kind,
+            )
+            .normalised_score()
+        })
+        .collect();
+    let mut distances = vec![vec![0.0; sequences.len()]; sequences.len()];
+    for ((i, j), score) in pairs.into_iter().zip(scores) {
+        let distance = if score.is_nan() { 1.0 } else { 1.0 - score };
+        distances[i][j] = distance;
+        distances[j][i] = distance;
+    }
+    distances
+}
+
+/// A binary tree with branch lengths
+#[derive(Debug, Clone)]
+pub enum Tree {
+    Leaf(String),
+    /// Two subtrees with the lengths of the branches leading to them
+    Node(Box<(Tree, f64)>, Box<(Tree, f64)>),
+}
+
+impl Tree {
+    fn node(a: Self, length_a: f64, b: Self, length_b: f64) -> Self {
+        Self::Node(
+            Box::new((a, length_a.max(0.0))),
+            Box::new((b, length_b.max(0.0))),
+        )
+    }
+
+    /// The longest distance from the root to any leaf
+    fn depth(&self) -> f64 {
+        match self {
+            Self::Leaf(_) => 0.0,
+            Self::Node(a, b) => (a.1 + a.0.depth()).max(b.1 + b.0.depth()),
+        }
+    }
+
+    /// Draw the tree as a dendrogram with one leaf per line, the branch lengths are scaled so that
+    /// the deepest leaf is at the given width (every branch is at least one character). The
+    /// leaf names are passed through the given function, to allow highlighting specific leaves.
+    pub fn dendrogram(&self, width: usize, leaf: impl Fn(&str) -> String) -> Vec<String> {
+        let depth = self.depth();
+        let scale = if depth > 0.0 {
+            width as f64 / depth
+        } else {
+            0.0
+        };
+        self.draw(scale, &leaf).0
+    }
+
+    /// Draw the tree and return the lines and the line the parent branch connects to
+    fn draw(&self, scale: f64, leaf: &impl Fn(&str) -> String) -> (Vec<String>, usize) {
+        match self {
+            Self::Leaf(name) => (vec![format!(" {}", leaf(name))], 0),
+            Self::Node(a, b) => {
+                let mut lines = Vec::new();
+                let dashes = |length: f64| ((length * scale).round() as usize).max(1);
+                let (top, top_middle) = a.0.draw(scale, leaf);
+                let n = dashes(a.1);
+                for (index, line) in top.into_iter().enumerate() {
+                    lines.push(
+                        match index.cmp(&top_middle) {
+                            Ordering::Less => " ".repeat(n + 1),
+                            Ordering::Equal => format!("┬{}", "─".repeat(n)),
+                            Ordering::Greater => format!("│{}", " ".repeat(n)),
+                        } + &line,
+                    );
+                }
+                let (bottom, bottom_middle) = b.0.draw(scale, leaf);
+                let n = dashes(b.1);
+                for (index, line) in bottom.into_iter().enumerate() {
+                    lines.push(
+                        match index.cmp(&bottom_middle) {
+                            Ordering::Less => format!("│{}", " ".repeat(n)),
+                            Ordering::Equal => format!("└{}", "─".repeat(n)),
+                            Ordering::Greater => " ".repeat(n + 1),
+                        } + &line,
+                    );
+                }
+                (lines, top_middle)
+            }
+        }
+    }
+}
+
+impl Display for Tree {
+    /// Newick format (without the closing `;`)
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Leaf(name) => write!(f, "{}", newick_name(name)),
+            Self::Node(a, b) => write!(f, "({}:{},{}:{})", a.0, branch(a.1), b.0, branch(b.1)),
+        }
+    }
+}
+
+/// Quote a name if it contains any characters with a special meaning in Newick
+fn newick_name(name: &str) -> String {
+    if name
+        .chars()
+        .any(|c| "()[]':;,".contains(c) || c.is_whitespace())
+    {
+        format!("'{}'", name.replace('\'', "''"))
+    } else {
+        name.to_string()
+    }
+}
+
+fn upgma(mut nodes: Vec<Tree>, mut distances: Vec<Vec<f64>>) -> Option<Tree> {
+    if nodes.is_empty() {
+        return None;
+    }
+    let mut sizes = vec![1.0; nodes.len()];
+    let mut heights = vec![0.0; nodes.len()];
+    while nodes.len() > 1 {
+        let (i, j) = closest_pair(&distances, |i, j| distances[i][j]);
+        let height = distances[i][j] / 2.0;
+        let node = Tree::node(
+            nodes[i].clone(),
+            height - heights[i],
+            nodes[j].clone(),
+            height - heights[j],
+        );
+        let merged: Vec<f64> = (0..nodes.len())
+            .map(|k| {
+                (distances[i][k] * sizes[i] + distances[j][k] * sizes[j]) / (sizes[i] + sizes[j])
+            })
+            .collect();
+        let size = sizes[i] + sizes[j];
+        replace_pair(&mut distances, &mut nodes, i, j, merged, node);
+        sizes[i] = size;
+        sizes.remove(j);
+        heights[i] = height;
+        heights.remove(j);
+    }
+    nodes.pop()
+}
+
+fn neighbour_joining(mut nodes: Vec<Tree>, mut distances: Vec<Vec<f64>>) -> Option<Tree> {
+    while nodes.len() > 2 {
+        let n = nodes.len() as f64;
+        let sums: Vec<f64> = distances.iter().map(|row| row.iter().sum()).collect();
+        let (i, j) = closest_pair(&distances, |i, j| {
+            (n - 2.0) * distances[i][j] - sums[i] - sums[j]
+        });
+        let length_i = distances[i][j] / 2.0 + (sums[i] - sums[j]) / (2.0 * (n - 2.0));
+        let length_j = distances[i][j] - length_i;
+        let node = Tree::node(nodes[i].clone(), length_i, nodes[j].clone(), length_j);
+        let merged: Vec<f64> = (0..nodes.len())
+            .map(|k| (distances[i][k] + distances[j][k] - distances[i][j]) / 2.0)
+            .collect();
+        replace_pair(&mut distances, &mut nodes, i, j, merged, node);
+    }
+    if nodes.len() == 2 {
+        let b = nodes.pop().unwrap();
+        let a = nodes.pop().unwrap();
+        Some(Tree::node(
+            a,
+            distances[0][1] / 2.0,
+            b,
+            distances[0][1] / 2.0,
+        ))
+    } else {
+        nodes.pop()
+    }
+}
+
+/// Find the pair (i < j) with the lowest value for the given criterion
+fn closest_pair(distances: &[Vec<f64>], criterion: impl Fn(usize, usize) -> f64) -> (usize, usize) {
+    let mut best = (0, 1, f64::INFINITY);
+    for i in 0..distances.len() {
+        for j in i + 1..distances.len() {
+            let value = criterion(i, j);
+            if value < best.2 {
+                best = (i, j, value);
+            }
+        }
+    }
+    (best.0, best.1)
+}
+
+/// Replace node i by the new merged node and remove node j
+fn replace_pair(
+    distances: &mut Vec<Vec<f64>>,
+    nodes: &mut Vec<Tree>,
+    i: usize,
+    j: usize,
+    mut merged: Vec<f64>,
+    node: Tree,
+) {
+    merged[i] = 0.0;
+    for (k, row) in distances.iter_mut().enumerate() {
+        row[i] = merged[k];
+    }
+    distances[i] = merged;
+    for row in distances.iter_mut() {
+        row.remove(j);
+    }
+    distances.remove(j);
+    nodes[i] = node;
+    nodes.remove(j);
+}
+
+/// Format a branch length, negative lengths (possible in neighbour joining) are set to zero
+fn branch(length: f64) -> String {
+    format!("{:.4}", length.max(0.0))
+}
+
+#[test]
+fn upgma_tree() {
+    let names = ["A", "B", "C"].map(String::from);
+    let distances = vec![
+        vec![0.0, 0.2, 0.6],
+        vec![0.2, 0.0, 0.6],
+        vec![0.6, 0.6, 0.0],
+    ];
+    assert_eq!(
+        TreeMethod::Upgma.build(&names, &distances),
+        "((A:0.1000,B:0.1000):0.2000,C:0.3000);"
+    );
+    assert_eq!(
+        TreeMethod::NeighbourJoining.build(&names, &distances),
+        "((A:0.1000,B:0.1000):0.2500,C:0.2500);"
+    );
+}
+
+#[test]
+fn dendrogram() {
+    let names = ["A", "B", "C"].map(String::from);
+    let distances = vec![
+        vec![0.0, 0.2, 0.6],
+        vec![0.2, 0.0, 0.6],
+        vec![0.6, 0.6, 0.0],
+    ];
+    let tree = TreeMethod::Upgma.build_tree(&names, &distances).unwrap();
+    assert_eq!(
+        tree.dendrogram(6, |n| n.to_string()),
+        ["┬────┬── A", "│    └── B", "└────── C"]
+    );
+}
+
+#[test]
+fn distances() {
+    use crate::test_helpers::{parse, scoring};
+    let sequences = ["ANGKT", "ANGKT", "WWWWW"].map(parse);
+    let distances = distance_matrix(
+        &sequences.iter().collect::<Vec<_>>(),
+        AlignSettings {
+            scoring: scoring(),
+            ty: rustyms::align::AlignType::GLOBAL,
+            kind: crate::cli::AlignmentKind::default(),
+        },
+    );
+    assert_eq!(distances.len(), 3);
+    assert!((0..3).all(|i| distances[i][i] == 0.0));
+    assert!((0..3).all(|i| (0..3).all(|j| distances[i][j] == distances[j][i])));
+    assert!(distances[0][1].abs() < 1e-9);
+    assert!(distances[0][2] > distances[0][1]);
+}
@@ -0,0 +1,170 @@
+use colored::Color;
+use rustyms::align::Alignment;
+
+use crate::render::CombinedLines;
+
+/// A single column (or block of columns for isobaric steps) of a three way alignment
+pub struct TripleColumn {
+    /// The residues of A, B, and C, padded to the same width
+    pub residues: [String; 3],
+    /// The marker for this column: ' ' all identical, 'b' B differs, 'c' C differs, 'a' A differs
+    /// from B and C which are identical, 'x' all differ
+    pub marker: char,
+}
+
+/// A step of a pairwise alignment relative to sequence A
+struct Segment {
+    /// The number of residues of A in this step
+    len_a: usize,
+    /// The residues of the other sequence, or gaps
+    other: String,
+}
+
+/// Get all segments of the given alignment, regions of A that are not aligned are shown as aligned
+/// to gaps, regions of the other sequence that are not aligned are not shown.
+fn segments<A, B>(alignment: &Alignment<'_, A, B>) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    if alignment.start_a() > 0 {
+        segments.push(Segment {
+            len_a: alignment.start_a(),
+            other: "-".repeat(alignment.start_a()),
+        });
+    }
+    let (mut a, mut b) = alignment.start();
+    for piece in alignment.path() {
+        segments.push(Segment {
+            len_a: piece.step_a as usize,
+            other: if piece.step_b == 0 {
+                "-".repeat(piece.step_a as usize)
+            } else {
+                alignment.seq_b().sequence()[b..b + piece.step_b as usize]
+                    .iter()
+                    .map(|s| s.aminoacid.char())
+                    .collect()
+            },
+        });
+        a += piece.step_a as usize;
+        b += piece.step_b as usize;
+    }
+    if a < alignment.seq_a().len() {
+        segments.push(Segment {
+            len_a: alignment.seq_a().len() - a,
+            other: "-".repeat(alignment.seq_a().len() - a),
+        });
+    }
+    segments
+}
+
+/// Merge two pairwise alignments that share sequence A (A-B and A-C) into a three way
+/// alignment. Steps that cover a different part of A in both alignments are merged into one
+/// block that is consistent with both alignments.
+pub fn merge<A, B, C>(ab: &Alignment<'_, A, B>, ac: &Alignment<'_, A, C>) -> Vec<TripleColumn> {
+    let a: Vec<char> = ab
+        .seq_a()
+        .sequence()
+        .iter()
+        .map(|s| s.aminoacid.char())
+        .collect();
+    let b = segments(ab);
+    let c = segments(ac);
+    let (mut i, mut j, mut position) = (0, 0, 0);
+    let mut columns = Vec::new();
+    while i < b.len() || j < c.len() {
+        if i < b.len() && b[i].len_a == 0 {
+            columns.push(column(String::new(), b[i].other.clone(), String::new()));
+            i += 1;
+        } else if j < c.len() && c[j].len_a == 0 {
+            columns.push(column(String::new(), String::new(), c[j].other.clone()));
+            j += 1;
+        } else {
+            let (mut end_b, mut end_c) = (position + b[i].len_a, position + c[j].len_a);
+            let (mut other_b, mut other_c) = (b[i].other.clone(), c[j].other.clone());
+            i += 1;
+            j += 1;
+            while end_b != end_c {
+                if end_b < end_c {
+                    end_b += b[i].len_a;
+                    other_b += &b[i].other;
+                    i += 1;
+                } else {
+                    end_c += c[j].len_a;
+                    other_c += &c[j].other;
+                    j += 1;
+                }
+            }
+            columns.push(column(
+                a[position..end_b].iter().collect(),
+                other_b,
+                other_c,
+            ));
+            position = end_b;
+        }
+    }
+    columns
+}
+
+/// Create a column, empty strings or only gaps are shown as gaps
+fn column(a: String, b: String, c: String) -> TripleColumn {
+    let residues = |s: &str| s.chars().filter(|c| *c != '-').collect::<String>();
+    let (ra, rb, rc) = (residues(&a), residues(&b), residues(&c));
+    let marker = match (ra == rb, ra == rc) {
+        (true, true) => ' ',
+        (false, true) => 'b',
+        (true, false) => 'c',
+        (false, false) if rb == rc => 'a',
+        (false, false) => 'x',
+    };
+    let width = [&ra, &rb, &rc]
+        .iter()
+        .map(|s| s.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let pad = |s: String| {
+        if s.is_empty() {
+            "-".repeat(width)
+        } else {
+            format!("{s:·<width$}")
+        }
+    };
+    TripleColumn {
+        residues: [pad(ra), pad(rb), pad(rc)],
+        marker,
+    }
+}
+
+/// Show the three way alignment, with below the sequences a marker line indicating which sequence
+/// differs.
+pub fn show_triple(columns: &[TripleColumn], line_width: usize) {
+    let mut writer = CombinedLines::stacked(line_width.max(1), "B", "C");
+    for column in columns {
+        let colour = match column.marker {
+            'a' => Color::Red,
+            'b' => Color::Yellow,
+            'c' => Color::Cyan,
+            _ => Color::Magenta,
+        };
+        let [a, b, c] = column.residues.each_ref().map(|s| s.chars());
+        for ((a, b), c) in a.zip(b).zip(c) {
+            writer.add_stacked_column("A", [a, b, c], column.marker, Some(colour));
+        }
+    }
+    print!("{}", writer.finish());
+}
+
+#[test]
+fn merge_three() {
+    use crate::test_helpers::{global, parse};
+    let (a, b, c) = (parse("AKTNLSHLGY"), parse("AKTNSHLGY"), parse("AKTNLSHVGY"));
+    let ab = global::<1>(&a, &b);
+    let ac = global::<1>(&a, &c);
+    let columns = merge(&ab, &ac);
+    let rows: Vec<String> = (0..3)
+        .map(|i| columns.iter().map(|c| c.residues[i].as_str()).collect())
+        .collect();
+    assert_eq!(rows, ["AKTNLSHLGY", "AKTN-SHLGY", "AKTNLSHVGY"]);
+    assert_eq!(
+        columns.iter().map(|c| c.marker).collect::<String>(),
+        "    b  c  "
+    );
+}
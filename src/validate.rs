@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use colored::Colorize;
+use rustyms::{
+    error::{Context, CustomError},
+    CompoundPeptidoformIon,
+};
+
+use crate::custom;
+
+/// Find the ambiguous residues (B, Z, J, and X) in a ProForma sequence, as the character offsets
+/// of residues outside of modifications, global modifications, and labile modifications
+fn ambiguous_residues(line: &str) -> Vec<(usize, char)> {
+    let mut depth = 0_usize;
+    let mut output = Vec::new();
+    for (offset, c) in line.char_indices() {
+        match c {
+            '[' | '{' | '<' => depth += 1,
+            ']' | '}' | '>' => depth = depth.saturating_sub(1),
+            'B' | 'Z' | 'J' | 'X' if depth == 0 => output.push((offset, c)),
+            _ => (),
+        }
+    }
+    output
+}
+
+/// Strictly parse one ProForma sequence and give all errors and warnings. The line index is used
+/// in the context of the diagnostics when validating a file.
+pub fn validate(line: &str, line_index: Option<usize>) -> Vec<CustomError> {
+    match CompoundPeptidoformIon::pro_forma(line, custom::database()) {
+        Err(error) => vec![match line_index {
+            Some(index) => error.overwrite_line_number(index),
+            None => error,
+        }],
+        Ok(_) => ambiguous_residues(line)
+            .into_iter()
+            .map(|(offset, residue)| {
+                CustomError::warning(
+                    "Ambiguous amino acid",
+                    format!(
+                        "'{residue}' is an ambiguous amino acid, {}",
+                        match residue {
+                            'X' => "so the mass of the sequence is not defined",
+                            'J' => "the mass is defined but not the identity",
+                            _ => "so the sequence has multiple possible masses",
+                        }
+                    ),
+                    Context::line(line_index, line, offset, 1),
+                )
+                .with_suggestions(match residue {
+                    'B' => vec!["D".to_string(), "N".to_string()],
+                    'Z' => vec!["E".to_string(), "Q".to_string()],
+                    'J' => vec!["I".to_string(), "L".to_string()],
+                    _ => Vec::new(),
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Validate a single sequence, or all sequences in a file (one per line, empty lines and lines
+/// starting with `#` are skipped), and show all diagnostics. Returns true if there are no errors.
+pub fn show(input: &str) -> bool {
+    let lines = if Path::new(input).is_file() {
+        std::fs::read_to_string(input)
+            .unwrap()
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(|(index, line)| (Some(index), line.trim_end().to_string()))
+            .collect()
+    } else {
+        vec![(None, input.to_string())]
+    };
+    let mut errors = 0;
+    let mut warnings = 0;
+    for (index, line) in &lines {
+        for diagnostic in validate(line, *index) {
+            if diagnostic.is_warning() {
+                warnings += 1;
+            } else {
+                errors += 1;
+            }
+            println!("{diagnostic}");
+        }
+    }
+    let summary = format!(
+        "Validated {} sequence{}: {errors} error{}, {warnings} warning{}",
+        lines.len(),
+        if lines.len() == 1 { "" } else { "s" },
+        if errors == 1 { "" } else { "s" },
+        if warnings == 1 { "" } else { "s" },
+    );
+    if errors == 0 {
+        println!("{}", summary.green());
+    } else {
+        println!("{}", summary.red());
+    }
+    errors == 0
+}
+
+#[test]
+fn diagnostics() {
+    assert!(validate("PEPTIDE[Oxidation]", None).is_empty());
+    assert!(validate("PEM[Oxidation]TIDE/2", None).is_empty());
+    let error = validate("PEPT[Oxidaton]IDE", Some(3));
+    assert_eq!(error.len(), 1);
+    assert!(!error[0].is_warning());
+    let warnings = validate("PEBTIDE[Formula:B1]", None);
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].is_warning());
+    assert_eq!(warnings[0].suggestions(), ["D", "N"]);
+}